@@ -4,7 +4,7 @@ use std::env;
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::{self, IsTerminal, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command as ProcCommand;
 use std::process::Stdio;
 use std::time::Duration;
@@ -13,14 +13,20 @@ use anyhow::{Context, Result, anyhow};
 use chrono::{
     DateTime, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
 };
-use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 use env_logger::fmt::WriteStyle;
-use h8_core::id::WordLists;
-use h8_core::maildir::{FOLDER_DRAFTS, FOLDER_TRASH, MessageFlags};
+use h8_core::compose::{
+    LintSeverity, address_domain, format_address, generate_message_id, parse_address,
+    parse_recipients,
+};
+use h8_core::maildir::{FOLDER_DRAFTS, FOLDER_SENT, FOLDER_TRASH, FolderStats, MessageFlags};
+use h8_core::sync::{BodyFetch, SyncOptions, sync_folder, truncate_body};
+use h8_core::thread::{build_thread_tree, latest_per_thread};
+use h8_core::types::{AttachmentMeta, format_size};
 use h8_core::{
-    AppConfig, AppPaths, ComposeBuilder, ComposeDocument, Database, IdGenerator, Maildir,
-    ServiceClient,
+    AppConfig, AppPaths, BulkResult, ComposeBuilder, ComposeDocument, Database, DisplayConfig,
+    Folder, HealthStatus, IdGenerator, Maildir, Progress, ServiceClient,
 };
 
 use log::{LevelFilter, debug};
@@ -29,6 +35,15 @@ use serde_json::{Value, json};
 
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
 
+/// Display formats from the loaded config, for printers that only receive
+/// the output options.
+static DISPLAY: std::sync::OnceLock<DisplayConfig> = std::sync::OnceLock::new();
+
+/// The configured display formats, or the defaults before config is loaded.
+fn display_config() -> &'static DisplayConfig {
+    DISPLAY.get_or_init(DisplayConfig::default)
+}
+
 fn main() {
     if let Err(err) = try_main() {
         let _ = writeln!(io::stderr(), "{err:?}");
@@ -37,30 +52,119 @@ fn main() {
 }
 
 fn try_main() -> Result<()> {
-    let cli = Cli::parse();
+    let cli = Cli::parse_args();
     let ctx = RuntimeContext::new(cli.common.clone())?;
     ctx.init_logging()?;
+    let _ = DISPLAY.set(ctx.config.display.clone());
     debug!("config loaded from {}", ctx.paths.global_config.display());
 
-    match cli.command {
-        Command::Calendar { command } => handle_calendar(&ctx, command),
-        Command::Mail { command } => handle_mail(&ctx, command),
-        Command::Agenda(args) => handle_agenda(&ctx, args),
-        Command::Contacts { command } => handle_contacts(&ctx, command),
-        Command::Addr { command } => handle_addr(&ctx, command),
-        Command::Free(cmd) => handle_free(&ctx, cmd),
-        Command::Resource { command } => handle_resource(&ctx, command),
-        Command::Ppl { command } => handle_ppl(&ctx, command),
-        Command::Config { command } => handle_config(&ctx, command),
-        Command::Init(cmd) => handle_init(&ctx, cmd),
+    if !ctx.config.service.auto_start || ctx.offline() || !may_auto_start(&cli.command) {
+        return run_command(&ctx, cli.command);
+    }
+    let mut command = Some(cli.command);
+    run_with_auto_start(
+        // Commands are consumed when run, so the retry parses the command
+        // line again
+        || {
+            run_command(
+                &ctx,
+                command.take().unwrap_or_else(|| Cli::parse_args().command),
+            )
+        },
+        || probe_health(&ctx),
+        || start_service(&ctx, false),
+        Duration::from_secs(ctx.config.service.start_timeout_secs),
+    )
+}
+
+/// Whether a failure of `command` may be retried after auto-starting the
+/// service. Only commands that are safe to run twice qualify: lookups and
+/// syncs, but nothing that prompts, opens an editor or changes mail,
+/// events, contacts or settings.
+fn may_auto_start(command: &Command) -> bool {
+    match command {
+        Command::Calendar { command } => matches!(
+            command,
+            CalendarCommand::List(_)
+                | CalendarCommand::Show(_)
+                | CalendarCommand::Weeks(_)
+                | CalendarCommand::Months(_)
+                | CalendarCommand::Get(_)
+                | CalendarCommand::Search(_)
+                | CalendarCommand::Invites(_)
+                | CalendarCommand::Sync(_)
+        ),
+        Command::Mail { command } => matches!(
+            command,
+            MailCommand::List(_)
+                | MailCommand::Search(_)
+                | MailCommand::Get(_)
+                | MailCommand::Read(_)
+                | MailCommand::Thread(_)
+                | MailCommand::Folders(_)
+                | MailCommand::Drafts(_)
+                | MailCommand::Sync(_)
+                | MailCommand::Attachments(_)
+                | MailCommand::Stats(_)
+        ),
+        Command::Contacts { command } => matches!(
+            command,
+            ContactsCommand::List(_) | ContactsCommand::Get(_) | ContactsCommand::Export(_)
+        ),
+        Command::Resource { command } => matches!(
+            command,
+            ResourceCommand::Free(_) | ResourceCommand::Agenda(_) | ResourceCommand::List
+        ),
+        Command::Ppl { command } => matches!(
+            command,
+            PplCommand::Agenda(_) | PplCommand::Free(_) | PplCommand::Common(_)
+        ),
+        Command::Rules { command } => {
+            matches!(command, RulesCommand::List(_) | RulesCommand::Show(_))
+        }
+        Command::Oof { command } => matches!(command, OofCommand::Status),
+        Command::Agenda(_)
+        | Command::Addr { .. }
+        | Command::Free(_)
+        | Command::Which(_)
+        | Command::Sync(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether `err` is a failure to connect to the service. Most commands
+/// flatten core errors into messages, so the message text is checked too.
+fn is_connection_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<h8_core::Error>(),
+            Some(h8_core::Error::Unreachable(_))
+        ) || cause.to_string().contains("service unreachable: ")
+    })
+}
+
+fn run_command(ctx: &RuntimeContext, command: Command) -> Result<()> {
+    match command {
+        Command::Calendar { command } => handle_calendar(ctx, command),
+        Command::Mail { command } => handle_mail(ctx, command),
+        Command::Agenda(args) => handle_agenda(ctx, args),
+        Command::Contacts { command } => handle_contacts(ctx, command),
+        Command::Addr { command } => handle_addr(ctx, command),
+        Command::Free(cmd) => handle_free(ctx, cmd),
+        Command::Resource { command } => handle_resource(ctx, command),
+        Command::Ppl { command } => handle_ppl(ctx, command),
+        Command::Config { command } => handle_config(ctx, command),
+        Command::Init(cmd) => handle_init(ctx, cmd),
         Command::Completions { shell } => handle_completions(shell),
-        Command::Service { command } => handle_service(&ctx, command),
-        Command::Which(args) => handle_natural_resource(&ctx, args),
-        Command::Book(args) => handle_book(&ctx, args),
-        Command::Trip(args) => handle_trip(&ctx, args),
-        Command::Rules { command } => handle_rules(&ctx, command),
-        Command::Oof { command } => handle_oof(&ctx, command),
-        Command::Sync(args) => handle_sync(&ctx, args),
+        Command::Service { command } => handle_service(ctx, command),
+        Command::Id { command } => handle_id(ctx, command),
+        Command::Db { command } => handle_db(ctx, command),
+        Command::Which(args) => handle_natural_resource(ctx, args),
+        Command::Book(args) => handle_book(ctx, args),
+        Command::Trip(args) => handle_trip(ctx, args),
+        Command::Rules { command } => handle_rules(ctx, command),
+        Command::Oof { command } => handle_oof(ctx, command),
+        Command::Sync(args) => handle_sync(ctx, args),
     }
 }
 
@@ -69,6 +173,7 @@ fn try_main() -> Result<()> {
     author,
     version,
     about = "Rust CLI for EWS backed by the Python service.",
+    // Subcommands whose `-V` means `--view` disable the version flag
     propagate_version = true
 )]
 struct Cli {
@@ -94,22 +199,128 @@ struct CommonOpts {
     json: bool,
     #[arg(long, global = true)]
     yaml: bool,
+    /// Output format (--json and --yaml are shorthands)
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        value_enum,
+        conflicts_with_all = ["json", "yaml"]
+    )]
+    format: Option<OutputFormat>,
+    /// Pretty line format with `{field}` placeholders, e.g. '{date} {from} {subject}'
+    #[arg(long = "output-template", value_name = "TEMPLATE", global = true)]
+    output_template: Option<String>,
     #[arg(long = "no-color", global = true, conflicts_with = "color")]
     no_color: bool,
     #[arg(long, value_enum, default_value_t = ColorOption::Auto, global = true)]
     color: ColorOption,
     #[arg(long = "dry-run", global = true)]
     dry_run: bool,
-    #[arg(short = 'y', long = "yes", alias = "force", global = true)]
+    #[arg(short = 'y', long = "yes", global = true)]
     assume_yes: bool,
     #[arg(long = "timeout", value_name = "SECONDS", global = true)]
     timeout: Option<u64>,
     #[arg(long = "no-progress", global = true)]
     no_progress: bool,
+    /// Forbid all service calls; work from synced local data only
+    #[arg(long, global = true)]
+    offline: bool,
     #[arg(long = "diagnostics", global = true)]
     diagnostics: bool,
     #[arg(short = 'a', long = "account", global = true)]
     account: Option<String>,
+    /// Timezone for this run, overriding the `timezone` config key (e.g. America/New_York)
+    #[arg(long = "tz", value_name = "ZONE", global = true)]
+    tz: Option<String>,
+}
+
+impl Cli {
+    /// The command definition, with the output `--format` added to every
+    /// subcommand that has no `--format` of its own (`mail fetch`, where it
+    /// is the export format). A global argument cannot skip one subcommand,
+    /// so the option is copied down explicitly instead.
+    fn definition() -> clap::Command {
+        fn add_format(mut cmd: clap::Command, format: &clap::Arg) -> clap::Command {
+            let names: Vec<String> = cmd
+                .get_subcommands()
+                .filter(|sc| !sc.get_arguments().any(|a| a.get_long() == Some("format")))
+                .map(|sc| sc.get_name().to_string())
+                .collect();
+            for name in names {
+                cmd = cmd.mut_subcommand(name, |sc| add_format(sc.arg(format.clone()), format));
+            }
+            cmd
+        }
+        let cmd = Cli::command();
+        let format = cmd
+            .get_arguments()
+            .find(|a| a.get_id() == "format")
+            .cloned()
+            .expect("--format is defined on the top-level command");
+        add_format(cmd, &format)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let mut cli = Cli::from_arg_matches(matches)?;
+        // The innermost `--format` wins, like a global option would
+        let mut matches = matches;
+        while let Some((_, sub)) = matches.subcommand() {
+            if let Ok(Some(format)) = sub.try_get_one::<OutputFormat>("format") {
+                cli.common.format = Some(*format);
+            }
+            matches = sub;
+        }
+        Ok(cli)
+    }
+
+    fn parse_args() -> Self {
+        Cli::from_matches(&Cli::definition().get_matches()).unwrap_or_else(|e| e.exit())
+    }
+
+    #[cfg(test)]
+    fn parse_from_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        Cli::from_matches(&Cli::definition().try_get_matches_from(args)?)
+    }
+}
+
+impl CommonOpts {
+    /// Color forced on or off by flags or the environment; `None` means
+    /// decide by whether the output is a terminal. Disabling wins.
+    fn color_override(&self) -> Option<bool> {
+        if self.no_color
+            || matches!(self.color, ColorOption::Never)
+            || env::var_os("NO_COLOR").is_some()
+        {
+            Some(false)
+        } else if matches!(self.color, ColorOption::Always) || env::var_os("FORCE_COLOR").is_some()
+        {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// Whether to colorize pretty output on stdout.
+    fn stdout_color(&self) -> bool {
+        self.color_override()
+            .unwrap_or_else(|| io::stdout().is_terminal())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable listing (default)
+    Pretty,
+    Json,
+    Yaml,
+    /// Aligned columns with a header row, fitted to the terminal width
+    Table,
+    /// Comma-separated values with a header row, for spreadsheets
+    Csv,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -131,6 +342,8 @@ enum Command {
         command: MailCommand,
     },
     /// Today's agenda (calendar)
+    // `-V` is --view here, not the propagated --version
+    #[command(disable_version_flag = true)]
     Agenda(AgendaArgs),
     Contacts {
         #[command(subcommand)]
@@ -165,6 +378,16 @@ enum Command {
         #[command(subcommand)]
         command: ServiceCommand,
     },
+    /// Inspect the pool of human-readable message IDs
+    Id {
+        #[command(subcommand)]
+        command: IdCommand,
+    },
+    /// Maintain the local sync database
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
     Completions {
         #[arg(value_enum)]
         shell: Shell,
@@ -367,6 +590,9 @@ struct CalendarCreateArgs {
     /// Input is in extraction/event.json schema format (from xtr)
     #[arg(short = 'e', long)]
     extracted: bool,
+    /// Prompt for subject, start, end, location and attendees instead of reading JSON
+    #[arg(short = 'i', long, conflicts_with_all = ["file", "extracted"])]
+    interactive: bool,
 }
 
 #[derive(Debug, Args)]
@@ -455,7 +681,7 @@ struct CalendarCancelArgs {
     /// Event ID to cancel (or use --query to cancel multiple)
     id: Option<String>,
     /// Search query to select events to cancel (e.g., "today", "standup")
-    #[arg(short = 'q', long)]
+    #[arg(long)]
     query: Option<String>,
     /// Optional cancellation message to send to attendees
     #[arg(short = 'm', long)]
@@ -497,16 +723,25 @@ enum MailCommand {
     Get(MailGetArgs),
     /// Read a message (view in pager)
     Read(MailReadArgs),
+    /// Show the conversation a message belongs to, from synced headers
+    Thread(MailThreadArgs),
+    /// List server folders (cached locally for mail.folder_cache_ttl_secs)
+    Folders(MailFoldersArgs),
     /// Fetch messages from server to local storage
     Fetch(MailFetchArgs),
     /// Send an email
     Send(MailSendArgs),
     /// Compose a new email
     Compose(MailComposeArgs),
+    /// Check a draft (or a compose document on stdin) before sending
+    ComposeLint(MailComposeLintArgs),
     /// Reply to a message
     Reply(MailReplyArgs),
     /// Forward a message
     Forward(MailForwardArgs),
+    /// Re-send a stored message unchanged to other recipients
+    #[command(alias = "bounce")]
+    Resend(MailResendArgs),
     /// Move a message to another folder
     #[command(alias = "mv")]
     Move(MailMoveArgs),
@@ -517,12 +752,20 @@ enum MailCommand {
     /// Delete a message (move to trash)
     #[command(alias = "rm")]
     Delete(MailDeleteArgs),
+    /// Restore a message from trash
+    Undelete(MailUndeleteArgs),
     /// Mark a message (read/unread/flagged)
     Mark(MailMarkArgs),
+    /// Flag message(s) for follow-up
+    Flag(MailFlagArgs),
+    /// Clear the follow-up flag on message(s)
+    Unflag(MailFlagArgs),
     /// List drafts
     Drafts(MailDraftsArgs),
     /// Edit an existing draft
     Edit(MailEditArgs),
+    /// Change a draft's recipients or subject without opening an editor
+    DraftSet(MailDraftSetArgs),
     /// Sync messages with server
     Sync(MailSyncArgs),
     /// List or download attachments
@@ -531,6 +774,13 @@ enum MailCommand {
     /// Empty a folder (permanently delete all items)
     #[command(alias = "empty")]
     EmptyFolder(MailEmptyFolderArgs),
+    /// Manage the local trash folder
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+    /// Summarize the local mailbox: counts, size on disk and date range
+    Stats(MailStatsArgs),
     /// Mark message(s) as spam/junk
     Spam(MailSpamArgs),
     /// Bulk unsubscribe from marketing emails
@@ -558,6 +808,136 @@ struct MailListArgs {
     limit: usize,
     #[arg(short = 'u', long)]
     unread: bool,
+    /// Date sort order
+    #[arg(long, value_enum)]
+    sort: Option<MailSort>,
+    /// Show newest messages first (default; shorthand for --sort newest)
+    #[arg(long, conflicts_with_all = ["oldest_first", "sort"])]
+    newest_first: bool,
+    /// Show oldest messages first (shorthand for --sort oldest)
+    #[arg(long, conflicts_with_all = ["newest_first", "sort"])]
+    oldest_first: bool,
+    /// Show one row per conversation: the latest message and its thread size
+    #[arg(long)]
+    thread: bool,
+    /// Print only the number of messages in the local Maildir (with
+    /// --unread, the number of unread ones)
+    #[arg(long, conflicts_with_all = ["when", "thread"])]
+    count_only: bool,
+    /// Print the messages under one header per sender address or per day
+    #[arg(long, value_enum, value_name = "KEY", conflicts_with = "count_only")]
+    group_by: Option<MailGroupBy>,
+}
+
+impl MailListArgs {
+    fn sort_order(&self) -> MailSort {
+        if self.oldest_first {
+            MailSort::Oldest
+        } else if self.newest_first {
+            MailSort::Newest
+        } else {
+            self.sort.unwrap_or_default()
+        }
+    }
+}
+
+/// Grouping key for `mail list --group-by`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MailGroupBy {
+    /// Sender address
+    Sender,
+    /// Day the message was received
+    Date,
+}
+
+impl MailGroupBy {
+    /// Group header for one listed message.
+    fn key(self, message: &Value, date_key: &str) -> String {
+        match self {
+            MailGroupBy::Sender => {
+                let from = message.get("from").and_then(|v| v.as_str()).unwrap_or("");
+                match parse_address(from) {
+                    Some((_, email)) => email.to_lowercase(),
+                    None if from.trim().is_empty() => "unknown".to_string(),
+                    None => from.trim().to_lowercase(),
+                }
+            }
+            MailGroupBy::Date => message
+                .get(date_key)
+                .and_then(|v| v.as_str())
+                .and_then(|d| d.get(..10))
+                .unwrap_or("unknown")
+                .to_string(),
+        }
+    }
+
+    /// Split an already sorted listing into (header, messages) groups.
+    /// Senders are ordered alphabetically and days follow the sort order;
+    /// each group keeps the listing's date order.
+    fn apply(
+        self,
+        messages: Vec<Value>,
+        date_key: &str,
+        sort: MailSort,
+    ) -> Vec<(String, Vec<Value>)> {
+        let mut groups: Vec<(String, Vec<Value>)> = Vec::new();
+        for message in messages {
+            let key = self.key(&message, date_key);
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, members)) => members.push(message),
+                None => groups.push((key, vec![message])),
+            }
+        }
+        groups.sort_by(|(a, _), (b, _)| match (self, sort) {
+            (MailGroupBy::Date, MailSort::Newest) => b.cmp(a),
+            _ => a.cmp(b),
+        });
+        groups
+    }
+}
+
+/// Print a grouped listing: a header per group in human output, or an
+/// array of `{group, messages}` objects for JSON and YAML.
+fn emit_grouped_output(opts: &CommonOpts, groups: &[(String, Vec<Value>)]) -> Result<()> {
+    if opts.json || opts.yaml {
+        let output: Vec<Value> = groups
+            .iter()
+            .map(|(group, messages)| json!({ "group": group, "messages": messages }))
+            .collect();
+        return emit_output(opts, &output);
+    }
+    for (i, (group, messages)) in groups.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{} ({}):", group, messages.len());
+        emit_output(opts, messages)?;
+    }
+    Ok(())
+}
+
+/// Date sort order for mail listings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum MailSort {
+    #[default]
+    Newest,
+    Oldest,
+}
+
+impl MailSort {
+    /// Sort message objects in place by the given date field.
+    fn apply(self, messages: &mut [Value], date_key: &str) {
+        let date = |m: &Value| {
+            m.get(date_key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        messages.sort_by_key(date);
+        if self == MailSort::Newest {
+            messages.reverse();
+        }
+    }
 }
 
 #[derive(Debug, Args)]
@@ -581,6 +961,10 @@ struct MailSearchArgs {
     /// Filter to last N days
     #[arg(short = 'd', long)]
     days: Option<i64>,
+    /// Search the local full-text index of synced messages instead of the
+    /// server (always used in offline mode). Supports "from:" and "subject:"
+    #[arg(long)]
+    local: bool,
 }
 
 #[derive(Debug, Args)]
@@ -592,6 +976,7 @@ struct MailGetArgs {
 }
 
 #[derive(Debug, Args)]
+#[command(disable_version_flag = true)]
 struct AgendaArgs {
     /// Date expression (e.g., today, tomorrow, mittwoch, feb 13, +2, 28.01)
     #[arg(num_args = 0..)]
@@ -601,6 +986,9 @@ struct AgendaArgs {
     /// View mode: list, gantt, or compact (default from config)
     #[arg(short = 'V', long = "view", value_enum)]
     view: Option<AgendaView>,
+    /// Show today with a current-time marker and the next upcoming event
+    #[arg(long, conflicts_with = "when")]
+    now: bool,
 }
 
 /// Event status for visual indicators.
@@ -698,24 +1086,41 @@ struct MailFetchArgs {
     folder: String,
     #[arg(short = 'o', long)]
     output: PathBuf,
-    #[arg(long, value_enum, default_value_t = FetchFormat::Maildir)]
+    /// Export format
+    #[arg(
+        long = "format",
+        id = "export_format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value = "maildir"
+    )]
     format: FetchFormat,
     #[arg(short = 'l', long)]
     limit: Option<usize>,
+    /// Gzip-compress the output (mbox only, written to mail.mbox.gz)
+    #[arg(long)]
+    gzip: bool,
+    /// Continue an interrupted fetch, skipping messages already written (an
+    /// mbox fetch that completed is written again)
+    #[arg(long)]
+    resume: bool,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 enum FetchFormat {
     Maildir,
     Mbox,
+    /// JSON array of {headers, body, attachments} (written to mail.json)
+    Json,
 }
 
-impl From<FetchFormat> for h8_core::types::FetchFormat {
-    fn from(f: FetchFormat) -> Self {
-        match f {
-            FetchFormat::Maildir => h8_core::types::FetchFormat::Maildir,
-            FetchFormat::Mbox => h8_core::types::FetchFormat::Mbox,
-        }
+/// Resolve `--format`/`--gzip` into the core fetch format.
+fn fetch_format(format: &FetchFormat, gzip: bool) -> Result<h8_core::types::FetchFormat> {
+    match format {
+        FetchFormat::Mbox => Ok(h8_core::types::FetchFormat::Mbox { gzip }),
+        _ if gzip => Err(anyhow!("--gzip is only supported with --format mbox")),
+        FetchFormat::Maildir => Ok(h8_core::types::FetchFormat::Maildir),
+        FetchFormat::Json => Ok(h8_core::types::FetchFormat::Json),
     }
 }
 
@@ -770,6 +1175,29 @@ struct MailReadArgs {
     /// Show raw RFC822 format
     #[arg(long)]
     raw: bool,
+    /// Render the body as HTML even if it is not detected as such
+    #[arg(long, conflicts_with = "raw")]
+    html: bool,
+    /// Collapse quoted replies and drop the trailing signature
+    #[arg(long, conflicts_with = "raw")]
+    plain: bool,
+    /// Fetch the whole message from the server when its stored body was
+    /// truncated (see mail.sync.max_body_bytes)
+    #[arg(long)]
+    full: bool,
+}
+
+#[derive(Debug, Args)]
+struct MailThreadArgs {
+    /// Message ID (e.g., 'cold-lamp')
+    id: String,
+}
+
+#[derive(Debug, Args)]
+struct MailFoldersArgs {
+    /// Query the server even if the cached folder list is still fresh
+    #[arg(long)]
+    refresh: bool,
 }
 
 #[derive(Debug, Args)]
@@ -777,6 +1205,27 @@ struct MailComposeArgs {
     /// Open editor immediately (default behavior)
     #[arg(long)]
     no_edit: bool,
+    /// Read a complete compose document from stdin and save it as a draft
+    #[arg(long)]
+    stdin: bool,
+    /// Add a contact (ID or [people] alias) as recipient; repeatable
+    #[arg(long = "to-contact", value_name = "ID", conflicts_with = "stdin")]
+    to_contact: Vec<String>,
+    /// Start from an RFC 822 message file (.eml)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["stdin", "to_contact"])]
+    import: Option<PathBuf>,
+    /// Show a summary and ask before saving the draft
+    #[arg(long)]
+    confirm: bool,
+}
+
+#[derive(Debug, Args)]
+struct MailComposeLintArgs {
+    /// Draft ID (reads a compose document from stdin when omitted or "-")
+    id: Option<String>,
+    /// Attachment to check for existence (repeatable)
+    #[arg(long, value_name = "FILE")]
+    attach: Vec<PathBuf>,
 }
 
 #[derive(Debug, Args)]
@@ -787,8 +1236,14 @@ struct MailReplyArgs {
     #[arg(short = 'f', long, default_value = "inbox")]
     folder: String,
     /// Reply to all recipients
-    #[arg(long, short = 'a')]
+    #[arg(long)]
     all: bool,
+    /// Quote the text read from stdin instead of the full original body
+    #[arg(long, alias = "quote-selection")]
+    quote: bool,
+    /// Show a summary and ask before saving the draft
+    #[arg(long)]
+    confirm: bool,
 }
 
 #[derive(Debug, Args)]
@@ -798,6 +1253,24 @@ struct MailForwardArgs {
     /// Folder containing the message
     #[arg(short = 'f', long, default_value = "inbox")]
     folder: String,
+    /// Show a summary and ask before saving the draft
+    #[arg(long)]
+    confirm: bool,
+}
+
+#[derive(Debug, Args)]
+struct MailResendArgs {
+    /// Message ID to re-send
+    id: String,
+    /// Recipient(s) - can be specified multiple times
+    #[arg(long, short = 't', required = true)]
+    to: Vec<String>,
+    /// CC recipient(s) - can be specified multiple times
+    #[arg(long, short = 'c')]
+    cc: Vec<String>,
+    /// Folder containing the message
+    #[arg(short = 'f', long, default_value = "inbox")]
+    folder: String,
 }
 
 #[derive(Debug, Args)]
@@ -805,6 +1278,9 @@ struct MailMoveArgs {
     /// Message ID(s) to move (space or comma separated). Optional if --query is used.
     #[arg(num_args = 0..)]
     ids: Vec<String>,
+    /// Positions from the last `mail list` output (e.g. "1-5,8")
+    #[arg(long, value_name = "RANGES", conflicts_with_all = ["query", "all_from"])]
+    select: Option<String>,
     /// Target folder (use --to or natural "to" keyword)
     #[arg(short = 't', long = "to")]
     target: Option<String>,
@@ -812,8 +1288,11 @@ struct MailMoveArgs {
     #[arg(short = 'f', long, default_value = "inbox")]
     folder: String,
     /// Search query to select messages (e.g., "from:newsletter", "subject:weekly")
-    #[arg(short = 'q', long)]
+    #[arg(long)]
     query: Option<String>,
+    /// Move every synced message in the source folder from this sender
+    #[arg(long, value_name = "SENDER", conflicts_with = "query")]
+    all_from: Option<String>,
     /// Maximum messages to move when using --query
     #[arg(short = 'n', long, default_value_t = 50)]
     limit: i64,
@@ -831,8 +1310,11 @@ struct MailMoveArgs {
 #[derive(Debug, Args)]
 struct MailDeleteArgs {
     /// Message ID(s) to delete (space or comma separated)
-    #[arg(required = true, num_args = 1..)]
+    #[arg(required_unless_present = "select", num_args = 0..)]
     ids: Vec<String>,
+    /// Positions from the last `mail list` output (e.g. "1-5,8")
+    #[arg(long, value_name = "RANGES")]
+    select: Option<String>,
     /// Folder containing the message(s)
     #[arg(short = 'f', long, default_value = "inbox")]
     folder: String,
@@ -844,6 +1326,22 @@ struct MailDeleteArgs {
     sync: bool,
 }
 
+#[derive(Debug, Args)]
+struct MailUndeleteArgs {
+    /// Message ID(s) to restore (space or comma separated)
+    #[arg(required_unless_present = "select", num_args = 0..)]
+    ids: Vec<String>,
+    /// Positions from the last `mail list` output (e.g. "1-5,8")
+    #[arg(long, value_name = "RANGES")]
+    select: Option<String>,
+    /// Destination folder
+    #[arg(short = 't', long = "to", default_value = "inbox")]
+    target: String,
+    /// Sync restore to server (default: true)
+    #[arg(long, default_value_t = true)]
+    sync: bool,
+}
+
 #[derive(Debug, Args)]
 struct MailMoveOldArgs {
     /// Source folder
@@ -856,7 +1354,7 @@ struct MailMoveOldArgs {
     #[arg(long, default_value_t = 7)]
     days: i64,
     /// Optional filter query (subject/sender contains)
-    #[arg(short = 'q', long)]
+    #[arg(long)]
     query: Option<String>,
     /// Maximum messages to process
     #[arg(short = 'n', long, default_value_t = 500)]
@@ -881,7 +1379,7 @@ struct MailArchiveArgs {
     #[arg(long, default_value_t = 7)]
     days: i64,
     /// Optional filter query (subject/sender contains)
-    #[arg(short = 'q', long)]
+    #[arg(long)]
     query: Option<String>,
     /// Maximum messages to process
     #[arg(short = 'n', long, default_value_t = 500)]
@@ -899,6 +1397,9 @@ struct MailMarkArgs {
     /// Message ID(s) to mark (space or comma separated). Optional when --query/--older-than is used.
     #[arg(num_args = 0..)]
     ids: Vec<String>,
+    /// Positions from the last `mail list` output (e.g. "1-5,8")
+    #[arg(long, value_name = "RANGES")]
+    select: Option<String>,
     /// Folder containing the message(s)
     #[arg(short = 'f', long, default_value = "inbox")]
     folder: String,
@@ -909,7 +1410,7 @@ struct MailMarkArgs {
     #[arg(long, conflicts_with = "read")]
     unread: bool,
     /// Optional query filter (subject/sender contains)
-    #[arg(short = 'q', long)]
+    #[arg(long)]
     query: Option<String>,
     /// Only process messages older than N days
     #[arg(long)]
@@ -922,6 +1423,19 @@ struct MailMarkArgs {
     dry_run: bool,
 }
 
+#[derive(Debug, Args)]
+struct MailFlagArgs {
+    /// Message ID(s) (space or comma separated)
+    #[arg(required_unless_present = "select", num_args = 0..)]
+    ids: Vec<String>,
+    /// Positions from the last `mail list` output (e.g. "1-5,8")
+    #[arg(long, value_name = "RANGES")]
+    select: Option<String>,
+    /// Folder containing the message(s)
+    #[arg(short = 'f', long, default_value = "inbox")]
+    folder: String,
+}
+
 #[derive(Debug, Args)]
 struct MailDraftsArgs {
     /// Maximum number of drafts to list
@@ -933,18 +1447,61 @@ struct MailDraftsArgs {
 struct MailEditArgs {
     /// Draft ID to edit
     id: String,
+    /// Show a summary and ask before saving the draft
+    #[arg(long)]
+    confirm: bool,
+}
+
+#[derive(Debug, Args)]
+struct MailDraftSetArgs {
+    /// Draft ID to change
+    id: String,
+    /// Replace the To recipients (repeatable)
+    #[arg(long, short = 't')]
+    to: Vec<String>,
+    /// Replace the CC recipients (repeatable)
+    #[arg(long, short = 'c')]
+    cc: Vec<String>,
+    /// Replace the BCC recipients (repeatable)
+    #[arg(long)]
+    bcc: Vec<String>,
+    /// Add a To recipient (repeatable)
+    #[arg(long, value_name = "ADDR")]
+    add_to: Vec<String>,
+    /// Add a CC recipient (repeatable)
+    #[arg(long, value_name = "ADDR")]
+    add_cc: Vec<String>,
+    /// Add a BCC recipient (repeatable)
+    #[arg(long, value_name = "ADDR")]
+    add_bcc: Vec<String>,
+    /// Replace the subject
+    #[arg(long)]
+    subject: Option<String>,
 }
 
 #[derive(Debug, Args)]
 struct MailSyncArgs {
     /// Folder to sync (default: all configured folders)
     folder: Option<String>,
-    /// Force full re-sync (ignore sync tokens)
+    /// Force full re-sync (ignore the saved sync cursor)
     #[arg(long)]
     full: bool,
     /// Only sync emails received in the last N days
     #[arg(short = 'l', long = "limit", value_name = "DAYS")]
     limit_days: Option<u32>,
+    /// Refresh the cached server folder list even if it is still fresh
+    /// (implied by --full)
+    #[arg(long)]
+    refresh_folders: bool,
+    /// Also fetch full messages into the local Maildir for offline reading
+    #[arg(long)]
+    bodies: bool,
+    /// Parallel message fetches for --bodies (default: mail.sync_jobs)
+    #[arg(short = 'j', long, value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
+    /// Fail instead of skipping configured folders the server does not have
+    #[arg(long)]
+    strict: bool,
 }
 
 #[derive(Debug, Args)]
@@ -957,6 +1514,10 @@ struct MailAttachmentsArgs {
     /// Download attachment by index
     #[arg(short = 'd', long)]
     download: Option<usize>,
+    /// Download every attachment into <output>/<message id>/, skipping
+    /// files already present with the same size
+    #[arg(long, conflicts_with = "download")]
+    download_all: bool,
     /// Output path (directory or file)
     #[arg(short = 'o', long)]
     output: Option<PathBuf>,
@@ -967,16 +1528,36 @@ struct MailEmptyFolderArgs {
     /// Folder to empty (default: trash)
     #[arg(default_value = "trash")]
     folder: String,
-    /// Skip confirmation prompt
-    #[arg(short = 'y', long)]
-    yes: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum TrashCommand {
+    /// Permanently delete trashed messages older than the retention period
+    Purge(TrashPurgeArgs),
+}
+
+#[derive(Debug, Args)]
+struct TrashPurgeArgs {
+    /// Delete messages older than N days (default: mail.trash_retention_days)
+    #[arg(long, value_name = "DAYS")]
+    older_than: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+struct MailStatsArgs {
+    /// Only report this folder (default: all local folders)
+    #[arg(short = 'f', long)]
+    folder: Option<String>,
 }
 
 #[derive(Debug, Args)]
 struct MailSpamArgs {
     /// Message ID(s) to mark as spam (space or comma separated)
-    #[arg(required = true, num_args = 1..)]
+    #[arg(required_unless_present = "select", num_args = 0..)]
     ids: Vec<String>,
+    /// Positions from the last `mail list` output (e.g. "1-5,8")
+    #[arg(long, value_name = "RANGES")]
+    select: Option<String>,
     /// Mark as NOT spam (move to inbox instead)
     #[arg(long)]
     not_spam: bool,
@@ -1019,6 +1600,8 @@ enum ContactsCommand {
     /// Update an existing contact
     Update(ContactsUpdateArgs),
     Delete(ContactsDeleteArgs),
+    /// Export contacts as vCard 3.0
+    Export(ContactsExportArgs),
 }
 
 #[derive(Debug, Args)]
@@ -1031,8 +1614,15 @@ struct ContactsListArgs {
 
 #[derive(Debug, Args)]
 struct ContactsGetArgs {
+    /// Contact ID
+    #[arg(long, required_unless_present_any = ["email", "name"], conflicts_with_all = ["email", "name"])]
+    id: Option<String>,
+    /// Look the contact up by email address
+    #[arg(long, conflicts_with = "name")]
+    email: Option<String>,
+    /// Look the contact up by display name
     #[arg(long)]
-    id: String,
+    name: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -1046,6 +1636,10 @@ struct ContactsUpdateArgs {
     /// Contact ID to update
     #[arg(long)]
     id: String,
+    /// JSON object of fields to change (`-` reads stdin); flags below
+    /// override its values
+    #[arg(long)]
+    file: Option<PathBuf>,
     /// Display name
     #[arg(long)]
     name: Option<String>,
@@ -1076,6 +1670,23 @@ struct ContactsDeleteArgs {
 }
 
 #[derive(Debug, Args)]
+struct ContactsExportArgs {
+    /// Write vCard 3.0 (the only export format so far)
+    #[arg(long, required = true)]
+    vcf: bool,
+    /// Output file (default: stdout)
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+    /// Maximum contacts to export
+    #[arg(short = 'l', long, default_value_t = 1000)]
+    limit: usize,
+    /// Only export contacts matching this search
+    #[arg(short = 's', long)]
+    search: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[command(disable_version_flag = true)]
 struct FreeCommand {
     #[arg(short = 'w', long, default_value_t = 1)]
     weeks: u8,
@@ -1086,6 +1697,9 @@ struct FreeCommand {
     /// View mode: list, gantt, or compact (default from config)
     #[arg(short = 'V', long = "view", value_enum)]
     view: Option<AgendaView>,
+    /// Print the slots as an iCalendar block (one VEVENT per slot)
+    #[arg(long)]
+    ics: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -1181,7 +1795,7 @@ struct ResourceSetupArgs {
     /// Target group name (e.g., "cars", "rooms"). Prompts if not given.
     group: Option<String>,
     /// Pre-fill the GAL search query
-    #[arg(short = 'q', long)]
+    #[arg(long)]
     query: Option<String>,
 }
 
@@ -1297,9 +1911,6 @@ struct RulesDisableArgs {
 struct RulesDeleteArgs {
     /// Rule ID
     id: String,
-    /// Skip confirmation
-    #[arg(short = 'y', long)]
-    yes: bool,
 }
 
 // === OOF Commands ===
@@ -1488,6 +2099,7 @@ enum PplCommand {
 }
 
 #[derive(Debug, Args)]
+#[command(disable_version_flag = true)]
 struct PplAgendaArgs {
     /// Person alias or email address
     person: String,
@@ -1503,6 +2115,7 @@ struct PplAgendaArgs {
 }
 
 #[derive(Debug, Args)]
+#[command(disable_version_flag = true)]
 struct PplFreeArgs {
     /// Person alias or email address
     person: String,
@@ -1518,6 +2131,7 @@ struct PplFreeArgs {
 }
 
 #[derive(Debug, Args)]
+#[command(disable_version_flag = true)]
 struct PplCommonArgs {
     /// Person aliases or email addresses (2 or more)
     #[arg(required = true, num_args = 2..)]
@@ -1620,7 +2234,11 @@ struct AliasPickArgs {
 enum ConfigCommand {
     Show,
     Path,
+    /// Print every resolved config, state and data path
+    Paths,
     Reset,
+    /// Open the config file in the editor and re-validate it on save
+    Edit,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -1632,7 +2250,7 @@ struct InitCommand {
 #[derive(Debug, Subcommand)]
 enum ServiceCommand {
     /// Start the Python service (background)
-    Start,
+    Start(ServiceStartArgs),
     /// Stop the Python service
     Stop,
     /// Restart the Python service
@@ -1641,6 +2259,45 @@ enum ServiceCommand {
     Status,
 }
 
+#[derive(Debug, Args)]
+struct ServiceStartArgs {
+    /// Run attached to the terminal with output streamed, without a PID file
+    #[arg(long)]
+    foreground: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum IdCommand {
+    /// Show the pool size and how many IDs are allocated
+    Stats,
+    /// Print the remote (server) ID a short ID maps to
+    Resolve(IdResolveArgs),
+    /// Print the short ID assigned to a remote ID
+    Reverse(IdReverseArgs),
+}
+
+#[derive(Debug, Args)]
+struct IdResolveArgs {
+    /// Short ID (e.g. cold-lamp)
+    id: String,
+}
+
+#[derive(Debug, Args)]
+struct IdReverseArgs {
+    /// Remote (server) message ID
+    remote_id: String,
+}
+
+#[derive(Debug, Subcommand)]
+enum DbCommand {
+    /// Drop rows for deleted messages, then VACUUM and ANALYZE
+    #[command(alias = "vacuum")]
+    Maintain,
+    /// Remove extra copies of messages stored more than once in a folder
+    /// (use --dry-run to only list them)
+    Dedup,
+}
+
 #[derive(Debug, Clone)]
 struct RuntimeContext {
     common: CommonOpts,
@@ -1649,11 +2306,18 @@ struct RuntimeContext {
 }
 
 impl RuntimeContext {
-    fn new(common: CommonOpts) -> Result<Self> {
+    fn new(mut common: CommonOpts) -> Result<Self> {
+        // --format json/yaml behave exactly like the --json/--yaml flags
+        match common.format {
+            Some(OutputFormat::Json) => common.json = true,
+            Some(OutputFormat::Yaml) => common.yaml = true,
+            _ => {}
+        }
         let paths = AppPaths::discover(common.config.clone()).map_err(|e| anyhow!("{e}"))?;
         AppConfig::ensure_default(&paths.global_config).map_err(|e| anyhow!("{e}"))?;
-        let config =
+        let mut config =
             AppConfig::load(&paths, common.account.as_deref()).map_err(|e| anyhow!("{e}"))?;
+        apply_timezone_override(&mut config, common.tz.as_deref())?;
         Ok(Self {
             common,
             paths,
@@ -1672,19 +2336,11 @@ impl RuntimeContext {
 
         builder.filter_level(self.effective_log_level());
 
-        let force_color = matches!(self.common.color, ColorOption::Always)
-            || env::var_os("FORCE_COLOR").is_some();
-        let disable_color = self.common.no_color
-            || matches!(self.common.color, ColorOption::Never)
-            || env::var_os("NO_COLOR").is_some();
-
-        if disable_color {
-            builder.write_style(WriteStyle::Never);
-        } else if force_color {
-            builder.write_style(WriteStyle::Always);
-        } else {
-            builder.write_style(WriteStyle::Auto);
-        }
+        builder.write_style(match self.common.color_override() {
+            Some(false) => WriteStyle::Never,
+            Some(true) => WriteStyle::Always,
+            None => WriteStyle::Auto,
+        });
 
         if self.common.diagnostics {
             builder.format_timestamp_millis();
@@ -1714,9 +2370,53 @@ impl RuntimeContext {
         }
     }
 
+    /// Whether core progress updates should be rendered on stderr.
+    fn show_progress(&self) -> bool {
+        !self.common.quiet && !self.common.no_progress && io::stderr().is_terminal()
+    }
+
+    /// Progress callback for a core operation: `report` when progress is
+    /// shown, otherwise none.
+    fn progress<'a>(
+        &self,
+        report: &'a mut dyn FnMut(Progress),
+    ) -> h8_core::types::ProgressCallback<'a> {
+        self.show_progress().then_some(report)
+    }
+
+    /// Whether offline mode is enabled via `--offline` or the `offline` config key.
+    fn offline(&self) -> bool {
+        self.common.offline || self.config.offline
+    }
+
     fn service_client(&self) -> Result<ServiceClient> {
         let timeout = self.common.timeout.map(Duration::from_secs);
-        ServiceClient::new(&self.config.service_url, timeout).map_err(|e| anyhow!("{e}"))
+        ServiceClient::new(&self.config.service_url, timeout)
+            .map(|client| client.with_offline(self.offline()))
+            .map_err(|e| anyhow!("{e}"))
+    }
+}
+
+/// Use `tz` (from `--tz`) instead of the configured timezone.
+fn apply_timezone_override(config: &mut AppConfig, tz: Option<&str>) -> Result<()> {
+    let Some(name) = tz else {
+        return Ok(());
+    };
+    let zone = name.parse::<chrono_tz::Tz>().map_err(|_| {
+        anyhow!("unknown timezone '{name}' (use an IANA name such as America/New_York)")
+    })?;
+    config.timezone = zone.name().to_string();
+    Ok(())
+}
+
+/// Render a core progress update as a single updating stderr line.
+fn print_progress(progress: Progress) {
+    eprint!(
+        "\r{} ({}/{})",
+        progress.message, progress.current, progress.total
+    );
+    if progress.current >= progress.total {
+        eprintln!();
     }
 }
 
@@ -1730,21 +2430,64 @@ fn strip_global_flags(ctx: &RuntimeContext, words: &[String]) -> (RuntimeContext
     let mut common = ctx.common.clone();
     let mut filtered = Vec::with_capacity(words.len());
 
-    for word in words {
+    let mut iter = words.iter().peekable();
+    while let Some(word) = iter.next() {
         match word.as_str() {
             "--json" => common.json = true,
             "--yaml" => common.yaml = true,
             "--quiet" | "-q" => common.quiet = true,
             "--verbose" | "-v" => common.verbose += 1,
             "--debug" => common.debug = true,
+            "--offline" => common.offline = true,
+            "--tz"
+                if iter
+                    .peek()
+                    .is_some_and(|v| v.parse::<chrono_tz::Tz>().is_ok()) =>
+            {
+                common.tz = iter.next().cloned();
+            }
+            "--format"
+                if iter
+                    .peek()
+                    .is_some_and(|v| OutputFormat::from_str(v, true).is_ok()) =>
+            {
+                common.format = iter
+                    .next()
+                    .and_then(|v| OutputFormat::from_str(v, true).ok());
+            }
+            w if w
+                .strip_prefix("--format=")
+                .is_some_and(|v| OutputFormat::from_str(v, true).is_ok()) =>
+            {
+                common.format = OutputFormat::from_str(&w["--format=".len()..], true).ok();
+            }
+            "--output-template" if iter.peek().is_some() => {
+                common.output_template = iter.next().cloned();
+            }
+            w if w.starts_with("--output-template=") => {
+                common.output_template = Some(w["--output-template=".len()..].to_string());
+            }
             _ => filtered.push(word.clone()),
         }
     }
+    match common.format {
+        Some(OutputFormat::Json) => common.json = true,
+        Some(OutputFormat::Yaml) => common.yaml = true,
+        _ => {}
+    }
 
+    let mut config = ctx.config.clone();
+    if let Some(zone) = common
+        .tz
+        .as_deref()
+        .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok())
+    {
+        config.timezone = zone.name().to_string();
+    }
     let new_ctx = RuntimeContext {
         common,
         paths: ctx.paths.clone(),
-        config: ctx.config.clone(),
+        config,
     };
     (new_ctx, filtered)
 }
@@ -1770,7 +2513,11 @@ fn handle_calendar(ctx: &RuntimeContext, cmd: CalendarCommand) -> Result<()> {
             emit_output(&ctx.common, &events_with_ids)?;
         }
         CalendarCommand::Create(args) => {
-            let payload = read_json_payload(args.file.as_ref())?;
+            let payload = if args.interactive {
+                event_form_payload(&prompt_event_form()?)?
+            } else {
+                read_json_payload(args.file.as_ref())?
+            };
             let event = client
                 .calendar_create(&account, payload)
                 .map_err(|e| anyhow!("{e}"))?;
@@ -2301,7 +3048,7 @@ fn sync_calendar_events(
     // Ensure ID pool is seeded
     let stats = id_gen.stats().map_err(|e| anyhow!("{e}"))?;
     if stats.total() == 0 {
-        let words = h8_core::id::WordLists::embedded();
+        let words = ctx.config.ids.word_lists().map_err(|e| anyhow!("{e}"))?;
         id_gen.init_pool(&words).map_err(|e| anyhow!("{e}"))?;
     }
 
@@ -2438,7 +3185,7 @@ fn handle_calendar_sync(
     // Ensure ID pool is seeded
     let stats = id_gen.stats().map_err(|e| anyhow!("{e}"))?;
     if stats.total() == 0 {
-        let words = h8_core::id::WordLists::embedded();
+        let words = ctx.config.ids.word_lists().map_err(|e| anyhow!("{e}"))?;
         id_gen.init_pool(&words).map_err(|e| anyhow!("{e}"))?;
     }
 
@@ -3451,21 +4198,33 @@ fn handle_mail(ctx: &RuntimeContext, cmd: MailCommand) -> Result<()> {
         MailCommand::Search(args) => handle_mail_search(ctx, &client, &account, args),
         MailCommand::Get(args) => handle_mail_get(ctx, &client, &account, args),
         MailCommand::Read(args) => handle_mail_read(ctx, &client, &account, args),
+        MailCommand::Thread(args) => handle_mail_thread(ctx, &account, args),
+        MailCommand::Folders(args) => handle_mail_folders(ctx, &client, &account, args),
         MailCommand::Fetch(args) => handle_mail_fetch(ctx, &client, &account, args),
         MailCommand::Send(args) => handle_mail_send(ctx, &client, &account, args),
         MailCommand::Compose(args) => handle_mail_compose(ctx, &account, args),
+        MailCommand::ComposeLint(args) => handle_mail_compose_lint(ctx, &account, args),
         MailCommand::Reply(args) => handle_mail_reply(ctx, &client, &account, args),
         MailCommand::Forward(args) => handle_mail_forward(ctx, &client, &account, args),
+        MailCommand::Resend(args) => handle_mail_resend(ctx, &client, &account, args),
         MailCommand::Move(args) => handle_mail_move(ctx, &account, args),
         MailCommand::MoveOld(args) => handle_mail_move_old(ctx, &client, &account, args),
         MailCommand::Archive(args) => handle_mail_archive(ctx, &client, &account, args),
         MailCommand::Delete(args) => handle_mail_delete(ctx, &account, args),
+        MailCommand::Undelete(args) => handle_mail_undelete(ctx, &account, args),
         MailCommand::Mark(args) => handle_mail_mark(ctx, &account, args),
+        MailCommand::Flag(args) => handle_mail_flag(ctx, &client, &account, args, true),
+        MailCommand::Unflag(args) => handle_mail_flag(ctx, &client, &account, args, false),
         MailCommand::Drafts(args) => handle_mail_drafts(ctx, &account, args),
         MailCommand::Edit(args) => handle_mail_edit(ctx, &account, args),
+        MailCommand::DraftSet(args) => handle_mail_draft_set(ctx, &account, args),
         MailCommand::Sync(args) => handle_mail_sync(ctx, &client, &account, args),
         MailCommand::Attachments(args) => handle_mail_attachments(ctx, &client, &account, args),
         MailCommand::EmptyFolder(args) => handle_mail_empty_folder(ctx, &client, &account, args),
+        MailCommand::Trash {
+            command: TrashCommand::Purge(args),
+        } => handle_mail_trash_purge(ctx, &account, args),
+        MailCommand::Stats(args) => handle_mail_stats(ctx, &account, args),
         MailCommand::Spam(args) => handle_mail_spam(ctx, &client, &account, args),
         MailCommand::Unsubscribe(args) => handle_mail_unsubscribe(ctx, &client, &account, args),
     }
@@ -3477,10 +4236,21 @@ fn handle_mail_list(
     account: &str,
     args: MailListArgs,
 ) -> Result<()> {
-    // Parse date filter if provided
-    let date_filter = if !args.when.is_empty() {
-        let when_text = args.when.join(" ");
-        parse_date_expr(&when_text)
+    if args.count_only {
+        let mail_dir = get_mail_dir(ctx, account)?;
+        let count = count_local_messages(&mail_dir, &args)?;
+        if ctx.common.json || ctx.common.yaml {
+            emit_output(&ctx.common, &json!({ "count": count }))?;
+        } else {
+            println!("{}", count);
+        }
+        return Ok(());
+    }
+
+    // Parse date filter if provided
+    let date_filter = if !args.when.is_empty() {
+        let when_text = args.when.join(" ");
+        parse_date_expr(&when_text)
     } else {
         None
     };
@@ -3500,84 +4270,167 @@ fn handle_mail_list(
     if db_path.exists() {
         let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
         let mail_dir = get_mail_dir(ctx, account)?;
+        let date = date_filter.map(|(date, _)| date);
+        let mut output = list_local_messages(&db, &mail_dir, &args, date)?;
+        args.sort_order().apply(&mut output, "date");
+        if let Some(group_by) = args.group_by {
+            let groups = group_by.apply(output, "date", args.sort_order());
+            let rows: Vec<Value> = groups.iter().flat_map(|(_, m)| m.iter().cloned()).collect();
+            LastListing::from_rows(account, &args.folder, &rows).save(&ctx.paths.state_dir)?;
+            emit_grouped_output(&ctx.common, &groups)?;
+            return Ok(());
+        }
+        LastListing::from_rows(account, &args.folder, &output).save(&ctx.paths.state_dir)?;
+        emit_output(&ctx.common, &output)?;
+    } else {
+        // Server rows carry no local IDs, so a previous listing must not be
+        // left for --select to resolve against
+        LastListing::clear(&ctx.paths.state_dir)?;
+        // Fall back to server (date filtering not supported for server-side)
+        if date_filter.is_some() {
+            return Err(anyhow!(
+                "Date filtering requires synced messages. Run 'h8 mail sync' first."
+            ));
+        }
+        if args.thread {
+            return Err(anyhow!(
+                "Thread grouping requires synced messages. Run 'h8 mail sync' first."
+            ));
+        }
+        let mut messages = client
+            .mail_list(account, &args.folder, args.limit, args.unread)
+            .map_err(|e| anyhow!("{e}"))?;
+        if let Some(arr) = messages.as_array_mut() {
+            args.sort_order().apply(arr, "datetime_received");
+        }
+        if let (Some(group_by), Value::Array(arr)) = (args.group_by, messages.clone()) {
+            let groups = group_by.apply(arr, "datetime_received", args.sort_order());
+            return emit_grouped_output(&ctx.common, &groups);
+        }
+        emit_output(&ctx.common, &messages)?;
+    }
 
-        // Get messages from database, already sorted by received_at DESC
-        // Request more than limit to account for filtering (more if date filtering)
-        let fetch_limit = if date_filter.is_some() {
-            args.limit * 10 // Fetch more when filtering by date
-        } else {
-            args.limit * 2
-        };
-        let db_messages = db
-            .list_messages(&args.folder, fetch_limit)
+    Ok(())
+}
+
+/// Number of messages in the listed folder for `mail list --count-only`.
+fn count_local_messages(mail_dir: &Maildir, args: &MailListArgs) -> Result<usize> {
+    let stats = mail_dir
+        .folder_stats(&args.folder)
+        .map_err(|e| anyhow!("{e}"))?;
+    Ok(if args.unread {
+        stats.unread
+    } else {
+        stats.total
+    })
+}
+
+/// Build `mail list` rows from the sync database, newest first, applying the
+/// unread, date and `--thread` options.
+fn list_local_messages(
+    db: &Database,
+    mail_dir: &Maildir,
+    args: &MailListArgs,
+    date_filter: Option<NaiveDate>,
+) -> Result<Vec<Value>> {
+    // Get messages from database, already sorted by received_at DESC
+    // Request more than limit to account for filtering (more if date filtering)
+    let mut fetch_limit = if date_filter.is_some() {
+        args.limit * 10 // Fetch more when filtering by date
+    } else {
+        args.limit * 2
+    };
+
+    // With --thread, only the latest message of each conversation is
+    // listed, so the whole folder has to be scanned
+    let thread_counts: Option<std::collections::HashMap<String, usize>> = if args.thread {
+        let messages = db
+            .list_thread_messages(Some(&args.folder))
             .map_err(|e| anyhow!("{e}"))?;
+        fetch_limit = fetch_limit.max(messages.len());
+        Some(
+            latest_per_thread(messages)
+                .into_iter()
+                .map(|(latest, count)| (latest.local_id, count))
+                .collect(),
+        )
+    } else {
+        None
+    };
 
-        let mut output: Vec<serde_json::Value> = Vec::new();
-        for db_msg in db_messages {
-            // Filter unread if requested
-            if args.unread && db_msg.is_read {
-                continue;
-            }
+    let db_messages = db
+        .list_messages(&args.folder, fetch_limit)
+        .map_err(|e| anyhow!("{e}"))?;
 
-            // Filter by date if requested
-            if let Some((filter_date, _)) = date_filter {
-                if let Some(ref received_at) = db_msg.received_at {
-                    // Parse the received_at timestamp and compare dates
-                    if let Ok(msg_dt) = DateTime::parse_from_rfc3339(received_at) {
-                        let msg_date = msg_dt.date_naive();
-                        if msg_date != filter_date {
-                            continue;
-                        }
-                    } else if let Ok(msg_dt) = NaiveDateTime::parse_from_str(received_at, "%Y-%m-%dT%H:%M:%S") {
-                        if msg_dt.date() != filter_date {
-                            continue;
-                        }
-                    } else {
-                        // Can't parse date, skip
+    let mut output: Vec<serde_json::Value> = Vec::new();
+    for db_msg in db_messages {
+        let thread_count = match thread_counts {
+            Some(ref counts) => match counts.get(&db_msg.local_id) {
+                Some(count) => Some(*count),
+                None => continue, // older message of a listed thread
+            },
+            None => None,
+        };
+
+        // Filter unread if requested
+        if args.unread && db_msg.is_read {
+            continue;
+        }
+
+        // Filter by date if requested
+        if let Some(filter_date) = date_filter {
+            if let Some(ref received_at) = db_msg.received_at {
+                // Parse the received_at timestamp and compare dates
+                if let Ok(msg_dt) = DateTime::parse_from_rfc3339(received_at) {
+                    let msg_date = msg_dt.date_naive();
+                    if msg_date != filter_date {
+                        continue;
+                    }
+                } else if let Ok(msg_dt) =
+                    NaiveDateTime::parse_from_str(received_at, "%Y-%m-%dT%H:%M:%S")
+                {
+                    if msg_dt.date() != filter_date {
                         continue;
                     }
                 } else {
-                    // No date, skip when filtering
+                    // Can't parse date, skip
                     continue;
                 }
+            } else {
+                // No date, skip when filtering
+                continue;
             }
+        }
 
-            // Get flags from Maildir if available
-            let (is_read, is_flagged) =
-                if let Ok(Some(maildir_msg)) = mail_dir.get(&args.folder, &db_msg.local_id) {
-                    (maildir_msg.flags.seen, maildir_msg.flags.flagged)
-                } else {
-                    (db_msg.is_read, false)
-                };
+        // Get flags from Maildir if available
+        let (is_read, is_flagged) =
+            if let Ok(Some(maildir_msg)) = mail_dir.get(&args.folder, &db_msg.local_id) {
+                (maildir_msg.flags.seen, maildir_msg.flags.flagged)
+            } else {
+                (db_msg.is_read, false)
+            };
 
-            output.push(serde_json::json!({
-                "id": db_msg.local_id,
-                "subject": db_msg.subject.unwrap_or_else(|| "(no subject)".to_string()),
-                "from": db_msg.from_addr.unwrap_or_else(|| "unknown".to_string()),
-                "date": db_msg.received_at.unwrap_or_default(),
-                "is_read": is_read,
-                "is_flagged": is_flagged,
-                "folder": db_msg.folder,
-            }));
-
-            if output.len() >= args.limit {
-                break;
-            }
+        let mut row = serde_json::json!({
+            "id": db_msg.local_id,
+            "subject": db_msg.subject.unwrap_or_else(|| "(no subject)".to_string()),
+            "from": db_msg.from_addr.unwrap_or_else(|| "unknown".to_string()),
+            "date": db_msg.received_at.unwrap_or_default(),
+            "is_read": is_read,
+            "is_flagged": is_flagged,
+            "folder": db_msg.folder,
+            "size_bytes": db_msg.size_bytes,
+            "attachments": db_msg.attachments,
+        });
+        if let Some(count) = thread_count {
+            row["thread_count"] = serde_json::json!(count);
         }
+        output.push(row);
 
-        emit_output(&ctx.common, &output)?;
-    } else {
-        // Fall back to server (date filtering not supported for server-side)
-        if date_filter.is_some() {
-            return Err(anyhow!("Date filtering requires synced messages. Run 'h8 mail sync' first."));
+        if output.len() >= args.limit {
+            break;
         }
-        let messages = client
-            .mail_list(account, &args.folder, args.limit, args.unread)
-            .map_err(|e| anyhow!("{e}"))?;
-        emit_output(&ctx.common, &messages)?;
     }
-
-    Ok(())
+    Ok(output)
 }
 
 fn handle_mail_search(
@@ -3599,6 +4452,16 @@ fn handle_mail_search(
         )
     };
 
+    if args.local || ctx.offline() {
+        return search_mail_locally(
+            ctx,
+            account,
+            &args,
+            from_date.as_deref(),
+            to_date.as_deref(),
+        );
+    }
+
     let messages = client
         .mail_search(
             account,
@@ -3656,6 +4519,65 @@ fn handle_mail_search(
     Ok(())
 }
 
+/// Search synced messages through the database full-text index.
+fn search_mail_locally(
+    ctx: &RuntimeContext,
+    account: &str,
+    args: &MailSearchArgs,
+    from_date: Option<&str>,
+    to_date: Option<&str>,
+) -> Result<()> {
+    let db_path = ctx.paths.sync_db_path(account);
+    if !db_path.exists() {
+        return Err(anyhow!("No synced messages. Run 'h8 mail sync' first."));
+    }
+    let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
+
+    let limit = args.limit.max(0) as usize;
+    // Fetch extra rows when date filters will drop some of them
+    let fetch_limit = if from_date.is_some() || to_date.is_some() {
+        limit * 10
+    } else {
+        limit
+    };
+    let messages = db
+        .search_messages(&args.query, Some(&args.folder), fetch_limit)
+        .map_err(|e| anyhow!("{e}"))?;
+
+    let output: Vec<Value> = messages
+        .into_iter()
+        .filter(|msg| {
+            let day = msg
+                .received_at
+                .as_deref()
+                .and_then(|d| d.get(..10))
+                .unwrap_or("");
+            from_date.is_none_or(|from| day >= from) && to_date.is_none_or(|to| day <= to)
+        })
+        .take(limit)
+        .map(|msg| {
+            json!({
+                "id": msg.local_id,
+                "subject": msg.subject.unwrap_or_else(|| "(no subject)".to_string()),
+                "from": msg.from_addr.unwrap_or_else(|| "unknown".to_string()),
+                "date": msg.received_at.unwrap_or_default(),
+                "is_read": msg.is_read,
+                "folder": msg.folder,
+            })
+        })
+        .collect();
+
+    if !ctx.common.json && !ctx.common.yaml {
+        println!(
+            "Found {} message(s) matching \"{}\" in {} (local):\n",
+            output.len(),
+            args.query,
+            args.folder
+        );
+    }
+    emit_output(&ctx.common, &json!(output))
+}
+
 fn handle_mail_get(
     ctx: &RuntimeContext,
     client: &ServiceClient,
@@ -3672,6 +4594,168 @@ fn handle_mail_get(
     Ok(())
 }
 
+/// Show the conversation containing a message, rebuilt from the
+/// In-Reply-To/References headers stored by `mail sync`.
+fn handle_mail_thread(ctx: &RuntimeContext, account: &str, args: MailThreadArgs) -> Result<()> {
+    let db_path = ctx.paths.sync_db_path(account);
+    let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
+    let messages = db.list_thread_messages(None).map_err(|e| anyhow!("{e}"))?;
+    let tree = build_thread_tree(messages)
+        .into_iter()
+        .find(|root| root.contains(&args.id))
+        .ok_or_else(|| {
+            anyhow!(
+                "message not found locally: {} (run `h8 mail sync`)",
+                args.id
+            )
+        })?;
+
+    let entries = tree.flatten();
+    if !ctx.common.json && !ctx.common.yaml {
+        for line in thread_lines(&entries, &args.id) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    let output: Vec<Value> = entries
+        .iter()
+        .map(|(depth, msg)| {
+            json!({
+                "id": msg.local_id,
+                "depth": depth,
+                "subject": msg.subject,
+                "from": msg.from_addr,
+                "date": msg.received_at,
+                "folder": msg.folder,
+                "message_id": msg.message_id,
+                "in_reply_to": msg.in_reply_to,
+            })
+        })
+        .collect();
+    emit_output(&ctx.common, &json!(output))
+}
+
+/// One line per thread message, indented by reply depth, with `*` marking
+/// the message `id`.
+fn thread_lines(entries: &[(usize, &h8_core::types::ThreadMessage)], id: &str) -> Vec<String> {
+    entries
+        .iter()
+        .map(|(depth, msg)| {
+            let marker = if msg.local_id == id { "*" } else { " " };
+            format!(
+                "{}{}{:<16} {:<12} {:<30} {}",
+                marker,
+                "  ".repeat(*depth),
+                msg.received_at
+                    .as_deref()
+                    .map(format_date_human)
+                    .unwrap_or_default(),
+                msg.local_id,
+                msg.from_addr.as_deref().unwrap_or("unknown"),
+                msg.subject.as_deref().unwrap_or("(no subject)"),
+            )
+        })
+        .collect()
+}
+
+/// Split configured sync folders into those on the server and a
+/// description of each missing one, with the closest server folder name as
+/// a suggestion. An empty `known` list (lookup failed) accepts everything.
+fn check_sync_folders(configured: &[String], known: &[Folder]) -> (Vec<String>, Vec<String>) {
+    if known.is_empty() {
+        return (configured.to_vec(), Vec::new());
+    }
+    let mut present = Vec::new();
+    let mut missing = Vec::new();
+    for name in configured {
+        if known.iter().any(|f| f.name.eq_ignore_ascii_case(name)) {
+            present.push(name.clone());
+            continue;
+        }
+        let mut problem = format!("folder '{}' not found on server", name);
+        if let Some(close) = closest_match(name, known.iter().map(|f| f.name.as_str())) {
+            problem.push_str(&format!(" (did you mean '{}'?)", close));
+        }
+        missing.push(problem);
+    }
+    (present, missing)
+}
+
+/// The candidate closest to `name` by case-insensitive edit distance, if
+/// it is close enough to be a likely typo.
+fn closest_match<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(2);
+    candidates
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, by character.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Server folder list, served from the sync database while it is younger
+/// than `mail.folder_cache_ttl_secs`. Offline, any cached list is used.
+fn load_server_folders(
+    ctx: &RuntimeContext,
+    client: &ServiceClient,
+    db: &Database,
+    account: &str,
+    force: bool,
+) -> Result<Vec<Folder>> {
+    let ttl = if ctx.offline() {
+        Duration::MAX
+    } else {
+        Duration::from_secs(ctx.config.mail.folder_cache_ttl_secs)
+    };
+    db.folders_or_fetch(ttl, force && !ctx.offline(), || {
+        client.mail_folders(account)
+    })
+    .map_err(|e| anyhow!("{e}"))
+}
+
+fn handle_mail_folders(
+    ctx: &RuntimeContext,
+    client: &ServiceClient,
+    account: &str,
+    args: MailFoldersArgs,
+) -> Result<()> {
+    let db = Database::open(&ctx.paths.sync_db_path(account)).map_err(|e| anyhow!("{e}"))?;
+    let folders = load_server_folders(ctx, client, &db, account, args.refresh)?;
+
+    if !ctx.common.json && !ctx.common.yaml && ctx.common.format.is_none() {
+        let width = folders.iter().map(|f| f.name.len()).max().unwrap_or(0);
+        for folder in &folders {
+            println!(
+                "  {:<width$}  {} unread / {} total",
+                folder.name, folder.unread, folder.total
+            );
+        }
+        return Ok(());
+    }
+    emit_output(&ctx.common, &folders)
+}
+
 fn handle_mail_read(
     ctx: &RuntimeContext,
     client: &ServiceClient,
@@ -3713,6 +4797,7 @@ fn handle_mail_read(
         None => {
             // Message not in Maildir - try fetching from server
             let remote_id = remote_id_resolved
+                .clone()
                 .or_else(|| {
                     // Last resort: try resolve_mail_id
                     let resolved = resolve_mail_id(ctx, account, &args.id);
@@ -3720,7 +4805,7 @@ fn handle_mail_read(
                 })
                 .ok_or_else(|| anyhow!("message not found: {}", args.id))?;
 
-            if !ctx.common.quiet {
+            if !ctx.common.quiet && !client.is_offline() {
                 eprintln!("Fetching message from server...");
             }
 
@@ -3735,49 +4820,24 @@ fn handle_mail_read(
                 ));
             }
 
-            // Build RFC822-ish content from server response
-            let subject = server_msg.get("subject").and_then(|v| v.as_str()).unwrap_or("(no subject)");
-            let from = server_msg.get("from").and_then(|v| v.as_str()).unwrap_or("unknown");
-            let to_list: Vec<&str> = server_msg
-                .get("to")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
-                .unwrap_or_default();
-            let cc_list: Vec<&str> = server_msg
-                .get("cc")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
-                .unwrap_or_default();
-            let date = server_msg.get("datetime_received").and_then(|v| v.as_str()).unwrap_or("");
-            let body = server_msg.get("body").and_then(|v| v.as_str()).unwrap_or("");
-            let body_type = server_msg.get("body_type").and_then(|v| v.as_str()).unwrap_or("text");
-
-            let mut content = format!("Subject: {}\nFrom: {}\nDate: {}\n", subject, from, date);
-            if !to_list.is_empty() {
-                content.push_str(&format!("To: {}\n", to_list.join(", ")));
-            }
-            if !cc_list.is_empty() {
-                content.push_str(&format!("Cc: {}\n", cc_list.join(", ")));
-            }
-            if body_type == "html" {
-                content.push_str("Content-Type: text/html; charset=utf-8\n");
-            } else {
-                content.push_str("Content-Type: text/plain; charset=utf-8\n");
-            }
-            content.push('\n');
-            content.push_str(body);
-
             // Store in Maildir for future reads
-            let is_read = server_msg.get("is_read").and_then(|v| v.as_bool()).unwrap_or(false);
-            let mut flags = h8_core::maildir::MessageFlags::default();
-            if is_read {
-                flags.mark_read();
-            }
+            let (content, flags, truncated) =
+                server_message_content(&server_msg, ctx.config.mail.sync.max_body_bytes);
+            let body = server_msg.get("body").and_then(|v| v.as_str()).unwrap_or("");
 
             mail_dir
                 .store_with_id(&args.folder, content.as_bytes(), &flags, &message_id)
                 .map_err(|e| anyhow!("failed to store message locally: {e}"))?;
 
+            // Make the fetched body searchable with `mail search --local`
+            if db_path.exists()
+                && let Ok(db) = Database::open(&db_path)
+            {
+                let _ = db.index_message(&message_id, Some(&convert_body_to_text(body)));
+                let _ = db.set_message_size(&message_id, content.len() as u64);
+                let _ = db.set_body_truncated(&message_id, truncated);
+            }
+
             mail_dir
                 .get(&args.folder, &message_id)
                 .map_err(|e| anyhow!("{e}"))?
@@ -3785,41 +4845,64 @@ fn handle_mail_read(
         }
     };
 
-    let raw_content = msg.read_content().map_err(|e| anyhow!("{e}"))?;
+    let mut raw_content = msg.read_content().map_err(|e| anyhow!("{e}"))?;
+
+    let truncated = stored_body_truncated(ctx, account, &message_id);
+    if truncated && args.full {
+        let remote_id =
+            remote_id_resolved.unwrap_or_else(|| resolve_mail_id(ctx, account, &args.id));
+        raw_content = fetch_full_content(client, account, &args.folder, &remote_id)?;
+    } else if truncated && !ctx.common.quiet {
+        eprintln!("Message body was truncated when stored; use --full to fetch all of it.");
+    }
 
-    // Parse headers and body
+    // Parse headers and body, undoing the transfer encoding (unless --raw
+    // is specified)
     let (headers, body) = parse_email_content(&raw_content);
+    let body = if args.raw {
+        body.into()
+    } else {
+        h8_core::compose::decode_transfer_encoding(headers, body)
+    };
 
     // Convert HTML to plain text if needed (unless --raw is specified)
-    let display_body = if args.raw {
-        body.to_string()
+    let width = ctx.config.mail.reader.effective_wrap_width(terminal_width);
+    let display_body = read_display_body(headers, &body, args.raw, args.html, width);
+    let display_body = if args.plain {
+        h8_core::compose::plain_text(&display_body, &ctx.config.mail.compose.quote_style)
     } else {
-        convert_body_to_text(body)
+        display_body
     };
 
     // Reconstruct the display content
     let content = format!("{}\n{}", headers, display_body);
 
-    if args.raw || !io::stdout().is_terminal() {
-        println!("{}", content);
+    let pager = if args.raw || !io::stdout().is_terminal() {
+        None
     } else {
-        // Use pager
-        let pager = ctx.config.mail.pager.clone();
-        let pager_parts: Vec<&str> = pager.split_whitespace().collect();
-        let (pager_cmd, pager_args) = pager_parts
-            .split_first()
-            .ok_or_else(|| anyhow!("invalid pager command"))?;
+        h8_core::select_pager(
+            None,
+            ctx.config.mail.pager.as_deref(),
+            &ctx.config.mail.pagers,
+            |k| env::var(k).ok(),
+            |program| which::which(program).is_ok(),
+        )
+    };
 
+    if let Some((pager_cmd, pager_args)) = pager.as_ref().and_then(|p| p.split_first()) {
         let mut child = ProcCommand::new(pager_cmd)
-            .args(pager_args.iter())
+            .args(pager_args)
             .stdin(Stdio::piped())
             .spawn()
-            .with_context(|| format!("starting pager: {}", pager))?;
+            .with_context(|| format!("starting pager: {pager_cmd}"))?;
 
         if let Some(mut stdin) = child.stdin.take() {
             stdin.write_all(content.as_bytes())?;
         }
         child.wait()?;
+    } else {
+        // No pager available (or not a terminal): print directly
+        println!("{}", content);
     }
 
     // Mark as read (unless already read)
@@ -3829,6 +4912,10 @@ fn handle_mail_read(
         mail_dir
             .update_flags(&args.folder, &message_id, &new_flags)
             .map_err(|e| anyhow!("{e}"))?;
+        // Queue the change for the next sync to push to the server
+        if let Ok(db) = Database::open(&ctx.paths.sync_db_path(account)) {
+            let _ = db.update_message_flags(&message_id, Some(true), None, true);
+        }
     }
 
     Ok(())
@@ -3849,47 +4936,141 @@ fn parse_email_content(content: &str) -> (&str, &str) {
     }
 }
 
-/// Convert email body to plain text, handling HTML if present.
-fn convert_body_to_text(body: &str) -> String {
-    let trimmed = body.trim();
+/// Terminal width for wrapping, default to 80.
+fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80)
+}
 
-    // Check if body looks like HTML
-    if trimmed.starts_with("<!DOCTYPE")
+/// Check if a body looks like an HTML document.
+fn looks_like_html(body: &str) -> bool {
+    let trimmed = body.trim();
+    trimmed.starts_with("<!DOCTYPE")
         || trimmed.starts_with("<html")
         || trimmed.starts_with("<HTML")
         || (trimmed.contains("<body") || trimmed.contains("<BODY"))
-    {
-        // Get terminal width for wrapping, default to 80
-        let width = terminal_size::terminal_size()
-            .map(|(w, _)| w.0 as usize)
-            .unwrap_or(80);
+}
 
-        h8_core::html_to_text(body, width)
+/// Check if the headers declare a `text/html` body.
+fn declares_html(headers: &str) -> bool {
+    headers.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("content-type:") && line.contains("text/html")
+    })
+}
+
+/// Convert email body to plain text, handling HTML if present.
+fn convert_body_to_text(body: &str) -> String {
+    if looks_like_html(body) {
+        h8_core::html_to_text(body, terminal_width())
     } else {
         // Already plain text
         body.to_string()
     }
 }
 
+/// Body text shown by `mail read`. HTML bodies (forced with `--html`,
+/// declared by Content-Type, or detected) are rendered as text wrapped at
+/// `width`; `--raw` and plain bodies are passed through unchanged.
+fn read_display_body(headers: &str, body: &str, raw: bool, html: bool, width: usize) -> String {
+    if raw {
+        return body.to_string();
+    }
+    if html || declares_html(headers) || looks_like_html(body) {
+        h8_core::html_to_text(body, width)
+    } else {
+        body.to_string()
+    }
+}
+
 fn handle_mail_fetch(
     ctx: &RuntimeContext,
     client: &ServiceClient,
     account: &str,
     args: MailFetchArgs,
 ) -> Result<()> {
+    let format = fetch_format(&args.format, args.gzip)?;
+    if ctx.offline()
+        && let h8_core::types::FetchFormat::Mbox { gzip } = format
+    {
+        // Offline, export the local copy of the folder instead.
+        let mail_dir = get_mail_dir(ctx, account)?;
+        let mut report = print_progress;
+        let result = export_local_mbox(
+            &mail_dir,
+            &args.folder,
+            &args.output,
+            gzip,
+            ctx.progress(&mut report),
+        )?;
+        emit_output(&ctx.common, &result)?;
+        return Ok(());
+    }
+
+    let resume = if args.resume {
+        Some(resume_fetch_keys(&args.output, &format)?)
+    } else {
+        None
+    };
     let result = client
         .mail_fetch(
             account,
             &args.folder,
             &args.output,
-            args.format.into(),
+            format,
             args.limit,
+            resume,
         )
         .map_err(|e| anyhow!("{e}"))?;
     emit_output(&ctx.common, &result)?;
     Ok(())
 }
 
+/// Keys of the messages an interrupted `mail fetch` already wrote to
+/// `output`: the fetched Maildir files, or the progress the service logs to
+/// `mail.mbox.part` while writing an mbox.
+fn resume_fetch_keys(
+    output: &std::path::Path,
+    format: &h8_core::types::FetchFormat,
+) -> Result<Vec<String>> {
+    use h8_core::types::FetchFormat as Format;
+    let mut keys: Vec<String> = match format {
+        Format::Maildir => h8_core::maildir::fetched_keys(output)
+            .map_err(|e| anyhow!("{e}"))?
+            .into_iter()
+            .collect(),
+        Format::Mbox { .. } => match fs::read_to_string(output.join("mail.mbox.part")) {
+            Ok(text) => text.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        },
+        Format::Json => {
+            return Err(anyhow!("--resume is not supported with --format json"));
+        }
+    };
+    keys.sort();
+    Ok(keys)
+}
+
+/// Write a local Maildir folder to `<output>/mail.mbox` (or `mail.mbox.gz`),
+/// mirroring the service's fetch result.
+fn export_local_mbox(
+    mail_dir: &Maildir,
+    folder: &str,
+    output: &std::path::Path,
+    gzip: bool,
+    progress: h8_core::types::ProgressCallback<'_>,
+) -> Result<Value> {
+    fs::create_dir_all(output)?;
+    let path = output.join(if gzip { "mail.mbox.gz" } else { "mail.mbox" });
+    let file = fs::File::create(&path)?;
+    let count = mail_dir
+        .export_mbox_with_progress(folder, io::BufWriter::new(file), gzip, progress)
+        .map_err(|e| anyhow!("{e}"))?;
+    Ok(json!({"success": true, "count": count, "output": path.display().to_string()}))
+}
+
 fn handle_mail_send(
     ctx: &RuntimeContext,
     client: &ServiceClient,
@@ -3976,38 +5157,67 @@ fn handle_mail_send(
                 attachments.push((name, content));
             }
 
+            let message_id = generate_message_id(&address_domain(account));
+            let sent_doc = || {
+                ComposeBuilder::new()
+                    .to(args.to.clone())
+                    .cc(args.cc.clone())
+                    .bcc(args.bcc.clone())
+                    .subject(&subject)
+                    .body(&body)
+                    .build()
+            };
             let result = if attachments.is_empty() {
                 let mut payload = serde_json::json!({
-                    "to": args.to,
-                    "cc": args.cc,
-                    "bcc": args.bcc,
+                    "to": parse_recipients(&args.to).map_err(|e| anyhow!("{e}"))?,
+                    "cc": parse_recipients(&args.cc).map_err(|e| anyhow!("{e}"))?,
+                    "bcc": parse_recipients(&args.bcc).map_err(|e| anyhow!("{e}"))?,
                     "subject": subject,
                     "body": body,
                     "html": args.html,
+                    "message_id": message_id,
                 });
 
                 if let Some(ref schedule) = schedule_at {
                     payload["schedule_at"] = serde_json::Value::String(schedule.clone());
                 }
 
-                client
+                let result = client
                     .mail_send(account, payload)
-                    .map_err(|e| anyhow!("{e}"))?
+                    .map_err(|e| anyhow!("{e}"))?;
+                if schedule_at.is_none() {
+                    save_sent_copy_if_enabled(ctx, account, &sent_doc(), &message_id, &[]);
+                }
+                result
             } else {
-                client
+                let result = client
                     .mail_send_with_attachments(
                         account,
                         &args.to,
                         &args.cc,
+                        &args.bcc,
                         &subject,
                         &body,
                         args.html,
                         schedule_at.as_deref(),
+                        Some(&message_id),
                         &attachments,
                     )
-                    .map_err(|e| anyhow!("{e}"))?
-            };
-
+                    .map_err(|e| anyhow!("{e}"))?;
+                if schedule_at.is_none() {
+                    let details: Vec<AttachmentMeta> = attachments
+                        .iter()
+                        .map(|(name, content)| AttachmentMeta {
+                            name: name.clone(),
+                            content_type: None,
+                            size: Some(content.len() as u64),
+                        })
+                        .collect();
+                    save_sent_copy_if_enabled(ctx, account, &sent_doc(), &message_id, &details);
+                }
+                result
+            };
+
             // User-friendly output
             if !ctx.common.json && !ctx.common.yaml {
                 if schedule_at.is_some() {
@@ -4058,21 +5268,46 @@ fn send_draft(
         .ok_or_else(|| anyhow!("draft not found: {}", draft_id))?;
 
     let content = draft.read_content().map_err(|e| anyhow!("{e}"))?;
-    let doc = ComposeDocument::parse(&content).map_err(|e| anyhow!("{e}"))?;
+    let mut doc = ComposeDocument::parse(&content).map_err(|e| anyhow!("{e}"))?;
 
     // Validate before sending
     doc.validate().map_err(|e| anyhow!("{e}"))?;
 
+    // Store the send token on the draft before the first attempt, so a retry
+    // after an unconfirmed send reuses it and the service drops the duplicate
+    let send_token = match doc.send_token.clone() {
+        Some(token) => token,
+        None => {
+            let token = doc.ensure_send_token().to_string();
+            let text = doc.to_string().map_err(|e| anyhow!("{e}"))?;
+            mail_dir
+                .replace_content(FOLDER_DRAFTS, draft_id, text.as_bytes(), &draft.flags)
+                .map_err(|e| anyhow!("{e}"))?;
+            token
+        }
+    };
+
     // Build send payload
+    let message_id = generate_message_id(&address_domain(account));
     let mut payload = serde_json::json!({
-        "to": doc.to,
-        "cc": doc.cc,
-        "bcc": doc.bcc,
+        "to": parse_recipients(&doc.to).map_err(|e| anyhow!("{e}"))?,
+        "cc": parse_recipients(&doc.cc).map_err(|e| anyhow!("{e}"))?,
+        "bcc": parse_recipients(&doc.bcc).map_err(|e| anyhow!("{e}"))?,
         "subject": doc.subject,
         "body": doc.body,
         "html": false,
+        "message_id": message_id,
+        "send_token": send_token,
     });
 
+    // Keep replies threaded on the server
+    if let Some(ref in_reply_to) = doc.in_reply_to {
+        payload["in_reply_to"] = Value::String(in_reply_to.clone());
+    }
+    if let Some(ref references) = doc.references {
+        payload["references"] = Value::String(references.clone());
+    }
+
     // Add schedule time if provided
     if let Some(schedule) = schedule_at {
         payload["schedule_at"] = serde_json::Value::String(schedule.to_string());
@@ -4083,10 +5318,19 @@ fn send_draft(
         .mail_send(account, payload)
         .map_err(|e| anyhow!("{e}"))?;
 
-    // Delete local draft on success
+    // Keep the draft unless the service confirmed the send
+    if result.get("success").and_then(Value::as_bool) != Some(true) {
+        return Err(anyhow!(
+            "send of {} was not confirmed; draft kept, retrying reuses its send token",
+            draft_id
+        ));
+    }
     mail_dir
         .delete(FOLDER_DRAFTS, draft_id)
         .map_err(|e| anyhow!("{e}"))?;
+    if schedule_at.is_none() {
+        save_sent_copy_if_enabled(ctx, account, &doc, &message_id, &[]);
+    }
 
     if schedule_at.is_some() {
         println!("Scheduled: {}", draft_id);
@@ -4098,16 +5342,181 @@ fn send_draft(
     Ok(())
 }
 
+/// Store a sent message in the local sent folder unless `mail.save_sent` is off.
+///
+/// The message has already left, so failures are reported but not fatal.
+/// Attachment contents are not kept, only their details.
+fn save_sent_copy_if_enabled(
+    ctx: &RuntimeContext,
+    account: &str,
+    doc: &ComposeDocument,
+    message_id: &str,
+    attachments: &[AttachmentMeta],
+) {
+    if !ctx.config.mail.save_sent {
+        return;
+    }
+    if let Err(e) = save_sent_copy(ctx, account, doc, message_id, attachments) {
+        eprintln!(
+            "Warning: message sent but not saved to {}: {}",
+            FOLDER_SENT, e
+        );
+    }
+}
+
+/// Write a sent message to the local sent folder (seen) and the sync database.
+/// Returns the allocated local ID.
+fn save_sent_copy(
+    ctx: &RuntimeContext,
+    account: &str,
+    doc: &ComposeDocument,
+    message_id: &str,
+    attachments: &[AttachmentMeta],
+) -> Result<String> {
+    let content = doc
+        .to_rfc822_with_message_id(account, message_id)
+        .map_err(|e| anyhow!("{e}"))?;
+    let local_hash = h8_core::types::MessageSync::compute_hash(content.as_bytes());
+
+    let db_path = ctx.paths.sync_db_path(account);
+    let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
+    let id_gen = IdGenerator::new(&db);
+    let stats = id_gen.stats().map_err(|e| anyhow!("{e}"))?;
+    if stats.total() == 0 {
+        let words = ctx.config.ids.word_lists().map_err(|e| anyhow!("{e}"))?;
+        id_gen.init_pool(&words).map_err(|e| anyhow!("{e}"))?;
+    }
+
+    // No server ID is known until the next sync, so key the entry by content
+    let remote_id = format!("{}{}", h8_core::db::LOCAL_REMOTE_PREFIX, local_hash);
+    let local_id = id_gen.allocate(&remote_id).map_err(|e| anyhow!("{e}"))?;
+
+    let mail_dir = get_mail_dir(ctx, account)?;
+    let flags = MessageFlags {
+        seen: true,
+        ..Default::default()
+    };
+    mail_dir
+        .store_with_id(FOLDER_SENT, content.as_bytes(), &flags, &local_id)
+        .map_err(|e| anyhow!("{e}"))?;
+
+    let msg_sync = h8_core::types::MessageSync {
+        local_id: local_id.clone(),
+        remote_id,
+        change_key: None,
+        folder: FOLDER_SENT.to_string(),
+        subject: Some(doc.subject.clone()),
+        from_addr: Some(account.to_string()),
+        received_at: Some(Utc::now().to_rfc3339()),
+        is_read: true,
+        is_draft: false,
+        has_attachments: !attachments.is_empty(),
+        synced_at: None,
+        local_hash: Some(local_hash),
+        size_bytes: Some(content.len() as u64),
+        attachments: (!attachments.is_empty()).then(|| attachments.to_vec()),
+    };
+    db.upsert_message(&msg_sync).map_err(|e| anyhow!("{e}"))?;
+    db.index_message(&local_id, Some(&doc.body))
+        .map_err(|e| anyhow!("{e}"))?;
+    db.set_thread_headers(
+        &local_id,
+        Some(message_id),
+        doc.in_reply_to.as_deref(),
+        doc.references.as_deref(),
+    )
+    .map_err(|e| anyhow!("{e}"))?;
+
+    Ok(local_id)
+}
+
 fn handle_mail_compose(ctx: &RuntimeContext, account: &str, args: MailComposeArgs) -> Result<()> {
-    let doc = ComposeBuilder::new().subject("").body("").build();
+    if args.stdin {
+        return compose_from_reader(ctx, account, io::stdin().lock(), args.confirm);
+    }
+
+    if let Some(ref path) = args.import {
+        let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let doc = ComposeDocument::from_rfc822(&String::from_utf8_lossy(&bytes))
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        return open_editor_and_save_draft(
+            ctx,
+            account,
+            doc,
+            !args.no_edit,
+            false,
+            None,
+            args.confirm,
+        );
+    }
+
+    let mut builder = ComposeBuilder::new().subject("").body("");
+    if !args.to_contact.is_empty() {
+        let client = ctx.service_client()?;
+        let recipients = resolve_contact_recipients(ctx, &args.to_contact, |id| {
+            client.contacts_get(account, id).map_err(|e| anyhow!("{e}"))
+        })?;
+        builder = builder.to(recipients);
+    }
+    let doc = builder.build();
 
     // Add signature if configured
     let mut doc = doc;
     if ctx.config.mail.compose.include_signature && !ctx.config.mail.signature.is_empty() {
-        doc.add_signature(&ctx.config.mail.signature);
+        doc.add_signature_with(&ctx.config.mail.signature, &ctx.config.mail.compose);
     }
 
-    open_editor_and_save_draft(ctx, account, doc, !args.no_edit, true)
+    open_editor_and_save_draft(ctx, account, doc, !args.no_edit, true, None, args.confirm)
+}
+
+/// Save the compose document read from `reader` as a draft, through the
+/// same confirm-and-save step as an edited one.
+fn compose_from_reader(
+    ctx: &RuntimeContext,
+    account: &str,
+    reader: impl Read,
+    confirm: bool,
+) -> Result<()> {
+    let doc = ComposeDocument::from_reader(reader).map_err(|e| anyhow!("{e}"))?;
+    doc.validate().map_err(|e| anyhow!("{e}"))?;
+    open_editor_and_save_draft(ctx, account, doc, false, false, None, confirm)
+}
+
+/// Resolve `--to-contact` values to recipient addresses.
+///
+/// `[people]` aliases are resolved locally; anything else is looked up as a
+/// contact ID via `lookup`.
+fn resolve_contact_recipients(
+    ctx: &RuntimeContext,
+    ids: &[String],
+    mut lookup: impl FnMut(&str) -> Result<Value>,
+) -> Result<Vec<String>> {
+    let mut recipients = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some((_, email)) = ctx
+            .config
+            .people
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(id))
+        {
+            recipients.push(email.clone());
+            continue;
+        }
+
+        let contact = lookup(id)?;
+        if let Some(err) = contact.get("error").and_then(|v| v.as_str()) {
+            return Err(anyhow!("contact {}: {}", id, err));
+        }
+        let email = contact
+            .get("email")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .ok_or_else(|| anyhow!("contact {} has no email address", id))?;
+        let name = contact.get("display_name").and_then(|v| v.as_str());
+        recipients.push(format_address(name, email));
+    }
+    Ok(recipients)
 }
 
 fn handle_mail_reply(
@@ -4116,6 +5525,17 @@ fn handle_mail_reply(
     account: &str,
     args: MailReplyArgs,
 ) -> Result<()> {
+    let selection = if args.quote {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        if buf.trim().is_empty() {
+            return Err(anyhow!("--quote: no text to quote on stdin"));
+        }
+        Some(buf)
+    } else {
+        None
+    };
+
     // Resolve short ID to remote ID
     let remote_id = resolve_mail_id(ctx, account, &args.id);
     // Get original message
@@ -4123,16 +5543,31 @@ fn handle_mail_reply(
         .mail_get(account, &args.folder, &remote_id)
         .map_err(|e| anyhow!("{e}"))?;
 
+    let doc = reply_document(ctx, account, &message, args.all, selection.as_deref());
+    open_editor_and_save_draft(ctx, account, doc, true, false, None, args.confirm)
+}
+
+/// Build a reply draft to `message`, quoting `selection` when given and the
+/// full original body otherwise.
+fn reply_document(
+    ctx: &RuntimeContext,
+    account: &str,
+    message: &Value,
+    all: bool,
+    selection: Option<&str>,
+) -> ComposeDocument {
     let original_from = message.get("from").and_then(|v| v.as_str()).unwrap_or("");
     let original_subject = message
         .get("subject")
         .and_then(|v| v.as_str())
         .unwrap_or("");
-    let original_body = message.get("body").and_then(|v| v.as_str()).unwrap_or("");
+    let original_body = selection
+        .or_else(|| message.get("body").and_then(|v| v.as_str()))
+        .unwrap_or("");
     let original_message_id = message.get("message_id").and_then(|v| v.as_str());
     let original_references = message.get("references").and_then(|v| v.as_str());
 
-    let doc = if args.all {
+    let doc = if all {
         let original_to: Vec<String> = message
             .get("to")
             .and_then(|v| v.as_array())
@@ -4176,10 +5611,9 @@ fn handle_mail_reply(
 
     let mut doc = doc;
     if ctx.config.mail.compose.include_signature && !ctx.config.mail.signature.is_empty() {
-        doc.add_signature(&ctx.config.mail.signature);
+        doc.add_signature_with(&ctx.config.mail.signature, &ctx.config.mail.compose);
     }
-
-    open_editor_and_save_draft(ctx, account, doc, true, false)
+    doc
 }
 
 fn handle_mail_forward(
@@ -4223,11 +5657,165 @@ fn handle_mail_forward(
 
     let mut doc = doc;
     if ctx.config.mail.compose.include_signature && !ctx.config.mail.signature.is_empty() {
-        doc.add_signature(&ctx.config.mail.signature);
+        doc.add_signature_with(&ctx.config.mail.signature, &ctx.config.mail.compose);
     }
 
     // Forward needs to show empty to/cc/bcc since recipient is not yet specified
-    open_editor_and_save_draft(ctx, account, doc, true, true)
+    open_editor_and_save_draft(ctx, account, doc, true, true, None, args.confirm)
+}
+
+/// Whether the stored body of `local_id` was cut at
+/// `mail.sync.max_body_bytes`.
+fn stored_body_truncated(ctx: &RuntimeContext, account: &str, local_id: &str) -> bool {
+    let db_path = ctx.paths.sync_db_path(account);
+    db_path.exists()
+        && Database::open(&db_path)
+            .and_then(|db| db.is_body_truncated(local_id))
+            .unwrap_or(false)
+}
+
+/// The complete content of a message, fetched from the server.
+fn fetch_full_content(
+    client: &ServiceClient,
+    account: &str,
+    folder: &str,
+    remote_id: &str,
+) -> Result<String> {
+    let server_msg = client
+        .mail_get(account, folder, remote_id)
+        .map_err(|e| anyhow!("failed to fetch message: {e}"))?;
+    if let Some(error) = server_msg.get("error") {
+        return Err(anyhow!("{}", error.as_str().unwrap_or("fetch failed")));
+    }
+    Ok(server_message_content(&server_msg, 0).0)
+}
+
+fn handle_mail_resend(
+    ctx: &RuntimeContext,
+    client: &ServiceClient,
+    account: &str,
+    args: MailResendArgs,
+) -> Result<()> {
+    let mail_dir = get_mail_dir(ctx, account)?;
+    let msg = mail_dir
+        .get(&args.folder, &args.id)
+        .map_err(|e| anyhow!("{e}"))?
+        .ok_or_else(|| {
+            anyhow!(
+                "message not stored locally: {} (fetch it with `h8 mail read {}` first)",
+                args.id,
+                args.id
+            )
+        })?;
+    let has_attachments = Database::open(&ctx.paths.sync_db_path(account))
+        .and_then(|db| db.get_message(&args.id))
+        .ok()
+        .flatten()
+        .is_some_and(|m| m.has_attachments);
+    if has_attachments {
+        return Err(resend_attachments_error());
+    }
+    let mut content = msg.read_content().map_err(|e| anyhow!("{e}"))?;
+    if stored_body_truncated(ctx, account, &args.id) {
+        // Never re-send the cut body; the server has the whole message
+        let remote_id = resolve_mail_id(ctx, account, &args.id);
+        content = fetch_full_content(client, account, &args.folder, &remote_id)
+            .context("the stored body is truncated")?;
+    }
+
+    let message_id = generate_message_id(&address_domain(account));
+    let payload = resend_payload(&content, &args.to, &args.cc, &message_id)?;
+    if ctx.common.dry_run {
+        println!(
+            "Would re-send {} to {}",
+            args.id,
+            args.to
+                .iter()
+                .chain(&args.cc)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        return emit_output(&ctx.common, &payload);
+    }
+
+    let result = client
+        .mail_send(account, payload.clone())
+        .map_err(|e| anyhow!("{e}"))?;
+    let mut builder = ComposeBuilder::new()
+        .to(args.to.clone())
+        .cc(args.cc.clone())
+        .subject(payload["subject"].as_str().unwrap_or_default())
+        .body(payload["body"].as_str().unwrap_or_default());
+    if let Some(in_reply_to) = payload["in_reply_to"].as_str() {
+        builder = builder.in_reply_to(in_reply_to);
+    }
+    if let Some(references) = payload["references"].as_str() {
+        builder = builder.references(references);
+    }
+    save_sent_copy_if_enabled(ctx, account, &builder.build(), &message_id, &[]);
+
+    if !ctx.common.json && !ctx.common.yaml {
+        println!("Re-sent {} to {}", args.id, args.to.join(", "));
+    }
+    emit_output(&ctx.common, &result)
+}
+
+/// Send payload re-sending a stored message to new recipients: the
+/// original subject, body and threading headers, unchanged and without a
+/// forward header. Messages with attachments are refused.
+fn resend_payload(content: &str, to: &[String], cc: &[String], message_id: &str) -> Result<Value> {
+    let (headers, body) = parse_email_content(content);
+    if header_value(headers, "content-type")
+        .is_some_and(|t| t.to_ascii_lowercase().starts_with("multipart/mixed"))
+    {
+        return Err(resend_attachments_error());
+    }
+    let body = h8_core::compose::decode_transfer_encoding(headers, body);
+    let subject = header_value(headers, "subject").unwrap_or_default();
+    let mut payload = serde_json::json!({
+        "to": parse_recipients(to).map_err(|e| anyhow!("{e}"))?,
+        "cc": parse_recipients(cc).map_err(|e| anyhow!("{e}"))?,
+        "bcc": [],
+        "subject": subject,
+        "body": body,
+        "html": declares_html(headers) || looks_like_html(&body),
+        "message_id": message_id,
+    });
+    if let Some(in_reply_to) = header_value(headers, "in-reply-to") {
+        payload["in_reply_to"] = Value::String(in_reply_to);
+    }
+    if let Some(references) = header_value(headers, "references") {
+        payload["references"] = Value::String(references);
+    }
+    Ok(payload)
+}
+
+fn resend_attachments_error() -> anyhow::Error {
+    anyhow!("the message has attachments, which 'mail resend' cannot re-send; forward it instead")
+}
+
+/// Value of the first header called `name`, with folded lines joined.
+fn header_value(headers: &str, name: &str) -> Option<String> {
+    let mut lines = headers.lines();
+    while let Some(line) = lines.next() {
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !field.trim().eq_ignore_ascii_case(name) {
+            continue;
+        }
+        let mut value = value.trim().to_string();
+        for cont in lines.by_ref() {
+            if !cont.starts_with([' ', '\t']) {
+                break;
+            }
+            value.push(' ');
+            value.push_str(cont.trim());
+        }
+        return Some(value);
+    }
+    None
 }
 
 /// Parse message IDs from command args, handling comma-separated values.
@@ -4241,36 +5829,150 @@ fn parse_message_ids(ids: &[String]) -> Vec<String> {
         .collect()
 }
 
-/// Parse an email address string like "Name <email@x.com>" or "email@x.com".
-/// Returns (email, Option<name>).
-fn parse_email_address(s: &str) -> Option<(String, Option<String>)> {
-    let s = s.trim();
-    if s.is_empty() {
-        return None;
+/// State file holding the rows of the most recent `mail list`.
+const LAST_LISTING_FILE: &str = "last_listing.json";
+
+/// Local IDs shown by the most recent `mail list`, in display order, so bulk
+/// commands can refer to them by position with `--select`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LastListing {
+    account: String,
+    folder: String,
+    ids: Vec<String>,
+}
+
+impl LastListing {
+    fn from_rows(account: &str, folder: &str, rows: &[Value]) -> Self {
+        Self {
+            account: account.to_string(),
+            folder: folder.to_string(),
+            ids: rows
+                .iter()
+                .filter_map(|row| row.get("id").and_then(|v| v.as_str()).map(String::from))
+                .collect(),
+        }
     }
 
-    // Check for "Name <email>" format
-    if let Some(start) = s.find('<') {
-        if let Some(end) = s.find('>') {
-            let email = s[start + 1..end].trim().to_lowercase();
-            let name = s[..start].trim();
-            let name = if name.is_empty() {
-                None
-            } else {
-                Some(name.trim_matches('"').to_string())
-            };
-            if email.contains('@') {
-                return Some((email, name));
+    fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("creating state directory {}", state_dir.display()))?;
+        let path = state_dir.join(LAST_LISTING_FILE);
+        fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    fn load(state_dir: &Path) -> Result<Option<Self>> {
+        let path = state_dir.join(LAST_LISTING_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(Some(
+            serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?,
+        ))
+    }
+
+    /// Forget the stored listing, for listings whose positions cannot be
+    /// used with `--select`.
+    fn clear(state_dir: &Path) -> Result<()> {
+        let path = state_dir.join(LAST_LISTING_FILE);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("removing {}", path.display()))
             }
+            _ => Ok(()),
         }
     }
 
-    // Just an email address
-    if s.contains('@') {
-        return Some((s.to_lowercase(), None));
+    /// Local IDs at the 1-based positions of a `--select` spec. `folder` is
+    /// the folder the command works on, if it has one.
+    fn resolve(&self, account: &str, folder: Option<&str>, spec: &str) -> Result<Vec<String>> {
+        if self.account != account {
+            return Err(anyhow!(
+                "last 'mail list' was for {}, not {}; list again before using --select",
+                self.account,
+                account
+            ));
+        }
+        if let Some(folder) = folder
+            && self.folder != folder
+        {
+            return Err(anyhow!(
+                "last 'mail list' was of {}, not {}; list again before using --select",
+                self.folder,
+                folder
+            ));
+        }
+        Ok(parse_selection(spec, self.ids.len())?
+            .into_iter()
+            .map(|pos| self.ids[pos - 1].clone())
+            .collect())
     }
+}
 
-    None
+/// Parse a `--select` spec such as "1-3,5" into 1-based positions, keeping
+/// the given order and dropping repeats. Every position must be at most
+/// `len`; ranges are checked before they are expanded.
+fn parse_selection(spec: &str, len: usize) -> Result<Vec<usize>> {
+    let position = |text: &str| -> Result<usize> {
+        match text.trim().parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(anyhow!(
+                "invalid position '{}' in selection '{}'",
+                text.trim(),
+                spec
+            )),
+        }
+    };
+    let mut positions = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (position(start)?, position(end)?),
+            None => {
+                let n = position(part)?;
+                (n, n)
+            }
+        };
+        if start > end {
+            return Err(anyhow!("invalid range '{}' in selection '{}'", part, spec));
+        }
+        if end > len {
+            return Err(anyhow!(
+                "position {} is outside the last listing ({} message(s))",
+                end,
+                len
+            ));
+        }
+        positions.extend((start..=end).filter(|n| seen.insert(*n)));
+    }
+    if positions.is_empty() {
+        return Err(anyhow!("empty selection"));
+    }
+    Ok(positions)
+}
+
+/// Append the messages picked with `--select` to the explicit IDs. The last
+/// listing must be of `folder`, when given.
+fn with_selection(
+    ctx: &RuntimeContext,
+    account: &str,
+    folder: Option<&str>,
+    mut ids: Vec<String>,
+    select: Option<&str>,
+) -> Result<Vec<String>> {
+    let Some(spec) = select else {
+        return Ok(ids);
+    };
+    let listing = LastListing::load(&ctx.paths.state_dir)?
+        .ok_or_else(|| anyhow!("--select needs a previous 'h8 mail list'"))?;
+    for id in listing.resolve(account, folder, spec)? {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
 }
 
 /// Parse move args to extract target folder from positional args or --to flag.
@@ -4310,6 +6012,26 @@ fn parse_move_args(args: &MailMoveArgs) -> Result<(Vec<String>, String)> {
     }
 }
 
+/// Target folder for `--all-from`: `--to`, `to <folder>`, or the only
+/// positional argument.
+fn all_from_target(args: &MailMoveArgs) -> Result<String> {
+    let combined = || anyhow!("message IDs cannot be combined with --all-from");
+    if let Ok((ids, target)) = parse_move_args(args) {
+        return if ids.is_empty() {
+            Ok(target)
+        } else {
+            Err(combined())
+        };
+    }
+    match parse_message_ids(&args.ids).as_slice() {
+        [target] => Ok(target.clone()),
+        [] => Err(anyhow!(
+            "target folder required: use --to <folder> or 'to <folder>'"
+        )),
+        _ => Err(combined()),
+    }
+}
+
 fn handle_mail_move(ctx: &RuntimeContext, account: &str, args: MailMoveArgs) -> Result<()> {
     let service = ctx.service_client()?;
 
@@ -4352,21 +6074,54 @@ fn handle_mail_move(ctx: &RuntimeContext, account: &str, args: MailMoveArgs) ->
             println!("  ... and {} more", search_ids.len() - 10);
         }
 
-        if args.dry_run {
+        if args.dry_run || ctx.common.dry_run {
             println!("\nDry run - no messages moved");
             return Ok(());
         }
 
         println!();
         (search_ids, target)
+    } else if let Some(ref sender) = args.all_from {
+        let target = all_from_target(&args)?;
+        let db = Database::open(&ctx.paths.sync_db_path(account)).map_err(|e| anyhow!("{e}"))?;
+        let ids: Vec<String> = db
+            .list_messages_from(&args.folder, sender)
+            .map_err(|e| anyhow!("{e}"))?
+            .into_iter()
+            .map(|m| m.local_id)
+            .collect();
+        if ids.is_empty() {
+            if !ctx.common.quiet {
+                println!("No messages from {} in {}", sender, args.folder);
+            }
+            return Ok(());
+        }
+        if args.dry_run || ctx.common.dry_run {
+            let mail_dir = get_mail_dir(ctx, account)?;
+            let plan = DryRunPlan::build(&mail_dir, &args.folder, &ids, |msg| {
+                describe_move(&mail_dir, msg, &target)
+            });
+            return plan.report(ctx);
+        }
+        (ids, target)
     } else {
         let (ids, target) = parse_move_args(&args)?;
+        let ids = with_selection(
+            ctx,
+            account,
+            Some(&args.folder),
+            ids,
+            args.select.as_deref(),
+        )?;
         if ids.is_empty() {
             return Err(anyhow!("no message IDs provided (use IDs or --query)"));
         }
-        if args.dry_run {
-            println!("Would move {} message(s) to {}", ids.len(), target);
-            return Ok(());
+        if args.dry_run || ctx.common.dry_run {
+            let mail_dir = get_mail_dir(ctx, account)?;
+            let plan = DryRunPlan::build(&mail_dir, &args.folder, &ids, |msg| {
+                describe_move(&mail_dir, msg, &target)
+            });
+            return plan.report(ctx);
         }
         (ids, target)
     };
@@ -4375,10 +6130,7 @@ fn handle_mail_move(ctx: &RuntimeContext, account: &str, args: MailMoveArgs) ->
     let db_path = ctx.paths.sync_db_path(account);
     let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
 
-    let mut moved_count = 0;
-    let mut errors: Vec<String> = Vec::new();
-
-    for id in &ids {
+    let result = BulkResult::run(&ids, |id| {
         // Get remote_id for server sync
         let remote_id = db
             .get_message(id)
@@ -4387,65 +6139,33 @@ fn handle_mail_move(ctx: &RuntimeContext, account: &str, args: MailMoveArgs) ->
             .map(|m| m.remote_id.clone());
 
         // Sync to server first if enabled and we have remote_id
-        if args.sync {
-            if let Some(ref rid) = remote_id {
-                match service.mail_move(account, &args.folder, rid, &target, args.create) {
-                    Ok(resp) => {
-                        if resp.get("success").and_then(|v| v.as_bool()) != Some(true) {
-                            let err = resp
-                                .get("error")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown error");
-                            errors.push(format!("{}: server error: {}", id, err));
-                            continue;
-                        }
-                    }
-                    Err(e) => {
-                        errors.push(format!("{}: server sync failed: {}", id, e));
-                        continue;
-                    }
-                }
-            }
+        if args.sync
+            && let Some(ref rid) = remote_id
+        {
+            check_bulk_response(service.mail_move(
+                account,
+                &args.folder,
+                rid,
+                &target,
+                args.create,
+            ))?;
         }
 
         // Move locally
         match mail_dir.move_to(&args.folder, id, &target) {
             Ok(Some(_)) => {
-                // Update database folder
-                if let Some(mut msg) = db.get_message(id).ok().flatten() {
-                    msg.folder = target.clone();
-                    let _ = db.upsert_message(&msg);
-                }
+                db.update_folder(id, &target).map_err(|e| e.to_string())?;
                 if !ctx.common.quiet {
                     println!("Moved {} to {}", id, target);
                 }
-                moved_count += 1;
-            }
-            Ok(None) => {
-                errors.push(format!("message not found locally: {}", id));
-            }
-            Err(e) => {
-                errors.push(format!("{}: {}", id, e));
+                Ok(())
             }
+            Ok(None) => Err("message not found locally".to_string()),
+            Err(e) => Err(e.to_string()),
         }
-    }
-
-    // Summary for multiple moves
-    if ids.len() > 1 && !ctx.common.quiet {
-        println!("\n{} of {} messages moved to {}", moved_count, ids.len(), target);
-    }
-
-    // Report errors
-    if !errors.is_empty() {
-        for err in &errors {
-            eprintln!("Error: {}", err);
-        }
-        if moved_count == 0 {
-            return Err(anyhow!("no messages were moved"));
-        }
-    }
+    });
 
-    Ok(())
+    finish_bulk(ctx, &result, &format!("moved to {}", target))
 }
 
 fn handle_mail_move_old(
@@ -4511,8 +6231,14 @@ fn handle_mail_archive(
     Ok(())
 }
 
-fn handle_mail_delete(ctx: &RuntimeContext, account: &str, args: MailDeleteArgs) -> Result<()> {
-    let ids = parse_message_ids(&args.ids);
+fn handle_mail_undelete(ctx: &RuntimeContext, account: &str, args: MailUndeleteArgs) -> Result<()> {
+    let ids = with_selection(
+        ctx,
+        account,
+        Some(FOLDER_TRASH),
+        parse_message_ids(&args.ids),
+        args.select.as_deref(),
+    )?;
 
     if ids.is_empty() {
         return Err(anyhow!("no message IDs provided"));
@@ -4523,109 +6249,266 @@ fn handle_mail_delete(ctx: &RuntimeContext, account: &str, args: MailDeleteArgs)
     let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
     let service = ctx.service_client()?;
 
-    let mut deleted_count = 0;
-    let mut errors: Vec<String> = Vec::new();
+    let result = BulkResult::run(&ids, |id| {
+        if mail_dir
+            .get(FOLDER_TRASH, id)
+            .map_err(|e| e.to_string())?
+            .is_none()
+        {
+            return Err(format!("not in {}", FOLDER_TRASH));
+        }
 
-    for id in &ids {
-        // Get remote_id for server sync
         let remote_id = db
             .get_message(id)
             .ok()
             .flatten()
             .map(|m| m.remote_id.clone());
 
-        // Sync deletion to server first if enabled and we have remote_id
-        if args.sync {
-            if let Some(ref rid) = remote_id {
-                match service.mail_delete(account, &args.folder, rid, args.force) {
-                    Ok(resp) => {
-                        if resp.get("success").and_then(|v| v.as_bool()) != Some(true) {
-                            let err = resp
-                                .get("error")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown error");
-                            errors.push(format!("{}: server error: {}", id, err));
-                            continue;
-                        }
-                    }
-                    Err(e) => {
-                        errors.push(format!("{}: server sync failed: {}", id, e));
-                        continue;
-                    }
-                }
-            }
+        // Sync restore to server first if enabled and we have remote_id
+        if args.sync
+            && let Some(ref rid) = remote_id
+        {
+            check_bulk_response(service.mail_move(account, FOLDER_TRASH, rid, &args.target, true))?;
+        }
+
+        mail_dir
+            .undelete(id, &args.target)
+            .map_err(|e| e.to_string())?;
+        db.update_folder(id, &args.target)
+            .map_err(|e| e.to_string())?;
+        if !ctx.common.quiet {
+            println!("Restored {} to {}", id, args.target);
+        }
+        Ok(())
+    });
+
+    finish_bulk(ctx, &result, "restored")
+}
+
+fn handle_mail_delete(ctx: &RuntimeContext, account: &str, args: MailDeleteArgs) -> Result<()> {
+    let ids = with_selection(
+        ctx,
+        account,
+        Some(&args.folder),
+        parse_message_ids(&args.ids),
+        args.select.as_deref(),
+    )?;
+
+    if ids.is_empty() {
+        return Err(anyhow!("no message IDs provided"));
+    }
+
+    let mail_dir = get_mail_dir(ctx, account)?;
+    if ctx.common.dry_run {
+        let plan = DryRunPlan::build(&mail_dir, &args.folder, &ids, |msg| {
+            if args.force {
+                format!("delete {} ({})", msg.id, msg.path.display())
+            } else {
+                describe_move(&mail_dir, msg, FOLDER_TRASH)
+            }
+        });
+        return plan.report(ctx);
+    }
+    if args.force
+        && !confirm_destructive(
+            ctx,
+            &format!("Permanently delete {} message(s)?", ids.len()),
+        )?
+    {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let db_path = ctx.paths.sync_db_path(account);
+    let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
+    let service = ctx.service_client()?;
+
+    let result = BulkResult::run(&ids, |id| {
+        // Get remote_id for server sync
+        let remote_id = db
+            .get_message(id)
+            .ok()
+            .flatten()
+            .map(|m| m.remote_id.clone());
+
+        // Sync deletion to server first if enabled and we have remote_id
+        if args.sync
+            && let Some(ref rid) = remote_id
+        {
+            check_bulk_response(service.mail_delete(account, &args.folder, rid, args.force))?;
         }
 
         // Delete/move locally
         if args.force {
             // Permanently delete locally
             match mail_dir.delete(&args.folder, id) {
-                Ok(true) => {
-                    // Also delete from database
+                // Server deletion may have succeeded while the local file is already gone
+                Ok(found) if found || remote_id.is_some() => {
                     let _ = db.delete_message(id);
                     if !ctx.common.quiet {
                         println!("Deleted {}", id);
                     }
-                    deleted_count += 1;
-                }
-                Ok(false) => {
-                    // Server deletion succeeded, local file may already be gone
-                    let _ = db.delete_message(id);
-                    if !ctx.common.quiet {
-                        println!("Deleted {}", id);
-                    }
-                    deleted_count += 1;
-                }
-                Err(e) => {
-                    errors.push(format!("{}: {}", id, e));
+                    Ok(())
                 }
+                Ok(_) => Err("message not found".to_string()),
+                Err(e) => Err(e.to_string()),
             }
         } else {
             // Move to trash locally
             match mail_dir.move_to(&args.folder, id, FOLDER_TRASH) {
                 Ok(Some(_)) => {
-                    // Update database folder
-                    if let Some(mut msg) = db.get_message(id).ok().flatten() {
-                        msg.folder = FOLDER_TRASH.to_string();
-                        let _ = db.upsert_message(&msg);
-                    }
+                    db.update_folder(id, FOLDER_TRASH)
+                        .map_err(|e| e.to_string())?;
                     if !ctx.common.quiet {
                         println!("Moved {} to trash", id);
                     }
-                    deleted_count += 1;
+                    Ok(())
                 }
-                Ok(None) => {
+                Ok(None) if remote_id.is_some() => {
                     // Server deletion succeeded, local file may already be gone
                     let _ = db.delete_message(id);
                     if !ctx.common.quiet {
                         println!("Moved {} to trash", id);
                     }
-                    deleted_count += 1;
+                    Ok(())
                 }
-                Err(e) => {
-                    errors.push(format!("{}: {}", id, e));
+                Ok(None) => Err("message not found".to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+    });
+
+    let action = if args.force {
+        "deleted"
+    } else {
+        "moved to trash"
+    };
+    finish_bulk(ctx, &result, action)
+}
+
+/// Planned local mail changes computed under `--dry-run`, without touching
+/// the Maildir.
+#[derive(Debug)]
+struct DryRunPlan {
+    /// (message ID, planned action) for each message that was found.
+    actions: Vec<(String, String)>,
+    result: BulkResult,
+}
+
+impl DryRunPlan {
+    /// Resolve each ID in `folder` and describe what would happen to it.
+    fn build(
+        mail_dir: &Maildir,
+        folder: &str,
+        ids: &[String],
+        describe: impl Fn(&h8_core::maildir::MaildirMessage) -> String,
+    ) -> Self {
+        let mut actions = Vec::new();
+        let result = BulkResult::run(ids, |id| match mail_dir.get(folder, id) {
+            Ok(Some(msg)) => {
+                actions.push((id.to_string(), describe(&msg)));
+                Ok(())
+            }
+            Ok(None) => Err("message not found locally".to_string()),
+            Err(e) => Err(e.to_string()),
+        });
+        Self { actions, result }
+    }
+
+    fn report(&self, ctx: &RuntimeContext) -> Result<()> {
+        if ctx.common.json || ctx.common.yaml {
+            let planned: Vec<Value> = self
+                .actions
+                .iter()
+                .map(|(id, action)| json!({"id": id, "action": action}))
+                .collect();
+            emit_output(
+                &ctx.common,
+                &json!({"dry_run": true, "planned": planned, "failed": self.result.failed}),
+            )?;
+        } else {
+            if !ctx.common.quiet {
+                for (_, action) in &self.actions {
+                    println!("dry-run: would {}", action);
                 }
             }
+            for failure in &self.result.failed {
+                eprintln!("Error: {}: {}", failure.id, failure.error);
+            }
+        }
+
+        if self.result.is_success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} of {} messages failed",
+                self.result.failed.len(),
+                self.result.total()
+            ))
         }
     }
+}
+
+/// Describe moving a message to `target`, naming the source and destination files.
+fn describe_move(
+    mail_dir: &Maildir,
+    msg: &h8_core::maildir::MaildirMessage,
+    target: &str,
+) -> String {
+    format!(
+        "move {} to {} ({} -> {})",
+        msg.id,
+        target,
+        msg.path.display(),
+        mail_dir.message_path(target, &msg.id, &msg.flags).display()
+    )
+}
 
-    // Summary for multiple deletions
-    if ids.len() > 1 && !ctx.common.quiet {
-        let action = if args.force { "deleted" } else { "moved to trash" };
-        println!("\n{} of {} messages {}", deleted_count, ids.len(), action);
+/// Turn a service `{success, error}` response into a per-ID bulk outcome.
+fn check_bulk_response(resp: h8_core::Result<Value>) -> std::result::Result<(), String> {
+    match resp {
+        Ok(resp) => {
+            if resp.get("success").and_then(|v| v.as_bool()) == Some(true) {
+                Ok(())
+            } else {
+                let err = resp
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error");
+                Err(format!("server error: {}", err))
+            }
+        }
+        Err(e) => Err(format!("server sync failed: {}", e)),
     }
+}
 
-    // Report errors
-    if !errors.is_empty() {
-        for err in &errors {
-            eprintln!("Error: {}", err);
+/// Report a bulk operation and fail if any ID failed.
+fn finish_bulk(ctx: &RuntimeContext, result: &BulkResult, action: &str) -> Result<()> {
+    if ctx.common.json || ctx.common.yaml {
+        emit_output(&ctx.common, result)?;
+    } else {
+        if result.total() > 1 && !ctx.common.quiet {
+            println!(
+                "\n{} of {} messages {}",
+                result.succeeded.len(),
+                result.total(),
+                action
+            );
         }
-        if deleted_count == 0 {
-            return Err(anyhow!("no messages were deleted"));
+        for failure in &result.failed {
+            eprintln!("Error: {}: {}", failure.id, failure.error);
         }
     }
 
-    Ok(())
+    if result.is_success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} of {} messages failed",
+            result.failed.len(),
+            result.total()
+        ))
+    }
 }
 
 fn handle_mail_mark(ctx: &RuntimeContext, account: &str, args: MailMarkArgs) -> Result<()> {
@@ -4633,8 +6516,52 @@ fn handle_mail_mark(ctx: &RuntimeContext, account: &str, args: MailMarkArgs) ->
         return Err(anyhow!("either --read or --unread is required"));
     }
 
-    let ids = parse_message_ids(&args.ids);
+    let ids = with_selection(
+        ctx,
+        account,
+        Some(&args.folder),
+        parse_message_ids(&args.ids),
+        args.select.as_deref(),
+    )?;
     let read = args.read;
+    let state = if read { "read" } else { "unread" };
+    let dry_run = args.dry_run || ctx.common.dry_run;
+
+    // Dry run with explicit IDs: report the local flag change only
+    if ctx.common.dry_run && !ids.is_empty() {
+        let mail_dir = get_mail_dir(ctx, account)?;
+        let plan = DryRunPlan::build(&mail_dir, &args.folder, &ids, |msg| {
+            let mut flags = msg.flags.clone();
+            flags.seen = read;
+            format!(
+                "mark {} as {} ({} -> {})",
+                msg.id,
+                state,
+                msg.path.display(),
+                mail_dir
+                    .message_path(&msg.folder, &msg.id, &flags)
+                    .display()
+            )
+        });
+        return plan.report(ctx);
+    }
+
+    // Offline: explicit IDs are marked in local storage only
+    if ctx.offline() && !ids.is_empty() && !dry_run {
+        let mail_dir = get_mail_dir(ctx, account)?;
+        let db = Database::open(&ctx.paths.sync_db_path(account)).map_err(|e| anyhow!("{e}"))?;
+        let outcome = BulkResult::run(&ids, |id| {
+            if !mark_locally(&mail_dir, &db, &args.folder, id, read, true) {
+                return Err("message not found locally".to_string());
+            }
+            if !ctx.common.quiet && !ctx.common.json && !ctx.common.yaml {
+                println!("Marked {} as {} (local only)", id, state);
+            }
+            Ok(())
+        });
+        return finish_bulk(ctx, &outcome, &format!("marked as {}", state));
+    }
+
     let service = ctx.service_client()?;
 
     if !ctx.common.quiet && !ctx.common.json && !ctx.common.yaml {
@@ -4643,29 +6570,216 @@ fn handle_mail_mark(ctx: &RuntimeContext, account: &str, args: MailMarkArgs) ->
         );
     }
 
+    // Explicit IDs are short IDs; the server needs the EWS item IDs.
+    let remote_ids: Vec<String> = ids
+        .iter()
+        .map(|id| resolve_mail_id(ctx, account, id))
+        .collect();
+
     let result = service
         .mail_mark_batch(
             account,
             &args.folder,
             read,
-            &ids,
+            &remote_ids,
             args.older_than,
             args.query.as_deref(),
             args.limit,
-            args.dry_run,
+            dry_run,
         )
         .map_err(|e| anyhow!("{e}"))?;
 
-    emit_output(&ctx.common, &result)?;
-    Ok(())
+    if ids.is_empty() || dry_run {
+        emit_output(&ctx.common, &result)?;
+        return Ok(());
+    }
+
+    if result.get("success").and_then(|v| v.as_bool()) != Some(true) {
+        let err = result
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error");
+        return Err(anyhow!("server error: {}", err));
+    }
+
+    let matched: Vec<&str> = result
+        .get("matches")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|m| m.get("id").and_then(|v| v.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let server_errors: Vec<&str> = result
+        .get("errors")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+
+    let mail_dir = get_mail_dir(ctx, account)?;
+    let db = Database::open(&ctx.paths.sync_db_path(account)).map_err(|e| anyhow!("{e}"))?;
+
+    let mut outcome = BulkResult::default();
+    for (id, remote_id) in ids.iter().zip(&remote_ids) {
+        if let Some(err) = server_errors
+            .iter()
+            .find_map(|e| e.strip_prefix(&format!("{}: ", remote_id)))
+        {
+            outcome.fail(id, format!("server error: {}", err));
+            continue;
+        }
+        if !matched.contains(&remote_id.as_str()) {
+            outcome.fail(id, "message not found");
+            continue;
+        }
+
+        // Mirror the new state locally
+        mark_locally(&mail_dir, &db, &args.folder, id, read, false);
+        if !ctx.common.quiet && !ctx.common.json && !ctx.common.yaml {
+            println!("Marked {} as {}", id, state);
+        }
+        outcome.succeed(id);
+    }
+
+    finish_bulk(ctx, &outcome, &format!("marked as {}", state))
 }
 
-fn handle_mail_drafts(ctx: &RuntimeContext, account: &str, args: MailDraftsArgs) -> Result<()> {
+/// Set the read state of a message in the Maildir and sync database.
+/// With `pending` the change is queued for the next `h8 mail sync` to push.
+/// Returns whether the message was found in either.
+fn mark_locally(
+    mail_dir: &Maildir,
+    db: &Database,
+    folder: &str,
+    id: &str,
+    read: bool,
+    pending: bool,
+) -> bool {
+    let mut found = false;
+    if let Ok(Some(msg)) = mail_dir.get(folder, id) {
+        let mut flags = msg.flags.clone();
+        flags.seen = read;
+        let _ = mail_dir.update_flags(folder, id, &flags);
+        // Mirror the flagged state without queueing it for a push
+        let _ = db.update_message_flags(id, None, Some(flags.flagged), false);
+        found = true;
+    }
+    if db
+        .update_message_flags(id, Some(read), None, pending)
+        .unwrap_or(false)
+    {
+        found = true;
+    }
+    found
+}
+
+/// Set or clear the flagged state of messages. The change is made locally
+/// first and pushed to the server right away; offline or when the push fails
+/// it stays queued for the next `h8 mail sync`.
+fn handle_mail_flag(
+    ctx: &RuntimeContext,
+    client: &ServiceClient,
+    account: &str,
+    args: MailFlagArgs,
+    flagged: bool,
+) -> Result<()> {
+    let ids = with_selection(
+        ctx,
+        account,
+        Some(&args.folder),
+        parse_message_ids(&args.ids),
+        args.select.as_deref(),
+    )?;
+    let state = if flagged { "flagged" } else { "unflagged" };
     let mail_dir = get_mail_dir(ctx, account)?;
-    let drafts = mail_dir.list(FOLDER_DRAFTS).map_err(|e| anyhow!("{e}"))?;
 
+    if ctx.common.dry_run {
+        let plan = DryRunPlan::build(&mail_dir, &args.folder, &ids, |msg| {
+            let mut flags = msg.flags.clone();
+            flags.flagged = flagged;
+            format!(
+                "mark {} as {} ({} -> {})",
+                msg.id,
+                state,
+                msg.path.display(),
+                mail_dir
+                    .message_path(&msg.folder, &msg.id, &flags)
+                    .display()
+            )
+        });
+        return plan.report(ctx);
+    }
+
+    let db = Database::open(&ctx.paths.sync_db_path(account)).map_err(|e| anyhow!("{e}"))?;
+    let show = !ctx.common.quiet && !ctx.common.json && !ctx.common.yaml;
+    let outcome = BulkResult::run(&ids, |id| {
+        if !flag_locally(&mail_dir, &db, &args.folder, id, flagged) {
+            return Err("message not found locally".to_string());
+        }
+        if ctx.offline() {
+            if show {
+                println!("Marked {} as {} (local only)", id, state);
+            }
+            return Ok(());
+        }
+
+        let remote_id = resolve_mail_id(ctx, account, id);
+        let pushed = client
+            .mail_set_flags(account, &args.folder, &remote_id, None, Some(flagged))
+            .map_err(|e| e.to_string())
+            .and_then(|result| {
+                if result.get("success").and_then(|v| v.as_bool()) == Some(false) {
+                    let err = result
+                        .get("error")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown error");
+                    return Err(err.to_string());
+                }
+                Ok(())
+            });
+        match pushed {
+            Ok(()) => {
+                let _ = db.clear_flagged_dirty(id);
+                if show {
+                    println!("Marked {} as {}", id, state);
+                }
+            }
+            Err(e) if show => {
+                println!("Marked {} as {} (will push on next sync: {})", id, state, e);
+            }
+            Err(_) => {}
+        }
+        Ok(())
+    });
+    finish_bulk(ctx, &outcome, &format!("marked as {}", state))
+}
+
+/// Set the flagged state of a message in the Maildir and sync database,
+/// keeping its read state, and queue it for the next `h8 mail sync` to push.
+/// Returns whether the message was found in either.
+fn flag_locally(mail_dir: &Maildir, db: &Database, folder: &str, id: &str, flagged: bool) -> bool {
+    let mut found = false;
+    if let Ok(Some(msg)) = mail_dir.get(folder, id) {
+        let mut flags = msg.flags.clone();
+        flags.flagged = flagged;
+        let _ = mail_dir.update_flags(folder, id, &flags);
+        found = true;
+    }
+    if db
+        .update_message_flags(id, None, Some(flagged), true)
+        .unwrap_or(false)
+    {
+        found = true;
+    }
+    found
+}
+
+fn handle_mail_drafts(ctx: &RuntimeContext, account: &str, args: MailDraftsArgs) -> Result<()> {
+    let mail_dir = get_mail_dir(ctx, account)?;
     let mut output: Vec<serde_json::Value> = Vec::new();
-    for draft in drafts.iter().take(args.limit) {
+    for draft in mail_dir.iter(FOLDER_DRAFTS).take(args.limit) {
+        let draft = draft.map_err(|e| anyhow!("{e}"))?;
         // Try to parse the draft to extract headers
         let content = draft.read_content().unwrap_or_default();
         let doc = ComposeDocument::parse(&content).ok();
@@ -4693,12 +6807,159 @@ fn handle_mail_edit(ctx: &RuntimeContext, account: &str, args: MailEditArgs) ->
     let content = draft.read_content().map_err(|e| anyhow!("{e}"))?;
     let doc = ComposeDocument::parse(&content).map_err(|e| anyhow!("{e}"))?;
 
-    // Delete old draft before creating new one
+    open_editor_and_save_draft(
+        ctx,
+        account,
+        doc,
+        true,
+        false,
+        Some(&draft.id),
+        args.confirm,
+    )
+}
+
+fn handle_mail_draft_set(
+    ctx: &RuntimeContext,
+    account: &str,
+    args: MailDraftSetArgs,
+) -> Result<()> {
+    let mail_dir = get_mail_dir(ctx, account)?;
+    let draft = mail_dir
+        .get(FOLDER_DRAFTS, &args.id)
+        .map_err(|e| anyhow!("{e}"))?
+        .ok_or_else(|| anyhow!("draft not found: {}", args.id))?;
+
+    let content = draft.read_content().map_err(|e| anyhow!("{e}"))?;
+    let mut doc = ComposeDocument::parse(&content).map_err(|e| anyhow!("{e}"))?;
+    apply_draft_set(&mut doc, &args)?;
+
+    let text = doc
+        .to_string_with(&ctx.config.mail.compose)
+        .map_err(|e| anyhow!("{e}"))?;
+    if ctx.common.dry_run {
+        print!("{text}");
+        return Ok(());
+    }
     mail_dir
-        .delete(FOLDER_DRAFTS, &args.id)
+        .replace_content(FOLDER_DRAFTS, &draft.id, text.as_bytes(), &draft.flags)
         .map_err(|e| anyhow!("{e}"))?;
 
-    open_editor_and_save_draft(ctx, account, doc, true, false)
+    if !ctx.common.json && !ctx.common.yaml && !ctx.common.quiet {
+        println!("Draft updated: {}", draft.id);
+    }
+    emit_output(
+        &ctx.common,
+        &serde_json::json!({
+            "id": draft.id,
+            "to": doc.to,
+            "cc": doc.cc,
+            "bcc": doc.bcc,
+            "subject": doc.subject,
+        }),
+    )
+}
+
+/// Apply `draft-set` changes to a draft. Replacements happen before
+/// additions, so `--to a --add-to b` leaves both; the body and threading
+/// headers are untouched.
+fn apply_draft_set(doc: &mut ComposeDocument, args: &MailDraftSetArgs) -> Result<()> {
+    let fields = [
+        (&mut doc.to, &args.to, &args.add_to),
+        (&mut doc.cc, &args.cc, &args.add_cc),
+        (&mut doc.bcc, &args.bcc, &args.add_bcc),
+    ];
+    if args.subject.is_none()
+        && fields
+            .iter()
+            .all(|(_, set, add)| set.is_empty() && add.is_empty())
+    {
+        return Err(anyhow!(
+            "nothing to change: pass --to, --cc, --bcc, --add-to, --add-cc, --add-bcc or --subject"
+        ));
+    }
+    for (field, set, add) in fields {
+        parse_recipients(set).map_err(|e| anyhow!("{e}"))?;
+        parse_recipients(add).map_err(|e| anyhow!("{e}"))?;
+        if !set.is_empty() {
+            *field = set.clone();
+        }
+        for addr in add {
+            if !field
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(addr))
+            {
+                field.push(addr.clone());
+            }
+        }
+    }
+    if let Some(ref subject) = args.subject {
+        doc.subject = subject.clone();
+    }
+    Ok(())
+}
+
+fn handle_mail_compose_lint(
+    ctx: &RuntimeContext,
+    account: &str,
+    args: MailComposeLintArgs,
+) -> Result<()> {
+    let doc = match args.id.as_deref() {
+        None | Some("-") => {
+            ComposeDocument::from_reader(io::stdin()).map_err(|e| anyhow!("{e}"))?
+        }
+        Some(id) => {
+            let mail_dir = get_mail_dir(ctx, account)?;
+            let draft = mail_dir
+                .get(FOLDER_DRAFTS, id)
+                .map_err(|e| anyhow!("{e}"))?
+                .ok_or_else(|| anyhow!("draft not found: {}", id))?;
+            let content = draft.read_content().map_err(|e| anyhow!("{e}"))?;
+            ComposeDocument::parse(&content).map_err(|e| anyhow!("{e}"))?
+        }
+    };
+
+    let report = compose_lint_report(&doc, &args.attach);
+    let count = |key: &str| report.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let errors = count("errors");
+
+    if ctx.common.json || ctx.common.yaml {
+        emit_output(&ctx.common, &report)?;
+    } else if !ctx.common.quiet {
+        let issues = report["issues"].as_array().cloned().unwrap_or_default();
+        for issue in &issues {
+            let field = |key: &str| issue.get(key).and_then(|v| v.as_str()).unwrap_or("");
+            println!(
+                "{}: {}: {}",
+                field("severity"),
+                field("field"),
+                field("message")
+            );
+        }
+        if issues.is_empty() {
+            println!("No problems found");
+        } else {
+            println!("{} error(s), {} warning(s)", errors, count("warnings"));
+        }
+    }
+
+    if errors > 0 {
+        return Err(anyhow!("{} error(s) found", errors));
+    }
+    Ok(())
+}
+
+/// Lint a compose document into `{errors, warnings, issues}`.
+fn compose_lint_report(doc: &ComposeDocument, attachments: &[PathBuf]) -> Value {
+    let issues = doc.lint(attachments);
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == LintSeverity::Error)
+        .count();
+    json!({
+        "errors": errors,
+        "warnings": issues.len() - errors,
+        "issues": issues,
+    })
 }
 
 fn handle_mail_sync(
@@ -4712,134 +6973,366 @@ fn handle_mail_sync(
     let limit_days = args.limit_days;
     let cutoff_time = limit_days.map(|days| Utc::now() - ChronoDuration::days(days as i64));
 
-    // Initialize ID pool if empty
-    let id_gen = IdGenerator::new(&db);
-    let stats = id_gen.stats().map_err(|e| anyhow!("{e}"))?;
-    if stats.total() == 0 {
-        let words = WordLists::embedded();
-        id_gen.init_pool(&words).map_err(|e| anyhow!("{e}"))?;
+    // Push local read/flagged changes before pulling server state
+    let (pushed, failed) = push_pending_flags(client, account, &db)?;
+    if !ctx.common.quiet {
+        for failure in &failed {
+            eprintln!("Warning: could not push flags for {}", failure);
+        }
+        if pushed > 0 {
+            println!("  ✓ pushed flag changes for {} message(s)", pushed);
+        }
     }
 
     // Determine folders to sync
     let folders: Vec<String> = if let Some(folder) = args.folder {
         vec![folder]
     } else {
-        ctx.config.mail.sync_folders.clone()
-    };
-
-    for folder in &folders {
-        // Fetch metadata from server (fast - uses .only() fields, no bodies)
-        let messages = client
-            .mail_list(account, folder, 100, false)
+        // Skip configured folders the server does not have, using the cached
+        // folder list; a failed lookup just syncs everything configured
+        let force = args.refresh_folders || args.full;
+        let known = match load_server_folders(ctx, client, &db, account, force) {
+            Ok(known) => known,
+            Err(e) if args.strict => return Err(e.context("checking mail.sync_folders")),
+            Err(e) => {
+                if !ctx.common.quiet {
+                    eprintln!("Warning: could not load folder list: {}", e);
+                }
+                Vec::new()
+            }
+        };
+        let (present, missing) = check_sync_folders(&ctx.config.mail.sync_folders, &known);
+        if args.strict && !missing.is_empty() {
+            return Err(anyhow!("mail.sync_folders: {}", missing.join("; ")));
+        }
+        if !ctx.common.quiet {
+            for problem in &missing {
+                eprintln!("Warning: {}, skipping", problem);
+            }
+        }
+        present
+    };
+
+    // Initialize ID pool if empty
+    let id_gen = IdGenerator::new(&db);
+    let stats = id_gen.stats().map_err(|e| anyhow!("{e}"))?;
+    if stats.total() == 0 {
+        let words = ctx.config.ids.word_lists().map_err(|e| anyhow!("{e}"))?;
+        let mut report = print_progress;
+        id_gen
+            .init_pool_with_progress(&words, ctx.progress(&mut report))
             .map_err(|e| anyhow!("{e}"))?;
+    }
 
-        let messages_arr = messages
-            .as_array()
-            .ok_or_else(|| anyhow!("expected array from server"))?;
+    let mail_dir = if args.bodies {
+        Some(get_mail_dir(ctx, account)?)
+    } else {
+        None
+    };
+    let jobs = args
+        .jobs
+        .map(usize::from)
+        .unwrap_or(ctx.config.mail.sync_jobs)
+        .max(1);
+
+    let mut opts = SyncOptions::new(account);
+    opts.cutoff = cutoff_time;
+    opts.resume = !args.full;
+    for folder in &folders {
+        opts.limit = ctx.config.mail.limits.for_folder(folder);
+        // Fetch metadata from server (fast - uses .only() fields, no bodies)
+        let mut report = print_progress;
+        let report = sync_folder(
+            client,
+            &db,
+            mail_dir.as_ref(),
+            &id_gen,
+            folder,
+            &opts,
+            ctx.progress(&mut report),
+        )
+        .map_err(|e| anyhow!("{e}"))?;
+        let pending_bodies = report.pending_bodies;
 
-        let mut synced = 0;
-        let mut skipped = 0;
+        if !ctx.common.quiet {
+            for failure in &report.failed {
+                eprintln!("Warning: could not sync {} (will retry)", failure);
+            }
+            let up_to_date = report.skipped + report.resumed;
+            if report.synced > 0 {
+                println!(
+                    "  ✓ {}: {} new, {} up-to-date",
+                    folder, report.synced, up_to_date
+                );
+            } else {
+                println!("  ✓ {}: {} up-to-date", folder, up_to_date);
+            }
+        }
 
-        for msg_val in messages_arr {
-            // Apply cutoff filter
-            if let Some(ref cutoff) = cutoff_time {
-                let email_ts = msg_val
-                    .get("datetime_received")
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                    .map(|dt| dt.with_timezone(&Utc));
-                if let Some(ts) = email_ts {
-                    if ts < *cutoff {
-                        continue;
-                    }
+        if let Some(ref mail_dir) = mail_dir
+            && !pending_bodies.is_empty()
+        {
+            let mut report = print_progress;
+            let (fetched, failed) = fetch_message_bodies(
+                mail_dir,
+                &db,
+                &pending_bodies,
+                jobs,
+                BODY_FETCH_BATCH,
+                ctx.config.mail.sync.max_body_bytes,
+                |batch| {
+                    let ids: Vec<&str> = batch.iter().map(|item| item.remote_id.as_str()).collect();
+                    client
+                        .mail_get_batch(account, folder, &ids)
+                        .map_err(|e| anyhow!("{e}"))
+                },
+                ctx.progress(&mut report),
+            );
+            if !ctx.common.quiet {
+                for failure in &failed {
+                    eprintln!("Warning: could not fetch {}", failure);
                 }
+                println!("  ✓ {}: fetched {} message(s)", folder, fetched);
             }
+        }
+    }
 
-            let remote_id = msg_val
-                .get("item_id")
-                .or_else(|| msg_val.get("id"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            if remote_id.is_empty() {
-                continue;
-            }
+    Ok(())
+}
 
-            // Skip if already synced
-            if db
-                .get_message_by_remote_id(remote_id)
-                .map_err(|e| anyhow!("{e}"))?
-                .is_some()
-            {
-                skipped += 1;
-                continue;
+/// Push every pending local flag change of `account` to the service; see
+/// [`push_flag_changes`].
+fn push_pending_flags(
+    client: &ServiceClient,
+    account: &str,
+    db: &Database,
+) -> Result<(usize, Vec<String>)> {
+    push_flag_changes(db, |pending| {
+        client
+            .mail_set_flags(
+                account,
+                &pending.folder,
+                &pending.remote_id,
+                pending.is_read,
+                pending.is_flagged,
+            )
+            .map_err(|e| anyhow!("{e}"))
+    })
+}
+
+/// Push every pending local flag change through `push`, clearing the dirty
+/// bit of each message the server accepted. Returns the number pushed and a
+/// description of each failure; failed changes stay queued for the next sync.
+fn push_flag_changes(
+    db: &Database,
+    mut push: impl FnMut(&h8_core::types::PendingFlags) -> Result<Value>,
+) -> Result<(usize, Vec<String>)> {
+    let mut pushed = 0;
+    let mut failed = Vec::new();
+    for pending in db.dirty_flags().map_err(|e| anyhow!("{e}"))? {
+        let outcome = push(&pending).and_then(|result| {
+            if result.get("success").and_then(|v| v.as_bool()) == Some(false) {
+                let err = result
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error");
+                return Err(anyhow!("server error: {}", err));
+            }
+            Ok(())
+        });
+        match outcome {
+            Ok(()) => {
+                db.clear_flags_dirty(&pending.local_id)
+                    .map_err(|e| anyhow!("{e}"))?;
+                pushed += 1;
             }
+            Err(e) => failed.push(format!("{}: {}", pending.local_id, e)),
+        }
+    }
+    Ok((pushed, failed))
+}
 
-            // Allocate human-readable ID
-            let local_id = id_gen.allocate(remote_id).map_err(|e| anyhow!("{e}"))?;
+/// Build the Maildir content and flags for a message returned by
+/// `mail_get`, cutting the body at `max_body_bytes` (0 = unlimited). The
+/// last value tells whether the body was truncated.
+fn server_message_content(
+    server_msg: &Value,
+    max_body_bytes: usize,
+) -> (String, MessageFlags, bool) {
+    let subject = server_msg
+        .get("subject")
+        .and_then(|v| v.as_str())
+        .unwrap_or("(no subject)");
+    let from = server_msg
+        .get("from")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let to_list: Vec<&str> = server_msg
+        .get("to")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    let cc_list: Vec<&str> = server_msg
+        .get("cc")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    let date = server_msg
+        .get("datetime_received")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let body = server_msg
+        .get("body")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let body_type = server_msg
+        .get("body_type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("text");
 
-            let subject = msg_val
-                .get("subject")
-                .and_then(|v| v.as_str())
-                .unwrap_or("(no subject)");
-            let from = msg_val
-                .get("from")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown");
-            let date = msg_val
-                .get("datetime_received")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            let is_read = msg_val
-                .get("is_read")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let has_attachments = msg_val
-                .get("has_attachments")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-
-            // Store metadata in database (body fetched on-demand via `h8 mail read`)
-            let msg_sync = h8_core::types::MessageSync {
-                local_id: local_id.clone(),
-                remote_id: remote_id.to_string(),
-                change_key: msg_val
-                    .get("changekey")
-                    .and_then(|v| v.as_str())
-                    .map(String::from),
-                folder: folder.clone(),
-                subject: Some(subject.to_string()),
-                from_addr: Some(from.to_string()),
-                received_at: Some(date.to_string()),
-                is_read,
-                is_draft: folder == FOLDER_DRAFTS,
-                has_attachments,
-                synced_at: Some(chrono::Utc::now().to_rfc3339()),
-                local_hash: None,
-            };
-            db.upsert_message(&msg_sync).map_err(|e| anyhow!("{e}"))?;
+    let mut content = format!("Subject: {}\nFrom: {}\nDate: {}\n", subject, from, date);
+    if !to_list.is_empty() {
+        content.push_str(&format!("To: {}\n", to_list.join(", ")));
+    }
+    if !cc_list.is_empty() {
+        content.push_str(&format!("Cc: {}\n", cc_list.join(", ")));
+    }
+    if body_type == "html" {
+        content.push_str("Content-Type: text/html; charset=utf-8\n");
+    } else {
+        content.push_str("Content-Type: text/plain; charset=utf-8\n");
+    }
+    content.push('\n');
+    let truncated = truncate_body(body, max_body_bytes);
+    content.push_str(truncated.as_deref().unwrap_or(body));
 
-            // Cache email addresses for autocomplete
-            if let Some((email, name)) = parse_email_address(from) {
-                if folder == "sent" {
-                    let _ = db.record_sent_address(&email, name.as_deref());
-                } else {
-                    let _ = db.record_received_address(&email, name.as_deref());
-                }
-            }
+    let mut flags = MessageFlags::default();
+    if server_msg
+        .get("is_read")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        flags.mark_read();
+    }
+    (content, flags, truncated.is_some())
+}
 
-            synced += 1;
+/// Run `work` over `items` on up to `jobs` threads, passing each result to
+/// `on_result` on the calling thread as it completes.
+fn run_bounded<T: Sync, R: Send>(
+    items: &[T],
+    jobs: usize,
+    work: impl Fn(&T) -> R + Sync,
+    mut on_result: impl FnMut(&T, R),
+) {
+    if jobs <= 1 || items.len() <= 1 {
+        for item in items {
+            on_result(item, work(item));
         }
+        return;
+    }
 
-        if !ctx.common.quiet {
-            if synced > 0 {
-                println!("  ✓ {}: {} new, {} up-to-date", folder, synced, skipped);
-            } else {
-                println!("  ✓ {}: {} up-to-date", folder, skipped);
-            }
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let tx = tx.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    let Some(item) = items.get(index) else { break };
+                    if tx.send((index, work(item))).is_err() {
+                        break;
+                    }
+                }
+            });
         }
-    }
+        drop(tx);
+        for (index, result) in rx {
+            on_result(&items[index], result);
+        }
+    });
+}
 
-    Ok(())
+/// Number of messages requested together when fetching bodies during sync.
+const BODY_FETCH_BATCH: usize = 25;
+
+/// Fetch full messages into the Maildir and search index in batches of
+/// `batch` messages, `jobs` requests at a time. `fetch` returns one message
+/// per item of its batch, in order. Only the requests run on worker threads;
+/// Maildir and database writes happen on the calling thread. Bodies longer
+/// than `max_body_bytes` are stored truncated and flagged in the database.
+/// Returns the number of messages stored and a description of each failure.
+#[allow(clippy::too_many_arguments)]
+fn fetch_message_bodies(
+    mail_dir: &Maildir,
+    db: &Database,
+    pending: &[BodyFetch],
+    jobs: usize,
+    batch: usize,
+    max_body_bytes: usize,
+    fetch: impl Fn(&[BodyFetch]) -> Result<Vec<Value>> + Sync,
+    mut progress: h8_core::types::ProgressCallback<'_>,
+) -> (usize, Vec<String>) {
+    let mut stored = 0;
+    let mut failed = Vec::new();
+    let mut done = 0;
+    let batches: Vec<&[BodyFetch]> = pending.chunks(batch.max(1)).collect();
+    run_bounded(
+        &batches,
+        jobs,
+        |items| fetch(items),
+        |items, result| {
+            // A failed or malformed batch fails each of its messages
+            let results: Vec<Result<Value>> = match result {
+                Ok(messages) if messages.len() == items.len() => {
+                    messages.into_iter().map(Ok).collect()
+                }
+                Ok(messages) => {
+                    let error = format!(
+                        "expected {} message(s), got {}",
+                        items.len(),
+                        messages.len()
+                    );
+                    items.iter().map(|_| Err(anyhow!("{error}"))).collect()
+                }
+                Err(e) => items.iter().map(|_| Err(anyhow!("{e}"))).collect(),
+            };
+            for (item, result) in items.iter().zip(results) {
+                let outcome = result.and_then(|server_msg| {
+                    if let Some(error) = server_msg.get("error") {
+                        return Err(anyhow!("{}", error.as_str().unwrap_or("fetch failed")));
+                    }
+                    let (content, flags, truncated) =
+                        server_message_content(&server_msg, max_body_bytes);
+                    mail_dir
+                        .store_with_id(&item.folder, content.as_bytes(), &flags, &item.local_id)
+                        .map_err(|e| anyhow!("{e}"))?;
+                    db.set_message_size(&item.local_id, content.len() as u64)
+                        .map_err(|e| anyhow!("{e}"))?;
+                    db.set_body_truncated(&item.local_id, truncated)
+                        .map_err(|e| anyhow!("{e}"))?;
+                    let body = server_msg
+                        .get("body")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    db.index_message(&item.local_id, Some(&convert_body_to_text(body)))
+                        .map_err(|e| anyhow!("{e}"))?;
+                    Ok(())
+                });
+                match outcome {
+                    Ok(()) => stored += 1,
+                    Err(e) => failed.push(format!("{}: {}", item.local_id, e)),
+                }
+                done += 1;
+                if let Some(report) = progress.as_mut() {
+                    report(Progress::new(done, pending.len(), "Fetching messages"));
+                }
+            }
+        },
+    );
+    (stored, failed)
 }
 
 fn handle_mail_attachments(
@@ -4862,6 +7355,40 @@ fn handle_mail_attachments(
             println!("Downloaded: {}", path);
         }
         emit_output(&ctx.common, &result)?;
+    } else if args.download_all {
+        let attachments = client
+            .mail_attachments_list(account, &args.folder, &remote_id)
+            .map_err(|e| anyhow!("{e}"))?;
+        let attachments = attachments.as_array().cloned().unwrap_or_default();
+        let dir = args
+            .output
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(attachment_dir_name(&args.id));
+        let summary = download_all_attachments(&attachments, &dir, |index| {
+            client
+                .mail_attachment_download(account, &args.folder, &remote_id, index, &dir)
+                .map_err(|e| anyhow!("{e}"))
+        })?;
+
+        if ctx.common.json || ctx.common.yaml {
+            emit_output(&ctx.common, &summary)?;
+        } else {
+            println!(
+                "Downloaded {} attachment(s) to {} ({} already present)",
+                summary.downloaded.len(),
+                dir.display(),
+                summary.skipped.len()
+            );
+            for failure in &summary.failed {
+                eprintln!("Failed: {}", failure);
+            }
+        }
+        if !summary.failed.is_empty() {
+            return Err(anyhow!(
+                "{} attachment(s) failed to download",
+                summary.failed.len()
+            ));
+        }
     } else {
         // List attachments
         let attachments = client
@@ -4900,6 +7427,76 @@ fn handle_mail_attachments(
     Ok(())
 }
 
+/// Result of `mail attachments --download-all`.
+#[derive(Debug, Default, Serialize)]
+struct AttachmentDownloadSummary {
+    downloaded: Vec<String>,
+    skipped: Vec<String>,
+    failed: Vec<String>,
+}
+
+/// Directory name for a message's attachments, safe for any message ID.
+fn attachment_dir_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Download each listed attachment into `dir` via `download(index)`.
+///
+/// Attachments whose file already exists in `dir` with the listed size are
+/// skipped. Failed downloads are collected rather than aborting the rest.
+fn download_all_attachments(
+    attachments: &[Value],
+    dir: &std::path::Path,
+    mut download: impl FnMut(usize) -> Result<Value>,
+) -> Result<AttachmentDownloadSummary> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+
+    let mut summary = AttachmentDownloadSummary::default();
+    for (position, att) in attachments.iter().enumerate() {
+        let index = att
+            .get("index")
+            .and_then(|v| v.as_u64())
+            .map(|i| i as usize)
+            .unwrap_or(position);
+        // Same fallback name the service uses when saving
+        let name = att
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| format!("attachment_{}", index));
+
+        let size = att.get("size").and_then(|v| v.as_u64());
+        let existing = fs::metadata(dir.join(&name)).ok().map(|m| m.len());
+        if size.is_some() && existing == size {
+            summary.skipped.push(name);
+            continue;
+        }
+
+        match download(index) {
+            Ok(result) if result.get("success").and_then(|v| v.as_bool()) != Some(false) => {
+                summary.downloaded.push(name)
+            }
+            Ok(result) => {
+                let error = result
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error");
+                summary.failed.push(format!("{}: {}", name, error));
+            }
+            Err(e) => summary.failed.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok(summary)
+}
+
 fn handle_mail_empty_folder(
     ctx: &RuntimeContext,
     client: &ServiceClient,
@@ -4907,7 +7504,7 @@ fn handle_mail_empty_folder(
     args: MailEmptyFolderArgs,
 ) -> Result<()> {
     // Confirm unless --yes is passed
-    if !args.yes {
+    if !ctx.common.assume_yes {
         print!(
             "Permanently delete all items in '{}'? This cannot be undone. [y/N] ",
             args.folder
@@ -4942,6 +7539,232 @@ fn handle_mail_empty_folder(
     Ok(())
 }
 
+/// How a command handles a confirmation setting such as
+/// `mail.confirm_destructive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfirmDecision {
+    Proceed,
+    Prompt,
+    Refuse,
+}
+
+fn confirm_decision(confirm: bool, assume_yes: bool, interactive: bool) -> ConfirmDecision {
+    if !confirm || assume_yes {
+        ConfirmDecision::Proceed
+    } else if interactive {
+        ConfirmDecision::Prompt
+    } else {
+        ConfirmDecision::Refuse
+    }
+}
+
+/// Ask before a permanent delete when `mail.confirm_destructive` is set.
+/// Returns false if the user declined; errors when there is no terminal to
+/// ask on.
+fn confirm_destructive(ctx: &RuntimeContext, prompt: &str) -> Result<bool> {
+    let decision = confirm_decision(
+        ctx.config.mail.confirm_destructive,
+        ctx.common.assume_yes,
+        io::stdin().is_terminal(),
+    );
+    match decision {
+        ConfirmDecision::Proceed => Ok(true),
+        ConfirmDecision::Refuse => Err(anyhow!(
+            "refusing to delete permanently without confirmation (stdin is not a terminal); pass -y to proceed"
+        )),
+        ConfirmDecision::Prompt => {
+            print!("{prompt} This cannot be undone. [y/N] ");
+            io::stdout().flush()?;
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            Ok(input.trim().eq_ignore_ascii_case("y"))
+        }
+    }
+}
+
+fn handle_mail_trash_purge(
+    ctx: &RuntimeContext,
+    account: &str,
+    args: TrashPurgeArgs,
+) -> Result<()> {
+    let days = args
+        .older_than
+        .unwrap_or(ctx.config.mail.trash_retention_days);
+    let cutoff = std::time::SystemTime::now() - Duration::from_secs(days * 86_400);
+
+    let mail_dir = get_mail_dir(ctx, account)?;
+    let db_path = ctx.paths.sync_db_path(account);
+    let db = if db_path.exists() {
+        Some(Database::open(&db_path).map_err(|e| anyhow!("{e}"))?)
+    } else {
+        None
+    };
+    let received_at = |id: &str| {
+        let msg = db.as_ref()?.get_message(id).ok()??;
+        let received = DateTime::parse_from_rfc3339(msg.received_at.as_deref()?).ok()?;
+        Some(std::time::SystemTime::from(received))
+    };
+
+    if ctx.common.dry_run {
+        let expired = mail_dir
+            .expired_trash(cutoff, received_at)
+            .map_err(|e| anyhow!("{e}"))?;
+        if ctx.common.json || ctx.common.yaml {
+            let planned: Vec<Value> = expired
+                .iter()
+                .map(|m| json!({"id": m.id, "action": format!("purge {}", m.path.display())}))
+                .collect();
+            emit_output(&ctx.common, &json!({"dry_run": true, "planned": planned}))?;
+        } else if expired.is_empty() {
+            println!("No trashed messages older than {} day(s)", days);
+        } else {
+            for msg in &expired {
+                println!("dry-run: would purge {} ({})", msg.id, msg.path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    let prompt = format!("Permanently delete trashed messages older than {days} day(s)?");
+    if !confirm_destructive(ctx, &prompt)? {
+        println!("Cancelled");
+        return Ok(());
+    }
+    let purged = mail_dir
+        .purge_trash(cutoff, received_at)
+        .map_err(|e| anyhow!("{e}"))?;
+    if let Some(db) = &db {
+        for id in &purged {
+            let _ = db.delete_message(id);
+        }
+    }
+
+    if ctx.common.json || ctx.common.yaml {
+        emit_output(&ctx.common, &json!({"purged": purged}))?;
+    } else if !ctx.common.quiet {
+        println!(
+            "Purged {} message(s) older than {} day(s) from trash",
+            purged.len(),
+            days
+        );
+    }
+    Ok(())
+}
+
+fn handle_mail_stats(ctx: &RuntimeContext, account: &str, args: MailStatsArgs) -> Result<()> {
+    let mail_dir = get_mail_dir(ctx, account)?;
+    let db_path = ctx.paths.sync_db_path(account);
+    let db = if db_path.exists() {
+        Some(Database::open(&db_path).map_err(|e| anyhow!("{e}"))?)
+    } else {
+        None
+    };
+    let stats = collect_mail_stats(&mail_dir, db.as_ref(), args.folder.as_deref())?;
+
+    if ctx.common.json || ctx.common.yaml {
+        return emit_output(&ctx.common, &stats);
+    }
+    let line = |label: &str, row: &Value| {
+        let count = |key: &str| row.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+        let date = |key: &str| {
+            row.get(key)
+                .and_then(|v| v.as_str())
+                .map(|d| display_config().format_timestamp(d.split('T').next().unwrap_or(d)))
+        };
+        let mut text = format!(
+            "{}: {} message(s), {} unread, {}",
+            label,
+            count("total"),
+            count("unread"),
+            format_size(count("size"))
+        );
+        if let (Some(oldest), Some(newest)) = (date("oldest"), date("newest")) {
+            text.push_str(&format!(", {} to {}", oldest, newest));
+        }
+        println!("{}", text);
+    };
+    let folders = stats["folders"].as_array().cloned().unwrap_or_default();
+    if folders.is_empty() {
+        println!("No local mail. Run 'h8 mail sync --bodies' first.");
+        return Ok(());
+    }
+    for row in &folders {
+        line(row["folder"].as_str().unwrap_or("?"), row);
+    }
+    if folders.len() > 1 {
+        line("Total", &stats["total"]);
+    }
+    Ok(())
+}
+
+/// Per-folder statistics for `mail stats`: message and unread counts and
+/// size on disk from the Maildir, synced count and receive date range from
+/// the sync database. Folders known to either are included.
+fn collect_mail_stats(
+    mail_dir: &Maildir,
+    db: Option<&Database>,
+    folder: Option<&str>,
+) -> Result<Value> {
+    let summaries = match db {
+        Some(db) => db.folder_summaries().map_err(|e| anyhow!("{e}"))?,
+        None => Vec::new(),
+    };
+    let folders = match folder {
+        Some(folder) => vec![folder.to_string()],
+        None => {
+            let mut names = mail_dir.list_folders().map_err(|e| anyhow!("{e}"))?;
+            names.extend(summaries.iter().map(|s| s.folder.clone()));
+            names.sort();
+            names.dedup();
+            names
+        }
+    };
+
+    let mut rows = Vec::new();
+    let mut total = FolderStats::default();
+    let mut synced = 0;
+    let mut oldest: Option<String> = None;
+    let mut newest: Option<String> = None;
+    for name in &folders {
+        let stats = mail_dir.folder_stats(name).map_err(|e| anyhow!("{e}"))?;
+        let summary = summaries.iter().find(|s| &s.folder == name);
+        let first = summary.and_then(|s| s.oldest.clone());
+        let last = summary.and_then(|s| s.newest.clone());
+        rows.push(json!({
+            "folder": name,
+            "total": stats.total,
+            "unread": stats.unread,
+            "size": stats.size,
+            "synced": summary.map_or(0, |s| s.messages),
+            "oldest": first,
+            "newest": last,
+        }));
+
+        total.total += stats.total;
+        total.unread += stats.unread;
+        total.size += stats.size;
+        synced += summary.map_or(0, |s| s.messages);
+        if first.is_some() && (oldest.is_none() || first < oldest) {
+            oldest = first;
+        }
+        if last > newest {
+            newest = last;
+        }
+    }
+
+    Ok(json!({
+        "folders": rows,
+        "total": {
+            "total": total.total,
+            "unread": total.unread,
+            "size": total.size,
+            "synced": synced,
+            "oldest": oldest,
+            "newest": newest,
+        },
+    }))
+}
+
 fn handle_mail_spam(
     ctx: &RuntimeContext,
     client: &ServiceClient,
@@ -4949,7 +7772,13 @@ fn handle_mail_spam(
     args: MailSpamArgs,
 ) -> Result<()> {
     // Parse message IDs (support comma-separated)
-    let ids = parse_message_ids(&args.ids);
+    let ids = with_selection(
+        ctx,
+        account,
+        None,
+        parse_message_ids(&args.ids),
+        args.select.as_deref(),
+    )?;
 
     if ids.is_empty() {
         eprintln!("No message IDs provided");
@@ -5225,23 +8054,33 @@ fn handle_mail_unsubscribe(
 }
 
 fn get_mail_dir(ctx: &RuntimeContext, account: &str) -> Result<Maildir> {
-    let mail_path = if let Some(ref data_dir) = ctx.config.mail.data_dir {
-        h8_core::paths::expand_str_path(data_dir)
+    let mail_path = mail_data_path(ctx, account)?;
+    Maildir::new(mail_path, account).map_err(|e| anyhow!("{e}"))
+}
+
+/// Mail storage directory for `account`, honoring `mail.data_dir`.
+fn mail_data_path(ctx: &RuntimeContext, account: &str) -> Result<PathBuf> {
+    if let Some(ref data_dir) = ctx.config.mail.data_dir {
+        Ok(h8_core::paths::expand_str_path(data_dir)
             .map_err(|e| anyhow!("{e}"))?
-            .join(account)
+            .join(account))
     } else {
-        ctx.paths.mail_dir(account)
-    };
-
-    Maildir::new(mail_path, account).map_err(|e| anyhow!("{e}"))
+        Ok(ctx.paths.mail_dir(account))
+    }
 }
 
+/// Let the user edit `doc` and save the result as a draft. With `draft_id`,
+/// the existing draft is overwritten in place and keeps its ID. With
+/// `confirm` (or `mail.compose.confirm_save`), a summary is shown and the
+/// user is asked before saving; a declined draft stays in its temp file.
 fn open_editor_and_save_draft(
     ctx: &RuntimeContext,
     account: &str,
     doc: ComposeDocument,
     open_editor: bool,
     is_new_compose: bool,
+    draft_id: Option<&str>,
+    confirm: bool,
 ) -> Result<()> {
     // Use template format for new compose to show empty to/cc/bcc fields
     let content = if is_new_compose {
@@ -5256,52 +8095,91 @@ fn open_editor_and_save_draft(
     fs::write(&temp_path, &content)?;
 
     if open_editor {
-        // Get editor command
-        let editor = ctx
-            .config
-            .mail
-            .editor
-            .clone()
-            .or_else(|| env::var("EDITOR").ok())
-            .unwrap_or_else(|| "vi".to_string());
-
-        // Open editor
-        let status = ProcCommand::new(&editor)
-            .arg(&temp_path)
-            .status()
-            .with_context(|| format!("starting editor: {}", editor))?;
-
-        if !status.success() {
+        // Get editor command (may carry arguments, e.g. "code --wait")
+        if let Err(e) = run_editor(ctx, &temp_path) {
             let _ = fs::remove_file(&temp_path);
-            return Err(anyhow!("editor exited with non-zero status"));
+            return Err(e);
         }
     }
 
     // Read edited content
     let edited_content = fs::read_to_string(&temp_path)?;
-    let _ = fs::remove_file(&temp_path);
 
-    // Parse to validate
-    let _edited_doc = ComposeDocument::parse(&edited_content).map_err(|e| anyhow!("{e}"))?;
+    // Parse to validate; the edited text is kept verbatim unless the body
+    // has to be wrapped
+    let edited_doc = match ComposeDocument::parse(&edited_content) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            return Err(anyhow!("{e}"));
+        }
+    };
+
+    // Without a terminal (or with -y) the draft is saved without asking
+    let decision = confirm_decision(
+        confirm || ctx.config.mail.compose.confirm_save,
+        ctx.common.assume_yes,
+        io::stdin().is_terminal(),
+    );
+    if decision == ConfirmDecision::Prompt {
+        let attachments = draft_id
+            .map(|id| draft_attachments(ctx, account, id))
+            .unwrap_or_default();
+        println!("{}", edited_doc.summary(&attachments));
+        print!("Save draft? [Y/n] ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if input.trim().eq_ignore_ascii_case("n") || input.trim().eq_ignore_ascii_case("no") {
+            println!("Draft not saved; your text is in {}", temp_path.display());
+            return Ok(());
+        }
+    }
+    let _ = fs::remove_file(&temp_path);
+    let edited_content = if ctx.config.mail.compose.wrap_width > 0 {
+        edited_doc
+            .to_string_with(&ctx.config.mail.compose)
+            .map_err(|e| anyhow!("{e}"))?
+    } else {
+        edited_content
+    };
 
     // Save as draft
     let mail_dir = get_mail_dir(ctx, account)?;
-    mail_dir.init().map_err(|e| anyhow!("{e}"))?;
+    mail_dir
+        .init_with(&ctx.config.mail.local_folders)
+        .map_err(|e| anyhow!("{e}"))?;
 
     let flags = MessageFlags {
         draft: true,
         ..Default::default()
     };
 
-    let draft = mail_dir
-        .store(FOLDER_DRAFTS, edited_content.as_bytes(), &flags)
-        .map_err(|e| anyhow!("{e}"))?;
+    let draft = match draft_id {
+        Some(id) => mail_dir.replace_content(FOLDER_DRAFTS, id, edited_content.as_bytes(), &flags),
+        None => mail_dir.store(FOLDER_DRAFTS, edited_content.as_bytes(), &flags),
+    }
+    .map_err(|e| anyhow!("{e}"))?;
 
     println!("Draft saved: {}", draft.id);
 
     Ok(())
 }
 
+/// Attachments of a draft as known from the sync database, e.g. for a draft
+/// created on the server. Local drafts have none.
+fn draft_attachments(ctx: &RuntimeContext, account: &str, draft_id: &str) -> Vec<PathBuf> {
+    Database::open(&ctx.paths.sync_db_path(account))
+        .and_then(|db| db.get_message(draft_id))
+        .ok()
+        .flatten()
+        .and_then(|m| m.attachments)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| PathBuf::from(a.name))
+        .collect()
+}
+
 fn handle_contacts(ctx: &RuntimeContext, cmd: ContactsCommand) -> Result<()> {
     let account = effective_account(ctx);
     let client = ctx.service_client()?;
@@ -5313,8 +8191,16 @@ fn handle_contacts(ctx: &RuntimeContext, cmd: ContactsCommand) -> Result<()> {
             emit_output(&ctx.common, &contacts)?;
         }
         ContactsCommand::Get(args) => {
+            let id = match args.id {
+                Some(ref id) => id.clone(),
+                None => resolve_contact_id(&args, |query| {
+                    client
+                        .contacts_list(&account, CONTACT_LOOKUP_LIMIT, Some(query))
+                        .map_err(|e| anyhow!("{e}"))
+                })?,
+            };
             let contact = client
-                .contacts_get(&account, &args.id)
+                .contacts_get(&account, &id)
                 .map_err(|e| anyhow!("{e}"))?;
             emit_output(&ctx.common, &contact)?;
         }
@@ -5326,36 +8212,15 @@ fn handle_contacts(ctx: &RuntimeContext, cmd: ContactsCommand) -> Result<()> {
             emit_output(&ctx.common, &result)?;
         }
         ContactsCommand::Update(args) => {
-            // Build update payload from provided args
-            let mut updates = serde_json::Map::new();
-            if let Some(v) = args.name {
-                updates.insert("display_name".to_string(), serde_json::json!(v));
-            }
-            if let Some(v) = args.given_name {
-                updates.insert("given_name".to_string(), serde_json::json!(v));
-            }
-            if let Some(v) = args.surname {
-                updates.insert("surname".to_string(), serde_json::json!(v));
-            }
-            if let Some(v) = args.email {
-                updates.insert("email".to_string(), serde_json::json!(v));
-            }
-            if let Some(v) = args.phone {
-                updates.insert("phone".to_string(), serde_json::json!(v));
-            }
-            if let Some(v) = args.company {
-                updates.insert("company".to_string(), serde_json::json!(v));
-            }
-            if let Some(v) = args.job_title {
-                updates.insert("job_title".to_string(), serde_json::json!(v));
-            }
-
-            if updates.is_empty() {
-                return Err(anyhow!("no fields to update - specify at least one of: --name, --email, --phone, --company, --job-title, --given-name, --surname"));
-            }
+            let payload = match args.file {
+                Some(ref path) if path.as_os_str() == "-" => Some(read_json_payload(None)?),
+                Some(ref path) => Some(read_json_payload(Some(path))?),
+                None => None,
+            };
+            let updates = contact_updates(&args, payload)?;
 
             let result = client
-                .contacts_update(&account, &args.id, serde_json::Value::Object(updates))
+                .contacts_update(&account, &args.id, updates)
                 .map_err(|e| anyhow!("{e}"))?;
 
             if !ctx.common.json && !ctx.common.yaml {
@@ -5376,10 +8241,236 @@ fn handle_contacts(ctx: &RuntimeContext, cmd: ContactsCommand) -> Result<()> {
                 .map_err(|e| anyhow!("{e}"))?;
             emit_output(&ctx.common, &result)?;
         }
+        ContactsCommand::Export(args) => {
+            let contacts = client
+                .contacts_list(&account, args.limit, args.search.as_deref())
+                .map_err(|e| anyhow!("{e}"))?;
+            let contacts = contacts.as_array().cloned().unwrap_or_default();
+            let vcf = contacts_to_vcard(&contacts);
+            match args.output {
+                Some(ref path) => {
+                    fs::write(path, &vcf)?;
+                    if !ctx.common.quiet {
+                        println!(
+                            "Exported {} contact(s) to {}",
+                            contacts.len(),
+                            path.display()
+                        );
+                    }
+                }
+                None => print!("{}", vcf),
+            }
+        }
     }
     Ok(())
 }
 
+/// Most contacts a `contacts get --email/--name` search considers.
+const CONTACT_LOOKUP_LIMIT: usize = 50;
+
+/// Resolve `contacts get --email` or `--name` to a contact ID. `search`
+/// returns the contacts matching a substring query; exact (case-insensitive)
+/// matches on the requested field win over partial ones, and more than one
+/// candidate is an error listing them.
+fn resolve_contact_id(
+    args: &ContactsGetArgs,
+    search: impl FnOnce(&str) -> Result<Value>,
+) -> Result<String> {
+    let (field, query) = match (&args.email, &args.name) {
+        (Some(email), _) => ("email", email.trim()),
+        (None, Some(name)) => ("display_name", name.trim()),
+        (None, None) => return Err(anyhow!("one of --id, --email or --name is required")),
+    };
+    let results = search(query)?;
+    let candidates = results.as_array().cloned().unwrap_or_default();
+    let exact: Vec<&Value> = candidates
+        .iter()
+        .filter(|c| {
+            c.get(field)
+                .and_then(|v| v.as_str())
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case(query))
+        })
+        .collect();
+    let matches = if exact.is_empty() {
+        candidates.iter().collect()
+    } else {
+        exact
+    };
+
+    match matches.as_slice() {
+        [] => Err(anyhow!("no contact matches '{}'", query)),
+        [contact] => contact
+            .get("id")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .ok_or_else(|| anyhow!("contact matching '{}' has no ID", query)),
+        many => {
+            let listing: Vec<String> = many
+                .iter()
+                .map(|c| {
+                    let text = |key: &str| c.get(key).and_then(|v| v.as_str()).unwrap_or("");
+                    format!(
+                        "  {} <{}> ({})",
+                        text("display_name"),
+                        text("email"),
+                        text("id")
+                    )
+                })
+                .collect();
+            Err(anyhow!(
+                "'{}' matches {} contacts; use --id with one of:\n{}",
+                query,
+                many.len(),
+                listing.join("\n")
+            ))
+        }
+    }
+}
+
+/// Render contacts as vCard 3.0 (RFC 2426) with FN, N, EMAIL, TEL and ORG
+/// lines. Missing fields are omitted; contacts without any name or email
+/// are skipped since FN is required.
+fn contacts_to_vcard(contacts: &[Value]) -> String {
+    let mut out = String::new();
+    for contact in contacts {
+        let field = |key: &str| {
+            contact
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let given = field("given_name");
+        let surname = field("surname");
+        let full_name = [given, surname]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let Some(name) = field("display_name")
+            .or((!full_name.is_empty()).then_some(full_name.as_str()))
+            .or(field("email"))
+        else {
+            continue;
+        };
+
+        let mut lines = vec![
+            "BEGIN:VCARD".to_string(),
+            "VERSION:3.0".to_string(),
+            format!("FN:{}", vcard_escape(name)),
+        ];
+        if given.is_some() || surname.is_some() {
+            lines.push(format!(
+                "N:{};{};;;",
+                vcard_escape(surname.unwrap_or("")),
+                vcard_escape(given.unwrap_or(""))
+            ));
+        }
+        if let Some(email) = field("email") {
+            lines.push(format!("EMAIL;TYPE=INTERNET:{}", vcard_escape(email)));
+        }
+        if let Some(phone) = field("phone") {
+            lines.push(format!("TEL;TYPE=WORK,VOICE:{}", vcard_escape(phone)));
+        }
+        if let Some(company) = field("company") {
+            lines.push(format!("ORG:{}", vcard_escape(company)));
+        }
+        lines.push("END:VCARD".to_string());
+
+        for line in lines {
+            out.push_str(&vcard_fold(&line));
+            out.push_str("\r\n");
+        }
+    }
+    out
+}
+
+/// Escape a vCard text value (backslash, comma, semicolon, newline).
+fn vcard_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ',' | ';' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fold a content line at 75 octets, continuing with a leading space.
+fn vcard_fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+/// Contact fields accepted by `contacts update`.
+const CONTACT_UPDATE_FIELDS: [&str; 7] = [
+    "display_name",
+    "given_name",
+    "surname",
+    "email",
+    "phone",
+    "company",
+    "job_title",
+];
+
+/// Build the `contacts update` request body: the fields from the JSON
+/// payload, overridden by any field flags. Fields that are not supplied
+/// are left out so the service leaves them unchanged.
+fn contact_updates(args: &ContactsUpdateArgs, payload: Option<Value>) -> Result<Value> {
+    let mut updates = match payload {
+        Some(Value::Object(map)) => map,
+        Some(_) => return Err(anyhow!("contact update payload must be a JSON object")),
+        None => serde_json::Map::new(),
+    };
+    if let Some(unknown) = updates
+        .keys()
+        .find(|key| !CONTACT_UPDATE_FIELDS.contains(&key.as_str()))
+    {
+        return Err(anyhow!(
+            "unknown contact field '{}' (expected one of: {})",
+            unknown,
+            CONTACT_UPDATE_FIELDS.join(", ")
+        ));
+    }
+
+    let flags = [
+        ("display_name", &args.name),
+        ("given_name", &args.given_name),
+        ("surname", &args.surname),
+        ("email", &args.email),
+        ("phone", &args.phone),
+        ("company", &args.company),
+        ("job_title", &args.job_title),
+    ];
+    for (key, value) in flags {
+        if let Some(v) = value {
+            updates.insert(key.to_string(), json!(v));
+        }
+    }
+
+    if updates.is_empty() {
+        return Err(anyhow!(
+            "no fields to update - pass --file or at least one of: --name, --email, --phone, --company, --job-title, --given-name, --surname"
+        ));
+    }
+    Ok(Value::Object(updates))
+}
+
 fn handle_addr(ctx: &RuntimeContext, command: AddrCommand) -> Result<()> {
     match command {
         AddrCommand::Search(args) => handle_addr_search(ctx, args),
@@ -7770,7 +10861,9 @@ fn handle_agenda(ctx: &RuntimeContext, args: AgendaArgs) -> Result<()> {
         args.when.join(" ")
     };
 
-    let target_date = if let Some((date, _desc)) = parse_single_date(&when_text) {
+    let target_date = if args.now {
+        Local::now().with_timezone(&tz).date_naive()
+    } else if let Some((date, _desc)) = parse_single_date(&when_text) {
         date
     } else {
         // Default to today if parsing fails
@@ -7823,7 +10916,14 @@ fn handle_agenda(ctx: &RuntimeContext, args: AgendaArgs) -> Result<()> {
 
     let events: Vec<AgendaItem> =
         serde_json::from_value(events_val.clone()).context("parsing agenda items")?;
-    render_agenda(&events, tz, view, target_date)?;
+    render_agenda(
+        &events,
+        tz,
+        view,
+        target_date,
+        args.now,
+        &ctx.config.display,
+    )?;
     Ok(())
 }
 
@@ -7859,6 +10959,19 @@ fn handle_free(ctx: &RuntimeContext, cmd: FreeCommand) -> Result<()> {
         .free_slots(&account, cmd.weeks, cmd.duration, cmd.limit)
         .map_err(|e| anyhow!("{e}"))?;
 
+    if cmd.ics {
+        let tz = ctx.config.timezone.parse::<chrono_tz::Tz>().map_err(|_| {
+            anyhow!(
+                "unknown timezone '{}' in config; --ics needs it to place slots without an offset",
+                ctx.config.timezone
+            )
+        })?;
+        let items: Vec<FreeSlotItem> =
+            serde_json::from_value(slots).context("parsing free slots")?;
+        print!("{}", free_slots_ics(&items, tz, Utc::now())?);
+        return Ok(());
+    }
+
     // Use JSON/YAML output if requested, otherwise render nicely
     if ctx.common.json || ctx.common.yaml || !io::stdout().is_terminal() {
         emit_output(&ctx.common, &slots)?;
@@ -8805,6 +11918,58 @@ fn extract_time(dt_str: &str) -> Option<String> {
     None
 }
 
+/// Parse a slot boundary into UTC. Values without an offset are taken to be
+/// local times in `tz`.
+fn slot_time_utc(value: &str, tz: chrono_tz::Tz) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").ok()?;
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Render free slots as an iCalendar object with one transparent VEVENT per
+/// slot. Times are written in UTC so no VTIMEZONE is needed; lines end in
+/// CRLF as RFC 5545 requires.
+fn free_slots_ics(
+    items: &[FreeSlotItem],
+    tz: chrono_tz::Tz,
+    stamp: DateTime<Utc>,
+) -> Result<String> {
+    const ICS_UTC: &str = "%Y%m%dT%H%M%SZ";
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//byteowlz//h8//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for item in items {
+        let (Some(start), Some(end)) = (item.start.as_deref(), item.end.as_deref()) else {
+            continue;
+        };
+        let start =
+            slot_time_utc(start, tz).ok_or_else(|| anyhow!("invalid free slot start: {start}"))?;
+        let end = slot_time_utc(end, tz).ok_or_else(|| anyhow!("invalid free slot end: {end}"))?;
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:free-{}@h8", start.format(ICS_UTC)),
+            format!("DTSTAMP:{}", stamp.format(ICS_UTC)),
+            format!("DTSTART:{}", start.format(ICS_UTC)),
+            format!("DTEND:{}", end.format(ICS_UTC)),
+            "SUMMARY:Free".to_string(),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = lines.join("\r\n");
+    out.push_str("\r\n");
+    Ok(out)
+}
+
 /// Interactive meeting scheduling from common free slots.
 fn interactive_schedule_meeting(
     ctx: &RuntimeContext,
@@ -9032,10 +12197,292 @@ fn handle_config(ctx: &RuntimeContext, command: ConfigCommand) -> Result<()> {
             println!("{}", ctx.paths.global_config.display());
             Ok(())
         }
+        ConfigCommand::Paths => {
+            let paths = resolved_paths(ctx, &effective_account(ctx))?;
+            if ctx.common.json || ctx.common.yaml {
+                let map: serde_json::Map<String, Value> = paths
+                    .iter()
+                    .map(|(key, path)| (key.to_string(), json!(path.display().to_string())))
+                    .collect();
+                return emit_output(&ctx.common, &map);
+            }
+            print!("{}", format_paths(&paths));
+            Ok(())
+        }
         ConfigCommand::Reset => {
             AppConfig::write_default(&ctx.paths.global_config).map_err(|e| anyhow!("{e}"))
         }
+        ConfigCommand::Edit => {
+            let path = &ctx.paths.global_config;
+            AppConfig::ensure_default(path).map_err(|e| anyhow!("{e}"))?;
+            let original = fs::read_to_string(path)?;
+            run_editor(ctx, path)?;
+            check_edited_config(&ctx.paths, &original)?;
+            if !ctx.common.quiet {
+                println!("Config saved: {}", path.display());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Re-validate the global config after an edit. If it no longer loads,
+/// `original` is restored and the rejected edit is kept next to it as
+/// `<name>.rejected`.
+fn check_edited_config(paths: &AppPaths, original: &str) -> Result<()> {
+    let Err(e) = AppConfig::load(paths, None) else {
+        return Ok(());
+    };
+    let path = &paths.global_config;
+    let rejected = path.with_extension("toml.rejected");
+    fs::copy(path, &rejected)?;
+    fs::write(path, original)?;
+    Err(anyhow!(
+        "config no longer loads ({e}); changes reverted, edited copy kept at {}",
+        rejected.display()
+    ))
+}
+
+fn handle_id(ctx: &RuntimeContext, command: IdCommand) -> Result<()> {
+    let account = effective_account(ctx);
+    let db = Database::open(&ctx.paths.sync_db_path(&account)).map_err(|e| anyhow!("{e}"))?;
+    let result = id_command_result(&IdGenerator::new(&db), &command)?;
+
+    if ctx.common.json || ctx.common.yaml {
+        return emit_output(&ctx.common, &result);
+    }
+    match command {
+        IdCommand::Stats => println!(
+            "{} IDs: {} allocated, {} remaining",
+            result["total"], result["allocated"], result["remaining"]
+        ),
+        IdCommand::Resolve(_) | IdCommand::Reverse(_) => println!(
+            "{} -> {}",
+            result["id"].as_str().unwrap_or_default(),
+            result["remote_id"].as_str().unwrap_or_default()
+        ),
+    }
+    Ok(())
+}
+
+/// Pool statistics or an ID mapping for an `id` subcommand. Unknown IDs are
+/// an error.
+fn id_command_result(id_gen: &IdGenerator<'_>, command: &IdCommand) -> Result<Value> {
+    match command {
+        IdCommand::Stats => {
+            let stats = id_gen.stats().map_err(|e| anyhow!("{e}"))?;
+            Ok(json!({
+                "total": stats.total(),
+                "allocated": stats.used,
+                "remaining": stats.free,
+            }))
+        }
+        IdCommand::Resolve(args) => {
+            let remote_id = id_gen
+                .resolve(&args.id)
+                .map_err(|e| anyhow!("{e}"))?
+                .ok_or_else(|| anyhow!("ID '{}' is not allocated", args.id))?;
+            Ok(json!({"id": args.id, "remote_id": remote_id}))
+        }
+        IdCommand::Reverse(args) => {
+            let id = id_gen
+                .reverse_lookup(&args.remote_id)
+                .map_err(|e| anyhow!("{e}"))?
+                .ok_or_else(|| anyhow!("no ID assigned to remote ID '{}'", args.remote_id))?;
+            Ok(json!({"id": id, "remote_id": args.remote_id}))
+        }
+    }
+}
+
+fn handle_db(ctx: &RuntimeContext, command: DbCommand) -> Result<()> {
+    match command {
+        DbCommand::Maintain => handle_db_maintain(ctx),
+        DbCommand::Dedup => handle_db_dedup(ctx),
+    }
+}
+
+fn handle_db_maintain(ctx: &RuntimeContext) -> Result<()> {
+    let account = effective_account(ctx);
+    let db_path = ctx.paths.sync_db_path(&account);
+    let file_size = || fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
+    let size_before = file_size();
+    let db = Database::open(&db_path).map_err(|e| anyhow!("{e}"))?;
+    let mail_dir = get_mail_dir(ctx, &account)?;
+
+    let mut result = maintain_database(&db, &mail_dir, ctx.common.dry_run)?;
+    result["size_before"] = json!(size_before);
+    result["size_after"] = json!(file_size());
+
+    if ctx.common.json || ctx.common.yaml {
+        return emit_output(&ctx.common, &result);
+    }
+    let orphans = result["orphans"].as_array().map_or(0, Vec::len);
+    if ctx.common.dry_run {
+        println!("dry-run: would remove {orphans} message row(s) without a Maildir file");
+    } else if !ctx.common.quiet {
+        println!(
+            "Removed {} message row(s) without a Maildir file and {} stale search entries; {} -> {}",
+            orphans,
+            result["index_removed"],
+            format_size(size_before),
+            format_size(file_size())
+        );
+    }
+    Ok(())
+}
+
+/// Remove sync DB rows for downloaded messages whose Maildir file is gone,
+/// drop search entries left without a message, and compact the database.
+/// A file counts in any local folder, so a row with a stale folder is kept.
+/// With `dry_run`, only the orphaned rows are reported.
+fn maintain_database(db: &Database, mail_dir: &Maildir, dry_run: bool) -> Result<Value> {
+    let mut on_disk = std::collections::HashSet::new();
+    for folder in mail_dir.list_folders().map_err(|e| anyhow!("{e}"))? {
+        for msg in mail_dir.iter(&folder) {
+            on_disk.insert(msg.map_err(|e| anyhow!("{e}"))?.id);
+        }
+    }
+    let orphans: Vec<String> = db
+        .stored_messages()
+        .map_err(|e| anyhow!("{e}"))?
+        .into_iter()
+        .map(|(local_id, _)| local_id)
+        .filter(|local_id| !on_disk.contains(local_id))
+        .collect();
+    if dry_run {
+        return Ok(json!({"orphans": orphans, "index_removed": 0}));
+    }
+
+    for local_id in &orphans {
+        db.delete_message(local_id).map_err(|e| anyhow!("{e}"))?;
+    }
+    let index_removed = db.prune_search_index().map_err(|e| anyhow!("{e}"))?;
+    db.vacuum().map_err(|e| anyhow!("{e}"))?;
+    Ok(json!({"orphans": orphans, "index_removed": index_removed}))
+}
+
+fn handle_db_dedup(ctx: &RuntimeContext) -> Result<()> {
+    let account = effective_account(ctx);
+    let db = Database::open(&ctx.paths.sync_db_path(&account)).map_err(|e| anyhow!("{e}"))?;
+    let mail_dir = get_mail_dir(ctx, &account)?;
+    let mut report = print_progress;
+    let sets = dedup_messages(
+        &db,
+        &mail_dir,
+        ctx.common.dry_run,
+        ctx.progress(&mut report),
+    )?;
+
+    if ctx.common.json || ctx.common.yaml {
+        return emit_output(&ctx.common, &sets);
+    }
+    let count: usize = sets.iter().map(|set| set.removed.len()).sum();
+    if ctx.common.dry_run {
+        println!("dry-run: would remove {count} duplicate message(s)");
+        for set in &sets {
+            println!(
+                "  {}: keep {}, remove {}",
+                set.folder,
+                set.kept,
+                set.removed.join(", ")
+            );
+        }
+    } else if !ctx.common.quiet {
+        println!(
+            "Removed {} duplicate(s) of {} message(s)",
+            count,
+            sets.len()
+        );
+    }
+    Ok(())
+}
+
+/// Copies of one message in a folder found by `db dedup`.
+#[derive(Debug, Serialize, PartialEq)]
+struct DuplicateSet {
+    folder: String,
+    kept: String,
+    removed: Vec<String>,
+}
+
+/// Find messages stored more than once in a folder: Maildir files with
+/// identical content. Sync DB rows alone are never matched, since their
+/// metadata cannot tell apart two messages with the same subject, sender and
+/// date. The copy known to the sync DB is kept. Unless `dry_run`, the other
+/// copies are removed from the Maildir and the database and their IDs
+/// returned to the pool. `progress` is called after each folder is scanned.
+fn dedup_messages(
+    db: &Database,
+    mail_dir: &Maildir,
+    dry_run: bool,
+    mut progress: h8_core::types::ProgressCallback<'_>,
+) -> Result<Vec<DuplicateSet>> {
+    let mut sets = Vec::new();
+    let folders = mail_dir.list_folders().map_err(|e| anyhow!("{e}"))?;
+    for (i, folder) in folders.iter().enumerate() {
+        let mut by_content: std::collections::BTreeMap<String, Vec<String>> = Default::default();
+        for msg in mail_dir.iter(folder) {
+            let msg = msg.map_err(|e| anyhow!("{e}"))?;
+            let content =
+                fs::read(&msg.path).with_context(|| format!("reading {}", msg.path.display()))?;
+            let hash = h8_core::types::MessageSync::compute_hash(&content);
+            by_content.entry(hash).or_default().push(msg.id);
+        }
+        for mut ids in by_content.into_values().filter(|ids| ids.len() > 1) {
+            ids.sort_by_cached_key(|id| (!matches!(db.get_message(id), Ok(Some(_))), id.clone()));
+            let kept = ids.remove(0);
+            sets.push(DuplicateSet {
+                folder: folder.clone(),
+                kept,
+                removed: ids,
+            });
+        }
+        if let Some(cb) = progress.as_mut() {
+            cb(Progress::new(
+                i + 1,
+                folders.len(),
+                "scanning for duplicates",
+            ));
+        }
     }
+
+    if !dry_run {
+        let id_gen = IdGenerator::new(db);
+        for set in &sets {
+            for id in &set.removed {
+                mail_dir
+                    .delete(&set.folder, id)
+                    .map_err(|e| anyhow!("{e}"))?;
+                db.delete_message(id).map_err(|e| anyhow!("{e}"))?;
+                let _ = id_gen.free(id);
+            }
+        }
+    }
+    Ok(sets)
+}
+
+/// Paths h8 resolved for `account`, keyed and in display order.
+fn resolved_paths(ctx: &RuntimeContext, account: &str) -> Result<Vec<(&'static str, PathBuf)>> {
+    let mut paths = vec![("global_config", ctx.paths.global_config.clone())];
+    if let Some(cli_config) = &ctx.paths.cli_config {
+        paths.push(("cli_config", cli_config.clone()));
+    }
+    paths.push(("state_dir", ctx.paths.state_dir.clone()));
+    paths.push(("data_dir", ctx.paths.data_dir.clone()));
+    paths.push(("mail_dir", mail_data_path(ctx, account)?));
+    paths.push(("sync_db", ctx.paths.sync_db_path(account)));
+    Ok(paths)
+}
+
+/// Render resolved paths as aligned `label: path` lines.
+fn format_paths(paths: &[(&str, PathBuf)]) -> String {
+    paths
+        .iter()
+        .map(|(key, path)| {
+            let label = format!("{}:", key.replace('_', " "));
+            format!("{:<15}{}\n", label, path.display())
+        })
+        .collect()
 }
 
 fn handle_init(ctx: &RuntimeContext, cmd: InitCommand) -> Result<()> {
@@ -9056,14 +12503,14 @@ fn handle_init(ctx: &RuntimeContext, cmd: InitCommand) -> Result<()> {
 }
 
 fn handle_completions(shell: Shell) -> Result<()> {
-    let mut cmd = Cli::command();
+    let mut cmd = Cli::definition();
     clap_complete::generate(shell, &mut cmd, APP_NAME, &mut io::stdout());
     Ok(())
 }
 
 fn handle_service(ctx: &RuntimeContext, command: ServiceCommand) -> Result<()> {
     match command {
-        ServiceCommand::Start => start_service(ctx),
+        ServiceCommand::Start(args) => start_service(ctx, args.foreground),
         ServiceCommand::Stop => stop_service(ctx),
         ServiceCommand::Restart => restart_service(ctx),
         ServiceCommand::Status => status_service(ctx),
@@ -9077,24 +12524,150 @@ fn effective_account(ctx: &RuntimeContext) -> String {
         .unwrap_or_else(|| ctx.config.account.clone())
 }
 
-fn read_json_payload(path: Option<&PathBuf>) -> Result<Value> {
-    let mut buffer = String::new();
-    match path {
-        Some(p) => {
-            buffer = fs::read_to_string(p)
-                .with_context(|| format!("reading payload from {}", p.display()))?;
-        }
-        None => {
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("reading JSON from stdin")?;
-        }
-    }
-    let json: Value = serde_json::from_str(&buffer).context("parsing JSON payload")?;
-    Ok(json)
+/// Fields entered in the `calendar create --interactive` form.
+#[derive(Debug, Default)]
+struct EventForm {
+    subject: String,
+    start: String,
+    end: String,
+    location: String,
+    attendees: String,
 }
 
-fn emit_output<T: ?Sized + Serialize + std::fmt::Debug>(
+fn prompt_event_form() -> Result<EventForm> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("--interactive requires a terminal"));
+    }
+    let input = |prompt: &str, allow_empty: bool| -> Result<String> {
+        dialoguer::Input::<String>::new()
+            .with_prompt(prompt)
+            .allow_empty(allow_empty)
+            .interact_text()
+            .map_err(|e| anyhow!("input cancelled: {e}"))
+    };
+    Ok(EventForm {
+        subject: input("Subject", false)?,
+        start: input("Start (2026-03-11 14:00, or a date for all-day)", false)?,
+        end: input("End (empty: 1 hour after start)", true)?,
+        location: input("Location", true)?,
+        attendees: input("Attendees (comma-separated)", true)?,
+    })
+}
+
+/// Parse an event time from the interactive form. Returns the time and
+/// whether only a date was given (an all-day boundary).
+fn parse_event_time(text: &str) -> Option<(NaiveDateTime, bool)> {
+    let text = text.trim();
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, format) {
+            return Some((dt, false));
+        }
+    }
+    let date = NaiveDate::parse_from_str(&normalize_date_arg(text), "%Y-%m-%d").ok()?;
+    Some((date.and_hms_opt(0, 0, 0)?, true))
+}
+
+/// Build a `calendar create` payload from the interactive form.
+///
+/// Two dates make an all-day event through the end date. An empty end
+/// means one hour after a timed start, or the same day for a date.
+fn event_form_payload(form: &EventForm) -> Result<Value> {
+    let subject = form.subject.trim();
+    if subject.is_empty() {
+        return Err(anyhow!("subject is required"));
+    }
+    let (start, start_is_date) = parse_event_time(&form.start)
+        .ok_or_else(|| anyhow!("invalid start: {}", form.start.trim()))?;
+    let (end, is_all_day) = if form.end.trim().is_empty() {
+        if start_is_date {
+            (start + ChronoDuration::days(1), true)
+        } else {
+            (start + ChronoDuration::hours(1), false)
+        }
+    } else {
+        let (end, end_is_date) = parse_event_time(&form.end)
+            .ok_or_else(|| anyhow!("invalid end: {}", form.end.trim()))?;
+        match (start_is_date, end_is_date) {
+            (true, true) => (end + ChronoDuration::days(1), true),
+            (false, true) => (
+                end + ChronoDuration::days(1) - ChronoDuration::seconds(1),
+                false,
+            ),
+            _ => (end, false),
+        }
+    };
+    if end <= start {
+        return Err(anyhow!("end must be after start"));
+    }
+
+    let mut payload = serde_json::json!({
+        "subject": subject,
+        "start": start.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "end": end.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    });
+    if is_all_day {
+        payload["is_all_day"] = Value::Bool(true);
+    }
+    let location = form.location.trim();
+    if !location.is_empty() {
+        payload["location"] = Value::String(location.to_string());
+    }
+    let attendees: Vec<&str> = form
+        .attendees
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .collect();
+    if let Some(bad) = attendees.iter().find(|a| !a.contains('@')) {
+        return Err(anyhow!("attendee is not an email address: {}", bad));
+    }
+    if !attendees.is_empty() {
+        payload["attendees"] = serde_json::json!(attendees);
+    }
+    Ok(payload)
+}
+
+/// Open `path` in the configured editor (`mail.editor`, then $VISUAL and
+/// $EDITOR) and wait for it to exit.
+fn run_editor(ctx: &RuntimeContext, path: &Path) -> Result<()> {
+    // The editor command may carry arguments, e.g. "code --wait"
+    let editor = h8_core::resolve_editor(None, ctx.config.mail.editor.as_deref(), |k| {
+        env::var(k).ok()
+    });
+    let mut editor_parts = editor.split_whitespace();
+    let editor_cmd = editor_parts
+        .next()
+        .ok_or_else(|| anyhow!("invalid editor command"))?;
+
+    let status = ProcCommand::new(editor_cmd)
+        .args(editor_parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("starting editor: {}", editor))?;
+    if !status.success() {
+        return Err(anyhow!("editor exited with non-zero status"));
+    }
+    Ok(())
+}
+
+fn read_json_payload(path: Option<&PathBuf>) -> Result<Value> {
+    let mut buffer = String::new();
+    match path {
+        Some(p) => {
+            buffer = fs::read_to_string(p)
+                .with_context(|| format!("reading payload from {}", p.display()))?;
+        }
+        None => {
+            io::stdin()
+                .read_to_string(&mut buffer)
+                .context("reading JSON from stdin")?;
+        }
+    }
+    let json: Value = serde_json::from_str(&buffer).context("parsing JSON payload")?;
+    Ok(json)
+}
+
+fn emit_output<T: ?Sized + Serialize + std::fmt::Debug>(
     opts: &CommonOpts,
     value: &T,
 ) -> Result<()> {
@@ -9110,23 +12683,352 @@ fn emit_output<T: ?Sized + Serialize + std::fmt::Debug>(
     }
 
     let v = serde_json::to_value(value)?;
-    pretty_print_value(&v);
+    let rendered = match opts.format {
+        Some(OutputFormat::Csv) => Some(render_csv(&v)?),
+        Some(OutputFormat::Table) => {
+            let width = terminal_size::terminal_size()
+                .map(|(w, _)| w.0 as usize)
+                .unwrap_or(120);
+            render_table(&v, width)
+        }
+        _ => opts
+            .output_template
+            .as_deref()
+            .and_then(|template| render_template(&v, template)),
+    };
+    match rendered {
+        Some(text) => print!("{text}"),
+        None => pretty_print_value(&v, opts.stdout_color()),
+    }
     Ok(())
 }
 
-fn pretty_print_value(v: &Value) {
+/// Narrowest a table column is shrunk to before the table overflows.
+const TABLE_MIN_WIDTH: usize = 4;
+const TABLE_GAP: &str = "  ";
+
+/// Pick columns for a row shape as (name, keys to read in order of
+/// preference), shared by CSV (which uses the names as headers) and table
+/// output. Unknown shapes get one column per scalar field.
+fn row_columns(sample: &serde_json::Map<String, Value>) -> Vec<(&str, Vec<&str>)> {
+    let known: &[(&str, &[&str])] = if sample.contains_key("start") {
+        &[
+            ("subject", &["subject"]),
+            ("start", &["start"]),
+            ("end", &["end"]),
+            ("location", &["location"]),
+        ]
+    } else if sample.contains_key("email")
+        && (sample.contains_key("display_name") || sample.contains_key("name"))
+    {
+        &[
+            ("name", &["display_name", "name"]),
+            ("email", &["email"]),
+            ("phone", &["phone"]),
+            ("company", &["company"]),
+        ]
+    } else if sample.contains_key("subject") {
+        &[
+            ("id", &["id"]),
+            ("subject", &["subject"]),
+            ("from", &["from", "sender"]),
+            ("date", &["date", "datetime_received"]),
+        ]
+    } else {
+        &[]
+    };
+    if !known.is_empty() {
+        return known
+            .iter()
+            .map(|(name, keys)| (*name, keys.to_vec()))
+            .collect();
+    }
+    sample
+        .iter()
+        .filter(|(_, v)| !v.is_object() && !v.is_array())
+        .map(|(key, _)| (key.as_str(), vec![key.as_str()]))
+        .collect()
+}
+
+/// Text of the first non-null field among `keys`, with arrays joined.
+fn cell_text(obj: &serde_json::Map<String, Value>, keys: &[&str]) -> String {
+    match keys
+        .iter()
+        .find_map(|k| obj.get(*k).filter(|v| !v.is_null()))
+    {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|i| {
+                i.as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| i.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+fn table_cell(obj: &serde_json::Map<String, Value>, keys: &[&str]) -> String {
+    let text = cell_text(obj, keys);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+/// (RFC 4180), doubling embedded quotes.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Render an array of objects (or a single object) as CSV with a header
+/// row. An empty array renders as nothing; other non-tabular values are an
+/// error.
+fn render_csv(value: &Value) -> Result<String> {
+    let not_tabular = || anyhow!("--format csv needs a list of objects with scalar fields");
+    let rows: Vec<&serde_json::Map<String, Value>> = match value {
+        Value::Array(items) => items
+            .iter()
+            .map(Value::as_object)
+            .collect::<Option<_>>()
+            .ok_or_else(not_tabular)?,
+        Value::Object(obj) => vec![obj],
+        _ => return Err(not_tabular()),
+    };
+    let Some(first) = rows.first() else {
+        return Ok(String::new());
+    };
+    let columns = row_columns(first);
+    if columns.is_empty() {
+        return Err(not_tabular());
+    }
+
+    let line = |fields: Vec<String>| {
+        let joined = fields
+            .iter()
+            .map(|f| csv_field(f))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{joined}\n")
+    };
+    let mut out = line(
+        columns
+            .iter()
+            .map(|(header, _)| header.to_string())
+            .collect(),
+    );
+    for row in rows {
+        out.push_str(&line(
+            columns
+                .iter()
+                .map(|(_, keys)| cell_text(row, keys))
+                .collect(),
+        ));
+    }
+    Ok(out)
+}
+
+/// Render each object (or a single object) as one line of `template`, with
+/// `{field}` replaced by the field's text and missing fields left empty.
+/// `{{` and `}}` stand for literal braces. Returns `None` for values that
+/// are not objects.
+fn render_template(value: &Value, template: &str) -> Option<String> {
+    let rows: Vec<&serde_json::Map<String, Value>> = match value {
+        Value::Array(items) => items.iter().map(Value::as_object).collect::<Option<_>>()?,
+        Value::Object(obj) => vec![obj],
+        _ => return None,
+    };
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(&fill_template(row, template));
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn fill_template(obj: &serde_json::Map<String, Value>, template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+        } else if let Some(end) = rest.find('}').filter(|_| rest.starts_with('{')) {
+            out.push_str(&cell_text(obj, &[rest[1..end].trim()]));
+            rest = &rest[end + 1..];
+        } else {
+            out.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Render an array of objects (or a single object) as aligned columns no
+/// wider than `width`, truncating the widest columns first. Returns `None`
+/// for values that are not tabular.
+fn render_table(value: &Value, width: usize) -> Option<String> {
+    let rows: Vec<&serde_json::Map<String, Value>> = match value {
+        Value::Array(items) => items.iter().map(Value::as_object).collect::<Option<_>>()?,
+        Value::Object(obj) => vec![obj],
+        _ => return None,
+    };
+    let columns: Vec<(String, Vec<&str>)> = row_columns(rows.first()?)
+        .into_iter()
+        .map(|(name, keys)| (name.to_uppercase(), keys))
+        .collect();
+    if columns.is_empty() {
+        return None;
+    }
+
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|(_, keys)| table_cell(row, keys))
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (header, _))| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let budget = width.saturating_sub(TABLE_GAP.len() * (columns.len() - 1));
+    while widths.iter().sum::<usize>() > budget {
+        let (widest, current) = widths
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by_key(|(i, w)| (*w, std::cmp::Reverse(*i)))
+            .unwrap_or((0, 0));
+        if current <= TABLE_MIN_WIDTH {
+            break;
+        }
+        widths[widest] -= 1;
+    }
+
+    let fit = |text: &str, width: usize| {
+        if text.chars().count() > width {
+            let kept: String = text.chars().take(width.saturating_sub(2)).collect();
+            format!("{kept}..")
+        } else {
+            format!("{text:<width$}")
+        }
+    };
+    let line = |values: Vec<String>| {
+        let joined = values
+            .iter()
+            .zip(&widths)
+            .map(|(value, w)| fit(value, *w))
+            .collect::<Vec<_>>()
+            .join(TABLE_GAP);
+        format!("{}\n", joined.trim_end())
+    };
+
+    let mut out = line(columns.iter().map(|(header, _)| header.clone()).collect());
+    out.push_str(&line(widths.iter().map(|w| "-".repeat(*w)).collect()));
+    for row in cells {
+        out.push_str(&line(row));
+    }
+    Some(out)
+}
+
+/// Render one mail list entry: unread messages get a `*` marker and a bold
+/// subject, flagged ones a `[flagged]` marker.
+fn format_mail_item(
+    obj: &serde_json::Map<String, Value>,
+    subject: &str,
+    use_color: bool,
+) -> String {
+    use owo_colors::OwoColorize;
+
+    let id = obj.get("id").and_then(|v| v.as_str()).unwrap_or("???");
+    let from = obj
+        .get("from")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let is_read = obj.get("is_read").and_then(|v| v.as_bool()).unwrap_or(true);
+    let is_flagged = obj
+        .get("is_flagged")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    // Collapsed conversations (mail list --thread) show their size
+    let subject = match obj.get("thread_count").and_then(|v| v.as_u64()) {
+        Some(count) if count > 1 => format!("{subject} ({count})"),
+        _ => subject.to_string(),
+    };
+
+    // Format date human-readably
+    let date_str = obj
+        .get("date")
+        .or_else(|| obj.get("datetime_received"))
+        .and_then(|v| v.as_str())
+        .map(format_date_human)
+        .unwrap_or_default();
+
+    if !use_color {
+        let marker = if is_read { " " } else { "*" };
+        let flag = if is_flagged { " [flagged]" } else { "" };
+        return format!("{marker} {subject}{flag} - {date_str} [{id}]\n  {from}\n\n");
+    }
+
+    let marker = if is_read {
+        " ".to_string()
+    } else {
+        "*".yellow().bold().to_string()
+    };
+    let subject = if is_read {
+        subject
+    } else {
+        subject.bold().to_string()
+    };
+    let flag = if is_flagged {
+        format!(" {}", "[flagged]".red().bold())
+    } else {
+        String::new()
+    };
+    format!(
+        "{} {}{} - {} [{}]\n  {}\n\n",
+        marker,
+        subject,
+        flag,
+        date_str.dimmed(),
+        id.cyan(),
+        from.dimmed()
+    )
+}
+
+fn pretty_print_value(v: &Value, use_color: bool) {
     match v {
         Value::Array(items) => {
             for item in items {
-                pretty_print_item(item);
+                pretty_print_item(item, use_color);
             }
         }
-        Value::Object(_) => pretty_print_item(v),
+        Value::Object(_) => pretty_print_item(v, use_color),
         _ => println!("{v}"),
     }
 }
 
-fn pretty_print_item(v: &Value) {
+fn pretty_print_item(v: &Value, use_color: bool) {
     let obj = match v {
         Value::Object(map) => map,
         _ => {
@@ -9143,56 +13045,10 @@ fn pretty_print_item(v: &Value) {
         // Check if this is a mail message (has "from" field) vs calendar event (has "start" field)
         let is_mail = obj.contains_key("from") && !obj.contains_key("start");
         if is_mail {
-            // Mail message format
-            let id = obj.get("id").and_then(|v| v.as_str()).unwrap_or("???");
-            let from = obj
-                .get("from")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown");
-            let is_read = obj.get("is_read").and_then(|v| v.as_bool()).unwrap_or(true);
-
-            // Format date human-readably
-            let date_str = obj
-                .get("date")
-                .or_else(|| obj.get("datetime_received"))
-                .and_then(|v| v.as_str())
-                .map(|dt| format_date_human(dt))
-                .unwrap_or_default();
-
-            // Use colors when outputting to TTY
-            use owo_colors::OwoColorize;
-
-            let use_color = std::io::stdout().is_terminal();
-
-            if is_read {
-                if use_color {
-                    println!("  {} - {} [{}]", subject, date_str.dimmed(), id.cyan());
-                } else {
-                    println!("  {} - {} [{}]", subject, date_str, id);
-                }
-            } else {
-                if use_color {
-                    println!(
-                        "{} {} - {} [{}]",
-                        "*".yellow().bold(),
-                        subject.bold(),
-                        date_str.dimmed(),
-                        id.cyan()
-                    );
-                } else {
-                    println!("* {} - {} [{}]", subject, date_str, id);
-                }
-            }
-            if use_color {
-                println!("  {}", from.dimmed());
-            } else {
-                println!("  {}", from);
-            }
-            println!();
+            print!("{}", format_mail_item(obj, subject, use_color));
         } else {
             // Calendar event format
             use owo_colors::OwoColorize;
-            let use_color = std::io::stdout().is_terminal();
 
             let start = obj.get("start").and_then(|v| v.as_str()).unwrap_or("");
             let end = obj.get("end").and_then(|v| v.as_str()).unwrap_or("");
@@ -9212,6 +13068,8 @@ fn pretty_print_item(v: &Value) {
 
             // Format start/end times
             let time_range = format_calendar_time_range(start, end);
+            let all_day_date =
+                display_config().format_timestamp(start.split('T').next().unwrap_or(start));
 
             // Check if this is a detailed event (has extra fields beyond basic)
             let is_detailed = body.is_some()
@@ -9227,7 +13085,7 @@ fn pretty_print_item(v: &Value) {
                     }
                     println!("{}", subject.bold().green());
                     if is_all_day {
-                        println!("  {}: {}", "All day".cyan(), start.split('T').next().unwrap_or(&start));
+                        println!("  {}: {}", "All day".cyan(), all_day_date);
                     } else {
                         println!("  {}", time_range.cyan().bold());
                     }
@@ -9237,7 +13095,7 @@ fn pretty_print_item(v: &Value) {
                     }
                     println!("{}", subject);
                     if is_all_day {
-                        println!("  All day: {}", start.split('T').next().unwrap_or(&start));
+                        println!("  All day: {}", all_day_date);
                     } else {
                         println!("  {}", time_range);
                     }
@@ -9478,6 +13336,15 @@ fn pretty_print_item(v: &Value) {
 /// Format an ISO date string to a human-readable format.
 /// Shows "Today 14:30", "Yesterday 09:15", "Mon 14:30", or "Dec 5" for older dates.
 fn format_date_human(iso_date: &str) -> String {
+    format_date_human_with(iso_date, display_config(), chrono::Local::now())
+}
+
+/// [`format_date_human`] with explicit display formats and current time.
+fn format_date_human_with(
+    iso_date: &str,
+    display: &DisplayConfig,
+    now: chrono::DateTime<chrono::Local>,
+) -> String {
     use chrono::{DateTime, Datelike, Local};
 
     let parsed = DateTime::parse_from_rfc3339(iso_date)
@@ -9489,18 +13356,20 @@ fn format_date_human(iso_date: &str) -> String {
         Err(_) => return iso_date.to_string(),
     };
 
-    let now = Local::now();
     let today = now.date_naive();
     let date = dt.date_naive();
     let yesterday = today.pred_opt().unwrap_or(today);
+    let time = display.format_time(dt.time());
 
     if date == today {
-        format!("Today {}", dt.format("%H:%M"))
+        format!("Today {}", time)
     } else if date == yesterday {
-        format!("Yesterday {}", dt.format("%H:%M"))
+        format!("Yesterday {}", time)
     } else if (today - date).num_days() < 7 {
         // Within last week, show day name
-        dt.format("%a %H:%M").to_string()
+        format!("{} {}", dt.format("%a"), time)
+    } else if let Some(formatted) = display.format_date(date) {
+        formatted
     } else if date.year() == today.year() {
         // Same year, show month and day
         dt.format("%b %-d").to_string()
@@ -9530,6 +13399,7 @@ fn format_calendar_time_range(start: &str, end: &str) -> String {
     let yesterday = today.pred_opt().unwrap_or(today);
     let tomorrow = today.succ_opt().unwrap_or(today);
 
+    let display = display_config();
     let format_date_prefix = |date: NaiveDate| -> String {
         if date == today {
             "Today".to_string()
@@ -9539,6 +13409,8 @@ fn format_calendar_time_range(start: &str, end: &str) -> String {
             "Tomorrow".to_string()
         } else if (date - today).num_days().abs() < 7 {
             date.format("%a").to_string()
+        } else if let Some(formatted) = display.format_date(date) {
+            formatted
         } else if date.year() == today.year() {
             date.format("%a %b %-d").to_string()
         } else {
@@ -9552,9 +13424,9 @@ fn format_calendar_time_range(start: &str, end: &str) -> String {
         let start_date = start_dt.date_naive();
         let date_prefix = format_date_prefix(start_date);
 
-        let start_time = start_dt.format("%H:%M");
+        let start_time = display.format_time(start_dt.time());
         let end_time = end_dt
-            .map(|dt| dt.format("%H:%M").to_string())
+            .map(|dt| display.format_time(dt.time()))
             .unwrap_or_default();
 
         if end_time.is_empty() {
@@ -9572,6 +13444,9 @@ fn format_calendar_time_range(start: &str, end: &str) -> String {
     }
 }
 
+/// Timeout for the HTTP probe in `service status`.
+const STATUS_PROBE_TIMEOUT_SECS: u64 = 5;
+
 fn service_pid_path(ctx: &RuntimeContext) -> Result<PathBuf> {
     fs::create_dir_all(&ctx.paths.state_dir)
         .with_context(|| format!("creating state directory {}", ctx.paths.state_dir.display()))?;
@@ -9719,10 +13594,22 @@ fn ensure_oama() -> Result<()> {
     Ok(())
 }
 
-fn start_service(ctx: &RuntimeContext) -> Result<()> {
+fn start_service(ctx: &RuntimeContext, foreground: bool) -> Result<()> {
     // Ensure oama is installed before starting service
     ensure_oama()?;
+    launch_service(ctx, foreground, |cmd| cmd.spawn())
+}
 
+/// Start `uv run h8-service` through `spawn`, passing the resolved config
+/// file and account as `H8_CONFIG` and `H8_ACCOUNT`. In the background, output goes
+/// to the service log and the PID is recorded; in the foreground, the service
+/// shares the terminal, no PID file is written, and this waits until it
+/// exits (Ctrl-C reaches the service directly).
+fn launch_service(
+    ctx: &RuntimeContext,
+    foreground: bool,
+    spawn: impl FnOnce(&mut ProcCommand) -> io::Result<std::process::Child>,
+) -> Result<()> {
     let pid_path = service_pid_path(ctx)?;
     if let Some(pid) = read_pid(&pid_path)?
         && pid_running(pid)
@@ -9730,6 +13617,36 @@ fn start_service(ctx: &RuntimeContext) -> Result<()> {
         return Err(anyhow!("service already running with pid {}", pid));
     }
 
+    let mut command = ProcCommand::new("uv");
+    command
+        .arg("run")
+        .arg("h8-service")
+        .env("H8_CONFIG", ctx.paths.effective_config())
+        .env("H8_ACCOUNT", effective_account(ctx));
+
+    if foreground {
+        command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+        let mut child = spawn(&mut command).context("starting service with `uv run h8-service`")?;
+        // Leave Ctrl-C to the service; set after spawning so the child does
+        // not inherit the ignored signal
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_IGN);
+        }
+        let status = child.wait().context("waiting for service")?;
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+        return match status.code() {
+            Some(0) | None => Ok(()),
+            Some(code) => Err(anyhow!("service exited with status {}", code)),
+        };
+    }
+
     let log_path = ctx.paths.state_dir.join("service.log");
     let log_file = OpenOptions::new()
         .create(true)
@@ -9737,14 +13654,11 @@ fn start_service(ctx: &RuntimeContext) -> Result<()> {
         .open(&log_path)
         .with_context(|| format!("opening service log {}", log_path.display()))?;
 
-    let child = ProcCommand::new("uv")
-        .arg("run")
-        .arg("h8-service")
+    command
         .stdin(Stdio::null())
         .stdout(Stdio::from(log_file.try_clone()?))
-        .stderr(Stdio::from(log_file))
-        .spawn()
-        .context("starting service with `uv run h8-service`")?;
+        .stderr(Stdio::from(log_file));
+    let child = spawn(&mut command).context("starting service with `uv run h8-service`")?;
 
     let pid = child.id();
     fs::write(&pid_path, pid.to_string()).context("writing pid file")?;
@@ -9775,30 +13689,154 @@ fn stop_service(ctx: &RuntimeContext) -> Result<()> {
     Ok(())
 }
 
+/// Time a stopped service gets to exit before `service restart` gives up.
+const RESTART_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
 fn restart_service(ctx: &RuntimeContext) -> Result<()> {
     let pid_path = service_pid_path(ctx)?;
-    if let Some(pid) = read_pid(&pid_path)? {
-        if pid_running(pid) {
-            terminate_pid(pid)?;
-            let _ = fs::remove_file(&pid_path);
+    restart_recorded_service(
+        &pid_path,
+        pid_running,
+        terminate_pid,
+        || start_service(ctx, false),
+        RESTART_STOP_TIMEOUT,
+    )
+}
+
+/// Stop the service recorded in `pid_path`, wait up to `timeout` for it to
+/// exit and `start` a new one. A stale PID file is removed without
+/// signalling anything; a service that does not exit is not restarted.
+fn restart_recorded_service(
+    pid_path: &Path,
+    running: impl Fn(u32) -> bool,
+    terminate: impl FnOnce(u32) -> Result<()>,
+    start: impl FnOnce() -> Result<()>,
+    timeout: Duration,
+) -> Result<()> {
+    if let Some(pid) = read_pid(pid_path)? {
+        if running(pid) {
+            terminate(pid)?;
+            let deadline = std::time::Instant::now() + timeout;
+            while running(pid) {
+                if std::time::Instant::now() >= deadline {
+                    return Err(anyhow!(
+                        "service (pid {}) did not exit within {}s",
+                        pid,
+                        timeout.as_secs()
+                    ));
+                }
+                std::thread::sleep(AUTO_START_POLL);
+            }
             println!("service stopped (pid {})", pid);
-        } else {
-            let _ = fs::remove_file(&pid_path);
         }
+        let _ = fs::remove_file(pid_path);
+    }
+    start()
+}
+
+/// Probe the service with a short timeout so a hung service does not stall
+/// the caller.
+fn probe_health(ctx: &RuntimeContext) -> Result<HealthStatus> {
+    let timeout = Duration::from_secs(ctx.common.timeout.unwrap_or(STATUS_PROBE_TIMEOUT_SECS));
+    ServiceClient::new(&ctx.config.service_url, Some(timeout))
+        .map_err(|e| anyhow!("{e}"))?
+        .with_offline(ctx.offline())
+        .health()
+        .map_err(|e| anyhow!("{e}"))
+}
+
+/// Delay between health probes while waiting for an auto-started service.
+const AUTO_START_POLL: Duration = Duration::from_millis(250);
+
+/// Run a command, and if it fails to connect to the service while the
+/// service is unreachable, start the service, wait up to `timeout` for it to
+/// report healthy and run the command once more. Any other failure is
+/// returned as is.
+fn run_with_auto_start<T>(
+    mut run: impl FnMut() -> Result<T>,
+    mut health: impl FnMut() -> Result<HealthStatus>,
+    start: impl FnOnce() -> Result<()>,
+    timeout: Duration,
+) -> Result<T> {
+    let err = match run() {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    if !is_connection_error(&err) || health().is_ok_and(|status| status.reachable) {
+        return Err(err);
+    }
+
+    eprintln!("service not reachable, starting it...");
+    start().context("auto-starting service")?;
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        if health().is_ok_and(|status| status.healthy) {
+            break;
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(err.context(format!(
+                "service did not become healthy within {}s",
+                timeout.as_secs()
+            )));
+        }
+        std::thread::sleep(AUTO_START_POLL);
+    }
+    run()
+}
+
+/// Health for `service status`. Offline mode forbids contacting the
+/// service, so it is reported as unreachable without a probe.
+fn status_health(ctx: &RuntimeContext) -> Result<HealthStatus> {
+    if ctx.offline() {
+        return Ok(HealthStatus {
+            error: Some("offline mode".to_string()),
+            ..HealthStatus::default()
+        });
     }
-    start_service(ctx)
+    probe_health(ctx)
 }
 
 fn status_service(ctx: &RuntimeContext) -> Result<()> {
     let pid_path = service_pid_path(ctx)?;
-    if let Some(pid) = read_pid(&pid_path)? {
-        if pid_running(pid) {
-            println!("service running (pid {})", pid);
-        } else {
-            println!("service pid file present but process not running ({})", pid);
-        }
+    let pid = read_pid(&pid_path)?;
+    let running = pid.is_some_and(pid_running);
+    let health = status_health(ctx)?;
+
+    if ctx.common.json || ctx.common.yaml {
+        let status = json!({
+            "pid": pid,
+            "process_running": running,
+            "offline": ctx.offline(),
+            "service_url": ctx.config.service_url,
+            "reachable": health.reachable,
+            "healthy": health.healthy,
+            "http_status": health.http_status,
+            "error": health.error,
+        });
+        return emit_output(&ctx.common, &status);
+    }
+
+    match pid {
+        Some(pid) if running => println!("service running (pid {})", pid),
+        Some(pid) => println!("service pid file present but process not running ({})", pid),
+        None => println!("service not running"),
+    }
+
+    let url = &ctx.config.service_url;
+    if ctx.offline() {
+        println!("offline mode: not checking {}", url);
+    } else if health.healthy {
+        println!("service responding at {}", url);
+    } else if health.reachable {
+        let code = health
+            .http_status
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("service reachable at {} but unhealthy (HTTP {})", url, code);
+    } else if running {
+        println!("process running but not responding at {}", url);
     } else {
-        println!("service not running");
+        println!("service not reachable at {}", url);
     }
     Ok(())
 }
@@ -9859,8 +13897,11 @@ fn render_agenda(
     tz: chrono_tz::Tz,
     view: AgendaView,
     target_date: NaiveDate,
+    mark_now: bool,
+    display: &DisplayConfig,
 ) -> Result<()> {
-    let today = Local::now().with_timezone(&tz).date_naive();
+    let now = Local::now().with_timezone(&tz);
+    let today = now.date_naive();
     let is_today = target_date == today;
     let start_naive = target_date.and_hms_opt(0, 0, 0).unwrap();
     let end_naive = today.and_hms_opt(23, 59, 59).unwrap();
@@ -9912,8 +13953,8 @@ fn render_agenda(
             subject: subject.clone(),
             short_name,
             location: ev.location.clone(),
-            start_label: start_dt.format("%H:%M").to_string(),
-            end_label: end_dt.format("%H:%M").to_string(),
+            start_label: display.format_time(start_dt.time()),
+            end_label: display.format_time(end_dt.time()),
             start_min,
             end_min,
             all_day: is_all_day,
@@ -9929,12 +13970,10 @@ fn render_agenda(
 
     // Print header
     let weekday = target_date.format("%a").to_string();
-    println!(
-        "{} {} \u{00b7} {}",
-        weekday,
-        target_date.format("%Y-%m-%d"),
-        tz
-    );
+    let date_label = display
+        .format_date(target_date)
+        .unwrap_or_else(|| target_date.format("%Y-%m-%d").to_string());
+    println!("{} {} \u{00b7} {}", weekday, date_label, tz);
     println!("{}", "\u{2500}".repeat(45));
 
     if slots.is_empty() && all_day_events.is_empty() {
@@ -9946,22 +13985,89 @@ fn render_agenda(
     slots.sort_by_key(|s| s.start_min);
 
     // Get current time in minutes from midnight for the time marker (only for today)
-    let now_min = if is_today {
-        let now = Local::now().with_timezone(&tz);
-        Some((now.hour() * 60 + now.minute()) as u32)
-    } else {
-        None
-    };
+    let now_min = is_today.then(|| minutes_of_day(&now));
+    let now_label = display.format_time(now.time());
+    let position = now_min
+        .filter(|_| mark_now)
+        .map(|min| now_position(&slots, min));
+    let marker = position
+        .as_ref()
+        .map(|p| (p.marker_index, now_label.as_str()));
 
     match view {
-        AgendaView::List => render_list_view(&all_day_events, &slots),
+        AgendaView::List => render_list_view(&all_day_events, &slots, marker),
         AgendaView::Gantt => render_gantt_view(&slots, now_min),
-        AgendaView::Compact => render_compact_view(&all_day_events, &slots),
+        AgendaView::Compact => render_compact_view(&all_day_events, &slots, marker),
+    }
+
+    if let (Some(position), Some(now_min)) = (position, now_min) {
+        println!();
+        for &idx in &position.current {
+            let slot = &slots[idx];
+            println!("Now: {} (until {})", slot.subject, slot.end_label);
+        }
+        match position.next {
+            Some(idx) => {
+                let slot = &slots[idx];
+                println!(
+                    "Next: {} at {} (in {})",
+                    slot.subject,
+                    slot.start_label,
+                    format_minutes(slot.start_min - now_min)
+                );
+            }
+            None => println!("No more events today"),
+        }
     }
 
     Ok(())
 }
 
+/// Minutes since midnight of `now`, in its own timezone.
+fn minutes_of_day<T: TimeZone>(now: &DateTime<T>) -> u32 {
+    now.hour() * 60 + now.minute()
+}
+
+/// Where the current time falls among a day's timed slots (sorted by start).
+#[derive(Debug, PartialEq, Eq)]
+struct NowPosition {
+    /// Index of the first slot starting after now; the marker goes before it.
+    marker_index: usize,
+    /// Slots in progress at now.
+    current: Vec<usize>,
+    /// The next slot to start.
+    next: Option<usize>,
+}
+
+fn now_position(slots: &[AgendaSlot], now_min: u32) -> NowPosition {
+    let marker_index = slots.partition_point(|s| s.start_min <= now_min);
+    let current = slots[..marker_index]
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.end_min > now_min)
+        .map(|(idx, _)| idx)
+        .collect();
+    NowPosition {
+        marker_index,
+        current,
+        next: (marker_index < slots.len()).then_some(marker_index),
+    }
+}
+
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, mins) => format!("{}m", mins),
+        (hours, 0) => format!("{}h", hours),
+        (hours, mins) => format!("{}h {}m", hours, mins),
+    }
+}
+
+fn print_now_marker(label: &str) {
+    let text = format!("\u{2500}\u{2500} now {} ", label);
+    let fill = 45usize.saturating_sub(text.chars().count());
+    println!("{}{}", text, "\u{2500}".repeat(fill));
+}
+
 /// Clean subject by removing status prefixes.
 fn clean_subject(subject: &str) -> String {
     let prefixes = [
@@ -10000,7 +14106,9 @@ const ICON_CALENDAR: &str = "\u{f073}"; // nf-fa-calendar
 const ICON_LOCATION: &str = "\u{f041}"; // nf-fa-map_marker
 
 /// Render the detailed list view (Option 1).
-fn render_list_view(all_day: &[AgendaSlot], timed: &[AgendaSlot]) {
+///
+/// `marker` places a current-time line before the timed slot at that index.
+fn render_list_view(all_day: &[AgendaSlot], timed: &[AgendaSlot], marker: Option<(usize, &str)>) {
     // All-day events section
     if !all_day.is_empty() {
         println!();
@@ -10013,7 +14121,10 @@ fn render_list_view(all_day: &[AgendaSlot], timed: &[AgendaSlot]) {
     }
 
     // Timed events
-    for slot in timed {
+    for (idx, slot) in timed.iter().enumerate() {
+        if let Some((_, label)) = marker.filter(|(at, _)| *at == idx) {
+            print_now_marker(label);
+        }
         let time_range = format!("{}\u{2013}{}", slot.start_label, slot.end_label);
         let icon = slot.status.icon();
         println!("{:<14} {}{}", time_range, icon, slot.subject);
@@ -10022,6 +14133,9 @@ fn render_list_view(all_day: &[AgendaSlot], timed: &[AgendaSlot]) {
             println!("{:14} {} {}", "", ICON_LOCATION, loc);
         }
     }
+    if let Some((_, label)) = marker.filter(|(at, _)| *at == timed.len()) {
+        print_now_marker(label);
+    }
 }
 
 /// Render the Gantt-style timeline view (Option 2).
@@ -10121,7 +14235,11 @@ fn render_gantt_view(slots: &[AgendaSlot], now_min: Option<u32>) {
 }
 
 /// Render the compact view (similar to ppl agenda style).
-fn render_compact_view(all_day: &[AgendaSlot], timed: &[AgendaSlot]) {
+fn render_compact_view(
+    all_day: &[AgendaSlot],
+    timed: &[AgendaSlot],
+    marker: Option<(usize, &str)>,
+) {
     use owo_colors::OwoColorize;
 
     // All-day events
@@ -10136,7 +14254,10 @@ fn render_compact_view(all_day: &[AgendaSlot], timed: &[AgendaSlot]) {
     }
 
     // Timed events
-    for slot in timed {
+    for (idx, slot) in timed.iter().enumerate() {
+        if let Some((_, label)) = marker.filter(|(at, _)| *at == idx) {
+            print_now_marker(label);
+        }
         let time_range = format!("{}-{}", slot.start_label, slot.end_label);
         let icon = slot.status.icon();
 
@@ -10146,6 +14267,9 @@ fn render_compact_view(all_day: &[AgendaSlot], timed: &[AgendaSlot]) {
             println!("    {} {}", ICON_LOCATION, loc.dimmed());
         }
     }
+    if let Some((_, label)) = marker.filter(|(at, _)| *at == timed.len()) {
+        print_now_marker(label);
+    }
 }
 
 fn parse_datetime_local(raw: &str, tz: chrono_tz::Tz) -> Option<DateTime<chrono_tz::Tz>> {
@@ -10469,7 +14593,7 @@ fn handle_rules(ctx: &RuntimeContext, command: RulesCommand) -> Result<()> {
             let db = open_database(ctx, &account)?;
             let id_gen = IdGenerator::new(&db);
             let remote_id = resolve_rule_id(&id_gen, &args.id)?;
-            if !args.yes && !ctx.common.assume_yes {
+            if !ctx.common.assume_yes {
                 print!("Delete rule {}? [y/N] ", args.id);
                 io::stdout().flush()?;
                 let mut input = String::new();
@@ -10957,14 +15081,34 @@ fn handle_sync(ctx: &RuntimeContext, args: SyncArgs) -> Result<()> {
                 match Database::open(&db_path) {
                     Ok(db) => {
                         let id_gen = IdGenerator::new(&db);
-                        // Ensure ID pool is seeded
-                        if let Ok(stats) = id_gen.stats() {
-                            if stats.total() == 0 {
-                                let words = h8_core::id::WordLists::embedded();
-                                let _ = id_gen.init_pool(&words);
+                        // Ensure ID pool is seeded; a word list that fails to
+                        // load is reported instead of leaving the pool empty
+                        let seeded = match id_gen.stats() {
+                            Ok(stats) if stats.total() == 0 => {
+                                ctx.config.ids.word_lists().map(|words| {
+                                    let _ = id_gen.init_pool(&words);
+                                })
                             }
-                        }
-                        if let Ok(synced) = sync_calendar_events(ctx, &account, &events, Some((&cal_start, &cal_end))) {
+                            _ => Ok(()),
+                        };
+                        if let Err(e) = seeded {
+                            has_errors = true;
+                            results.insert(
+                                "calendar".to_string(),
+                                json!({
+                                    "status": "error",
+                                    "message": format!("Word list error: {}", e),
+                                }),
+                            );
+                            if !ctx.common.quiet {
+                                eprintln!("  ✗ Calendar sync failed: {}", e);
+                            }
+                        } else if let Ok(synced) = sync_calendar_events(
+                            ctx,
+                            &account,
+                            &events,
+                            Some((&cal_start, &cal_end)),
+                        ) {
                             results.insert("calendar".to_string(), json!({
                                 "status": "ok",
                                 "events_synced": synced.as_array().map(|a| a.len()).unwrap_or(0),
@@ -11011,6 +15155,10 @@ fn handle_sync(ctx: &RuntimeContext, args: SyncArgs) -> Result<()> {
             folder: None,
             full: args.full,
             limit_days: args.limit_days,
+            refresh_folders: false,
+            bodies: false,
+            jobs: None,
+            strict: false,
         };
         match handle_mail_sync(ctx, &client, &account, mail_args) {
             Ok(()) => {
@@ -11076,3 +15224,3330 @@ fn handle_sync(ctx: &RuntimeContext, args: SyncArgs) -> Result<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_mail_list(args: &[&str]) -> std::result::Result<MailListArgs, clap::Error> {
+        let argv = ["h8", "mail", "list"].iter().chain(args);
+        match Cli::parse_from_args(argv)?.command {
+            Command::Mail {
+                command: MailCommand::List(list),
+            } => Ok(list),
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    fn sorted_dates(order: MailSort) -> Vec<String> {
+        let mut messages = vec![
+            json!({"id": "b", "date": "2026-02-01T09:00:00Z"}),
+            json!({"id": "a", "date": "2026-01-01T09:00:00Z"}),
+            json!({"id": "c", "date": "2026-03-01T09:00:00Z"}),
+        ];
+        order.apply(&mut messages, "date");
+        messages
+            .iter()
+            .map(|m| m["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_mail_list_newest_first() {
+        let args = parse_mail_list(&["--newest-first"]).unwrap();
+        assert_eq!(args.sort_order(), MailSort::Newest);
+        assert_eq!(sorted_dates(args.sort_order()), ["c", "b", "a"]);
+        assert_eq!(parse_mail_list(&[]).unwrap().sort_order(), MailSort::Newest);
+    }
+
+    #[test]
+    fn test_mail_list_oldest_first() {
+        let args = parse_mail_list(&["--oldest-first"]).unwrap();
+        assert_eq!(args.sort_order(), MailSort::Oldest);
+        assert_eq!(sorted_dates(args.sort_order()), ["a", "b", "c"]);
+    }
+
+    fn grouped_ids(groups: &[(String, Vec<Value>)]) -> Vec<(&str, Vec<&str>)> {
+        groups
+            .iter()
+            .map(|(key, members)| {
+                let ids = members.iter().map(|m| m["id"].as_str().unwrap()).collect();
+                (key.as_str(), ids)
+            })
+            .collect()
+    }
+
+    fn group_fixture() -> Vec<Value> {
+        let mut messages = vec![
+            json!({"id": "a", "from": "Bob <Bob@example.com>", "date": "2026-03-02T08:00:00Z"}),
+            json!({"id": "b", "from": "alice@example.com", "date": "2026-03-01T09:00:00Z"}),
+            json!({"id": "c", "from": "bob@example.com", "date": "2026-03-02T17:30:00Z"}),
+            json!({"id": "d", "from": "Alice A. <alice@example.com>", "date": "2026-03-03T07:00:00Z"}),
+            json!({"id": "e", "from": "", "date": "2026-03-01T12:00:00Z"}),
+        ];
+        MailSort::Newest.apply(&mut messages, "date");
+        messages
+    }
+
+    #[test]
+    fn test_mail_list_group_by_sender() {
+        let args = parse_mail_list(&["--group-by", "sender"]).unwrap();
+        assert_eq!(args.group_by, Some(MailGroupBy::Sender));
+        assert!(parse_mail_list(&["--group-by", "sender", "--count-only"]).is_err());
+
+        let groups = MailGroupBy::Sender.apply(group_fixture(), "date", MailSort::Newest);
+        assert_eq!(
+            grouped_ids(&groups),
+            [
+                ("alice@example.com", vec!["d", "b"]),
+                ("bob@example.com", vec!["c", "a"]),
+                ("unknown", vec!["e"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mail_list_group_by_date() {
+        let groups = MailGroupBy::Date.apply(group_fixture(), "date", MailSort::Newest);
+        assert_eq!(
+            grouped_ids(&groups),
+            [
+                ("2026-03-03", vec!["d"]),
+                ("2026-03-02", vec!["c", "a"]),
+                ("2026-03-01", vec!["e", "b"]),
+            ]
+        );
+
+        let mut oldest = group_fixture();
+        MailSort::Oldest.apply(&mut oldest, "date");
+        let groups = MailGroupBy::Date.apply(oldest, "date", MailSort::Oldest);
+        assert_eq!(
+            grouped_ids(&groups),
+            [
+                ("2026-03-01", vec!["b", "e"]),
+                ("2026-03-02", vec!["a", "c"]),
+                ("2026-03-03", vec!["d"]),
+            ]
+        );
+    }
+
+    const TEST_ACCOUNT: &str = "me@example.com";
+
+    fn test_context(dir: &std::path::Path, argv: &[&str]) -> RuntimeContext {
+        let cli = Cli::parse_from_args(argv).unwrap();
+        RuntimeContext {
+            common: cli.common,
+            paths: AppPaths {
+                global_config: dir.join("config.toml"),
+                local_config: dir.join("local.toml"),
+                cli_config: None,
+                state_dir: dir.join("state"),
+                data_dir: dir.join("data"),
+            },
+            config: AppConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_offline_mail_get_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--offline", "mail", "get", "--id", "x"]);
+        assert!(ctx.offline());
+
+        let client = ctx.service_client().unwrap();
+        let args = MailGetArgs {
+            id: "cold-lamp".to_string(),
+            folder: "inbox".to_string(),
+        };
+        let err = handle_mail_get(&ctx, &client, TEST_ACCOUNT, args).unwrap_err();
+        assert!(err.to_string().contains("offline mode"), "{err}");
+    }
+
+    #[test]
+    fn test_offline_service_status_reports_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(
+            dir.path(),
+            &["h8", "--offline", "--json", "service", "status"],
+        );
+        let health = status_health(&ctx).unwrap();
+        assert!(!health.reachable);
+        assert_eq!(health.error.as_deref(), Some("offline mode"));
+        status_service(&ctx).unwrap();
+    }
+
+    #[test]
+    fn test_offline_config_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "mail", "list"]);
+        assert!(!ctx.offline());
+        ctx.config.offline = true;
+        assert!(ctx.offline());
+        assert!(ctx.service_client().unwrap().is_offline());
+    }
+
+    #[test]
+    fn test_offline_mail_read_uses_local_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(
+            dir.path(),
+            &["h8", "--offline", "mail", "read", "cold-lamp"],
+        );
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        mail_dir
+            .store_with_id(
+                "inbox",
+                b"Subject: Offline\nFrom: a@example.com\n\nStored body",
+                &Default::default(),
+                "cold-lamp",
+            )
+            .unwrap();
+
+        let client = ctx.service_client().unwrap();
+        let args = MailReadArgs {
+            id: "cold-lamp".to_string(),
+            folder: "inbox".to_string(),
+            raw: true,
+            html: false,
+            plain: false,
+            full: false,
+        };
+        handle_mail_read(&ctx, &client, TEST_ACCOUNT, args).unwrap();
+
+        let msg = mail_dir.get("inbox", "cold-lamp").unwrap().unwrap();
+        assert!(msg.flags.seen);
+    }
+
+    /// Serve a single HTTP request with a fixed JSON body. Returns the base URL
+    /// and a receiver for the request body.
+    fn fake_service(body: &'static str) -> (String, std::sync::mpsc::Receiver<Value>) {
+        use std::io::{BufRead, BufReader};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            let _ = tx.send(serde_json::from_slice(&request_body).unwrap_or(Value::Null));
+        });
+        (url, rx)
+    }
+
+    #[test]
+    fn test_send_draft_saves_sent_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let doc = ComposeBuilder::new()
+            .to(vec!["Jane Doe <jane@example.com>".to_string()])
+            .subject("Quarterly report")
+            .body("Numbers attached.")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+
+        let client = ctx.service_client().unwrap();
+        send_draft(&ctx, &client, TEST_ACCOUNT, &mail_dir, &draft.id, None).unwrap();
+
+        assert!(mail_dir.list(FOLDER_DRAFTS).unwrap().is_empty());
+        let sent = mail_dir.list(FOLDER_SENT).unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].flags.seen);
+        let content = sent[0].read_content().unwrap();
+        assert!(content.contains("Subject: Quarterly report\r\n"));
+        assert!(content.contains("From: me@example.com\r\n"));
+        let payload = requests.recv().unwrap();
+        let message_id = payload["message_id"].as_str().unwrap();
+        assert!(message_id.ends_with("@example.com>"));
+        assert!(content.contains(&format!("Message-ID: {}\r\n", message_id)));
+
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let row = db.get_message(&sent[0].id).unwrap().unwrap();
+        assert_eq!(row.folder, FOLDER_SENT);
+        assert_eq!(row.subject.as_deref(), Some("Quarterly report"));
+        assert!(row.is_read);
+        assert_eq!(db.search_messages("quarterly", None, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_load_server_folders_uses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "mail", "folders"]);
+        let (url, requests) =
+            fake_service(r#"[{"name": "inbox", "total": 4, "unread": 1}, {"name": "Projects"}]"#);
+        ctx.config.service_url = url;
+        let client = ctx.service_client().unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+
+        let folders = load_server_folders(&ctx, &client, &db, TEST_ACCOUNT, false).unwrap();
+        requests.recv().unwrap();
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[1].total, 0);
+
+        // The fake service only answers once, so these must come from the cache
+        let cached = load_server_folders(&ctx, &client, &db, TEST_ACCOUNT, false).unwrap();
+        assert_eq!(cached, folders);
+        assert!(load_server_folders(&ctx, &client, &db, TEST_ACCOUNT, true).is_err());
+
+        ctx.config.mail.folder_cache_ttl_secs = 0;
+        ctx.common.offline = true;
+        let offline = load_server_folders(&ctx, &client, &db, TEST_ACCOUNT, true).unwrap();
+        assert_eq!(offline, folders);
+    }
+
+    #[test]
+    fn test_send_draft_respects_save_sent_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        ctx.config.service_url = fake_service(r#"{"success": true}"#).0;
+        ctx.config.mail.save_sent = false;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@example.com".to_string()])
+            .subject("No copy")
+            .body("Body")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+
+        let client = ctx.service_client().unwrap();
+        send_draft(&ctx, &client, TEST_ACCOUNT, &mail_dir, &draft.id, None).unwrap();
+
+        assert!(mail_dir.list(FOLDER_SENT).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_compose_to_contact_resolves_addresses() {
+        let argv = [
+            "h8",
+            "mail",
+            "compose",
+            "--to-contact",
+            "AAMk1",
+            "--to-contact",
+            "bob",
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &argv);
+        ctx.config
+            .people
+            .insert("Bob".to_string(), "bob@example.com".to_string());
+        let Command::Mail {
+            command: MailCommand::Compose(args),
+        } = Cli::parse_from_args(argv).unwrap().command
+        else {
+            panic!("expected mail compose");
+        };
+
+        let mut looked_up = Vec::new();
+        let to = resolve_contact_recipients(&ctx, &args.to_contact, |id| {
+            looked_up.push(id.to_string());
+            Ok(json!({"id": id, "display_name": "Doe, Jane", "email": "jane@example.com"}))
+        })
+        .unwrap();
+        let doc = ComposeBuilder::new().to(to).build();
+
+        assert_eq!(looked_up, ["AAMk1"]);
+        assert_eq!(
+            doc.to,
+            [r#""Doe, Jane" <jane@example.com>"#, "bob@example.com"]
+        );
+        assert!(doc.validate().is_ok());
+    }
+
+    #[test]
+    fn test_compose_to_contact_without_email_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "compose"]);
+        let err = resolve_contact_recipients(&ctx, &["AAMk2".to_string()], |_| {
+            Ok(json!({"id": "AAMk2", "display_name": "No Mail", "email": null}))
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("has no email address"), "{err}");
+    }
+
+    #[test]
+    fn test_mail_thread_local() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "thread", "blue-frog"]);
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let messages = [
+            ("cold-lamp", None, "2026-01-01T09:00:00Z"),
+            ("red-kite", Some("blue-frog"), "2026-01-03T09:00:00Z"),
+            ("blue-frog", Some("cold-lamp"), "2026-01-02T09:00:00Z"),
+            ("tall-pine", Some("cold-lamp"), "2026-01-04T09:00:00Z"),
+        ];
+        for (id, parent, date) in messages {
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+                subject: Some("Plan".to_string()),
+                received_at: Some(date.to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+            let message_id = format!("<{id}@example.com>");
+            let parent_id = parent.map(|p| format!("<{p}@example.com>"));
+            db.set_thread_headers(id, Some(&message_id), parent_id.as_deref(), None)
+                .unwrap();
+        }
+
+        let args = |id: &str| MailThreadArgs { id: id.to_string() };
+        handle_mail_thread(&ctx, TEST_ACCOUNT, args("blue-frog")).unwrap();
+        assert!(handle_mail_thread(&ctx, TEST_ACCOUNT, args("unknown")).is_err());
+
+        let messages = db.list_thread_messages(None).unwrap();
+        let tree = build_thread_tree(messages).remove(0);
+        let lines = thread_lines(&tree.flatten(), "blue-frog");
+        let shape: Vec<(char, usize, &str)> = lines
+            .iter()
+            .map(|line| {
+                let body = &line[1..];
+                let indent = body.len() - body.trim_start().len();
+                let id = line.split_whitespace().find(|w| w.contains('-')).unwrap();
+                (line.chars().next().unwrap(), indent / 2, id)
+            })
+            .collect();
+        assert_eq!(
+            shape,
+            [
+                (' ', 0, "cold-lamp"),
+                ('*', 1, "blue-frog"),
+                (' ', 2, "red-kite"),
+                (' ', 1, "tall-pine"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mail_list_thread_collapses_conversation() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "list", "--thread"]);
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let messages = [
+            ("cold-lamp", None, "2026-01-01T09:00:00Z"),
+            ("blue-frog", Some("cold-lamp"), "2026-01-02T09:00:00Z"),
+            ("red-kite", Some("blue-frog"), "2026-01-03T09:00:00Z"),
+            ("tall-pine", None, "2026-01-02T12:00:00Z"),
+        ];
+        for (id, parent, date) in messages {
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+                subject: Some(format!("Subject {id}")),
+                received_at: Some(date.to_string()),
+                is_read: true,
+                ..Default::default()
+            })
+            .unwrap();
+            let message_id = format!("<{id}@example.com>");
+            let parent_id = parent.map(|p| format!("<{p}@example.com>"));
+            db.set_thread_headers(id, Some(&message_id), parent_id.as_deref(), None)
+                .unwrap();
+        }
+
+        let rows = |argv: &[&str]| {
+            let args = parse_mail_list(argv).unwrap();
+            list_local_messages(&db, &mail_dir, &args, None).unwrap()
+        };
+        let threaded = rows(&["--thread"]);
+        let summary: Vec<(&str, u64)> = threaded
+            .iter()
+            .map(|r| {
+                (
+                    r["id"].as_str().unwrap(),
+                    r["thread_count"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(summary, [("red-kite", 3), ("tall-pine", 1)]);
+
+        let flat = rows(&[]);
+        assert_eq!(flat.len(), 4);
+        assert!(flat.iter().all(|r| r.get("thread_count").is_none()));
+    }
+
+    #[test]
+    fn test_read_display_body_converts_html() {
+        let html = "<p>Hello <b>world</b></p><p>Second &amp; last</p>";
+        let declared = read_display_body(
+            "Content-Type: text/html; charset=utf-8",
+            html,
+            false,
+            false,
+            80,
+        );
+        assert!(declared.contains("Hello"));
+        assert!(declared.contains("Second & last"));
+        assert!(!declared.contains("<p>"));
+
+        let forced = read_display_body("Subject: Hi", html, false, true, 80);
+        assert_eq!(forced, declared);
+
+        let document = "<html><body><h1>Title</h1></body></html>";
+        assert!(!read_display_body("", document, false, false, 80).contains("<h1>"));
+
+        // --raw bypasses conversion
+        assert_eq!(
+            read_display_body("Content-Type: text/html", html, true, false, 80),
+            html
+        );
+    }
+
+    #[test]
+    fn test_read_display_body_passes_plain_text_through() {
+        let body = "Hi Jane,\n\nuse a <placeholder> here & there.\n";
+        let headers = "Subject: Hi\nContent-Type: text/plain; charset=utf-8";
+        assert_eq!(read_display_body(headers, body, false, false, 20), body);
+    }
+
+    #[test]
+    fn test_render_table_aligns_mail_columns() {
+        let messages = json!([
+            {"id": "cold-lamp", "subject": "Budget", "from": "alice@example.com",
+             "date": "2026-01-02", "is_read": true},
+            {"id": "blue-frog", "subject": "A very long subject line that cannot possibly fit",
+             "from": "bob@example.com", "date": "2026-01-03", "is_read": false},
+        ]);
+
+        let table = render_table(&messages, 200).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("ID         SUBJECT"));
+        assert!(lines[1].starts_with("---------  -------"));
+        for column in ["SUBJECT", "FROM", "DATE"] {
+            let offset = lines[0].find(column).unwrap();
+            assert!(lines[2..].iter().all(|l| l.as_bytes()[offset - 1] == b' '));
+            assert!(lines[2..].iter().all(|l| l.as_bytes()[offset] != b' '));
+        }
+
+        let narrow = render_table(&messages, 60).unwrap();
+        assert!(narrow.lines().all(|l| l.chars().count() <= 60));
+        assert!(narrow.contains("A very long subj"));
+        assert!(narrow.contains(".."));
+        assert!(narrow.contains("blue-frog"));
+    }
+
+    /// Minimal RFC 4180 reader for checking `render_csv` output.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => row.push(std::mem::take(&mut field)),
+                (false, '\n') => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_render_csv_escapes_contact_fields() {
+        let contacts = json!([
+            {"id": "x", "display_name": "Alice \"Al\" Smith", "email": "alice@example.com",
+             "phone": null, "company": "Acme, Inc.", "job_title": "CEO"},
+            {"id": "y", "display_name": "Bob", "email": "bob@example.com",
+             "phone": "+49 30 1234", "company": "Line\nBreak"},
+        ]);
+
+        let csv = render_csv(&contacts).unwrap();
+        assert!(csv.contains("\"Acme, Inc.\""));
+        assert!(csv.contains("\"Alice \"\"Al\"\" Smith\""));
+        assert_eq!(
+            parse_csv(&csv),
+            [
+                vec!["name", "email", "phone", "company"],
+                vec!["Alice \"Al\" Smith", "alice@example.com", "", "Acme, Inc."],
+                vec!["Bob", "bob@example.com", "+49 30 1234", "Line\nBreak"],
+            ]
+        );
+
+        let events = json!([{"subject": "Sync", "start": "2026-01-05T09:00:00",
+                             "end": "2026-01-05T09:30:00", "location": "Room 1, 2nd floor"}]);
+        assert_eq!(
+            parse_csv(&render_csv(&events).unwrap())[1],
+            [
+                "Sync",
+                "2026-01-05T09:00:00",
+                "2026-01-05T09:30:00",
+                "Room 1, 2nd floor"
+            ]
+        );
+
+        assert_eq!(render_csv(&json!([])).unwrap(), "");
+        assert!(render_csv(&json!(["a", "b"])).is_err());
+        assert!(render_csv(&json!("text")).is_err());
+        assert!(render_csv(&json!({"rules": [1, 2]})).is_err());
+    }
+
+    #[test]
+    fn test_strip_global_flags_extracts_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "list"]);
+        let words: Vec<String> = [
+            "tomorrow",
+            "--format",
+            "table",
+            "--format=json",
+            "--format",
+            "x",
+        ]
+        .iter()
+        .map(|w| w.to_string())
+        .collect();
+        let (stripped, rest) = strip_global_flags(&ctx, &words);
+        assert_eq!(stripped.common.format, Some(OutputFormat::Json));
+        assert!(stripped.common.json);
+        assert_eq!(rest, ["tomorrow", "--format", "x"]);
+    }
+
+    #[test]
+    fn test_cli_definition_and_format_options() {
+        Cli::definition().debug_assert();
+
+        let format = |argv: &[&str]| {
+            let cli = Cli::parse_from_args(argv).unwrap();
+            let output = cli.common.format;
+            let fetch = match cli.command {
+                Command::Mail {
+                    command: MailCommand::Fetch(args),
+                } => Some(args.format),
+                _ => None,
+            };
+            (output, fetch)
+        };
+        assert_eq!(
+            format(&["h8", "mail", "list", "--format", "table"]),
+            (Some(OutputFormat::Table), None)
+        );
+        // `mail fetch --format` is the export format, not the output format
+        assert_eq!(
+            format(&["h8", "mail", "fetch", "-o", "out", "--format", "mbox"]),
+            (None, Some(FetchFormat::Mbox))
+        );
+        assert_eq!(
+            format(&["h8", "mail", "fetch", "-o", "out"]),
+            (None, Some(FetchFormat::Maildir))
+        );
+        assert_eq!(
+            format(&["h8", "--format", "table", "mail", "fetch", "-o", "out"]),
+            (Some(OutputFormat::Table), Some(FetchFormat::Maildir))
+        );
+        assert_eq!(
+            format(&["h8", "mail", "--format", "csv", "list"]),
+            (Some(OutputFormat::Csv), None)
+        );
+        assert!(Cli::parse_from_args(["h8", "mail", "list", "--format", "mbox"]).is_err());
+        let version = Cli::parse_from_args(["h8", "mail", "--version"]).unwrap_err();
+        assert_eq!(version.kind(), clap::error::ErrorKind::DisplayVersion);
+    }
+
+    #[test]
+    fn test_render_table_contact_and_fallback_shapes() {
+        let contacts = json!([{"id": "x", "display_name": "Alice", "email": "a@example.com"}]);
+        let table = render_table(&contacts, 80).unwrap();
+        assert_eq!(
+            table.lines().next(),
+            Some("NAME   EMAIL          PHONE  COMPANY")
+        );
+        assert!(table.contains("Alice  a@example.com"));
+
+        let generic = json!([{"name": "work", "rules": [1, 2]}]);
+        assert_eq!(
+            render_table(&generic, 80).unwrap().lines().next(),
+            Some("NAME")
+        );
+        assert!(render_table(&json!(["a", "b"]), 80).is_none());
+        assert!(render_table(&json!([]), 80).is_none());
+    }
+
+    fn snapshot_files(dir: &std::path::Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(path) = pending.pop() {
+            for entry in std::fs::read_dir(&path).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_dry_run_leaves_maildir_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--dry-run", "--quiet", "mail", "list"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        for id in ["cold-lamp", "blue-frog"] {
+            mail_dir
+                .store_with_id("inbox", b"Subject: Hi\n\nBody", &Default::default(), id)
+                .unwrap();
+        }
+        let before = snapshot_files(dir.path());
+
+        let ids = vec!["cold-lamp".to_string(), "blue-frog".to_string()];
+        handle_mail_delete(
+            &ctx,
+            TEST_ACCOUNT,
+            MailDeleteArgs {
+                ids: ids.clone(),
+                select: None,
+                folder: "inbox".to_string(),
+                force: true,
+                sync: true,
+            },
+        )
+        .unwrap();
+        handle_mail_move(
+            &ctx,
+            TEST_ACCOUNT,
+            MailMoveArgs {
+                ids: ids.clone(),
+                select: None,
+                target: Some("archive".to_string()),
+                folder: "inbox".to_string(),
+                query: None,
+                all_from: None,
+                limit: 50,
+                create: true,
+                sync: true,
+                dry_run: false,
+            },
+        )
+        .unwrap();
+        handle_mail_mark(
+            &ctx,
+            TEST_ACCOUNT,
+            MailMarkArgs {
+                ids,
+                select: None,
+                folder: "inbox".to_string(),
+                read: true,
+                unread: false,
+                query: None,
+                older_than: None,
+                limit: 500,
+                dry_run: false,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(snapshot_files(dir.path()), before);
+    }
+
+    #[test]
+    fn test_dry_run_plan_reports_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--dry-run", "mail", "list"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let msg = mail_dir
+            .store_with_id(
+                "inbox",
+                b"Subject: Hi\n\nBody",
+                &Default::default(),
+                "cold-lamp",
+            )
+            .unwrap();
+
+        let ids = vec!["cold-lamp".to_string(), "missing".to_string()];
+        let plan = DryRunPlan::build(&mail_dir, "inbox", &ids, |msg| {
+            describe_move(&mail_dir, msg, FOLDER_TRASH)
+        });
+        assert_eq!(plan.actions.len(), 1);
+        let (id, action) = &plan.actions[0];
+        assert_eq!(id, "cold-lamp");
+        let dest = mail_dir
+            .folder_path(FOLDER_TRASH)
+            .join("new")
+            .join("cold-lamp");
+        assert_eq!(
+            action,
+            &format!(
+                "move cold-lamp to trash ({} -> {})",
+                msg.path.display(),
+                dest.display()
+            )
+        );
+        assert_eq!(plan.result.failed.len(), 1);
+        assert_eq!(plan.result.failed[0].id, "missing");
+        assert!(plan.report(&ctx).is_err());
+        assert!(msg.path.exists());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_mail_list_sort_flags_conflict() {
+        assert!(parse_mail_list(&["--oldest-first", "--sort", "newest"]).is_err());
+        assert!(parse_mail_list(&["--newest-first", "--sort", "oldest"]).is_err());
+        assert!(parse_mail_list(&["--newest-first", "--oldest-first"]).is_err());
+        assert_eq!(
+            parse_mail_list(&["--sort", "oldest"]).unwrap().sort_order(),
+            MailSort::Oldest
+        );
+    }
+
+    #[test]
+    fn test_free_slots_ics() {
+        let slot = |start: &str, end: &str| FreeSlotItem {
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+            date: None,
+            day: None,
+            duration_minutes: None,
+        };
+        let items = vec![
+            // Offset input is written as UTC
+            slot("2026-01-05T09:00:00+01:00", "2026-01-05T09:30:00Z"),
+            // Offset-less input is local to the configured zone
+            slot("2026-07-06T14:00:00", "2026-07-06T15:00:00"),
+        ];
+        let stamp = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        let ics = free_slots_ics(&items, chrono_tz::Europe::Berlin, stamp).unwrap();
+
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(!ics.replace("\r\n", "").contains('\n'));
+        let lines: Vec<&str> = ics.trim_end().split("\r\n").collect();
+        assert_eq!(lines[0], "BEGIN:VCALENDAR");
+        assert!(lines.contains(&"VERSION:2.0"));
+
+        let events: Vec<&[&str]> = lines.split(|l| *l == "BEGIN:VEVENT").skip(1).collect();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains(&"DTSTART:20260105T080000Z"));
+        assert!(events[0].contains(&"DTEND:20260105T093000Z"));
+        assert!(events[0].contains(&"DTSTAMP:20260101T120000Z"));
+        assert!(events[1].contains(&"DTSTART:20260706T120000Z"));
+        assert!(events[1].contains(&"DTEND:20260706T130000Z"));
+        assert!(!ics.contains("TZID"));
+        for event in &events {
+            assert!(event.iter().any(|l| l.starts_with("UID:")));
+            assert!(event.contains(&"END:VEVENT"));
+        }
+    }
+
+    #[test]
+    fn test_download_all_attachments_skips_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join(attachment_dir_name("cold-lamp"));
+        let attachments = vec![
+            json!({"index": 0, "name": "report.pdf", "size": 6}),
+            json!({"index": 1, "name": "photo.jpg", "size": 5}),
+        ];
+        let contents = [&b"report"[..], &b"photo"[..]];
+
+        // Stand-in for the service, which writes the file itself
+        let mut calls = Vec::new();
+        let summary = download_all_attachments(&attachments, &target, |index| {
+            calls.push(index);
+            let name = attachments[index]["name"].as_str().unwrap();
+            fs::write(target.join(name), contents[index]).unwrap();
+            Ok(json!({"success": true, "name": name}))
+        })
+        .unwrap();
+        assert_eq!(calls, [0, 1]);
+        assert_eq!(summary.downloaded, ["report.pdf", "photo.jpg"]);
+        assert_eq!(fs::read(target.join("report.pdf")).unwrap(), b"report");
+        assert_eq!(fs::read(target.join("photo.jpg")).unwrap(), b"photo");
+
+        let summary = download_all_attachments(&attachments, &target, |index| {
+            panic!("unexpected download of {index}")
+        })
+        .unwrap();
+        assert!(summary.downloaded.is_empty());
+        assert_eq!(summary.skipped.len(), 2);
+
+        // A size mismatch means a partial or different file
+        fs::write(target.join("photo.jpg"), b"ph").unwrap();
+        let summary = download_all_attachments(&attachments, &target, |_| {
+            Ok(json!({"success": false, "error": "Message not found"}))
+        })
+        .unwrap();
+        assert_eq!(summary.skipped, ["report.pdf"]);
+        assert_eq!(summary.failed, ["photo.jpg: Message not found"]);
+    }
+
+    #[test]
+    fn test_attachment_dir_name() {
+        assert_eq!(attachment_dir_name("cold-lamp"), "cold-lamp");
+        assert_eq!(attachment_dir_name("AAMk/x+y=="), "AAMk_x_y__");
+    }
+
+    #[test]
+    fn test_fetch_message_bodies_concurrent_matches_sequential() {
+        let pending: Vec<BodyFetch> = (0..12_u32)
+            .map(|i| BodyFetch {
+                local_id: format!("msg-{i}"),
+                remote_id: format!("remote-{i}"),
+                folder: if i.is_multiple_of(3) { "sent" } else { "inbox" }.to_string(),
+            })
+            .collect();
+        // Mocked client: varying latency so completion order differs from
+        // request order, and one message the server cannot find
+        let fetch_one = |item: &BodyFetch| -> Value {
+            let n: u64 = item
+                .remote_id
+                .trim_start_matches("remote-")
+                .parse()
+                .unwrap();
+            std::thread::sleep(Duration::from_millis((12 - n) * 2));
+            if n == 7 {
+                return json!({"error": "Message not found"});
+            }
+            json!({
+                "subject": format!("Subject {n}"),
+                "from": "alice@example.com",
+                "body": format!("Body {n}"),
+                "is_read": n.is_multiple_of(2),
+            })
+        };
+        let fetch = |batch: &[BodyFetch]| -> Result<Vec<Value>> {
+            Ok(batch.iter().map(fetch_one).collect())
+        };
+
+        let stored_set = |jobs: usize, batch: usize| {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = test_context(dir.path(), &["h8", "mail", "sync", "--bodies"]);
+            let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+            let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+            let mut reports = Vec::new();
+            let mut report = |p: Progress| reports.push((p.current, p.total));
+            let (stored, failed) = fetch_message_bodies(
+                &mail_dir,
+                &db,
+                &pending,
+                jobs,
+                batch,
+                0,
+                fetch,
+                Some(&mut report),
+            );
+            assert_eq!(stored, 11);
+            assert_eq!(failed, ["msg-7: Message not found"]);
+            assert_eq!(reports.len(), 12);
+            assert_eq!(reports.last(), Some(&(12, 12)));
+
+            let mut messages: Vec<(String, String, String, bool)> = ["inbox", "sent"]
+                .iter()
+                .flat_map(|folder| mail_dir.list(folder).unwrap())
+                .map(|m| {
+                    let content = m.read_content().unwrap();
+                    (m.folder.clone(), m.id.clone(), content, m.flags.seen)
+                })
+                .collect();
+            messages.sort();
+            messages
+        };
+
+        let sequential = stored_set(1, 1);
+        assert_eq!(sequential.len(), 11);
+        assert_eq!(stored_set(4, 1), sequential);
+        assert_eq!(stored_set(2, 5), sequential);
+        assert_eq!(stored_set(1, 25), sequential);
+    }
+
+    #[test]
+    fn test_fetch_message_bodies_failed_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "sync", "--bodies"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let pending: Vec<BodyFetch> = ["cold-lamp", "blue-frog", "red-kite"]
+            .iter()
+            .map(|id| BodyFetch {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+            })
+            .collect();
+        // The first batch of two fails as a whole, the second succeeds
+        let fetch = |batch: &[BodyFetch]| -> Result<Vec<Value>> {
+            if batch.len() == 2 {
+                return Err(anyhow!("connection reset"));
+            }
+            Ok(vec![
+                json!({"subject": "Hi", "from": "a@example.com", "body": "x"}),
+            ])
+        };
+        let (stored, failed) = fetch_message_bodies(&mail_dir, &db, &pending, 1, 2, 0, fetch, None);
+        assert_eq!(stored, 1);
+        assert_eq!(
+            failed,
+            ["cold-lamp: connection reset", "blue-frog: connection reset"]
+        );
+        assert!(mail_dir.get("inbox", "red-kite").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_fetch_message_bodies_truncates_large_bodies() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "sync", "--bodies"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let pending: Vec<BodyFetch> = ["cold-lamp", "blue-frog"]
+            .iter()
+            .map(|id| {
+                db.upsert_message(&h8_core::types::MessageSync {
+                    local_id: id.to_string(),
+                    remote_id: format!("remote-{id}"),
+                    folder: "inbox".to_string(),
+                    ..Default::default()
+                })
+                .unwrap();
+                BodyFetch {
+                    local_id: id.to_string(),
+                    remote_id: format!("remote-{id}"),
+                    folder: "inbox".to_string(),
+                }
+            })
+            .collect();
+        let large = "x".repeat(5000);
+        let fetch = |batch: &[BodyFetch]| -> Result<Vec<Value>> {
+            Ok(batch
+                .iter()
+                .map(|item| {
+                    let body = if item.local_id == "cold-lamp" {
+                        large.as_str()
+                    } else {
+                        "Short note"
+                    };
+                    json!({"subject": "Hi", "from": "a@example.com", "body": body})
+                })
+                .collect())
+        };
+        let (stored, failed) =
+            fetch_message_bodies(&mail_dir, &db, &pending, 1, 2, 1024, fetch, None);
+        assert_eq!((stored, failed.len()), (2, 0));
+
+        let content = |id: &str| {
+            let msg = mail_dir.get("inbox", id).unwrap().unwrap();
+            let content = msg.read_content().unwrap();
+            parse_email_content(&content).1.to_string()
+        };
+        let body = content("cold-lamp");
+        assert_eq!(body, format!("{}\n\n[truncated]\n", "x".repeat(1024)));
+        assert!(db.is_body_truncated("cold-lamp").unwrap());
+        assert!(db.get_message("cold-lamp").unwrap().unwrap().size_bytes < Some(1200));
+
+        assert_eq!(content("blue-frog"), "Short note");
+        assert!(!db.is_body_truncated("blue-frog").unwrap());
+    }
+
+    #[test]
+    fn test_mail_list_count_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "list", "--count-only"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let mut read = MessageFlags::default();
+        read.mark_read();
+        mail_dir
+            .store("inbox", b"Subject: a\n\nA", &MessageFlags::default())
+            .unwrap();
+        mail_dir.store("inbox", b"Subject: b\n\nB", &read).unwrap();
+        mail_dir.store("inbox", b"Subject: c\n\nC", &read).unwrap();
+        mail_dir.store("sent", b"Subject: d\n\nD", &read).unwrap();
+
+        let total = parse_mail_list(&["--count-only"]).unwrap();
+        assert_eq!(count_local_messages(&mail_dir, &total).unwrap(), 3);
+        let unread = parse_mail_list(&["--count-only", "--unread"]).unwrap();
+        assert_eq!(count_local_messages(&mail_dir, &unread).unwrap(), 1);
+        let sent = parse_mail_list(&["--count-only", "-f", "sent", "-u"]).unwrap();
+        assert_eq!(count_local_messages(&mail_dir, &sent).unwrap(), 0);
+        let missing = parse_mail_list(&["--count-only", "-f", "nowhere"]).unwrap();
+        assert_eq!(count_local_messages(&mail_dir, &missing).unwrap(), 0);
+
+        assert!(parse_mail_list(&["--count-only", "--thread"]).is_err());
+        assert!(parse_mail_list(&["--count-only", "today"]).is_err());
+    }
+
+    #[test]
+    fn test_contacts_update_sends_only_supplied_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let payload = dir.path().join("contact.json");
+        fs::write(&payload, r#"{"company": "Acme", "job_title": "CTO"}"#).unwrap();
+        let payload_arg = payload.to_str().unwrap();
+        let argv = [
+            "h8",
+            "contacts",
+            "update",
+            "--id",
+            "AAMk=",
+            "--file",
+            payload_arg,
+        ];
+        let argv = argv.into_iter().chain(["--job-title", "CEO"]);
+        let args = match Cli::parse_from_args(argv).unwrap().command {
+            Command::Contacts {
+                command: ContactsCommand::Update(args),
+            } => args,
+            other => panic!("unexpected command: {other:?}"),
+        };
+
+        let updates = contact_updates(&args, Some(read_json_payload(args.file.as_ref()).unwrap()));
+        let (url, requests) = fake_service(r#"{"display_name": "Jane"}"#);
+        let client = ServiceClient::new(&url, None).unwrap();
+        client
+            .contacts_update(TEST_ACCOUNT, &args.id, updates.unwrap())
+            .unwrap();
+        assert_eq!(
+            requests.recv().unwrap(),
+            json!({"company": "Acme", "job_title": "CEO"})
+        );
+
+        assert!(contact_updates(&args, Some(json!({"nickname": "J"}))).is_err());
+        assert!(contact_updates(&args, Some(json!(["company"]))).is_err());
+        let no_fields = ContactsUpdateArgs {
+            id: "x".to_string(),
+            file: None,
+            name: None,
+            given_name: None,
+            surname: None,
+            email: None,
+            phone: None,
+            company: None,
+            job_title: None,
+        };
+        assert!(contact_updates(&no_fields, None).is_err());
+    }
+
+    #[test]
+    fn test_draft_edit_keeps_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "edit", "draft"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@example.com".to_string()])
+            .subject("Before")
+            .body("Old body")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+
+        let mut edited = doc.clone();
+        edited.subject = "After".to_string();
+        edited.body = "New body".to_string();
+        open_editor_and_save_draft(
+            &ctx,
+            TEST_ACCOUNT,
+            edited,
+            false,
+            false,
+            Some(&draft.id),
+            false,
+        )
+        .unwrap();
+
+        let drafts = mail_dir.list(FOLDER_DRAFTS).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].id, draft.id);
+        let saved = ComposeDocument::parse(&drafts[0].read_content().unwrap()).unwrap();
+        assert_eq!(saved.subject, "After");
+        assert_eq!(saved.body.trim(), "New body");
+    }
+
+    #[test]
+    fn test_compose_from_reader_saves_draft() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "mail", "compose", "--stdin"]);
+        ctx.config.mail.local_folders = vec!["notes".to_string()];
+        let text = "---\nto:\n  - jane@example.com\nsubject: Piped\n---\n\nFrom stdin\n";
+        compose_from_reader(&ctx, TEST_ACCOUNT, text.as_bytes(), true).unwrap();
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let drafts = mail_dir.list(FOLDER_DRAFTS).unwrap();
+        assert_eq!(drafts.len(), 1);
+        let saved = ComposeDocument::parse(&drafts[0].read_content().unwrap()).unwrap();
+        assert_eq!(saved.subject, "Piped");
+        assert!(
+            mail_dir
+                .list_folders()
+                .unwrap()
+                .contains(&"notes".to_string())
+        );
+
+        let no_recipients = "---\nsubject: Nobody\n---\n\nBody\n";
+        assert!(compose_from_reader(&ctx, TEST_ACCOUNT, no_recipients.as_bytes(), false).is_err());
+    }
+
+    #[test]
+    fn test_compose_import_saves_eml_as_draft() {
+        let dir = tempfile::tempdir().unwrap();
+        let eml = dir.path().join("msg.eml");
+        fs::write(
+            &eml,
+            "To: jane@example.com,\r\n bob@example.com\r\nSubject: Imported\r\n\r\nHello\r\n",
+        )
+        .unwrap();
+        let eml_arg = eml.to_str().unwrap();
+        let argv = [
+            "h8",
+            "--quiet",
+            "mail",
+            "compose",
+            "--no-edit",
+            "--import",
+            eml_arg,
+        ];
+        let ctx = test_context(dir.path(), &argv);
+        let args = match Cli::parse_from_args(argv).unwrap().command {
+            Command::Mail {
+                command: MailCommand::Compose(args),
+            } => args,
+            other => panic!("unexpected command: {other:?}"),
+        };
+        handle_mail_compose(&ctx, TEST_ACCOUNT, args).unwrap();
+
+        let drafts = get_mail_dir(&ctx, TEST_ACCOUNT)
+            .unwrap()
+            .list(FOLDER_DRAFTS)
+            .unwrap();
+        assert_eq!(drafts.len(), 1);
+        let saved = ComposeDocument::parse(&drafts[0].read_content().unwrap()).unwrap();
+        assert_eq!(saved.to, ["jane@example.com", "bob@example.com"]);
+        assert_eq!(saved.subject, "Imported");
+        assert_eq!(saved.body.trim(), "Hello");
+    }
+
+    #[test]
+    fn test_mark_locally_queues_flags_and_push_clears_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "sync"]);
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let msg = mail_dir
+            .store("inbox", b"Subject: hi\n\nHello", &MessageFlags::default())
+            .unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        db.upsert_message(&h8_core::types::MessageSync {
+            local_id: msg.id.clone(),
+            remote_id: "AAMkRemote".to_string(),
+            folder: "inbox".to_string(),
+            subject: Some("hi".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(mark_locally(&mail_dir, &db, "inbox", &msg.id, true, true));
+        assert!(mail_dir.get("inbox", &msg.id).unwrap().unwrap().flags.seen);
+        let pending = db.dirty_flags().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].is_read, Some(true));
+        assert_eq!(pending[0].is_flagged, None);
+
+        // A failed push keeps the change queued
+        let (pushed, failed) =
+            push_flag_changes(&db, |_| Ok(json!({"success": false, "error": "busy"}))).unwrap();
+        assert_eq!(pushed, 0);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(db.dirty_flags().unwrap().len(), 1);
+
+        let client = ctx.service_client().unwrap();
+        let (pushed, failed) = push_pending_flags(&client, TEST_ACCOUNT, &db).unwrap();
+        assert_eq!((pushed, failed.len()), (1, 0));
+        assert!(db.dirty_flags().unwrap().is_empty());
+        // Only the changed field is sent
+        let payload = requests.recv().unwrap();
+        assert_eq!(payload["read"], json!(true));
+        assert!(payload.get("flagged").is_none());
+        assert_eq!(payload["folder"], json!("inbox"));
+    }
+
+    #[test]
+    fn test_config_paths_reflect_xdg_and_overrides() {
+        let env = |key: &str| match key {
+            "XDG_CONFIG_HOME" => Some("/xdg/config".to_string()),
+            "XDG_STATE_HOME" => Some("/xdg/state".to_string()),
+            "XDG_DATA_HOME" => Some("/xdg/data".to_string()),
+            _ => None,
+        };
+        let cli =
+            Cli::parse_from_args(["h8", "--config", "/etc/h8.toml", "config", "paths"]).unwrap();
+        let mut ctx = RuntimeContext {
+            paths: AppPaths::discover_with_env(cli.common.config.clone(), env).unwrap(),
+            common: cli.common,
+            config: AppConfig::default(),
+        };
+
+        let printed = format_paths(&resolved_paths(&ctx, TEST_ACCOUNT).unwrap());
+        assert_eq!(
+            printed,
+            "global config: /xdg/config/h8/config.toml\n\
+             cli config:    /etc/h8.toml\n\
+             state dir:     /xdg/state/h8\n\
+             data dir:      /xdg/data/h8\n\
+             mail dir:      /xdg/data/h8/mail/me@example.com\n\
+             sync db:       /xdg/data/h8/mail/me@example.com/.sync.db\n"
+        );
+
+        ctx.config.mail.data_dir = Some("/srv/mail".to_string());
+        let paths = resolved_paths(&ctx, TEST_ACCOUNT).unwrap();
+        let mail_dir = paths.iter().find(|(key, _)| *key == "mail_dir").unwrap();
+        assert_eq!(mail_dir.1, PathBuf::from("/srv/mail/me@example.com"));
+    }
+
+    #[test]
+    fn test_format_date_human_uses_display_formats() {
+        use chrono::TimeZone;
+        let now = Local.with_ymd_and_hms(2024, 6, 20, 18, 0, 0).unwrap();
+        let at = |y, m, d, h, min| {
+            Local
+                .with_ymd_and_hms(y, m, d, h, min, 0)
+                .unwrap()
+                .to_rfc3339()
+        };
+        let twelve_hour = DisplayConfig {
+            date_format: Some("%d/%m/%Y".to_string()),
+            time_format: "%-I:%M %p".to_string(),
+        };
+
+        let today = at(2024, 6, 20, 14, 5);
+        assert_eq!(
+            format_date_human_with(&today, &DisplayConfig::default(), now),
+            "Today 14:05"
+        );
+        assert_eq!(
+            format_date_human_with(&today, &twelve_hour, now),
+            "Today 2:05 PM"
+        );
+        assert_eq!(
+            format_date_human_with(&at(2024, 3, 1, 9, 0), &twelve_hour, now),
+            "01/03/2024"
+        );
+        assert_eq!(
+            format_date_human_with(&at(2024, 3, 1, 9, 0), &DisplayConfig::default(), now),
+            "Mar 1"
+        );
+        assert_eq!(
+            format_date_human_with("garbage", &twelve_hour, now),
+            "garbage"
+        );
+    }
+
+    #[test]
+    fn test_mail_move_all_from_moves_only_matching_sender() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "list"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        for (id, from) in [
+            ("cold-lamp", "newsletter@x.com"),
+            ("blue-frog", "alice@example.com"),
+            ("warm-tide", "Newsletter@X.com"),
+        ] {
+            mail_dir
+                .store_with_id("inbox", b"Subject: Hi\n\nBody", &Default::default(), id)
+                .unwrap();
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+                subject: Some("Hi".to_string()),
+                from_addr: Some(from.to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        let args = |dry_run| MailMoveArgs {
+            ids: vec!["archive".to_string()],
+            select: None,
+            target: None,
+            folder: "inbox".to_string(),
+            query: None,
+            all_from: Some("newsletter@x.com".to_string()),
+            limit: 50,
+            create: true,
+            sync: false,
+            dry_run,
+        };
+
+        let before = snapshot_files(dir.path());
+        handle_mail_move(&ctx, TEST_ACCOUNT, args(true)).unwrap();
+        assert_eq!(snapshot_files(dir.path()), before);
+
+        handle_mail_move(&ctx, TEST_ACCOUNT, args(false)).unwrap();
+        let ids_in = |folder| {
+            let mut ids: Vec<String> = mail_dir
+                .list(folder)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids_in("inbox"), ["blue-frog"]);
+        assert_eq!(ids_in("archive"), ["cold-lamp", "warm-tide"]);
+        let row = db.get_message("warm-tide").unwrap().unwrap();
+        assert_eq!(row.folder, "archive");
+    }
+
+    #[test]
+    fn test_all_from_target_forms() {
+        let args = |ids: &[&str], target: Option<&str>| MailMoveArgs {
+            ids: ids.iter().map(|s| s.to_string()).collect(),
+            select: None,
+            target: target.map(String::from),
+            folder: "inbox".to_string(),
+            query: None,
+            all_from: Some("a@b.c".to_string()),
+            limit: 50,
+            create: true,
+            sync: true,
+            dry_run: false,
+        };
+        assert_eq!(
+            all_from_target(&args(&["archive"], None)).unwrap(),
+            "archive"
+        );
+        assert_eq!(
+            all_from_target(&args(&["to", "archive"], None)).unwrap(),
+            "archive"
+        );
+        assert_eq!(
+            all_from_target(&args(&[], Some("archive"))).unwrap(),
+            "archive"
+        );
+        assert!(all_from_target(&args(&[], None)).is_err());
+        assert!(all_from_target(&args(&["cold-lamp", "archive"], None)).is_err());
+    }
+
+    #[test]
+    fn test_parse_selection_ranges() {
+        assert_eq!(parse_selection("1-3,5", 5).unwrap(), [1, 2, 3, 5]);
+        assert_eq!(parse_selection(" 4 , 2-3 ,3", 5).unwrap(), [4, 2, 3]);
+        assert_eq!(parse_selection("7", 7).unwrap(), [7]);
+        assert!(parse_selection("0", 5).is_err());
+        assert!(parse_selection("3-1", 5).is_err());
+        assert!(parse_selection("1-x", 5).is_err());
+        assert!(parse_selection("8", 7).is_err());
+        // Out-of-range ends are rejected without expanding the range
+        assert!(parse_selection("1-999999999999", 7).is_err());
+        assert!(parse_selection(",", 5).is_err());
+    }
+
+    #[test]
+    fn test_selection_resolves_against_stored_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "list"]);
+        let rows = vec![
+            json!({"id": "cold-lamp"}),
+            json!({"id": "blue-frog"}),
+            json!({"id": "warm-desk"}),
+        ];
+        LastListing::from_rows(TEST_ACCOUNT, "inbox", &rows)
+            .save(&ctx.paths.state_dir)
+            .unwrap();
+
+        let inbox = Some("inbox");
+        let ids = with_selection(
+            &ctx,
+            TEST_ACCOUNT,
+            inbox,
+            vec!["blue-frog".into()],
+            Some("1-2"),
+        )
+        .unwrap();
+        assert_eq!(ids, ["blue-frog", "cold-lamp"]);
+        assert_eq!(
+            with_selection(&ctx, TEST_ACCOUNT, inbox, Vec::new(), Some("3")).unwrap(),
+            ["warm-desk"]
+        );
+
+        let err = with_selection(&ctx, TEST_ACCOUNT, inbox, Vec::new(), Some("2-4")).unwrap_err();
+        assert!(err.to_string().contains("position 4"), "{err}");
+        let err =
+            with_selection(&ctx, "other@example.com", inbox, Vec::new(), Some("1")).unwrap_err();
+        assert!(err.to_string().contains("list again"), "{err}");
+        let err =
+            with_selection(&ctx, TEST_ACCOUNT, Some("trash"), Vec::new(), Some("1")).unwrap_err();
+        assert!(err.to_string().contains("not trash"), "{err}");
+
+        LastListing::clear(&ctx.paths.state_dir).unwrap();
+        LastListing::clear(&ctx.paths.state_dir).unwrap();
+        let err = with_selection(&ctx, TEST_ACCOUNT, inbox, Vec::new(), Some("1")).unwrap_err();
+        assert!(err.to_string().contains("previous"), "{err}");
+    }
+
+    #[test]
+    fn test_fetch_format_gzip_requires_mbox() {
+        assert_eq!(
+            fetch_format(&FetchFormat::Mbox, true).unwrap(),
+            h8_core::types::FetchFormat::Mbox { gzip: true }
+        );
+        assert!(fetch_format(&FetchFormat::Json, true).is_err());
+        assert_eq!(
+            fetch_format(&FetchFormat::Maildir, false).unwrap(),
+            h8_core::types::FetchFormat::Maildir
+        );
+    }
+
+    #[test]
+    fn test_offline_mail_fetch_exports_local_mbox() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("export");
+        let ctx = test_context(
+            dir.path(),
+            &["h8", "--offline", "mail", "fetch", "-o", "export"],
+        );
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        mail_dir
+            .store_with_id("inbox", b"Subject: Kept\n\nBody", &Default::default(), "m1")
+            .unwrap();
+
+        let client = ctx.service_client().unwrap();
+        let args = MailFetchArgs {
+            folder: "inbox".to_string(),
+            output: out.clone(),
+            format: FetchFormat::Mbox,
+            limit: None,
+            gzip: true,
+            resume: false,
+        };
+        handle_mail_fetch(&ctx, &client, TEST_ACCOUNT, args).unwrap();
+
+        let bytes = fs::read(out.join("mail.mbox.gz")).unwrap();
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+        assert!(!out.join("mail.mbox").exists());
+    }
+
+    #[test]
+    fn test_mail_trash_purge_removes_only_old_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(
+            dir.path(),
+            &["h8", "mail", "trash", "purge", "--older-than", "30"],
+        );
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let old = (Utc::now() - ChronoDuration::days(90)).to_rfc3339();
+        let recent = (Utc::now() - ChronoDuration::days(2)).to_rfc3339();
+        let messages = [
+            ("cold-lamp", "trash", &old),
+            ("blue-frog", "trash", &recent),
+            ("red-kite", "inbox", &old),
+        ];
+        for (id, folder, date) in messages {
+            mail_dir
+                .store_with_id(folder, b"Subject: x\n\nbody", &Default::default(), id)
+                .unwrap();
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: folder.to_string(),
+                received_at: Some(date.to_string()),
+                is_read: true,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let args = TrashPurgeArgs {
+            older_than: Some(30),
+        };
+        handle_mail_trash_purge(&ctx, TEST_ACCOUNT, args).unwrap();
+
+        assert!(mail_dir.get("trash", "cold-lamp").unwrap().is_none());
+        assert!(db.get_message("cold-lamp").unwrap().is_none());
+        assert!(mail_dir.get("trash", "blue-frog").unwrap().is_some());
+        assert!(mail_dir.get("inbox", "red-kite").unwrap().is_some());
+        assert!(db.get_message("red-kite").unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_service_start_foreground_writes_no_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let argv = ["h8", "service", "start", "--foreground"];
+        let Command::Service {
+            command: ServiceCommand::Start(args),
+        } = Cli::parse_from_args(argv).unwrap().command
+        else {
+            panic!("expected service start");
+        };
+        assert!(args.foreground);
+
+        let ctx = test_context(dir.path(), &argv);
+        let pid_path = ctx.paths.state_dir.join("service.pid");
+        let mut launched = Vec::new();
+        launch_service(&ctx, args.foreground, |cmd| {
+            launched.push(format!("{:?}", cmd));
+            ProcCommand::new("true").spawn()
+        })
+        .unwrap();
+        assert_eq!(launched.len(), 1);
+        assert!(launched[0].contains("h8-service"), "{}", launched[0]);
+        assert!(!pid_path.exists());
+
+        // Background mode still records the PID
+        launch_service(&ctx, false, |_| ProcCommand::new("true").spawn()).unwrap();
+        assert!(pid_path.exists());
+    }
+
+    #[test]
+    fn test_restart_stops_then_starts() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("service.pid");
+        fs::write(&pid_path, "4242").unwrap();
+
+        // The old process exits a couple of polls after being signalled
+        let events = std::cell::RefCell::new(Vec::new());
+        let polls = std::cell::Cell::new(0);
+        let running = |pid: u32| {
+            let terminated = events.borrow().contains(&format!("terminate {pid}"));
+            polls.set(polls.get() + 1);
+            !terminated || polls.get() < 3
+        };
+        restart_recorded_service(
+            &pid_path,
+            running,
+            |pid| {
+                events.borrow_mut().push(format!("terminate {pid}"));
+                Ok(())
+            },
+            || {
+                assert!(!pid_path.exists(), "pid file removed before start");
+                events.borrow_mut().push("start".to_string());
+                Ok(())
+            },
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert_eq!(*events.borrow(), ["terminate 4242", "start"]);
+        assert_eq!(polls.get(), 3);
+    }
+
+    #[test]
+    fn test_restart_cleans_stale_pid_and_stuck_service() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("service.pid");
+
+        // Stale PID: nothing is signalled, the file goes and a service starts
+        fs::write(&pid_path, "4242").unwrap();
+        let started = std::cell::Cell::new(false);
+        restart_recorded_service(
+            &pid_path,
+            |_| false,
+            |pid| panic!("terminated stale pid {pid}"),
+            || {
+                started.set(true);
+                Ok(())
+            },
+            Duration::ZERO,
+        )
+        .unwrap();
+        assert!(started.get());
+        assert!(!pid_path.exists());
+
+        // No PID file at all just starts
+        started.set(false);
+        restart_recorded_service(
+            &pid_path,
+            |_| true,
+            |pid| panic!("terminated pid {pid}"),
+            || {
+                started.set(true);
+                Ok(())
+            },
+            Duration::ZERO,
+        )
+        .unwrap();
+        assert!(started.get());
+
+        // A service that ignores the signal keeps the new one from starting
+        fs::write(&pid_path, "4242").unwrap();
+        let err = restart_recorded_service(
+            &pid_path,
+            |_| true,
+            |_| Ok(()),
+            || panic!("started while the old service was running"),
+            Duration::ZERO,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "service (pid 4242) did not exit within 0s");
+        assert!(pid_path.exists());
+    }
+
+    #[test]
+    fn test_format_mail_item_color() {
+        let item = json!({
+            "id": "cold-lamp",
+            "subject": "Budget",
+            "from": "alice@example.com",
+            "is_read": false,
+            "is_flagged": true,
+        });
+        let obj = item.as_object().unwrap();
+
+        let plain = format_mail_item(obj, "Budget", false);
+        assert!(!plain.contains('\x1b'), "{plain:?}");
+        assert!(plain.starts_with("* Budget [flagged] - "), "{plain:?}");
+        assert!(plain.contains("[cold-lamp]\n  alice@example.com\n"));
+
+        let colored = format_mail_item(obj, "Budget", true);
+        assert!(colored.contains("\x1b[1mBudget"), "{colored:?}");
+        assert!(colored.contains("[flagged]"));
+        assert!(colored.contains('\x1b'));
+
+        let read = json!({"id": "blue-frog", "subject": "Hi", "from": "bob", "is_read": true});
+        let read = format_mail_item(read.as_object().unwrap(), "Hi", true);
+        assert!(read.starts_with("  Hi"), "{read:?}");
+        assert!(!read.contains("flagged"));
+    }
+
+    #[test]
+    fn test_color_flags_override_terminal() {
+        let common = |argv: &[&str]| Cli::parse_from_args(argv).unwrap().common;
+        let off = common(&["h8", "--no-color", "mail", "list"]);
+        assert_eq!(off.color_override(), Some(false));
+        assert!(!off.stdout_color());
+        let never = common(&["h8", "--color", "never", "mail", "list"]);
+        assert!(!never.stdout_color());
+        if env::var_os("NO_COLOR").is_none() {
+            let always = common(&["h8", "--color", "always", "mail", "list"]);
+            assert_eq!(always.color_override(), Some(true));
+        }
+    }
+
+    #[test]
+    fn test_collect_mail_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "stats"]);
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let messages = [
+            (
+                "cold-lamp",
+                "inbox",
+                "a\n\nbody",
+                true,
+                "2026-01-05T08:00:00Z",
+            ),
+            (
+                "blue-frog",
+                "inbox",
+                "b\n\nlonger body",
+                false,
+                "2026-03-01T09:00:00Z",
+            ),
+            ("red-kite", "sent", "c\n\nx", true, "2026-02-01T10:00:00Z"),
+        ];
+        for (id, folder, content, seen, date) in messages {
+            let flags = MessageFlags {
+                seen,
+                ..Default::default()
+            };
+            let content = format!("Subject: {content}");
+            mail_dir
+                .store_with_id(folder, content.as_bytes(), &flags, id)
+                .unwrap();
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: folder.to_string(),
+                received_at: Some(date.to_string()),
+                is_read: seen,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let stats = collect_mail_stats(&mail_dir, Some(&db), None).unwrap();
+        let folders = stats["folders"].as_array().unwrap();
+        let inbox = folders.iter().find(|f| f["folder"] == "inbox").unwrap();
+        assert_eq!(inbox["total"], 2);
+        assert_eq!(inbox["unread"], 1);
+        assert_eq!(inbox["synced"], 2);
+        // Sizes are the on-disk file sizes
+        let on_disk: u64 = ["cold-lamp", "blue-frog"]
+            .iter()
+            .map(|id| {
+                let msg = mail_dir.get("inbox", id).unwrap().unwrap();
+                fs::metadata(&msg.path).unwrap().len()
+            })
+            .sum();
+        assert_eq!(on_disk, 39);
+        assert_eq!(inbox["size"], on_disk);
+        assert_eq!(inbox["oldest"], "2026-01-05T08:00:00Z");
+        assert_eq!(inbox["newest"], "2026-03-01T09:00:00Z");
+
+        let total = &stats["total"];
+        assert_eq!(total["total"], 3);
+        assert_eq!(total["unread"], 1);
+        assert_eq!(total["size"], 52);
+        assert_eq!(total["oldest"], "2026-01-05T08:00:00Z");
+        assert_eq!(total["newest"], "2026-03-01T09:00:00Z");
+
+        let sent = collect_mail_stats(&mail_dir, None, Some("sent")).unwrap();
+        assert_eq!(sent["folders"].as_array().unwrap().len(), 1);
+        assert_eq!(sent["folders"][0]["size"], 13);
+        assert_eq!(sent["folders"][0]["synced"], 0);
+        assert!(sent["folders"][0]["oldest"].is_null());
+    }
+
+    #[test]
+    fn test_service_start_passes_config_and_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let argv = ["h8", "-a", TEST_ACCOUNT, "service", "start"];
+        let ctx = test_context(dir.path(), &argv);
+        let mut envs = Vec::new();
+        launch_service(&ctx, true, |cmd| {
+            envs = cmd
+                .get_envs()
+                .map(|(k, v)| (k.to_owned(), v.map(|v| v.to_owned())))
+                .collect();
+            ProcCommand::new("true").spawn()
+        })
+        .unwrap();
+
+        let env = |key: &str| {
+            envs.iter()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, v)| v.clone())
+        };
+        assert_eq!(
+            env("H8_CONFIG").as_deref(),
+            Some(ctx.paths.effective_config().as_os_str())
+        );
+        assert_eq!(
+            env("H8_ACCOUNT").as_deref(),
+            Some(std::ffi::OsStr::new(TEST_ACCOUNT))
+        );
+    }
+
+    fn health(reachable: bool, healthy: bool) -> HealthStatus {
+        HealthStatus {
+            reachable,
+            healthy,
+            ..HealthStatus::default()
+        }
+    }
+
+    fn refused() -> anyhow::Error {
+        anyhow!(h8_core::Error::Unreachable("connection refused".into()))
+    }
+
+    #[test]
+    fn test_auto_start_retries_after_service_is_healthy() {
+        let runs = std::cell::Cell::new(0);
+        let mut probes = vec![
+            health(true, true),
+            health(false, false),
+            health(false, false),
+        ];
+        let started = std::cell::Cell::new(false);
+        let result = run_with_auto_start(
+            || {
+                runs.set(runs.get() + 1);
+                if started.get() {
+                    Ok("done")
+                } else {
+                    Err(refused())
+                }
+            },
+            || Ok(probes.pop().unwrap()),
+            || {
+                started.set(true);
+                Ok(())
+            },
+            Duration::from_secs(5),
+        );
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(runs.get(), 2);
+        assert!(probes.is_empty());
+    }
+
+    #[test]
+    fn test_auto_start_skipped_when_service_reachable() {
+        let mut runs = 0;
+        let result: Result<()> = run_with_auto_start(
+            || {
+                runs += 1;
+                Err(anyhow!("server error: bad request"))
+            },
+            || Ok(health(true, true)),
+            || panic!("service started"),
+            Duration::from_secs(5),
+        );
+        assert!(result.unwrap_err().to_string().contains("bad request"));
+        assert_eq!(runs, 1);
+    }
+
+    #[test]
+    fn test_auto_start_skipped_for_other_errors() {
+        let mut runs = 0;
+        let result: Result<()> = run_with_auto_start(
+            || {
+                runs += 1;
+                Err(anyhow!("unknown folder: archiv"))
+            },
+            || Ok(health(false, false)),
+            || panic!("service started"),
+            Duration::from_secs(5),
+        );
+        assert!(result.unwrap_err().to_string().contains("archiv"));
+        assert_eq!(runs, 1);
+
+        // Core errors flattened into a message still count
+        let flattened = anyhow!("{}", h8_core::Error::Unreachable("refused".into()));
+        assert!(is_connection_error(&flattened));
+    }
+
+    #[test]
+    fn test_may_auto_start_only_repeatable_commands() {
+        let command = |args: &[&str]| Cli::parse_from_args(args).unwrap().command;
+        assert!(may_auto_start(&command(&["h8", "mail", "list"])));
+        assert!(may_auto_start(&command(&["h8", "calendar", "list"])));
+        assert!(may_auto_start(&command(&["h8", "sync"])));
+        assert!(!may_auto_start(&command(&["h8", "mail", "compose"])));
+        assert!(!may_auto_start(&command(&["h8", "mail", "delete", "1"])));
+        assert!(!may_auto_start(&command(&["h8", "service", "status"])));
+    }
+
+    #[test]
+    fn test_auto_start_gives_up_when_never_healthy() {
+        let mut runs = 0;
+        let result: Result<()> = run_with_auto_start(
+            || {
+                runs += 1;
+                Err(refused())
+            },
+            || Ok(health(false, false)),
+            || Ok(()),
+            Duration::ZERO,
+        );
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("did not become healthy"), "{err}");
+        assert_eq!(runs, 1);
+    }
+
+    #[test]
+    fn test_auto_start_start_failure_is_reported() {
+        let result: Result<()> = run_with_auto_start(
+            || Err(refused()),
+            || Ok(health(false, false)),
+            || Err(anyhow!("uv not found")),
+            Duration::from_secs(5),
+        );
+        assert!(format!("{:#}", result.unwrap_err()).contains("uv not found"));
+    }
+
+    #[test]
+    fn test_check_sync_folders() {
+        let known: Vec<Folder> = ["Inbox", "Sent Items", "archive"]
+            .iter()
+            .map(|name| Folder {
+                name: name.to_string(),
+                total: 0,
+                unread: 0,
+            })
+            .collect();
+        let configured = ["inbox".to_string(), "archiv".to_string(), "zzz".to_string()];
+
+        let (present, missing) = check_sync_folders(&configured, &known);
+        assert_eq!(present, ["inbox"]);
+        assert_eq!(
+            missing,
+            [
+                "folder 'archiv' not found on server (did you mean 'archive'?)",
+                "folder 'zzz' not found on server",
+            ]
+        );
+
+        // Known folders never warn, and a failed lookup accepts everything
+        let (present, missing) = check_sync_folders(&configured[..1], &known);
+        assert_eq!((present.len(), missing.len()), (1, 0));
+        let (present, missing) = check_sync_folders(&configured, &[]);
+        assert_eq!((present.len(), missing.len()), (3, 0));
+    }
+
+    #[test]
+    fn test_mail_sync_strict_rejects_unknown_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let argv = ["h8", "mail", "sync", "--strict"];
+        let Command::Mail {
+            command: MailCommand::Sync(args),
+        } = Cli::parse_from_args(argv).unwrap().command
+        else {
+            panic!("expected mail sync");
+        };
+        let mut ctx = test_context(dir.path(), &argv);
+        ctx.config.mail.sync_folders = vec!["inbox".to_string(), "snet".to_string()];
+        // Seed the folder cache so no service call is needed
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        db.folders_or_fetch(Duration::MAX, true, || {
+            Ok(vec![
+                Folder {
+                    name: "inbox".to_string(),
+                    total: 1,
+                    unread: 0,
+                },
+                Folder {
+                    name: "sent".to_string(),
+                    total: 1,
+                    unread: 0,
+                },
+            ])
+        })
+        .unwrap();
+
+        let client = ServiceClient::new("http://127.0.0.1:9", None)
+            .unwrap()
+            .with_offline(true);
+        let err = handle_mail_sync(&ctx, &client, TEST_ACCOUNT, args).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("folder 'snet' not found on server (did you mean 'sent'?)"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("inbox", "inbox"), 0);
+        assert_eq!(edit_distance("inbx", "inbox"), 1);
+        assert_eq!(edit_distance("snet", "sent"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(
+            closest_match("Archiv", ["inbox", "archive"].into_iter()),
+            Some("archive")
+        );
+        assert_eq!(closest_match("zzz", ["inbox", "archive"].into_iter()), None);
+    }
+    #[test]
+    fn test_id_commands() {
+        let db = Database::open_memory().unwrap();
+        let id_gen = IdGenerator::new(&db);
+        let words = h8_core::id::WordLists::from_toml(
+            r#"
+            adjectives = ["cold", "blue"]
+            nouns = ["lamp", "frog"]
+            "#,
+        )
+        .unwrap();
+        id_gen.init_pool(&words).unwrap();
+        let short_id = id_gen.allocate("AAMkRemote1").unwrap();
+
+        let command = |argv: &[&str]| {
+            let Command::Id { command } = Cli::parse_from_args(argv).unwrap().command else {
+                panic!("expected id command");
+            };
+            command
+        };
+
+        let stats = id_command_result(&id_gen, &command(&["h8", "id", "stats"])).unwrap();
+        assert_eq!(stats, json!({"total": 4, "allocated": 1, "remaining": 3}));
+
+        let resolved =
+            id_command_result(&id_gen, &command(&["h8", "id", "resolve", &short_id])).unwrap();
+        assert_eq!(resolved["remote_id"], "AAMkRemote1");
+        assert_eq!(resolved["id"], short_id.as_str());
+
+        let reversed =
+            id_command_result(&id_gen, &command(&["h8", "id", "reverse", "AAMkRemote1"])).unwrap();
+        assert_eq!(reversed["id"], short_id.as_str());
+
+        let err = id_command_result(&id_gen, &command(&["h8", "id", "resolve", "warm-kite"]))
+            .unwrap_err();
+        assert!(err.to_string().contains("not allocated"), "{err}");
+        assert!(id_command_result(&id_gen, &command(&["h8", "id", "reverse", "nope"])).is_err());
+    }
+
+    #[test]
+    fn test_sync_reports_word_list_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "sync", "--calendar"]);
+        ctx.config.service_url = fake_service("[]").0;
+        ctx.config.ids.adjectives = Some(dir.path().join("missing.txt").display().to_string());
+        let Command::Sync(args) = Cli::parse_from_args(["h8", "sync", "--calendar"])
+            .unwrap()
+            .command
+        else {
+            panic!("expected sync command");
+        };
+
+        assert!(handle_sync(&ctx, args).is_err());
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        assert_eq!(IdGenerator::new(&db).stats().unwrap().total(), 0);
+    }
+
+    #[test]
+    fn test_compose_lint_report_counts() {
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@examplecom".to_string()])
+            .build();
+        let report = compose_lint_report(&doc, &[PathBuf::from("/nonexistent/h8-lint.pdf")]);
+        assert_eq!(report["errors"], 2);
+        assert_eq!(report["warnings"], 2);
+        let fields: Vec<&str> = report["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["to", "subject", "body", "attachment"]);
+        assert_eq!(report["issues"][0]["severity"], "error");
+        assert_eq!(report["issues"][1]["severity"], "warning");
+    }
+
+    #[test]
+    fn test_mail_compose_lint_exit_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "compose-lint"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let lint = |id: &str| {
+            let cli = Cli::parse_from_args(["h8", "mail", "compose-lint", id]).unwrap();
+            let Command::Mail {
+                command: MailCommand::ComposeLint(args),
+            } = cli.command
+            else {
+                panic!("expected compose-lint");
+            };
+            handle_mail_compose_lint(&ctx, TEST_ACCOUNT, args)
+        };
+
+        // Warnings only: empty subject still exits successfully
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@example.com".to_string()])
+            .body("Hi")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+        lint(&draft.id).unwrap();
+
+        // Drafts saved from the editor are not validated, so typos can land
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@examplecom".to_string()])
+            .subject("Hi")
+            .body("Hi")
+            .build();
+        let flags = MessageFlags {
+            draft: true,
+            ..Default::default()
+        };
+        let draft = mail_dir
+            .store(FOLDER_DRAFTS, doc.to_string().unwrap().as_bytes(), &flags)
+            .unwrap();
+        assert_eq!(lint(&draft.id).unwrap_err().to_string(), "1 error(s) found");
+
+        assert_eq!(
+            lint("no-such-draft").unwrap_err().to_string(),
+            "draft not found: no-such-draft"
+        );
+    }
+
+    #[test]
+    fn test_reply_document_quotes_selection_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "list"]);
+        let message = json!({
+            "from": "jane@example.com",
+            "subject": "Budget",
+            "body": "Hi,\nthe numbers are in.\nQ3 is over budget by 4%.\nCheers, Jane",
+        });
+
+        let doc = reply_document(
+            &ctx,
+            TEST_ACCOUNT,
+            &message,
+            false,
+            Some("Q3 is over budget by 4%.\n"),
+        );
+        assert_eq!(doc.to, vec!["jane@example.com"]);
+        assert_eq!(doc.subject, "Re: Budget");
+        assert_eq!(doc.body, "\n> Q3 is over budget by 4%.");
+
+        let doc = reply_document(&ctx, TEST_ACCOUNT, &message, false, None);
+        assert!(doc.body.contains("> the numbers are in."));
+        assert!(doc.body.contains("> Cheers, Jane"));
+    }
+
+    #[test]
+    fn test_mail_flag_and_unflag_toggle_maildir_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "list"]);
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let seen = MessageFlags {
+            seen: true,
+            ..Default::default()
+        };
+        let msg = mail_dir
+            .store("inbox", b"Subject: hi\n\nHello", &seen)
+            .unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        db.upsert_message(&h8_core::types::MessageSync {
+            local_id: msg.id.clone(),
+            remote_id: "AAMkRemote".to_string(),
+            folder: "inbox".to_string(),
+            subject: Some("hi".to_string()),
+            is_read: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let run = |ctx: &RuntimeContext, flagged: bool| {
+            let command = if flagged { "flag" } else { "unflag" };
+            let cli = Cli::parse_from_args(["h8", "mail", command, msg.id.as_str()]).unwrap();
+            let Command::Mail {
+                command: MailCommand::Flag(args) | MailCommand::Unflag(args),
+            } = cli.command
+            else {
+                panic!("expected mail {command}");
+            };
+            let client = ctx.service_client().unwrap();
+            handle_mail_flag(ctx, &client, TEST_ACCOUNT, args, flagged)
+        };
+        let maildir_flags = || {
+            let msg = mail_dir.get("inbox", &msg.id).unwrap().unwrap();
+            let name = msg.path.file_name().unwrap().to_string_lossy().into_owned();
+            name.rsplit_once(":2,").unwrap().1.to_string()
+        };
+
+        // A read change queued earlier stays queued; only the flag is sent
+        db.update_message_flags(&msg.id, Some(true), None, true)
+            .unwrap();
+        run(&ctx, true).unwrap();
+        assert_eq!(maildir_flags(), "FS");
+        let payload = requests.recv().unwrap();
+        assert_eq!(payload["flagged"], json!(true));
+        assert!(payload.get("read").is_none());
+        let pending = db.dirty_flags().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].is_read, Some(true));
+        assert_eq!(pending[0].is_flagged, None);
+        db.clear_flags_dirty(&msg.id).unwrap();
+
+        // Offline, the change is only queued for the next sync
+        ctx.config.offline = true;
+        run(&ctx, false).unwrap();
+        assert_eq!(maildir_flags(), "S");
+        let pending = db.dirty_flags().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].is_flagged, Some(false));
+        assert_eq!(pending[0].is_read, None);
+    }
+
+    #[test]
+    fn test_read_display_body_uses_configured_wrap_width() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "mail", "list"]);
+        let html = format!("<html><body><p>{}</p></body></html>", "word ".repeat(60));
+        let longest = |text: &str| text.lines().map(|l| l.trim_end().len()).max().unwrap();
+
+        let width = ctx.config.mail.reader.effective_wrap_width(|| 200);
+        assert!(longest(&read_display_body("", &html, false, false, width)) > 100);
+
+        ctx.config.mail.reader.wrap_width = 40;
+        let width = ctx.config.mail.reader.effective_wrap_width(|| 200);
+        let text = read_display_body("", &html, false, false, width);
+        assert!(longest(&text) <= 40, "{text}");
+    }
+
+    #[test]
+    fn test_contacts_to_vcard() {
+        let contacts = vec![
+            json!({
+                "id": "AAMkC1",
+                "display_name": "Doe, Jane",
+                "given_name": "Jane",
+                "surname": "Doe",
+                "email": "jane@example.com",
+                "phone": "+49 30 1234",
+                "company": "Acme; Research",
+                "job_title": null,
+            }),
+            json!({"id": "AAMkC2", "email": "info@example.com", "phone": ""}),
+            json!({"id": "AAMkC3"}),
+        ];
+        let vcf = contacts_to_vcard(&contacts);
+        assert!(vcf.ends_with("END:VCARD\r\n"));
+
+        let cards: Vec<Vec<(&str, &str)>> = vcf
+            .split("BEGIN:VCARD\r\n")
+            .skip(1)
+            .map(|card| {
+                card.split("\r\n")
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.split_once(':').unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(
+            cards[0],
+            [
+                ("VERSION", "3.0"),
+                ("FN", "Doe\\, Jane"),
+                ("N", "Doe;Jane;;;"),
+                ("EMAIL;TYPE=INTERNET", "jane@example.com"),
+                ("TEL;TYPE=WORK,VOICE", "+49 30 1234"),
+                ("ORG", "Acme\\; Research"),
+                ("END", "VCARD"),
+            ]
+        );
+        // Only an email: it doubles as FN, and the empty phone is omitted
+        assert_eq!(
+            cards[1],
+            [
+                ("VERSION", "3.0"),
+                ("FN", "info@example.com"),
+                ("EMAIL;TYPE=INTERNET", "info@example.com"),
+                ("END", "VCARD"),
+            ]
+        );
+    }
+
+    fn contacts_get_args(argv: &[&str]) -> ContactsGetArgs {
+        let mut full = vec!["h8", "contacts", "get"];
+        full.extend_from_slice(argv);
+        match Cli::parse_from_args(full).unwrap().command {
+            Command::Contacts {
+                command: ContactsCommand::Get(args),
+            } => args,
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    fn contact_search_results() -> Value {
+        json!([
+            {"id": "AAMkC1", "display_name": "Jane Doe", "email": "jane@example.com"},
+            {"id": "AAMkC2", "display_name": "Jane Doerr", "email": "jane.doerr@example.com"},
+            {"id": "AAMkC3", "display_name": "Bob Stone", "email": "bob@example.com"},
+        ])
+    }
+
+    #[test]
+    fn test_resolve_contact_id_unique() {
+        let mut queries = Vec::new();
+        let mut search = |query: &str| {
+            queries.push(query.to_string());
+            Ok(contact_search_results())
+        };
+        let args = contacts_get_args(&["--email", "JANE@example.com"]);
+        assert_eq!(resolve_contact_id(&args, &mut search).unwrap(), "AAMkC1");
+        let args = contacts_get_args(&["--name", "jane doe"]);
+        assert_eq!(resolve_contact_id(&args, &mut search).unwrap(), "AAMkC1");
+        assert_eq!(queries, ["JANE@example.com", "jane doe"]);
+
+        // A single partial match is accepted
+        let args = contacts_get_args(&["--name", "Stone"]);
+        let id = resolve_contact_id(&args, |_| Ok(json!([contact_search_results()[2]])));
+        assert_eq!(id.unwrap(), "AAMkC3");
+    }
+
+    #[test]
+    fn test_resolve_contact_id_no_match() {
+        let args = contacts_get_args(&["--email", "nobody@example.com"]);
+        let err = resolve_contact_id(&args, |_| Ok(json!([]))).unwrap_err();
+        assert!(err.to_string().contains("no contact matches"), "{err}");
+    }
+
+    #[test]
+    fn test_resolve_contact_id_ambiguous_lists_matches() {
+        let args = contacts_get_args(&["--name", "Jane"]);
+        let err = resolve_contact_id(&args, |_| Ok(contact_search_results())).unwrap_err();
+        let text = err.to_string();
+        assert!(text.contains("matches 3 contacts"), "{text}");
+        assert!(
+            text.contains("Jane Doe <jane@example.com> (AAMkC1)"),
+            "{text}"
+        );
+        assert!(
+            text.contains("Jane Doerr <jane.doerr@example.com> (AAMkC2)"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn test_contacts_get_requires_a_selector() {
+        assert!(Cli::parse_from_args(["h8", "contacts", "get"]).is_err());
+        assert!(
+            Cli::parse_from_args(["h8", "contacts", "get", "--id", "x", "--name", "y"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_vcard_fold_long_lines() {
+        let line = format!("FN:{}", "ü".repeat(50));
+        let folded = vcard_fold(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn test_send_draft_retry_reuses_send_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        let (url, requests) = fake_service("{}");
+        ctx.config.service_url = url;
+        ctx.config.mail.save_sent = false;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@example.com".to_string()])
+            .subject("Retry")
+            .body("Body")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+
+        // An unconfirmed send keeps the draft along with its token
+        let client = ctx.service_client().unwrap();
+        assert!(send_draft(&ctx, &client, TEST_ACCOUNT, &mail_dir, &draft.id, None).is_err());
+        let first = requests.recv().unwrap()["send_token"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let kept = mail_dir.get(FOLDER_DRAFTS, &draft.id).unwrap().unwrap();
+        let stored = ComposeDocument::parse(&kept.read_content().unwrap()).unwrap();
+        assert_eq!(stored.send_token.as_deref(), Some(first.as_str()));
+
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+        let client = ctx.service_client().unwrap();
+        send_draft(&ctx, &client, TEST_ACCOUNT, &mail_dir, &draft.id, None).unwrap();
+        assert_eq!(requests.recv().unwrap()["send_token"], first.as_str());
+        assert!(mail_dir.list(FOLDER_DRAFTS).unwrap().is_empty());
+    }
+
+    fn event_form(subject: &str, start: &str, end: &str) -> EventForm {
+        EventForm {
+            subject: subject.to_string(),
+            start: start.to_string(),
+            end: end.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_event_form_payload_timed_event() {
+        let mut form = event_form(" Planning ", "2026-03-11 14:00", "2026-03-11T15:30");
+        form.location = " Room 4 ".to_string();
+        form.attendees = "alice@example.com, , bob@example.com".to_string();
+        let payload = event_form_payload(&form).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "subject": "Planning",
+                "start": "2026-03-11T14:00:00",
+                "end": "2026-03-11T15:30:00",
+                "location": "Room 4",
+                "attendees": ["alice@example.com", "bob@example.com"],
+            })
+        );
+
+        // An empty end defaults to one hour
+        let payload = event_form_payload(&event_form("Sync", "2026-03-11 09:00", "")).unwrap();
+        assert_eq!(payload["end"], "2026-03-11T10:00:00");
+        assert!(payload.get("is_all_day").is_none());
+        assert!(payload.get("attendees").is_none());
+    }
+
+    #[test]
+    fn test_event_form_payload_all_day() {
+        let payload =
+            event_form_payload(&event_form("Offsite", "2026-03-11", "2026-03-13")).unwrap();
+        assert_eq!(payload["start"], "2026-03-11T00:00:00");
+        assert_eq!(payload["end"], "2026-03-14T00:00:00");
+        assert_eq!(payload["is_all_day"], true);
+
+        let payload = event_form_payload(&event_form("Holiday", "2026-03-11", "")).unwrap();
+        assert_eq!(payload["end"], "2026-03-12T00:00:00");
+    }
+
+    #[test]
+    fn test_event_form_payload_rejects_invalid_input() {
+        let err = event_form_payload(&event_form("Late", "2026-03-11 15:00", "2026-03-11 14:00"))
+            .unwrap_err();
+        assert!(err.to_string().contains("end must be after start"));
+        assert!(
+            event_form_payload(&event_form("Zero", "2026-03-11 15:00", "2026-03-11 15:00"))
+                .is_err()
+        );
+        assert!(event_form_payload(&event_form("  ", "2026-03-11 15:00", "")).is_err());
+        assert!(event_form_payload(&event_form("Bad", "someday", "")).is_err());
+
+        let mut form = event_form("Sync", "2026-03-11 09:00", "");
+        form.attendees = "alice".to_string();
+        let err = event_form_payload(&form).unwrap_err();
+        assert!(err.to_string().contains("alice"));
+    }
+
+    #[test]
+    fn test_calendar_create_interactive_conflicts_with_file() {
+        assert!(Cli::parse_from_args(["h8", "calendar", "create", "--interactive"]).is_ok());
+        assert!(
+            Cli::parse_from_args(["h8", "calendar", "create", "-i", "--file", "event.json"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_send_draft_carries_threading_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+        ctx.config.mail.save_sent = false;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let doc = ComposeBuilder::new()
+            .to(vec!["jane@example.com".to_string()])
+            .subject("Re: Budget")
+            .body("Agreed.")
+            .in_reply_to("<orig@example.com>")
+            .references("<root@example.com> <orig@example.com>")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+
+        let client = ctx.service_client().unwrap();
+        send_draft(&ctx, &client, TEST_ACCOUNT, &mail_dir, &draft.id, None).unwrap();
+
+        let payload = requests.recv().unwrap();
+        assert_eq!(payload["in_reply_to"], "<orig@example.com>");
+        assert_eq!(
+            payload["references"],
+            "<root@example.com> <orig@example.com>"
+        );
+    }
+
+    #[test]
+    fn test_check_edited_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "config", "edit"]);
+        let path = &ctx.paths.global_config;
+        let original = "account = \"me@example.com\"\n";
+
+        fs::write(path, "account = \"other@example.com\"\n").unwrap();
+        check_edited_config(&ctx.paths, original).unwrap();
+        assert!(
+            fs::read_to_string(path)
+                .unwrap()
+                .contains("other@example.com")
+        );
+
+        let broken = "[mail\nsave_sent = true\n";
+        fs::write(path, broken).unwrap();
+        let err = check_edited_config(&ctx.paths, original).unwrap_err();
+        assert!(err.to_string().contains("changes reverted"));
+        assert_eq!(fs::read_to_string(path).unwrap(), original);
+        let rejected = dir.path().join("config.toml.rejected");
+        assert_eq!(fs::read_to_string(rejected).unwrap(), broken);
+
+        // Valid TOML that fails config validation is rejected too
+        fs::write(path, "[display]\ntime_format = \"%Q\"\n").unwrap();
+        assert!(check_edited_config(&ctx.paths, original).is_err());
+        assert_eq!(fs::read_to_string(path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_confirm_decision() {
+        use ConfirmDecision::*;
+        // Setting off: never ask
+        assert_eq!(confirm_decision(false, false, true), Proceed);
+        assert_eq!(confirm_decision(false, false, false), Proceed);
+        // -y skips the prompt, with or without a terminal
+        assert_eq!(confirm_decision(true, true, true), Proceed);
+        assert_eq!(confirm_decision(true, true, false), Proceed);
+        assert_eq!(confirm_decision(true, false, true), Prompt);
+        assert_eq!(confirm_decision(true, false, false), Refuse);
+    }
+
+    #[test]
+    fn test_draft_attachments_from_sync_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "mail", "edit", "cold-lamp"]);
+        assert!(draft_attachments(&ctx, TEST_ACCOUNT, "cold-lamp").is_empty());
+
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        db.upsert_message(&h8_core::types::MessageSync {
+            local_id: "cold-lamp".to_string(),
+            remote_id: "AAMk1".to_string(),
+            folder: FOLDER_DRAFTS.to_string(),
+            has_attachments: true,
+            attachments: Some(vec![AttachmentMeta {
+                name: "q3.pdf".to_string(),
+                content_type: None,
+                size: Some(1024),
+            }]),
+            ..Default::default()
+        })
+        .unwrap();
+        let attachments = draft_attachments(&ctx, TEST_ACCOUNT, "cold-lamp");
+        assert_eq!(attachments, [PathBuf::from("q3.pdf")]);
+        assert!(
+            ComposeDocument::default()
+                .summary(&attachments)
+                .ends_with("Attachments: q3.pdf")
+        );
+    }
+
+    #[test]
+    fn test_force_delete_refuses_without_terminal() {
+        let dir = tempfile::tempdir().unwrap();
+        // --force is the delete flag, not an alias for -y
+        let mut ctx = test_context(dir.path(), &["h8", "mail", "delete", "--force", "abc"]);
+        ctx.config.mail.confirm_destructive = true;
+        assert!(!ctx.common.assume_yes);
+        if io::stdin().is_terminal() {
+            return;
+        }
+        let err = confirm_destructive(&ctx, "Delete?").unwrap_err();
+        assert!(err.to_string().contains("pass -y"));
+
+        ctx.common.assume_yes = true;
+        assert!(confirm_destructive(&ctx, "Delete?").unwrap());
+    }
+
+    fn agenda_slot(subject: &str, start_min: u32, end_min: u32) -> AgendaSlot {
+        AgendaSlot {
+            subject: subject.to_string(),
+            short_name: subject.to_string(),
+            location: None,
+            start_label: String::new(),
+            end_label: String::new(),
+            start_min,
+            end_min,
+            all_day: false,
+            status: EventStatus::Normal,
+        }
+    }
+
+    #[test]
+    fn test_minutes_of_day_uses_configured_timezone() {
+        let clock = Utc.with_ymd_and_hms(2026, 3, 11, 13, 30, 0).unwrap();
+        let berlin: chrono_tz::Tz = "Europe/Berlin".parse().unwrap();
+        assert_eq!(minutes_of_day(&clock), 13 * 60 + 30);
+        assert_eq!(minutes_of_day(&clock.with_timezone(&berlin)), 14 * 60 + 30);
+    }
+
+    #[test]
+    fn test_now_position() {
+        let slots = vec![
+            agenda_slot("standup", 9 * 60, 9 * 60 + 15),
+            agenda_slot("review", 10 * 60, 11 * 60),
+            agenda_slot("lunch", 12 * 60, 13 * 60),
+        ];
+
+        // Before the first event
+        let pos = now_position(&slots, 8 * 60);
+        assert_eq!(pos.marker_index, 0);
+        assert!(pos.current.is_empty());
+        assert_eq!(pos.next, Some(0));
+
+        // During the review
+        let pos = now_position(&slots, 10 * 60 + 30);
+        assert_eq!(pos.marker_index, 2);
+        assert_eq!(pos.current, vec![1]);
+        assert_eq!(pos.next, Some(2));
+
+        // An event starting exactly now is in progress, not next
+        let pos = now_position(&slots, 12 * 60);
+        assert_eq!(pos.current, vec![2]);
+        assert_eq!(pos.next, None);
+
+        // After the last event
+        let pos = now_position(&slots, 18 * 60);
+        assert_eq!(pos.marker_index, 3);
+        assert!(pos.current.is_empty());
+        assert_eq!(pos.next, None);
+    }
+
+    #[test]
+    fn test_agenda_now_flag() {
+        assert!(Cli::parse_from_args(["h8", "agenda", "--now"]).is_ok());
+        assert!(Cli::parse_from_args(["h8", "agenda", "--now", "tomorrow"]).is_err());
+        assert_eq!(format_minutes(5), "5m");
+        assert_eq!(format_minutes(120), "2h");
+        assert_eq!(format_minutes(65), "1h 5m");
+    }
+
+    #[test]
+    fn test_send_draft_bcc_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let doc = ComposeBuilder::new()
+            .bcc(vec!["team@example.com".to_string()])
+            .subject("Offsite")
+            .body("See you there.")
+            .build();
+        let draft = mail_dir.store_draft(&doc).unwrap();
+
+        let client = ctx.service_client().unwrap();
+        send_draft(&ctx, &client, TEST_ACCOUNT, &mail_dir, &draft.id, None).unwrap();
+
+        let payload = requests.recv().unwrap();
+        assert_eq!(payload["to"], json!([]));
+        assert_eq!(payload["bcc"][0]["email"], "team@example.com");
+        let sent = mail_dir.list(FOLDER_SENT).unwrap();
+        let content = sent[0].read_content().unwrap();
+        assert!(content.contains("To: undisclosed-recipients:;\r\n"));
+    }
+
+    #[test]
+    fn test_resume_fetch_keys() {
+        use h8_core::types::FetchFormat as Format;
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+
+        // Nothing fetched yet
+        assert!(
+            resume_fetch_keys(output, &Format::Maildir)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            resume_fetch_keys(output, &Format::Mbox { gzip: false })
+                .unwrap()
+                .is_empty()
+        );
+
+        for subdir in ["cur", "new", "tmp"] {
+            fs::create_dir_all(output.join(subdir)).unwrap();
+        }
+        fs::write(output.join("cur/1700000000.aaaa.h8:2,S"), "x").unwrap();
+        fs::write(output.join("new/1700000100.bbbb.h8:2,"), "x").unwrap();
+        fs::write(output.join("tmp/1700000200.cccc.h8:2,"), "x").unwrap();
+        assert_eq!(
+            resume_fetch_keys(output, &Format::Maildir).unwrap(),
+            vec!["aaaa", "bbbb"]
+        );
+
+        fs::write(output.join("mail.mbox.part"), "dddd\neeee\n").unwrap();
+        assert_eq!(
+            resume_fetch_keys(output, &Format::Mbox { gzip: true }).unwrap(),
+            vec!["dddd", "eeee"]
+        );
+        assert!(resume_fetch_keys(output, &Format::Json).is_err());
+    }
+
+    #[test]
+    fn test_mail_fetch_resume_sends_skip_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("backup");
+        fs::create_dir_all(output.join("cur")).unwrap();
+        fs::write(output.join("cur/1700000000.aaaa.h8:2,S"), "x").unwrap();
+        let mut ctx = test_context(
+            dir.path(),
+            &["h8", "mail", "fetch", "-o", "backup", "--resume"],
+        );
+        let (url, requests) = fake_service(r#"{"success": true, "count": 2, "skipped": 1}"#);
+        ctx.config.service_url = url;
+        let client = ctx.service_client().unwrap();
+
+        let args = MailFetchArgs {
+            folder: "inbox".to_string(),
+            output,
+            format: FetchFormat::Maildir,
+            limit: None,
+            gzip: false,
+            resume: true,
+        };
+        handle_mail_fetch(&ctx, &client, TEST_ACCOUNT, args).unwrap();
+
+        let payload = requests.recv().unwrap();
+        assert_eq!(payload["resume"], true);
+        assert_eq!(payload["skip"], json!(["aaaa"]));
+    }
+
+    #[test]
+    fn test_maintain_database_removes_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "db", "maintain"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        for (id, size) in [("kept", Some(20)), ("orphan", Some(20)), ("headers", None)] {
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+                subject: Some("Status".to_string()),
+                size_bytes: size,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        // "kept" now lives in the archive, though its row still says inbox
+        mail_dir
+            .store_with_id(
+                "archive",
+                b"Subject: Status\n\nBody",
+                &Default::default(),
+                "kept",
+            )
+            .unwrap();
+
+        let report = maintain_database(&db, &mail_dir, true).unwrap();
+        assert_eq!(report["orphans"], json!(["orphan"]));
+        assert!(db.get_message("orphan").unwrap().is_some());
+
+        let report = maintain_database(&db, &mail_dir, false).unwrap();
+        assert_eq!(report["orphans"], json!(["orphan"]));
+        assert!(db.get_message("orphan").unwrap().is_none());
+        assert!(db.get_message("kept").unwrap().is_some());
+        // Metadata-only rows never had a file
+        assert!(db.get_message("headers").unwrap().is_some());
+
+        assert!(Cli::parse_from_args(["h8", "db", "vacuum"]).is_ok());
+    }
+
+    #[test]
+    fn test_dedup_messages_keeps_one_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "db", "dedup"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        // All rows share subject, sender, date and hash; red-kite has no body
+        for id in [
+            "cold-lamp",
+            "blue-frog",
+            "red-kite",
+            "green-owl",
+            "grey-owl",
+        ] {
+            db.upsert_message(&h8_core::types::MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+                subject: Some("Status".to_string()),
+                from_addr: Some("alice@example.com".to_string()),
+                received_at: Some("2026-03-01T09:00:00Z".to_string()),
+                local_hash: Some("same".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        let flags = MessageFlags::default();
+        let status = |message_id: &str, body: &str| {
+            format!(
+                "Message-ID: {message_id}\nSubject: Status\nFrom: alice@example.com\n\
+                 Date: Sun, 01 Mar 2026 09:00:00 +0000\n\n{body}"
+            )
+        };
+        for (id, content) in [
+            // Synced twice under different IDs
+            ("cold-lamp", status("<a@example.com>", "Body")),
+            ("blue-frog", status("<a@example.com>", "Body")),
+            // Two distinct messages with the same subject, sender and date
+            ("green-owl", status("<b@example.com>", "Green")),
+            ("grey-owl", status("<c@example.com>", "Grey")),
+            // Stored twice without sync rows
+            ("file-b", "Subject: Local\n\nNote".to_string()),
+            ("file-a", "Subject: Local\n\nNote".to_string()),
+        ] {
+            mail_dir
+                .store_with_id("inbox", content.as_bytes(), &flags, id)
+                .unwrap();
+        }
+        // Identical content in another folder is not a duplicate
+        mail_dir
+            .store_with_id("archive", b"Subject: Local\n\nNote", &flags, "file-c")
+            .unwrap();
+
+        let expected = [
+            DuplicateSet {
+                folder: "inbox".to_string(),
+                kept: "blue-frog".to_string(),
+                removed: vec!["cold-lamp".to_string()],
+            },
+            DuplicateSet {
+                folder: "inbox".to_string(),
+                kept: "file-a".to_string(),
+                removed: vec!["file-b".to_string()],
+            },
+        ];
+        let mut scanned = Vec::new();
+        let mut cb = |p: Progress| scanned.push((p.current, p.total));
+        dedup_messages(&db, &mail_dir, true, Some(&mut cb)).unwrap();
+        assert_eq!(scanned, [(1, 2), (2, 2)]);
+
+        let dedup = |dry_run| {
+            let mut sets = dedup_messages(&db, &mail_dir, dry_run, None).unwrap();
+            sets.sort_by(|a, b| a.kept.cmp(&b.kept));
+            sets
+        };
+        assert_eq!(dedup(true), expected);
+        assert!(db.get_message("cold-lamp").unwrap().is_some());
+        assert!(mail_dir.get("inbox", "file-b").unwrap().is_some());
+
+        assert_eq!(dedup(false), expected);
+        let mut left: Vec<String> = mail_dir
+            .list("inbox")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        left.sort();
+        assert_eq!(left, ["blue-frog", "file-a", "green-owl", "grey-owl"]);
+        assert!(db.get_message("cold-lamp").unwrap().is_none());
+        for id in ["blue-frog", "red-kite", "green-owl", "grey-owl"] {
+            assert!(db.get_message(id).unwrap().is_some());
+        }
+        assert!(mail_dir.get("archive", "file-c").unwrap().is_some());
+
+        assert!(dedup(false).is_empty());
+    }
+
+    #[test]
+    fn test_render_template_fills_fields() {
+        let messages = json!([
+            {
+                "id": "cold-lamp",
+                "subject": "Quarterly report",
+                "from": "alice@example.com",
+                "date": "2026-03-01",
+                "size_bytes": 2048,
+                "to": ["bob@example.com", "carol@example.com"],
+            },
+            {"id": "blue-frog", "subject": "No sender"},
+        ]);
+        let out = render_template(&messages, "{date} {from} {subject} ({ size_bytes })").unwrap();
+        assert_eq!(
+            out,
+            "2026-03-01 alice@example.com Quarterly report (2048)\n  No sender ()\n"
+        );
+
+        let out = render_template(&messages[0], "{id}: {to} {{raw}} {missing}|").unwrap();
+        assert_eq!(
+            out,
+            "cold-lamp: bob@example.com, carol@example.com {raw} |\n"
+        );
+
+        // Unbalanced braces are kept as written
+        let out = render_template(&messages[1], "{id} } {subject").unwrap();
+        assert_eq!(out, "blue-frog } {subject\n");
+
+        assert!(render_template(&json!(["plain"]), "{id}").is_none());
+    }
+
+    #[test]
+    fn test_resend_payload_keeps_original() {
+        let content = "Subject: Quarterly\r\n report\r\nFrom: alice@example.com\r\nTo: me@example.com\r\n\r\nNumbers attached.\r\n\r\n-- \r\nAlice\r\n";
+        let to = vec!["Bob <bob@example.com>".to_string()];
+        let payload = resend_payload(content, &to, &[], "<id@example.com>").unwrap();
+        assert_eq!(payload["subject"], "Quarterly report");
+        assert_eq!(payload["body"], "Numbers attached.\r\n\r\n-- \r\nAlice\r\n");
+        assert_eq!(payload["html"], false);
+        assert_eq!(payload["to"][0]["email"], "bob@example.com");
+        assert_eq!(payload["cc"], json!([]));
+        assert_eq!(payload["message_id"], "<id@example.com>");
+        assert!(!payload["body"].as_str().unwrap().contains("Forwarded"));
+
+        let html = "Subject: Hi\nContent-Type: text/html; charset=utf-8\n\n<p>Hello</p>";
+        let payload =
+            resend_payload(html, &to, &["carol@example.com".to_string()], "<x@y>").unwrap();
+        assert_eq!(payload["html"], true);
+        assert_eq!(payload["body"], "<p>Hello</p>");
+        assert_eq!(payload["cc"][0]["email"], "carol@example.com");
+
+        let qp = "Subject: Long\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\nGr=C3=BC=\r\n=C3=9Fe\r\n";
+        let payload = resend_payload(qp, &to, &[], "<x@y>").unwrap();
+        assert_eq!(payload["body"], "Grüße\n");
+
+        assert!(resend_payload(content, &["not an address".to_string()], &[], "<x@y>").is_err());
+
+        let reply = "Subject: Re: Plan\nIn-Reply-To: <a@x>\nReferences: <root@x>\n <a@x>\n\nOk.";
+        let payload = resend_payload(reply, &to, &[], "<x@y>").unwrap();
+        assert_eq!(payload["in_reply_to"], "<a@x>");
+        assert_eq!(payload["references"], "<root@x> <a@x>");
+        let payload = resend_payload(content, &to, &[], "<x@y>").unwrap();
+        assert!(payload.get("references").is_none());
+
+        let mixed = "Subject: Files\nContent-Type: multipart/mixed; boundary=b\n\n--b\n";
+        let err = resend_payload(mixed, &to, &[], "<x@y>").unwrap_err();
+        assert!(err.to_string().contains("attachments"), "{err}");
+    }
+
+    #[test]
+    fn test_handle_mail_resend_sends_stored_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(
+            dir.path(),
+            &[
+                "h8",
+                "--quiet",
+                "mail",
+                "resend",
+                "cold-lamp",
+                "--to",
+                "bob@example.com",
+            ],
+        );
+        let (url, requests) = fake_service(r#"{"success": true}"#);
+        ctx.config.service_url = url;
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        mail_dir
+            .store_with_id(
+                "inbox",
+                b"Subject: Minutes\nFrom: alice@example.com\n\nAction items below.",
+                &MessageFlags::default(),
+                "cold-lamp",
+            )
+            .unwrap();
+
+        let args = MailResendArgs {
+            id: "cold-lamp".to_string(),
+            to: vec!["bob@example.com".to_string()],
+            cc: Vec::new(),
+            folder: "inbox".to_string(),
+        };
+        let client = ctx.service_client().unwrap();
+        handle_mail_resend(&ctx, &client, TEST_ACCOUNT, args).unwrap();
+
+        let payload = requests.recv().unwrap();
+        assert_eq!(payload["subject"], "Minutes");
+        assert_eq!(payload["body"], "Action items below.");
+        assert_eq!(payload["to"][0]["email"], "bob@example.com");
+
+        let missing = MailResendArgs {
+            id: "blue-frog".to_string(),
+            to: vec!["bob@example.com".to_string()],
+            cc: Vec::new(),
+            folder: "inbox".to_string(),
+        };
+        let err = handle_mail_resend(&ctx, &client, TEST_ACCOUNT, missing).unwrap_err();
+        assert!(err.to_string().contains("not stored locally"));
+    }
+
+    #[test]
+    fn test_handle_mail_resend_refetches_truncated_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(
+            dir.path(),
+            &[
+                "h8",
+                "--offline",
+                "mail",
+                "resend",
+                "cold-lamp",
+                "--to",
+                "bob@example.com",
+            ],
+        );
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        mail_dir
+            .store_with_id(
+                "inbox",
+                b"Subject: Minutes\n\nAction items\n[truncated]",
+                &MessageFlags::default(),
+                "cold-lamp",
+            )
+            .unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        db.upsert_message(&h8_core::types::MessageSync {
+            local_id: "cold-lamp".to_string(),
+            remote_id: "AAMk1".to_string(),
+            folder: "inbox".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        db.set_body_truncated("cold-lamp", true).unwrap();
+
+        // Offline the full body cannot be fetched, so nothing is sent
+        let args = MailResendArgs {
+            id: "cold-lamp".to_string(),
+            to: vec!["bob@example.com".to_string()],
+            cc: Vec::new(),
+            folder: "inbox".to_string(),
+        };
+        let client = ctx.service_client().unwrap();
+        let err = handle_mail_resend(&ctx, &client, TEST_ACCOUNT, args).unwrap_err();
+        assert!(format!("{err:#}").contains("truncated"), "{err:#}");
+        assert!(format!("{err:#}").contains("offline"), "{err:#}");
+    }
+
+    fn draft_set_args(argv: &[&str]) -> MailDraftSetArgs {
+        let mut full = vec!["h8", "mail", "draft-set", "cold-lamp"];
+        full.extend_from_slice(argv);
+        match Cli::parse_from_args(full).unwrap().command {
+            Command::Mail {
+                command: MailCommand::DraftSet(args),
+            } => args,
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    fn sample_draft() -> ComposeDocument {
+        let mut doc = ComposeBuilder::new()
+            .to(vec!["alice@example.com".to_string()])
+            .cc(vec!["carol@example.com".to_string()])
+            .subject("Plans")
+            .body("Line one.\n\nLine two.\n")
+            .build();
+        doc.in_reply_to = Some("<orig@example.com>".to_string());
+        doc
+    }
+
+    #[test]
+    fn test_apply_draft_set_changes_only_given_fields() {
+        let original = sample_draft();
+
+        let mut doc = original.clone();
+        apply_draft_set(&mut doc, &draft_set_args(&["--add-to", "bob@example.com"])).unwrap();
+        assert_eq!(doc.to, vec!["alice@example.com", "bob@example.com"]);
+        assert_eq!(doc.cc, original.cc);
+        assert_eq!(doc.subject, original.subject);
+        assert_eq!(doc.body, original.body);
+        assert_eq!(doc.in_reply_to, original.in_reply_to);
+
+        let mut doc = original.clone();
+        apply_draft_set(&mut doc, &draft_set_args(&["--subject", "New plans"])).unwrap();
+        assert_eq!(doc.subject, "New plans");
+        assert_eq!(doc.to, original.to);
+        assert_eq!(doc.body, original.body);
+
+        // Replace, then add; duplicates are not added twice
+        let mut doc = original.clone();
+        let args = draft_set_args(&[
+            "--cc",
+            "dave@example.com",
+            "--add-cc",
+            "erin@example.com",
+            "--add-to",
+            "ALICE@example.com",
+        ]);
+        apply_draft_set(&mut doc, &args).unwrap();
+        assert_eq!(doc.cc, vec!["dave@example.com", "erin@example.com"]);
+        assert_eq!(doc.to, original.to);
+
+        let mut doc = original.clone();
+        assert!(apply_draft_set(&mut doc, &draft_set_args(&[])).is_err());
+        assert!(
+            apply_draft_set(&mut doc, &draft_set_args(&["--add-bcc", "not an address"])).is_err()
+        );
+        assert!(doc.bcc.is_empty());
+    }
+
+    #[test]
+    fn test_handle_mail_draft_set_saves_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "drafts"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let draft = mail_dir.store_draft(&sample_draft()).unwrap();
+
+        let mut args = draft_set_args(&["--add-to", "bob@example.com"]);
+        args.id = draft.id.clone();
+        handle_mail_draft_set(&ctx, TEST_ACCOUNT, args).unwrap();
+
+        let drafts = mail_dir.list(FOLDER_DRAFTS).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].id, draft.id);
+        let saved = ComposeDocument::parse(&drafts[0].read_content().unwrap()).unwrap();
+        assert_eq!(saved.to, vec!["alice@example.com", "bob@example.com"]);
+        assert_eq!(saved.subject, "Plans");
+        assert_eq!(saved.body, sample_draft().body);
+        assert_eq!(saved.in_reply_to.as_deref(), Some("<orig@example.com>"));
+    }
+
+    fn direct_send_args(dir: &Path) -> MailSendArgs {
+        let report = dir.join("report.txt");
+        fs::write(&report, "numbers").unwrap();
+        let argv = [
+            "h8",
+            "mail",
+            "send",
+            "--to",
+            "jane@example.com",
+            "--subject",
+            "Report",
+            "--body",
+            "Attached.",
+            "--attach",
+            report.to_str().unwrap(),
+        ];
+        match Cli::parse_from_args(argv).unwrap().command {
+            Command::Mail {
+                command: MailCommand::Send(args),
+            } => args,
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_send_with_attachment_saves_sent_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        ctx.config.service_url = fake_service(r#"{"success": true}"#).0;
+
+        let client = ctx.service_client().unwrap();
+        handle_mail_send(&ctx, &client, TEST_ACCOUNT, direct_send_args(dir.path())).unwrap();
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let sent = mail_dir.list(FOLDER_SENT).unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].flags.seen);
+        assert!(sent[0].read_content().unwrap().contains("Attached."));
+
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        let row = db.get_message(&sent[0].id).unwrap().unwrap();
+        assert!(row.has_attachments);
+        let attachments = row.attachments.unwrap();
+        assert_eq!(attachments[0].name, "report.txt");
+        assert_eq!(attachments[0].size, Some(7));
+    }
+
+    #[test]
+    fn test_send_with_attachment_respects_save_sent_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "send", "draft"]);
+        ctx.config.service_url = fake_service(r#"{"success": true}"#).0;
+        ctx.config.mail.save_sent = false;
+
+        let client = ctx.service_client().unwrap();
+        handle_mail_send(&ctx, &client, TEST_ACCOUNT, direct_send_args(dir.path())).unwrap();
+
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        assert!(mail_dir.list(FOLDER_SENT).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tz_flag_overrides_config_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let default = test_context(dir.path(), &["h8", "agenda"]);
+        let mut ctx = test_context(dir.path(), &["h8", "--tz", "America/New_York", "agenda"]);
+        apply_timezone_override(&mut ctx.config, ctx.common.tz.as_deref()).unwrap();
+        assert_eq!(default.config.timezone, "Europe/Berlin");
+        assert_eq!(ctx.config.timezone, "America/New_York");
+
+        // The same event renders six hours earlier in New York than in Berlin
+        let label = |config: &AppConfig| {
+            let tz = config.timezone.parse::<chrono_tz::Tz>().unwrap();
+            let start = parse_datetime_local("2026-03-02T15:00:00Z", tz).unwrap();
+            config.display.format_time(start.time())
+        };
+        assert_eq!(label(&default.config), "16:00");
+        assert_eq!(label(&ctx.config), "10:00");
+
+        let mut config = AppConfig::default();
+        apply_timezone_override(&mut config, None).unwrap();
+        assert_eq!(config.timezone, "Europe/Berlin");
+        let err = apply_timezone_override(&mut config, Some("Mars/Olympus")).unwrap_err();
+        assert!(err.to_string().contains("unknown timezone 'Mars/Olympus'"));
+        assert_eq!(config.timezone, "Europe/Berlin");
+    }
+
+    #[test]
+    fn test_strip_global_flags_applies_tz() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "agenda"]);
+        let words: Vec<String> = ["tomorrow", "--tz", "Asia/Tokyo"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let (stripped, rest) = strip_global_flags(&ctx, &words);
+        assert_eq!(rest, vec!["tomorrow"]);
+        assert_eq!(stripped.config.timezone, "Asia/Tokyo");
+    }
+
+    #[test]
+    fn test_move_delete_undelete_update_db_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = test_context(dir.path(), &["h8", "--quiet", "mail", "list"]);
+        let mail_dir = get_mail_dir(&ctx, TEST_ACCOUNT).unwrap();
+        let db = Database::open(&ctx.paths.sync_db_path(TEST_ACCOUNT)).unwrap();
+        mail_dir
+            .store_with_id(
+                "inbox",
+                b"Subject: Hi\n\nBody",
+                &Default::default(),
+                "cold-lamp",
+            )
+            .unwrap();
+        db.upsert_message(&h8_core::types::MessageSync {
+            local_id: "cold-lamp".to_string(),
+            remote_id: "remote-cold-lamp".to_string(),
+            folder: "inbox".to_string(),
+            subject: Some("Hi".to_string()),
+            size_bytes: Some(18),
+            ..Default::default()
+        })
+        .unwrap();
+        let folder = || db.get_message("cold-lamp").unwrap().unwrap().folder;
+
+        let move_args = MailMoveArgs {
+            ids: vec!["cold-lamp".to_string()],
+            select: None,
+            target: Some("archive".to_string()),
+            folder: "inbox".to_string(),
+            query: None,
+            all_from: None,
+            limit: 50,
+            create: true,
+            sync: false,
+            dry_run: false,
+        };
+        handle_mail_move(&ctx, TEST_ACCOUNT, move_args).unwrap();
+        assert_eq!(folder(), "archive");
+
+        let delete_args = MailDeleteArgs {
+            ids: vec!["cold-lamp".to_string()],
+            select: None,
+            folder: "archive".to_string(),
+            force: false,
+            sync: false,
+        };
+        handle_mail_delete(&ctx, TEST_ACCOUNT, delete_args).unwrap();
+        assert_eq!(folder(), FOLDER_TRASH);
+
+        let undelete_args = MailUndeleteArgs {
+            ids: vec!["cold-lamp".to_string()],
+            select: None,
+            target: "inbox".to_string(),
+            sync: false,
+        };
+        handle_mail_undelete(&ctx, TEST_ACCOUNT, undelete_args).unwrap();
+        assert_eq!(folder(), "inbox");
+        assert_eq!(
+            db.get_message("cold-lamp").unwrap().unwrap().size_bytes,
+            Some(18)
+        );
+    }
+}
@@ -19,14 +19,49 @@
 //! > Original quoted text here
 //! ```
 
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
-use crate::config::ComposeConfig;
+use crate::config::{ComposeConfig, SignaturePlacement};
 use crate::error::{Error, Result};
+use crate::types::Recipient;
 
 /// Frontmatter delimiter.
 const FRONTMATTER_DELIM: &str = "---";
 
+/// Severity of a [`LintIssue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// Worth a second look, but the message can be sent.
+    Warning,
+    /// The message cannot be sent as is.
+    Error,
+}
+
+/// A problem found by [`ComposeDocument::lint`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    pub severity: LintSeverity,
+    /// Field the issue concerns (`to`, `cc`, `bcc`, `subject`, `body` or
+    /// `attachment`).
+    pub field: String,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(severity: LintSeverity, field: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
 /// Parsed email compose document.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ComposeDocument {
@@ -52,6 +87,13 @@ pub struct ComposeDocument {
     /// References header for threading.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub references: Option<String>,
+    /// Client-generated token the service uses to drop duplicate sends.
+    #[serde(
+        rename = "send-token",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub send_token: Option<String>,
     /// Message body text.
     #[serde(skip)]
     pub body: String,
@@ -72,8 +114,8 @@ impl ComposeDocument {
             ComposeDocument::default()
         } else {
             // Handle comma-separated values for to, cc, bcc
-            let yaml_value: serde_yaml::Value = serde_yaml::from_str(&frontmatter)
-                .map_err(|e| Error::Config(format!("parsing frontmatter YAML: {e}")))?;
+            let yaml_value: serde_yaml::Value =
+                serde_yaml::from_str(&frontmatter).map_err(Error::FrontmatterParse)?;
 
             let mut doc = ComposeDocument::default();
 
@@ -87,6 +129,7 @@ impl ComposeDocument {
                         "subject" => doc.subject = value.as_str().unwrap_or("").to_string(),
                         "in-reply-to" => doc.in_reply_to = value.as_str().map(String::from),
                         "references" => doc.references = value.as_str().map(String::from),
+                        "send-token" => doc.send_token = value.as_str().map(String::from),
                         _ => {}
                     }
                 }
@@ -99,11 +142,89 @@ impl ComposeDocument {
         Ok(doc)
     }
 
+    /// Build a compose document from an RFC 822 message such as an `.eml`
+    /// file.
+    ///
+    /// `To`, `Cc`, `Bcc`, `Subject`, `In-Reply-To` and `References` are taken
+    /// from the header, unfolding continuation lines and decoding RFC 2047
+    /// encoded words; other headers are ignored. Everything after the first
+    /// blank line becomes the body, with CRLF line endings normalized and a
+    /// quoted-printable or base64 transfer encoding undone by
+    /// [`decode_transfer_encoding`]. Multipart bodies are kept as they are.
+    pub fn from_rfc822(text: &str) -> Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let (head, body) = text.split_once("\n\n").unwrap_or((&text, ""));
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in head.lines() {
+            if line.starts_with([' ', '\t']) {
+                let (_, value) = headers.last_mut().ok_or_else(|| {
+                    Error::InvalidMessage("header starts with a continuation line".into())
+                })?;
+                value.push(' ');
+                value.push_str(line.trim());
+            } else if let Some((name, value)) = line.split_once(':')
+                && !name.is_empty()
+                && !name.contains(char::is_whitespace)
+            {
+                headers.push((name.to_string(), value.trim().to_string()));
+            } else {
+                return Err(Error::InvalidMessage(format!(
+                    "malformed header line: {line}"
+                )));
+            }
+        }
+        if headers.is_empty() {
+            return Err(Error::InvalidMessage("no headers found".into()));
+        }
+
+        let mut doc = ComposeDocument::default();
+        for (name, value) in headers {
+            match name.to_ascii_lowercase().as_str() {
+                "to" => doc
+                    .to
+                    .extend(split_address_list(&decode_header_value(&value))),
+                "cc" => doc
+                    .cc
+                    .extend(split_address_list(&decode_header_value(&value))),
+                "bcc" => doc
+                    .bcc
+                    .extend(split_address_list(&decode_header_value(&value))),
+                "subject" => doc.subject = decode_header_value(&value),
+                "in-reply-to" => doc.in_reply_to = Some(value),
+                "references" => doc.references = Some(value),
+                _ => {}
+            }
+        }
+        // The empty group stands for "no direct recipients"
+        doc.to.retain(|addr| !addr.ends_with(":;"));
+        doc.body = decode_transfer_encoding(head, body).into_owned();
+        Ok(doc)
+    }
+
+    /// Read and parse a compose document from a reader (e.g. stdin).
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Self::parse(&text)
+    }
+
     /// Serialize the document to compose format.
     pub fn to_string(&self) -> Result<String> {
         self.to_string_impl(false)
     }
 
+    /// Serialize the document, wrapping the body at `config.wrap_width`
+    /// with [`wrap_flowed`].
+    pub fn to_string_with(&self, config: &ComposeConfig) -> Result<String> {
+        if config.wrap_width == 0 {
+            return self.to_string();
+        }
+        let mut doc = self.clone();
+        doc.body = wrap_flowed(&self.body, config.wrap_width, &config.quote_style);
+        doc.to_string()
+    }
+
     /// Serialize the document as a template with all fields shown.
     ///
     /// Unlike `to_string()`, this always includes to, cc, bcc fields
@@ -160,6 +281,13 @@ impl ComposeDocument {
             output.push('\n');
         }
 
+        // Send token - set once the first send attempt is made
+        if let Some(ref token) = self.send_token {
+            output.push_str("send-token: ");
+            output.push_str(&yaml_quote_if_needed(token));
+            output.push('\n');
+        }
+
         output.push_str(FRONTMATTER_DELIM);
         output.push('\n');
 
@@ -172,6 +300,14 @@ impl ComposeDocument {
         Ok(output)
     }
 
+    /// Return the document's send token, generating one if it has none.
+    ///
+    /// Retrying a send with the same token lets the service recognise it as
+    /// a duplicate.
+    pub fn ensure_send_token(&mut self) -> &str {
+        self.send_token.get_or_insert_with(random_uuid)
+    }
+
     /// Create a reply document from an original message.
     pub fn reply(
         original_from: &str,
@@ -187,7 +323,7 @@ impl ComposeDocument {
         doc.to = vec![original_from.to_string()];
 
         // Set subject with Re: prefix if not already present
-        doc.subject = if original_subject.to_lowercase().starts_with("re:") {
+        doc.subject = if has_subject_prefix(original_subject, &config.reply_prefixes) {
             original_subject.to_string()
         } else {
             format!("Re: {}", original_subject)
@@ -205,8 +341,15 @@ impl ComposeDocument {
             doc.references = Some(refs);
         }
 
-        // Quote original body
-        doc.body = quote_text(original_body, &config.quote_style);
+        // Leave room for the new text above the (attributed) quote
+        doc.body = "\n".repeat(config.reply_blank_lines);
+        if !config.reply_attribution.is_empty() {
+            doc.body
+                .push_str(&config.reply_attribution.replace("{from}", original_from));
+            doc.body.push('\n');
+        }
+        doc.body
+            .push_str(&quote_text(original_body, &config.quote_style));
 
         doc
     }
@@ -267,14 +410,12 @@ impl ComposeDocument {
         original_subject: &str,
         original_date: Option<&str>,
         original_body: &str,
-        _config: &ComposeConfig,
+        config: &ComposeConfig,
     ) -> Self {
         let mut doc = Self::new();
 
         // Set subject with Fwd: prefix if not already present
-        doc.subject = if original_subject.to_lowercase().starts_with("fwd:")
-            || original_subject.to_lowercase().starts_with("fw:")
-        {
+        doc.subject = if has_subject_prefix(original_subject, &config.forward_prefixes) {
             original_subject.to_string()
         } else {
             format!("Fwd: {}", original_subject)
@@ -282,7 +423,9 @@ impl ComposeDocument {
 
         // Build forwarded message body
         let mut body = String::new();
-        body.push_str("\n\n---------- Forwarded message ----------\n");
+        body.push_str("\n\n");
+        body.push_str(FORWARD_SEPARATOR);
+        body.push('\n');
         body.push_str(&format!("From: {}\n", original_from));
         if let Some(date) = original_date {
             body.push_str(&format!("Date: {}\n", date));
@@ -299,43 +442,282 @@ impl ComposeDocument {
         doc
     }
 
-    /// Add signature to the body.
+    /// Add signature to the body using the default delimiter and placement.
     pub fn add_signature(&mut self, signature: &str) {
+        self.add_signature_with(signature, &ComposeConfig::default());
+    }
+
+    /// Add signature to the body, preceded by `config.signature_delimiter`
+    /// unless the signature already starts with it. With
+    /// [`SignaturePlacement::Above`], the signature goes between the new
+    /// text and the quoted or forwarded message; otherwise it is appended.
+    pub fn add_signature_with(&mut self, signature: &str, config: &ComposeConfig) {
         if signature.is_empty() {
             return;
         }
 
-        // Ensure body ends with newline
-        if !self.body.is_empty() && !self.body.ends_with('\n') {
-            self.body.push('\n');
-        }
-
-        // Add signature separator if not present
-        if !signature.starts_with("--") {
-            self.body.push_str("\n--\n");
+        let delimiter = config.signature_delimiter.trim_end();
+        let block = if delimiter.is_empty() || signature.starts_with(delimiter) {
+            signature.to_string()
         } else {
-            self.body.push('\n');
+            format!("{}\n{}", config.signature_delimiter, signature)
+        };
+
+        let quote_start = match config.signature_placement {
+            SignaturePlacement::Above => self.quote_start(config),
+            SignaturePlacement::Below => None,
+        };
+        let (mut head, tail) = match quote_start {
+            Some(pos) => (
+                self.body[..pos].trim_end_matches('\n').to_string(),
+                Some(self.body[pos..].to_string()),
+            ),
+            None => (std::mem::take(&mut self.body), None),
+        };
+
+        // Ensure the text before the signature ends with a newline
+        if !head.is_empty() && !head.ends_with('\n') {
+            head.push('\n');
+        }
+        head.push('\n');
+        head.push_str(&block);
+        if let Some(tail) = tail {
+            head.push_str("\n\n");
+            head.push_str(&tail);
         }
+        self.body = head;
+    }
 
-        self.body.push_str(signature);
+    /// Byte offset of the first quoted or forwarded line in the body, or of
+    /// the reply attribution introducing the quote.
+    fn quote_start(&self, config: &ComposeConfig) -> Option<usize> {
+        let marker = config.quote_style.trim_end();
+        let attribution = config.reply_attribution.split_once("{from}");
+        let mut offset = 0;
+        for line in self.body.split_inclusive('\n') {
+            let text = line.trim_end();
+            let attributed = match attribution {
+                Some((prefix, suffix)) => {
+                    text.len() > prefix.len() + suffix.len()
+                        && text.starts_with(prefix)
+                        && text.ends_with(suffix.trim_end())
+                }
+                None => !config.reply_attribution.is_empty() && text == config.reply_attribution,
+            };
+            if (!marker.is_empty() && text.trim_start().starts_with(marker))
+                || text == FORWARD_SEPARATOR
+                || attributed
+            {
+                return Some(offset);
+            }
+            offset += line.len();
+        }
+        None
     }
 
     /// Validate the document for sending.
+    ///
+    /// At least one of `to`, `cc` or `bcc` must be set; a BCC-only message
+    /// is valid.
     pub fn validate(&self) -> Result<()> {
-        if self.to.is_empty() {
-            return Err(Error::Config("no recipients specified".into()));
+        if self.all_recipients().is_empty() {
+            return Err(Error::NoRecipients);
         }
 
         // Validate email addresses
         for addr in self.to.iter().chain(self.cc.iter()).chain(self.bcc.iter()) {
-            if !is_valid_email(addr) {
-                return Err(Error::Config(format!("invalid email address: {}", addr)));
+            if parse_address(addr).is_none() {
+                return Err(Error::InvalidAddress(addr.clone()));
             }
         }
 
         Ok(())
     }
 
+    /// Check the document before sending.
+    ///
+    /// Reports everything [`validate`](Self::validate) rejects as errors,
+    /// naming each bad address and flagging domains without a dot as likely
+    /// typos. Also warns about an empty subject or body, and reports
+    /// `attachments` that are not readable files.
+    pub fn lint(&self, attachments: &[PathBuf]) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+        if self.all_recipients().is_empty() {
+            issues.push(LintIssue::new(
+                LintSeverity::Error,
+                "to",
+                "no recipients specified",
+            ));
+        }
+        for (field, addrs) in [("to", &self.to), ("cc", &self.cc), ("bcc", &self.bcc)] {
+            for addr in addrs {
+                if parse_address(addr).is_some() {
+                    continue;
+                }
+                let undotted = addr
+                    .trim()
+                    .trim_end_matches('>')
+                    .rsplit_once('@')
+                    .is_some_and(|(_, domain)| !domain.is_empty() && !domain.contains('.'));
+                let message = if undotted {
+                    format!("'{addr}' looks like a typo: the domain has no dot")
+                } else {
+                    format!("invalid email address: {addr}")
+                };
+                issues.push(LintIssue::new(LintSeverity::Error, field, message));
+            }
+        }
+        if self.subject.trim().is_empty() {
+            issues.push(LintIssue::new(
+                LintSeverity::Warning,
+                "subject",
+                "subject is empty",
+            ));
+        }
+        if self.body.trim().is_empty() {
+            issues.push(LintIssue::new(
+                LintSeverity::Warning,
+                "body",
+                "body is empty",
+            ));
+        }
+        for path in attachments {
+            let message = if !path.exists() {
+                format!("attachment not found: {}", path.display())
+            } else if !path.is_file() {
+                format!("attachment is not a file: {}", path.display())
+            } else {
+                continue;
+            };
+            issues.push(LintIssue::new(LintSeverity::Error, "attachment", message));
+        }
+        issues
+    }
+
+    /// One-line-per-field overview of the document for review before it is
+    /// saved: recipients, subject, body size and `attachments` by file name.
+    /// Bcc is listed only when set.
+    pub fn summary(&self, attachments: &[PathBuf]) -> String {
+        let list = |items: &[String]| {
+            if items.is_empty() {
+                "(none)".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+        let mut lines = vec![
+            format!("To:          {}", list(&self.to)),
+            format!("Cc:          {}", list(&self.cc)),
+        ];
+        if !self.bcc.is_empty() {
+            lines.push(format!("Bcc:         {}", list(&self.bcc)));
+        }
+        let subject = if self.subject.trim().is_empty() {
+            "(no subject)"
+        } else {
+            &self.subject
+        };
+        lines.push(format!("Subject:     {}", subject));
+        let chars = self.body.chars().count();
+        let body_lines = self.body.lines().count();
+        lines.push(format!(
+            "Body:        {} character{}, {} line{}",
+            chars,
+            if chars == 1 { "" } else { "s" },
+            body_lines,
+            if body_lines == 1 { "" } else { "s" }
+        ));
+        let names: Vec<String> = attachments
+            .iter()
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or(path.as_os_str())
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        lines.push(format!("Attachments: {}", list(&names)));
+        lines.join("\n")
+    }
+
+    /// Render the document as an RFC 5322 message.
+    ///
+    /// Generates `Date` and `Message-ID` headers and uses CRLF line endings.
+    /// `Bcc` is kept since the output is meant for local copies (drafts,
+    /// Sent, export), not for transmission. Non-ASCII subjects are encoded
+    /// per RFC 2047 and header lines are folded at 78 columns. The body is a
+    /// single `text/plain` part, sent as 7bit or 8bit, or quoted-printable
+    /// when a line exceeds the 998 octet limit.
+    pub fn to_rfc822(&self, from: &str) -> Result<String> {
+        let message_id = generate_message_id(&address_domain(from));
+        self.to_rfc822_with_message_id(from, &message_id)
+    }
+
+    /// Render the document as an RFC 5322 message with a caller-supplied
+    /// `Message-ID`, so a local copy can match what the server sent.
+    pub fn to_rfc822_with_message_id(&self, from: &str, message_id: &str) -> Result<String> {
+        self.validate()?;
+        if !is_valid_email(from) {
+            return Err(Error::InvalidSender(from.to_string()));
+        }
+
+        let mut headers = vec![
+            ("Date", chrono::Local::now().to_rfc2822()),
+            ("Message-ID", message_id.to_string()),
+            ("From", from.trim().to_string()),
+            ("To", self.to_header()),
+        ];
+        if !self.cc.is_empty() {
+            headers.push(("Cc", self.cc.join(", ")));
+        }
+        if !self.bcc.is_empty() {
+            headers.push(("Bcc", self.bcc.join(", ")));
+        }
+        headers.push(("Subject", encode_header_value(&self.subject)));
+        if let Some(ref in_reply_to) = self.in_reply_to {
+            headers.push(("In-Reply-To", in_reply_to.clone()));
+        }
+        if let Some(ref references) = self.references {
+            headers.push(("References", references.clone()));
+        }
+        let encoding = if self.body.lines().any(|line| line.len() > MAX_LINE_OCTETS) {
+            "quoted-printable"
+        } else if self.body.is_ascii() {
+            "7bit"
+        } else {
+            "8bit"
+        };
+        headers.push(("MIME-Version", "1.0".to_string()));
+        headers.push(("Content-Type", "text/plain; charset=utf-8".to_string()));
+        headers.push(("Content-Transfer-Encoding", encoding.to_string()));
+
+        let mut output = String::new();
+        for (name, value) in headers {
+            output.push_str(&fold_header(name, &value));
+            output.push_str("\r\n");
+        }
+        output.push_str("\r\n");
+        for line in self.body.lines() {
+            if encoding == "quoted-printable" {
+                output.push_str(&encode_quoted_printable_line(line));
+            } else {
+                output.push_str(line);
+            }
+            output.push_str("\r\n");
+        }
+        Ok(output)
+    }
+
+    /// The `To` header value; messages without direct recipients use the
+    /// RFC 5322 empty group.
+    fn to_header(&self) -> String {
+        if self.to.is_empty() {
+            "undisclosed-recipients:;".to_string()
+        } else {
+            self.to.join(", ")
+        }
+    }
+
     /// Get all recipients (to + cc + bcc).
     pub fn all_recipients(&self) -> Vec<&String> {
         let mut recipients: Vec<&String> = Vec::new();
@@ -379,22 +761,14 @@ fn split_frontmatter(text: &str) -> Result<(String, String)> {
         Ok((frontmatter.to_string(), body.to_string()))
     } else {
         // No closing delimiter - treat as error
-        Err(Error::Config(
-            "unclosed frontmatter (missing closing ---)".into(),
-        ))
+        Err(Error::UnclosedFrontmatter)
     }
 }
 
 /// Parse address list from YAML value.
 fn parse_address_list(value: &serde_yaml::Value) -> Vec<String> {
     match value {
-        serde_yaml::Value::String(s) => {
-            // Split by comma and trim
-            s.split(',')
-                .map(|addr| addr.trim().to_string())
-                .filter(|addr| !addr.is_empty())
-                .collect()
-        }
+        serde_yaml::Value::String(s) => split_address_list(s),
         serde_yaml::Value::Sequence(seq) => seq
             .iter()
             .filter_map(|v| v.as_str().map(|s| s.trim().to_string()))
@@ -434,6 +808,248 @@ fn yaml_quote_if_needed(s: &str) -> String {
     }
 }
 
+/// Longest line RFC 5322 allows, excluding the CRLF.
+const MAX_LINE_OCTETS: usize = 998;
+
+/// Column header lines are folded at.
+const FOLD_WIDTH: usize = 78;
+
+/// Render `name: value`, folding before spaces so lines stay within
+/// [`FOLD_WIDTH`] where possible. Values that are already folded (encoded
+/// words) are kept as they are.
+fn fold_header(name: &str, value: &str) -> String {
+    let mut out = format!("{}:", name);
+    if value.contains("\r\n") {
+        out.push(' ');
+        out.push_str(value);
+        return out;
+    }
+    let mut line_len = out.len();
+    let mut line_has_word = false;
+    for word in value.split(' ') {
+        if line_has_word && line_len + 1 + word.len() > FOLD_WIDTH {
+            out.push_str("\r\n");
+            line_len = 0;
+        }
+        out.push(' ');
+        out.push_str(word);
+        line_len += 1 + word.len();
+        line_has_word = true;
+    }
+    out
+}
+
+/// Encode one body line as quoted-printable (RFC 2045), using soft line
+/// breaks to stay within 76 characters.
+fn encode_quoted_printable_line(line: &str) -> String {
+    const MAX: usize = 76;
+    let mut out = String::with_capacity(line.len() + line.len() / MAX * 3);
+    let mut line_len = 0;
+    let bytes = line.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        let last = i + 1 == bytes.len();
+        let literal =
+            ((b == b' ' || b == b'\t') && !last) || ((b'!'..=b'~').contains(&b) && b != b'=');
+        let encoded = if literal {
+            (b as char).to_string()
+        } else {
+            format!("={:02X}", b)
+        };
+        // Leave room for the "=" of a soft break
+        if line_len + encoded.len() > MAX - 1 {
+            out.push_str("=\r\n");
+            line_len = 0;
+        }
+        line_len += encoded.len();
+        out.push_str(&encoded);
+    }
+    out
+}
+
+/// Decode a quoted-printable body (RFC 2045), joining soft line breaks.
+fn decode_quoted_printable(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let line = line.trim_end_matches([' ', '\t', '\r']);
+        let (line, soft) = match line.strip_suffix('=') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let raw = line.as_bytes();
+        let mut i = 0;
+        while i < raw.len() {
+            let hex = raw
+                .get(i + 1..i + 3)
+                .filter(|_| raw[i] == b'=')
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+            match hex {
+                Some(b) => {
+                    bytes.push(b);
+                    i += 3;
+                }
+                None => {
+                    bytes.push(raw[i]);
+                    i += 1;
+                }
+            }
+        }
+        if !soft && lines.peek().is_some() {
+            bytes.push(b'\n');
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Encode a header value as RFC 2047 `Q` encoded-words if it is not ASCII.
+fn encode_header_value(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    // Encoded words are limited to 75 characters, leaving 63 for the payload
+    const MAX_PAYLOAD: usize = 75 - "=?UTF-8?Q??=".len();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for c in value.chars() {
+        let mut buf = [0u8; 4];
+        let encoded: String = c
+            .encode_utf8(&mut buf)
+            .bytes()
+            .map(|b| match b {
+                b' ' => "_".to_string(),
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'!' | b'*' | b'+' | b'-' | b'/' => {
+                    (b as char).to_string()
+                }
+                _ => format!("={:02X}", b),
+            })
+            .collect();
+        if current.len() + encoded.len() > MAX_PAYLOAD {
+            words.push(format!("=?UTF-8?Q?{}?=", current));
+            current.clear();
+        }
+        current.push_str(&encoded);
+    }
+    words.push(format!("=?UTF-8?Q?{}?=", current));
+    words.join("\r\n ")
+}
+
+/// Decode RFC 2047 encoded words (`=?charset?Q|B?text?=`) in a header
+/// value. Whitespace between adjacent encoded words is dropped; words in
+/// charsets other than UTF-8 and US-ASCII, or that fail to decode, are kept
+/// as is.
+fn decode_header_value(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let decoded = rest[start + 2..]
+            .find("?=")
+            .map(|len| &rest[start..start + 2 + len + 2])
+            .and_then(|word| Some((word, decode_encoded_word(word)?)));
+        let Some((word, text)) = decoded else {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            after_word = false;
+            continue;
+        };
+        let gap = &rest[..start];
+        if !(after_word && gap.trim().is_empty()) {
+            out.push_str(gap);
+        }
+        out.push_str(&text);
+        rest = &rest[start + word.len()..];
+        after_word = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decode a single `=?charset?encoding?text?=` word.
+fn decode_encoded_word(word: &str) -> Option<String> {
+    let inner = word.strip_prefix("=?")?.strip_suffix("?=")?;
+    let mut parts = inner.splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    if !charset.eq_ignore_ascii_case("utf-8") && !charset.eq_ignore_ascii_case("us-ascii") {
+        return None;
+    }
+    let bytes = match encoding {
+        "Q" | "q" => {
+            let mut bytes = Vec::with_capacity(text.len());
+            let mut iter = text.bytes();
+            while let Some(b) = iter.next() {
+                match b {
+                    b'_' => bytes.push(b' '),
+                    b'=' => {
+                        let hex = [iter.next()?, iter.next()?];
+                        bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+                    }
+                    _ => bytes.push(b),
+                }
+            }
+            bytes
+        }
+        "B" | "b" => decode_base64(text)?,
+        _ => return None,
+    };
+    String::from_utf8(bytes).ok()
+}
+
+/// Decode standard base64, ignoring padding.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = ((buffer << 6) | u32::from(value)) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Decode a single-part body according to the `Content-Transfer-Encoding`
+/// in `headers`: quoted-printable and base64 are decoded, anything else (or
+/// an undecodable base64 body) is returned as is.
+pub fn decode_transfer_encoding<'a>(headers: &str, body: &'a str) -> Cow<'a, str> {
+    let encoding = headers
+        .lines()
+        .filter(|line| !line.starts_with([' ', '\t']))
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-transfer-encoding")
+                .then(|| value.trim().to_ascii_lowercase())
+        });
+    match encoding.as_deref() {
+        Some("quoted-printable") => Cow::Owned(decode_quoted_printable(body)),
+        Some("base64") => {
+            let compact: String = body.split_whitespace().collect();
+            match decode_base64(&compact) {
+                Some(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).replace("\r\n", "\n")),
+                None => Cow::Borrowed(body),
+            }
+        }
+        _ => Cow::Borrowed(body),
+    }
+}
+
+/// Line that starts the original message in a forward.
+const FORWARD_SEPARATOR: &str = "---------- Forwarded message ----------";
+
 /// Quote text with a prefix.
 pub fn quote_text(text: &str, prefix: &str) -> String {
     text.lines()
@@ -442,35 +1058,276 @@ pub fn quote_text(text: &str, prefix: &str) -> String {
         .join("\n")
 }
 
+/// Wrap lines longer than `width` columns in the style of format=flowed
+/// (RFC 3676): each soft break leaves a trailing space on the broken line.
+/// Quoted lines and the signature separator are never wrapped, and a single
+/// word longer than `width` stays on its own line. A width of 0 disables
+/// wrapping.
+pub fn wrap_flowed(text: &str, width: usize, quote_prefix: &str) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    let marker = quote_prefix.trim_end();
+    let mut out: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let quoted = !marker.is_empty() && line.starts_with(marker);
+        if quoted || line.trim_end() == "--" || line.chars().count() <= width {
+            out.push(line.to_string());
+            continue;
+        }
+
+        // `current` always ends with the space that becomes the soft break
+        let mut current = String::new();
+        for word in line.split(' ') {
+            if !word.is_empty()
+                && !current.trim().is_empty()
+                && current.chars().count() + word.chars().count() > width
+            {
+                out.push(std::mem::take(&mut current));
+            }
+            current.push_str(word);
+            current.push(' ');
+        }
+        current.pop();
+        out.push(current);
+    }
+    out.join("\n")
+}
+
+/// Condense a message body for reading: runs of lines starting with the
+/// quote prefix become a `[N quoted lines]` placeholder, and everything from
+/// the last `-- ` signature separator onwards is dropped. A bare `--`
+/// without the trailing space is ordinary text.
+pub fn plain_text(text: &str, quote_prefix: &str) -> String {
+    let marker = quote_prefix.trim_end();
+    let mut lines: Vec<&str> = text.lines().collect();
+    if let Some(sig) = lines.iter().rposition(|line| *line == "-- ") {
+        lines.truncate(sig);
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut quoted = 0;
+    let flush = |out: &mut Vec<String>, quoted: &mut usize| {
+        match *quoted {
+            0 => {}
+            1 => out.push("[1 quoted line]".to_string()),
+            n => out.push(format!("[{n} quoted lines]")),
+        }
+        *quoted = 0;
+    };
+    for line in lines {
+        if !marker.is_empty() && line.trim_start().starts_with(marker) {
+            quoted += 1;
+        } else {
+            flush(&mut out, &mut quoted);
+            out.push(line.to_string());
+        }
+    }
+    flush(&mut out, &mut quoted);
+
+    while out.last().is_some_and(|line| line.trim().is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
 /// Basic email validation.
 fn is_valid_email(email: &str) -> bool {
-    let email = email.trim();
+    parse_address(email).is_some()
+}
 
-    // Handle "Name <email>" format
-    let email = if email.contains('<') && email.contains('>') {
-        if let Some(start) = email.find('<') {
-            if let Some(end) = email.find('>') {
-                &email[start + 1..end]
-            } else {
-                email
+/// Parse an RFC 5322 mailbox into display name and address.
+///
+/// Accepts bare addresses (`jane@example.com`), angle-bracket forms
+/// (`Jane Doe <jane@example.com>`, `<jane@example.com>`) and quoted display
+/// names (`"Doe, Jane" <jane@example.com>`). Returns `None` for malformed
+/// input such as unbalanced quotes or brackets.
+pub fn parse_address(input: &str) -> Option<(Option<String>, String)> {
+    let input = input.trim();
+
+    let Some(open) = find_unquoted(input, '<') else {
+        // Bare address: no display name allowed
+        return is_valid_addr_spec(input).then(|| (None, input.to_string()));
+    };
+    let rest = &input[open + 1..];
+    let close = rest.find('>')?;
+    if !rest[close + 1..].trim().is_empty() {
+        return None;
+    }
+    let address = rest[..close].trim();
+    if !is_valid_addr_spec(address) {
+        return None;
+    }
+
+    let name = input[..open].trim();
+    let name = if let Some(quoted) = name.strip_prefix('"') {
+        let inner = quoted.strip_suffix('"')?;
+        let mut unescaped = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unescaped.push(chars.next()?),
+                '"' => return None,
+                _ => unescaped.push(c),
             }
-        } else {
-            email
         }
+        unescaped
     } else {
-        email
+        if name.contains(['"', '<', '>', ',']) {
+            return None;
+        }
+        name.to_string()
+    };
+
+    let name = (!name.is_empty()).then_some(name);
+    Some((name, address.to_string()))
+}
+
+/// Format a name/address pair as `Name <addr>`, quoting the name when it
+/// contains characters that [`parse_address`] would otherwise split on.
+pub fn format_address(name: Option<&str>, email: &str) -> String {
+    let email = email.trim();
+    let Some(name) = name.map(str::trim).filter(|n| !n.is_empty()) else {
+        return email.to_string();
     };
+    if name.contains(['"', '<', '>', ',', '\\', '@', ';', ':']) {
+        let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{}\" <{}>", escaped, email)
+    } else {
+        format!("{} <{}>", name, email)
+    }
+}
 
-    // Basic validation: must have @ and at least one char on each side
-    let parts: Vec<&str> = email.split('@').collect();
-    if parts.len() != 2 {
+/// Validate the `local@domain` part of an address.
+fn is_valid_addr_spec(addr: &str) -> bool {
+    let Some((local, domain)) = addr.split_once('@') else {
         return false;
+    };
+    let forbidden = |c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | ',' | '@');
+    !local.is_empty() && !local.contains(forbidden) && ascii_domain(domain).is_some()
+}
+
+/// Normalize a domain to its ASCII (punycode) form, so internationalized
+/// names like `münchen.de` validate as `xn--mnchen-3ya.de`. Returns `None`
+/// unless the result is a dotted hostname of letters, digits and hyphens.
+pub fn ascii_domain(domain: &str) -> Option<String> {
+    let ascii = idna::domain_to_ascii(domain).ok()?;
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    };
+    (ascii.contains('.') && ascii.split('.').all(valid_label)).then_some(ascii)
+}
+
+/// Byte index of the first `needle` outside a quoted string.
+fn find_unquoted(s: &str, needle: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == needle && !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a comma-separated address list, keeping commas inside quoted
+/// display names and angle brackets.
+pub fn split_address_list(list: &str) -> Vec<String> {
+    let mut addresses = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut in_angle = false;
+    for c in list.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '<' if !in_quotes => in_angle = true,
+            '>' if !in_quotes => in_angle = false,
+            ',' if !in_quotes && !in_angle => {
+                addresses.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
     }
+    addresses.push(current);
+    addresses
+        .into_iter()
+        .map(|addr| addr.trim().to_string())
+        .filter(|addr| !addr.is_empty())
+        .collect()
+}
+
+/// Generate a globally unique `Message-ID` of the form `<uuid@domain>`.
+///
+/// The left part is a random (version 4) UUID.
+pub fn generate_message_id(domain: &str) -> String {
+    let domain = match domain.trim() {
+        "" => "localhost",
+        d => d,
+    };
+    format!("<{}@{}>", random_uuid(), domain)
+}
+
+/// A random (version 4) UUID in its hyphenated form.
+fn random_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Domain part of an address (with or without display name), used for
+/// `Message-ID` generation. Falls back to `localhost`.
+pub fn address_domain(address: &str) -> String {
+    parse_address(address)
+        .and_then(|(_, addr)| addr.rsplit_once('@').map(|(_, d)| d.to_string()))
+        .unwrap_or_else(|| "localhost".to_string())
+}
 
-    let local = parts[0];
-    let domain = parts[1];
+/// Whether `subject` starts with one of `prefixes` followed by a colon, e.g.
+/// `AW: Meeting` for the prefix `aw`. Case is ignored, and a trailing colon
+/// in the configured prefix is optional.
+fn has_subject_prefix(subject: &str, prefixes: &[String]) -> bool {
+    let Some((head, _)) = subject.trim_start().split_once(':') else {
+        return false;
+    };
+    let head = head.trim_end();
+    prefixes
+        .iter()
+        .any(|p| p.trim_end_matches(':').eq_ignore_ascii_case(head))
+}
 
-    !local.is_empty() && !domain.is_empty() && domain.contains('.')
+/// Parse recipients into explicit name/address pairs for the send payload.
+pub fn parse_recipients(addresses: &[String]) -> Result<Vec<Recipient>> {
+    addresses
+        .iter()
+        .map(|addr| {
+            parse_address(addr)
+                .map(|(name, email)| Recipient { name, email })
+                .ok_or_else(|| Error::InvalidAddress(addr.clone()))
+        })
+        .collect()
 }
 
 /// Builder for compose documents.
@@ -660,6 +1517,24 @@ Thanks!"#;
         assert!(parsed.body.contains("Test body content"));
     }
 
+    #[test]
+    fn test_send_token_is_stable_and_round_trips() {
+        let mut doc = ComposeBuilder::new()
+            .to(vec!["alice@example.com".to_string()])
+            .subject("Retry me")
+            .body("Hi")
+            .build();
+        assert!(!doc.to_string().unwrap().contains("send-token"));
+
+        let token = doc.ensure_send_token().to_string();
+        assert_eq!(token.len(), 36);
+        assert_eq!(doc.ensure_send_token(), token);
+
+        let parsed = ComposeDocument::parse(&doc.to_string().unwrap()).unwrap();
+        assert_eq!(parsed.send_token.as_deref(), Some(token.as_str()));
+        assert_eq!(parsed.body, "Hi");
+    }
+
     #[test]
     fn test_reply() {
         let config = ComposeConfig::default();
@@ -679,6 +1554,56 @@ Thanks!"#;
         assert!(doc.body.contains("> with multiple lines"));
     }
 
+    #[test]
+    fn test_reply_blank_lines_above_quote() {
+        let reply = |config: &ComposeConfig| {
+            ComposeDocument::reply("alice@example.com", "Plans", None, None, "Lunch?", config).body
+        };
+        assert_eq!(reply(&ComposeConfig::default()), "\n> Lunch?");
+
+        let mut config = ComposeConfig {
+            reply_blank_lines: 3,
+            ..Default::default()
+        };
+        assert_eq!(reply(&config), "\n\n\n> Lunch?");
+        config.reply_blank_lines = 0;
+        assert_eq!(reply(&config), "> Lunch?");
+
+        config.reply_blank_lines = 2;
+        config.reply_attribution = "{from} wrote:".to_string();
+        assert_eq!(reply(&config), "\n\nalice@example.com wrote:\n> Lunch?");
+
+        let doc = ComposeDocument::reply_all(
+            "alice@example.com",
+            &[],
+            &[],
+            "Plans",
+            None,
+            None,
+            "Lunch?",
+            "me@example.com",
+            &config,
+        );
+        assert!(doc.body.starts_with("\n\nalice@example.com wrote:\n> "));
+    }
+
+    #[test]
+    fn test_signature_above_attribution() {
+        let config = ComposeConfig {
+            signature_placement: SignaturePlacement::Above,
+            reply_attribution: "On Monday, {from} wrote:".to_string(),
+            ..Default::default()
+        };
+        let mut doc =
+            ComposeDocument::reply("alice@example.com", "Plans", None, None, "Lunch?", &config);
+        doc.body = format!("Sure!{}", doc.body);
+        doc.add_signature_with("Bob", &config);
+        assert_eq!(
+            doc.body,
+            "Sure!\n\n--\nBob\n\nOn Monday, alice@example.com wrote:\n> Lunch?"
+        );
+    }
+
     #[test]
     fn test_reply_preserves_re() {
         let config = ComposeConfig::default();
@@ -694,6 +1619,38 @@ Thanks!"#;
         assert_eq!(doc.subject, "Re: Already has Re");
     }
 
+    #[test]
+    fn test_reply_and_forward_respect_localized_prefixes() {
+        let config = ComposeConfig::default();
+        let reply = |subject| {
+            ComposeDocument::reply("a@example.com", subject, None, None, "", &config).subject
+        };
+        let forward = |subject| {
+            ComposeDocument::forward("a@example.com", &[], subject, None, "", &config).subject
+        };
+
+        assert_eq!(reply("AW: Meeting"), "AW: Meeting");
+        assert_eq!(reply("aw:Meeting"), "aw:Meeting");
+        assert_eq!(reply("SV: Lunch"), "SV: Lunch");
+        // A forward prefix is not a reply prefix
+        assert_eq!(reply("WG: Meeting"), "Re: WG: Meeting");
+        // Only a whole word before the colon counts
+        assert_eq!(reply("Rework: plan"), "Re: Rework: plan");
+
+        assert_eq!(forward("WG: Meeting"), "WG: Meeting");
+        assert_eq!(forward("FW: Meeting"), "FW: Meeting");
+        assert_eq!(forward("AW: Meeting"), "Fwd: AW: Meeting");
+
+        let config = ComposeConfig {
+            reply_prefixes: vec!["Odp:".to_string()],
+            ..ComposeConfig::default()
+        };
+        let doc = ComposeDocument::reply("a@example.com", "ODP: Plan", None, None, "", &config);
+        assert_eq!(doc.subject, "ODP: Plan");
+        let doc = ComposeDocument::reply("a@example.com", "AW: Plan", None, None, "", &config);
+        assert_eq!(doc.subject, "Re: AW: Plan");
+    }
+
     #[test]
     fn test_reply_all() {
         let config = ComposeConfig::default();
@@ -749,15 +1706,109 @@ Thanks!"#;
     }
 
     #[test]
-    fn test_validate() {
-        let mut doc = ComposeDocument::new();
-        assert!(doc.validate().is_err()); // No recipients
-
-        doc.to = vec!["invalid-email".to_string()];
-        assert!(doc.validate().is_err()); // Invalid email
+    fn test_signature_below_quote() {
+        let config = ComposeConfig::default();
+        let mut doc =
+            ComposeDocument::reply("alice@example.com", "Plans", None, None, "Lunch?", &config);
+        doc.add_signature_with("Bob", &config);
+        assert_eq!(doc.body, "\n> Lunch?\n\n--\nBob");
+    }
 
-        doc.to = vec!["valid@example.com".to_string()];
+    #[test]
+    fn test_signature_above_quote() {
+        let config = ComposeConfig {
+            signature_delimiter: "-- ".to_string(),
+            signature_placement: SignaturePlacement::Above,
+            ..Default::default()
+        };
+        let mut doc = ComposeDocument::reply(
+            "alice@example.com",
+            "Plans",
+            None,
+            None,
+            "Lunch?\nNoon works",
+            &config,
+        );
+        doc.body = format!("Sure!\n\n{}", doc.body);
+        doc.add_signature_with("Bob", &config);
+        assert_eq!(doc.body, "Sure!\n\n-- \nBob\n\n> Lunch?\n> Noon works");
+
+        // An empty reply leaves room for the new text above the signature
+        let mut doc = ComposeDocument::reply("a@example.com", "X", None, None, "Hi", &config);
+        doc.add_signature_with("Bob", &config);
+        assert_eq!(doc.body, "\n-- \nBob\n\n> Hi");
+
+        // Without quoted text the signature is appended
+        let mut doc = ComposeDocument::new();
+        doc.body = "Hello".to_string();
+        doc.add_signature_with("-- \nBob", &config);
+        assert_eq!(doc.body, "Hello\n\n-- \nBob");
+    }
+
+    #[test]
+    fn test_signature_above_forward() {
+        let config = ComposeConfig {
+            signature_placement: SignaturePlacement::Above,
+            signature_delimiter: String::new(),
+            ..Default::default()
+        };
+        let mut doc =
+            ComposeDocument::forward("alice@example.com", &[], "Report", None, "Body", &config);
+        doc.add_signature_with("Bob", &config);
+        assert!(
+            doc.body
+                .starts_with("\nBob\n\n---------- Forwarded message ----------\n"),
+            "{:?}",
+            doc.body
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut doc = ComposeDocument::new();
+        assert!(matches!(doc.validate(), Err(Error::NoRecipients)));
+
+        doc.to = vec!["invalid-email".to_string()];
+        assert!(matches!(
+            doc.validate(),
+            Err(Error::InvalidAddress(addr)) if addr == "invalid-email"
+        ));
+
+        doc.to = vec!["valid@example.com".to_string()];
+        doc.bcc = vec!["bad bcc".to_string()];
+        assert!(matches!(doc.validate(), Err(Error::InvalidAddress(_))));
+        doc.bcc.clear();
+
+        doc.to = vec!["valid@example.com".to_string()];
+        assert!(doc.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_bcc_only() {
+        let doc = ComposeBuilder::new()
+            .subject("Announcement")
+            .bcc(vec!["alice@example.com".to_string()])
+            .build();
         assert!(doc.validate().is_ok());
+        assert!(
+            lint_summary(&doc.lint(&[]))
+                .iter()
+                .all(|(severity, _)| *severity != LintSeverity::Error)
+        );
+
+        let message = doc.to_rfc822("me@example.org").unwrap();
+        assert!(message.contains("To: undisclosed-recipients:;\r\n"));
+        assert!(message.contains("Bcc: alice@example.com\r\n"));
+
+        let doc = ComposeBuilder::new()
+            .subject("Announcement")
+            .body("Hello")
+            .build();
+        assert!(matches!(doc.validate(), Err(Error::NoRecipients)));
+        assert_eq!(
+            lint_summary(&doc.lint(&[])),
+            vec![(LintSeverity::Error, "to")]
+        );
     }
 
     #[test]
@@ -767,6 +1818,184 @@ Thanks!"#;
         assert_eq!(quoted, "> Line 1\n> Line 2\n> Line 3");
     }
 
+    #[test]
+    fn test_plain_text_collapses_quotes() {
+        let body = "Sounds good.\n\nOn Monday, Bob wrote:\n> Can we meet?\n>> Earlier\n> Thanks\n\nSee you\n> one more";
+        assert_eq!(
+            plain_text(body, "> "),
+            "Sounds good.\n\nOn Monday, Bob wrote:\n[3 quoted lines]\n\nSee you\n[1 quoted line]"
+        );
+    }
+
+    #[test]
+    fn test_plain_text_trims_signature() {
+        let body = "Hi,\n\nsee attached.\n\n-- \nAlice\nAcme Corp\n";
+        assert_eq!(plain_text(body, "> "), "Hi,\n\nsee attached.");
+
+        // A bare "--" in the middle of text that is not the last separator
+        // keeps the body above the final one.
+        let body = "Step one\n--\nStep two\n-- \nSig";
+        assert_eq!(plain_text(body, "> "), "Step one\n--\nStep two");
+
+        // Without the trailing space it is not a separator at all
+        let body = "Options:\n--\nverbose output\n";
+        assert_eq!(plain_text(body, "> "), "Options:\n--\nverbose output");
+        assert_eq!(plain_text("Hi\r\n-- \r\nSig\r\n", "> "), "Hi");
+    }
+
+    #[test]
+    fn test_wrap_flowed_breaks_long_lines() {
+        let body = "The quick brown fox jumps over the lazy dog\nshort line";
+        let wrapped = wrap_flowed(body, 20, "> ");
+        assert_eq!(
+            wrapped,
+            "The quick brown fox \njumps over the lazy \ndog\nshort line"
+        );
+        for line in wrapped.lines() {
+            assert!(line.trim_end().chars().count() <= 20, "{line:?}");
+        }
+        // Already wrapped text is left as is
+        assert_eq!(wrap_flowed(&wrapped, 20, "> "), wrapped);
+        assert_eq!(wrap_flowed(body, 0, "> "), body);
+    }
+
+    #[test]
+    fn test_wrap_flowed_keeps_quotes_and_long_words() {
+        let quote = "> a quoted line that is much longer than the configured width";
+        let url = "https://example.com/a/very/long/path/that/cannot/be/broken";
+        let body = format!("{quote}\n{url} end\n-- \nSig");
+        assert_eq!(
+            wrap_flowed(&body, 20, "> "),
+            format!("{quote}\n{url} \nend\n-- \nSig")
+        );
+    }
+
+    #[test]
+    fn test_to_string_with_wraps_body_only() {
+        let doc = ComposeBuilder::new()
+            .to(vec!["alice@example.com".to_string()])
+            .subject("A subject that is longer than the wrap width")
+            .body("one two three four five six\n> quoted text stays on one line")
+            .build();
+        let config = ComposeConfig {
+            wrap_width: 14,
+            ..Default::default()
+        };
+        let text = doc.to_string_with(&config).unwrap();
+        assert!(text.contains("subject: A subject that is longer than the wrap width\n"));
+        assert!(text.ends_with("\none two three \nfour five six\n> quoted text stays on one line"));
+        assert_eq!(
+            doc.to_string_with(&ComposeConfig::default()).unwrap(),
+            doc.to_string().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_address_bare() {
+        assert_eq!(
+            parse_address(" jane@example.com "),
+            Some((None, "jane@example.com".to_string()))
+        );
+        assert_eq!(parse_address("jane@example"), None);
+        assert_eq!(parse_address("jane doe@example.com"), None);
+        assert_eq!(parse_address("a@b@example.com"), None);
+    }
+
+    #[test]
+    fn test_parse_address_angle_brackets() {
+        assert_eq!(
+            parse_address("Jane Doe <jane@example.com>"),
+            Some((Some("Jane Doe".to_string()), "jane@example.com".to_string()))
+        );
+        assert_eq!(
+            parse_address("<jane@example.com>"),
+            Some((None, "jane@example.com".to_string()))
+        );
+        assert_eq!(parse_address("Jane <jane@example.com"), None);
+        assert_eq!(parse_address("Jane <jane@example.com> trailing"), None);
+        assert_eq!(parse_address("Jane <not-an-address>"), None);
+    }
+
+    #[test]
+    fn test_parse_address_quoted_names() {
+        assert_eq!(
+            parse_address(r#""Jane Doe" <jane@example.com>"#),
+            Some((Some("Jane Doe".to_string()), "jane@example.com".to_string()))
+        );
+        assert_eq!(
+            parse_address(r#""Doe, Jane <HR>" <jane@example.com>"#),
+            Some((
+                Some("Doe, Jane <HR>".to_string()),
+                "jane@example.com".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_address(r#""Jane \"JD\" Doe" <jane@example.com>"#),
+            Some((
+                Some(r#"Jane "JD" Doe"#.to_string()),
+                "jane@example.com".to_string()
+            ))
+        );
+        assert_eq!(parse_address(r#""Unclosed <jane@example.com>"#), None);
+        assert_eq!(parse_address("Doe, Jane <jane@example.com>"), None);
+    }
+
+    #[test]
+    fn test_format_address_round_trip() {
+        assert_eq!(format_address(None, "jane@example.com"), "jane@example.com");
+        assert_eq!(
+            format_address(Some("Jane Doe"), "jane@example.com"),
+            "Jane Doe <jane@example.com>"
+        );
+        for name in ["Doe, Jane", r#"Jane "JD" Doe"#, r"back\slash"] {
+            let formatted = format_address(Some(name), "jane@example.com");
+            assert_eq!(
+                parse_address(&formatted),
+                Some((Some(name.to_string()), "jane@example.com".to_string())),
+                "{formatted}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_address_list_keeps_quoted_commas() {
+        assert_eq!(
+            split_address_list(r#""Doe, Jane" <jane@example.com>, bob@example.com"#),
+            vec![r#""Doe, Jane" <jane@example.com>"#, "bob@example.com"]
+        );
+
+        let text = "---\nto: '\"Doe, Jane\" <jane@example.com>, bob@example.com'\n---\n\nHi";
+        let doc = ComposeDocument::parse(text).unwrap();
+        assert_eq!(doc.to.len(), 2);
+        assert!(doc.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_recipients() {
+        let recipients = parse_recipients(&[
+            r#""Doe, Jane" <jane@example.com>"#.to_string(),
+            "bob@example.com".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            recipients,
+            vec![
+                Recipient {
+                    name: Some("Doe, Jane".to_string()),
+                    email: "jane@example.com".to_string(),
+                },
+                Recipient {
+                    name: None,
+                    email: "bob@example.com".to_string(),
+                },
+            ]
+        );
+        assert!(matches!(
+            parse_recipients(&["Jane <broken".to_string()]),
+            Err(Error::InvalidAddress(_))
+        ));
+    }
+
     #[test]
     fn test_is_valid_email() {
         assert!(is_valid_email("test@example.com"));
@@ -776,6 +2005,28 @@ Thanks!"#;
         assert!(!is_valid_email("test@"));
     }
 
+    #[test]
+    fn test_is_valid_email_idn() {
+        assert!(is_valid_email("user@münchen.de"));
+        assert_eq!(
+            ascii_domain("münchen.de").as_deref(),
+            Some("xn--mnchen-3ya.de")
+        );
+        // Mixed scripts within one name are still valid IDNA.
+        assert!(is_valid_email("Anna <anna@bücher-日本.example>"));
+        assert_eq!(
+            ascii_domain("bücher-日本.example").as_deref(),
+            Some("xn--bcher--3ya3877rdoc.example")
+        );
+        assert!(!is_valid_email("user@münchen"));
+        assert!(!is_valid_email("user@exa mple.com"));
+        assert!(!is_valid_email("user@-bad.com"));
+
+        // The original form is kept for storing and sending.
+        let (_, addr) = parse_address("user@münchen.de").unwrap();
+        assert_eq!(addr, "user@münchen.de");
+    }
+
     #[test]
     fn test_builder() {
         let doc = ComposeBuilder::new()
@@ -803,6 +2054,15 @@ Thanks!"#;
         assert_eq!(all.len(), 3);
     }
 
+    #[test]
+    fn test_from_reader() {
+        let input = "---\nto: alice@example.com\nsubject: Piped\n---\n\nFrom stdin";
+        let doc = ComposeDocument::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(doc.to, vec!["alice@example.com"]);
+        assert_eq!(doc.subject, "Piped");
+        assert_eq!(doc.body, "From stdin");
+    }
+
     #[test]
     fn test_no_frontmatter() {
         let text = "Just a plain body without frontmatter";
@@ -818,4 +2078,477 @@ Thanks!"#;
         assert!(doc.to.is_empty());
         assert!(doc.body.trim().contains("Body only"));
     }
+
+    /// Parse RFC 5322 headers (unfolding continuation lines) and body.
+    fn parse_rfc822(message: &str) -> (Vec<(String, String)>, String) {
+        let (head, body) = message.split_once("\r\n\r\n").unwrap();
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in head.split("\r\n") {
+            if line.starts_with(' ') {
+                headers.last_mut().unwrap().1.push_str(line);
+            } else {
+                let (name, value) = line.split_once(": ").unwrap();
+                headers.push((name.to_string(), value.to_string()));
+            }
+        }
+        (headers, body.to_string())
+    }
+
+    fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn split_addresses(value: &str) -> Vec<String> {
+        value.split(',').map(|a| a.trim().to_string()).collect()
+    }
+
+    fn is_valid_message_id(id: &str) -> bool {
+        let Some(inner) = id.strip_prefix('<').and_then(|s| s.strip_suffix('>')) else {
+            return false;
+        };
+        let Some((left, right)) = inner.split_once('@') else {
+            return false;
+        };
+        let groups: Vec<&str> = left.split('-').collect();
+        groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+            && groups
+                .iter()
+                .all(|g| g.chars().all(|c| c.is_ascii_hexdigit()))
+            && groups[2].starts_with('4')
+            && !right.is_empty()
+            && !right.contains(['<', '>', '@', ' '])
+    }
+
+    #[test]
+    fn test_generate_message_id_format() {
+        let id = generate_message_id("example.org");
+        assert!(is_valid_message_id(&id), "{id}");
+        assert!(id.ends_with("@example.org>"));
+        assert!(generate_message_id("").ends_with("@localhost>"));
+    }
+
+    #[test]
+    fn test_generate_message_id_unique() {
+        let ids: std::collections::HashSet<String> = (0..1000)
+            .map(|_| generate_message_id("example.org"))
+            .collect();
+        assert_eq!(ids.len(), 1000);
+    }
+
+    #[test]
+    fn test_address_domain() {
+        assert_eq!(address_domain("Me <me@Example.org>"), "Example.org");
+        assert_eq!(address_domain("me@example.org"), "example.org");
+        assert_eq!(address_domain("nobody"), "localhost");
+    }
+
+    #[test]
+    fn test_to_rfc822_with_message_id() {
+        let doc = ComposeBuilder::new()
+            .to(vec!["alice@example.com".into()])
+            .subject("Hi")
+            .build();
+        let message = doc
+            .to_rfc822_with_message_id("me@example.org", "<fixed@example.org>")
+            .unwrap();
+        let (headers, _) = parse_rfc822(&message);
+        assert_eq!(header(&headers, "Message-ID"), Some("<fixed@example.org>"));
+    }
+
+    #[test]
+    fn test_to_rfc822_round_trip() {
+        let doc = ComposeBuilder::new()
+            .to(vec![
+                "alice@example.com".into(),
+                "Bob <bob@example.com>".into(),
+            ])
+            .cc(vec!["carol@example.com".into()])
+            .subject("Quarterly report")
+            .in_reply_to("<orig@example.com>")
+            .body("Hi all,\n\nSee attached.")
+            .build();
+
+        let message = doc.to_rfc822("Me <me@example.org>").unwrap();
+        assert!(!message.replace("\r\n", "").contains('\n'));
+
+        let (headers, body) = parse_rfc822(&message);
+        assert_eq!(split_addresses(header(&headers, "To").unwrap()), doc.to);
+        assert_eq!(split_addresses(header(&headers, "Cc").unwrap()), doc.cc);
+        assert_eq!(header(&headers, "Subject"), Some("Quarterly report"));
+        assert_eq!(header(&headers, "From"), Some("Me <me@example.org>"));
+        assert_eq!(header(&headers, "In-Reply-To"), Some("<orig@example.com>"));
+        assert!(header(&headers, "Bcc").is_none());
+        assert!(chrono::DateTime::parse_from_rfc2822(header(&headers, "Date").unwrap()).is_ok());
+        assert_eq!(body, "Hi all,\r\n\r\nSee attached.\r\n");
+
+        let message_id = header(&headers, "Message-ID").unwrap();
+        assert!(is_valid_message_id(message_id));
+        assert!(message_id.ends_with("@example.org>"));
+
+        // Message-IDs are unique per rendering
+        let again = doc.to_rfc822("me@example.org").unwrap();
+        let (headers_again, _) = parse_rfc822(&again);
+        assert_ne!(header(&headers_again, "Message-ID"), Some(message_id));
+    }
+
+    #[test]
+    fn test_to_rfc822_encodes_non_ascii_subject() {
+        let subject = "Grüße aus Köln und noch viel mehr Text damit die Zeile umbrochen wird";
+        let doc = ComposeBuilder::new()
+            .add_to("alice@example.com")
+            .subject(subject)
+            .build();
+        let message = doc.to_rfc822("me@example.org").unwrap();
+        assert!(message.split("\r\n\r\n").next().unwrap().is_ascii());
+
+        let (headers, _) = parse_rfc822(&message);
+        let encoded = header(&headers, "Subject").unwrap();
+        assert!(encoded.starts_with("=?UTF-8?Q?"));
+        for word in encoded.split(' ') {
+            assert!(word.len() <= 75, "{word}");
+        }
+    }
+
+    #[test]
+    fn test_from_rfc822_folded_headers_and_recipients() {
+        let message = "From: Sender <sender@example.com>\r\n\
+            To: Alice <alice@example.com>,\r\n\
+            \t\"Doe, Bob\" <bob@example.com>\r\n\
+            Cc: carol@example.com, dave@example.com\r\n\
+            Subject: A long subject line that was\r\n\
+            \x20folded by the sender\r\n\
+            In-Reply-To: <orig@example.com>\r\n\
+            References: <root@example.com>\r\n\
+            \x20<orig@example.com>\r\n\
+            X-Mailer: Test\r\n\
+            \r\n\
+            Hi all,\r\n\
+            \r\n\
+            Subject: not a header\r\n";
+        let doc = ComposeDocument::from_rfc822(message).unwrap();
+        assert_eq!(
+            doc.to,
+            [
+                "Alice <alice@example.com>",
+                "\"Doe, Bob\" <bob@example.com>"
+            ]
+        );
+        assert_eq!(doc.cc, ["carol@example.com", "dave@example.com"]);
+        assert!(doc.bcc.is_empty());
+        assert_eq!(
+            doc.subject,
+            "A long subject line that was folded by the sender"
+        );
+        assert_eq!(doc.in_reply_to.as_deref(), Some("<orig@example.com>"));
+        assert_eq!(
+            doc.references.as_deref(),
+            Some("<root@example.com> <orig@example.com>")
+        );
+        assert_eq!(doc.body, "Hi all,\n\nSubject: not a header\n");
+    }
+
+    #[test]
+    fn test_from_rfc822_decodes_encoded_words() {
+        let message = "To: undisclosed-recipients:;\n\
+            Bcc: hidden@example.com\n\
+            Subject: =?UTF-8?Q?Gr=C3=BC=C3=9Fe?= =?utf-8?B?YXVzIEvDtmxu?= heute\n\
+            \n\
+            Body";
+        let doc = ComposeDocument::from_rfc822(message).unwrap();
+        assert!(doc.to.is_empty());
+        assert_eq!(doc.bcc, ["hidden@example.com"]);
+        assert_eq!(doc.subject, "Grüßeaus Köln heute");
+        assert_eq!(doc.body, "Body");
+    }
+
+    #[test]
+    fn test_from_rfc822_decodes_transfer_encoding() {
+        let base64 = "Subject: x\nContent-Transfer-Encoding: BASE64\n\nSGVs\nbG8=\n";
+        let doc = ComposeDocument::from_rfc822(base64).unwrap();
+        assert_eq!(doc.body, "Hello");
+
+        // Like decode_transfer_encoding, an undecodable body is kept as is
+        let broken = "Subject: x\nContent-Transfer-Encoding: base64\n\nnot base64!\n";
+        let doc = ComposeDocument::from_rfc822(broken).unwrap();
+        assert_eq!(doc.body, "not base64!\n");
+    }
+
+    #[test]
+    fn test_from_rfc822_rejects_malformed_headers() {
+        assert!(matches!(
+            ComposeDocument::from_rfc822("just some text\n\nbody"),
+            Err(Error::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            ComposeDocument::from_rfc822(" continued: x\n"),
+            Err(Error::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            ComposeDocument::from_rfc822(""),
+            Err(Error::InvalidMessage(_))
+        ));
+    }
+
+    #[test]
+    fn test_to_rfc822_folds_long_headers() {
+        let subject = "Minutes of the quarterly planning meeting with notes on the budget, \
+                       hiring and the office move";
+        let doc = ComposeBuilder::new()
+            .to((1..=6)
+                .map(|i| format!("Person Number{i} <person{i}@example.com>"))
+                .collect())
+            .subject(subject)
+            .body("Hi")
+            .build();
+        let message = doc.to_rfc822("me@example.org").unwrap();
+        let (head, _) = message.split_once("\r\n\r\n").unwrap();
+        for line in head.split("\r\n") {
+            assert!(line.len() <= 78, "{line:?}");
+        }
+        for name in [
+            "Date",
+            "Message-ID",
+            "From",
+            "To",
+            "Subject",
+            "MIME-Version",
+            "Content-Type",
+        ] {
+            assert!(
+                head.contains(&format!("\r\n{name}: ")) || head.starts_with(&format!("{name}: "))
+            );
+        }
+        assert!(head.contains("\r\nSubject: Minutes of the quarterly planning meeting with notes on the budget,\r\n hiring"), "{head}");
+
+        let (headers, _) = parse_rfc822(&message);
+        assert_eq!(header(&headers, "Subject"), Some(subject));
+        assert_eq!(header(&headers, "Content-Transfer-Encoding"), Some("7bit"));
+    }
+
+    #[test]
+    fn test_to_rfc822_round_trips_through_from_rfc822() {
+        let long_line = "word ".repeat(250);
+        let doc = ComposeBuilder::new()
+            .to(vec![
+                "Alice <alice@example.com>".into(),
+                "\"Doe, Bob\" <bob@example.com>".into(),
+            ])
+            .cc(vec!["carol@example.com".into()])
+            .bcc(vec!["dave@example.com".into()])
+            .subject("Grüße – a subject long enough to need several encoded words to fit")
+            .in_reply_to("<orig@example.com>")
+            .references("<root@example.com> <orig@example.com>")
+            .body(&format!("Hallo,\n\n{}\n= done", long_line.trim_end()))
+            .build();
+
+        let message = doc.to_rfc822("me@example.org").unwrap();
+        assert!(message.contains("Content-Transfer-Encoding: quoted-printable\r\n"));
+        let (_, body) = message.split_once("\r\n\r\n").unwrap();
+        assert!(body.split("\r\n").all(|line| line.len() <= 76), "{body}");
+
+        let parsed = ComposeDocument::from_rfc822(&message).unwrap();
+        assert_eq!(parsed.to, doc.to);
+        assert_eq!(parsed.cc, doc.cc);
+        assert_eq!(parsed.bcc, doc.bcc);
+        assert_eq!(parsed.subject, doc.subject);
+        assert_eq!(parsed.in_reply_to, doc.in_reply_to);
+        assert_eq!(parsed.references, doc.references);
+        assert_eq!(parsed.body, format!("{}\n", doc.body));
+    }
+
+    #[test]
+    fn test_decode_transfer_encoding() {
+        let long_line = "word ".repeat(250);
+        let doc = ComposeBuilder::new()
+            .to(vec!["alice@example.com".into()])
+            .subject("Long")
+            .body(&format!("Grüße\n{}", long_line.trim_end()))
+            .build();
+        let message = doc.to_rfc822("me@example.org").unwrap();
+        let (headers, body) = message.split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            decode_transfer_encoding(headers, body),
+            format!("{}\n", doc.body)
+        );
+
+        let headers = "Subject: Hi\nContent-Transfer-Encoding: BASE64";
+        assert_eq!(decode_transfer_encoding(headers, "SGk=\n"), "Hi");
+        assert_eq!(
+            decode_transfer_encoding(headers, "not base64!"),
+            "not base64!"
+        );
+        assert_eq!(decode_transfer_encoding("Subject: Hi", "a=3Db"), "a=3Db");
+    }
+
+    #[test]
+    fn test_to_rfc822_requires_valid_addresses() {
+        let doc = ComposeBuilder::new().subject("No recipients").build();
+        assert!(matches!(
+            doc.to_rfc822("me@example.org"),
+            Err(Error::NoRecipients)
+        ));
+
+        let doc = ComposeBuilder::new().add_to("alice@example.com").build();
+        assert!(matches!(
+            doc.to_rfc822("not-an-address"),
+            Err(Error::InvalidSender(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors_are_specific() {
+        let err = ComposeDocument::parse("---\nto: alice@example.com\nHello").unwrap_err();
+        assert!(matches!(err, Error::UnclosedFrontmatter));
+        assert_eq!(
+            err.to_string(),
+            "configuration error: unclosed frontmatter (missing closing ---)"
+        );
+
+        let err = ComposeDocument::parse("---\nto: [unclosed\n---\nHello").unwrap_err();
+        assert!(matches!(err, Error::FrontmatterParse(_)));
+        assert!(
+            err.to_string()
+                .starts_with("configuration error: parsing frontmatter YAML:")
+        );
+
+        let err = ComposeDocument::new().validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "configuration error: no recipients specified"
+        );
+    }
+
+    fn lint_summary(issues: &[LintIssue]) -> Vec<(LintSeverity, &str)> {
+        issues
+            .iter()
+            .map(|i| (i.severity, i.field.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_document() {
+        let doc = ComposeBuilder::new()
+            .add_to("alice@example.com")
+            .subject("Hello")
+            .body("Hi Alice")
+            .build();
+        assert!(doc.lint(&[]).is_empty());
+        assert!(doc.validate().is_ok());
+    }
+
+    #[test]
+    fn test_lint_warns_on_empty_subject_and_body() {
+        let doc = ComposeBuilder::new()
+            .add_to("alice@example.com")
+            .body("  \n")
+            .build();
+        assert_eq!(
+            lint_summary(&doc.lint(&[])),
+            [
+                (LintSeverity::Warning, "subject"),
+                (LintSeverity::Warning, "body")
+            ]
+        );
+        // Warnings alone don't block sending
+        assert!(doc.validate().is_ok());
+    }
+
+    #[test]
+    fn test_lint_recipient_errors() {
+        let doc = ComposeBuilder::new().subject("Hi").body("Hello").build();
+        let issues = doc.lint(&[]);
+        assert_eq!(lint_summary(&issues), [(LintSeverity::Error, "to")]);
+        assert_eq!(issues[0].message, "no recipients specified");
+
+        let doc = ComposeBuilder::new()
+            .to(vec!["alice@example.com".to_string()])
+            .cc(vec!["Bob <bob@examplecom>".to_string()])
+            .bcc(vec!["not an address".to_string()])
+            .subject("Hi")
+            .body("Hello")
+            .build();
+        let issues = doc.lint(&[]);
+        assert_eq!(
+            lint_summary(&issues),
+            [(LintSeverity::Error, "cc"), (LintSeverity::Error, "bcc")]
+        );
+        assert_eq!(
+            issues[0].message,
+            "'Bob <bob@examplecom>' looks like a typo: the domain has no dot"
+        );
+        assert_eq!(issues[1].message, "invalid email address: not an address");
+        assert!(doc.validate().is_err());
+    }
+
+    #[test]
+    fn test_lint_attachments() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let present = temp.path().join("report.pdf");
+        std::fs::write(&present, b"%PDF").unwrap();
+        let missing = temp.path().join("missing.pdf");
+        let doc = ComposeBuilder::new()
+            .add_to("alice@example.com")
+            .subject("Report")
+            .body("Attached")
+            .build();
+
+        let issues = doc.lint(&[present, missing.clone(), temp.path().to_path_buf()]);
+        assert_eq!(
+            lint_summary(&issues),
+            [
+                (LintSeverity::Error, "attachment"),
+                (LintSeverity::Error, "attachment")
+            ]
+        );
+        assert_eq!(
+            issues[0].message,
+            format!("attachment not found: {}", missing.display())
+        );
+        assert!(issues[1].message.starts_with("attachment is not a file: "));
+    }
+
+    #[test]
+    fn test_summary_lists_fields() {
+        let doc = ComposeBuilder::new()
+            .to(vec![
+                "Jane Doe <jane@example.com>".to_string(),
+                "bob@example.com".to_string(),
+            ])
+            .subject("Quarterly report")
+            .body("Hi,\nsee attached.")
+            .build();
+        let attachments = [PathBuf::from("/tmp/reports/q3.pdf")];
+        assert_eq!(
+            doc.summary(&attachments),
+            "To:          Jane Doe <jane@example.com>, bob@example.com\n\
+             Cc:          (none)\n\
+             Subject:     Quarterly report\n\
+             Body:        17 characters, 2 lines\n\
+             Attachments: q3.pdf"
+        );
+    }
+
+    #[test]
+    fn test_summary_of_sparse_document() {
+        let mut doc = ComposeDocument::new();
+        doc.bcc = vec!["hidden@example.com".to_string()];
+        doc.body = "x".to_string();
+        let summary = doc.summary(&[]);
+        assert!(summary.contains("Bcc:         hidden@example.com\n"));
+        assert!(summary.contains("Subject:     (no subject)\n"));
+        assert!(summary.contains("Body:        1 character, 1 line\n"));
+        assert!(summary.ends_with("Attachments: (none)"));
+    }
+
+    #[test]
+    fn test_lint_issue_serializes_severity_lowercase() {
+        let issue = LintIssue::new(LintSeverity::Warning, "subject", "subject is empty");
+        assert_eq!(
+            serde_json::to_value(&issue).unwrap(),
+            serde_json::json!({"severity": "warning", "field": "subject", "message": "subject is empty"})
+        );
+    }
 }
@@ -7,10 +7,20 @@ use config::{Config, Environment, File, FileFormat};
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
-use crate::paths::AppPaths;
+use crate::id::{WordLists, read_word_list};
+use crate::paths::{AppPaths, expand_str_path};
 
 const APP_NAME: &str = "h8";
 
+/// Editor used when neither flag, config nor environment name one.
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// Time-of-day format used when `display.time_format` is not set.
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+
+/// Pager used when neither flag, config nor environment name one.
+pub const DEFAULT_PAGER: &str = "less -R";
+
 /// Calendar view mode.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -63,6 +73,95 @@ impl Default for CalendarConfig {
     }
 }
 
+/// Date/time display configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// strftime format for dates. Unset keeps relative labels ("Today",
+    /// "Mon", "Mar 3") where a listing uses them.
+    pub date_format: Option<String>,
+    /// strftime format for times of day.
+    pub time_format: String,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            date_format: None,
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Check that the formats only use specifiers valid for a date and a
+    /// time of day respectively.
+    pub fn validate(&self) -> Result<()> {
+        let sample = chrono::NaiveDate::from_ymd_opt(2024, 1, 2)
+            .and_then(|d| d.and_hms_opt(3, 4, 5))
+            .expect("valid sample timestamp");
+        if let Some(format) = &self.date_format
+            && strftime(sample.date().format(format)).is_none()
+        {
+            return Err(Error::Config(format!(
+                "display.date_format: invalid date format '{format}'"
+            )));
+        }
+        if strftime(sample.time().format(&self.time_format)).is_none() {
+            return Err(Error::Config(format!(
+                "display.time_format: invalid time format '{}'",
+                self.time_format
+            )));
+        }
+        Ok(())
+    }
+
+    /// Format `date` with `date_format`, or `None` when it is unset.
+    pub fn format_date(&self, date: chrono::NaiveDate) -> Option<String> {
+        self.date_format
+            .as_deref()
+            .and_then(|format| strftime(date.format(format)))
+    }
+
+    /// Format `time` with `time_format`.
+    pub fn format_time(&self, time: chrono::NaiveTime) -> String {
+        strftime(time.format(&self.time_format))
+            .unwrap_or_else(|| time.format(DEFAULT_TIME_FORMAT).to_string())
+    }
+
+    /// Reformat a stored timestamp (RFC 3339, ISO without offset, or a plain
+    /// date) as `<date> <time>`, keeping its own UTC offset. Dates use
+    /// `date_format`, or `%Y-%m-%d` when unset. Unparseable values are
+    /// returned unchanged.
+    pub fn format_timestamp(&self, raw: &str) -> String {
+        let datetime = chrono::DateTime::parse_from_rfc3339(raw)
+            .map(|dt| dt.naive_local())
+            .or_else(|_| chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S"))
+            .or_else(|_| chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S"));
+        let date_text = |date| {
+            self.format_date(date)
+                .unwrap_or_else(|| date.format("%Y-%m-%d").to_string())
+        };
+        if let Ok(dt) = datetime {
+            format!("{} {}", date_text(dt.date()), self.format_time(dt.time()))
+        } else if let Ok(date) = chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+            date_text(date)
+        } else {
+            raw.to_string()
+        }
+    }
+}
+
+/// Render a chrono `format(...)` result, or `None` if the format string is
+/// invalid or does not apply to the value (chrono reports both as a
+/// formatting error, which would panic in `to_string`).
+fn strftime(formatted: impl std::fmt::Display) -> Option<String> {
+    use std::fmt::Write;
+    let mut out = String::new();
+    write!(out, "{formatted}").ok()?;
+    Some(out)
+}
+
 /// A resource entry in a resource group.
 ///
 /// Supports two config formats:
@@ -214,8 +313,15 @@ pub struct AppConfig {
     pub account: String,
     /// Timezone for display (e.g., "Europe/Berlin").
     pub timezone: String,
-    /// URL of the Python EWS service.
+    /// URL of the Python EWS service. `$VAR` and `${VAR}` references are
+    /// expanded from the environment at load.
     pub service_url: String,
+    /// Forbid all service calls and work from local storage only.
+    #[serde(default)]
+    pub offline: bool,
+    /// Local service management.
+    #[serde(default)]
+    pub service: ServiceConfig,
     /// Free slots configuration.
     pub free_slots: FreeSlotsConfig,
     /// Mail configuration.
@@ -224,6 +330,15 @@ pub struct AppConfig {
     /// Calendar display configuration.
     #[serde(default)]
     pub calendar: CalendarConfig,
+    /// Date/time display formats.
+    #[serde(default)]
+    pub display: DisplayConfig,
+    /// Terminal UI configuration.
+    #[serde(default)]
+    pub tui: TuiConfig,
+    /// Short ID word lists.
+    #[serde(default)]
+    pub ids: IdsConfig,
     /// Trip planning configuration.
     #[serde(default)]
     pub trip: TripConfig,
@@ -244,9 +359,14 @@ impl Default for AppConfig {
             account: "your.email@example.com".to_string(),
             timezone: "Europe/Berlin".to_string(),
             service_url: "http://127.0.0.1:8787".to_string(),
+            offline: false,
+            service: ServiceConfig::default(),
             free_slots: FreeSlotsConfig::default(),
             mail: MailConfig::default(),
             calendar: CalendarConfig::default(),
+            display: DisplayConfig::default(),
+            tui: TuiConfig::default(),
+            ids: IdsConfig::default(),
             trip: TripConfig::default(),
             unsubscribe: UnsubscribeConfig::default(),
             people: std::collections::HashMap::new(),
@@ -255,6 +375,64 @@ impl Default for AppConfig {
     }
 }
 
+/// Local service management, configured under `[service]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceConfig {
+    /// Start the service when a command cannot reach it, then retry the
+    /// command once.
+    pub auto_start: bool,
+    /// Seconds to wait for an auto-started service to report healthy.
+    pub start_timeout_secs: u64,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            auto_start: false,
+            start_timeout_secs: 30,
+        }
+    }
+}
+
+/// Terminal UI configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Seconds between automatic mail list refreshes (0 disables auto-refresh).
+    pub refresh_interval_secs: u64,
+    /// Normal-mode key overrides (action name -> single character).
+    pub keys: std::collections::HashMap<String, String>,
+}
+
+/// Word lists for short IDs like `cold-lamp`.
+///
+/// Each path points at a newline-separated word file; unset lists fall back
+/// to the embedded words. Changes apply when the ID pool is next seeded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdsConfig {
+    /// Adjectives file (first half of an ID).
+    pub adjectives: Option<String>,
+    /// Nouns file (second half of an ID).
+    pub nouns: Option<String>,
+}
+
+impl IdsConfig {
+    /// Word lists to seed the ID pool with.
+    pub fn word_lists(&self) -> Result<WordLists> {
+        let embedded = WordLists::embedded();
+        let load = |path: &Option<String>, fallback: Vec<String>| match path {
+            Some(path) => read_word_list(&expand_str_path(path)?),
+            None => Ok(fallback),
+        };
+        Ok(WordLists {
+            adjectives: load(&self.adjectives, embedded.adjectives)?,
+            nouns: load(&self.nouns, embedded.nouns)?,
+        })
+    }
+}
+
 /// Free slots finder configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -283,16 +461,41 @@ impl Default for FreeSlotsConfig {
 pub struct MailConfig {
     /// Override data directory for mail storage.
     pub data_dir: Option<String>,
-    /// Editor command (defaults to $EDITOR).
+    /// Editor command (defaults to $VISUAL, then $EDITOR, then `vi`).
     pub editor: Option<String>,
-    /// Pager command for viewing messages.
-    pub pager: String,
+    /// Pager command for viewing messages (defaults to $PAGER, then `less -R`).
+    pub pager: Option<String>,
+    /// Fallback pagers tried in order when the preferred one is not installed.
+    pub pagers: Vec<String>,
     /// Folders to sync.
     pub sync_folders: Vec<String>,
+    /// Folders created in the local Maildir when it is initialized.
+    pub local_folders: Vec<String>,
     /// Default target folder for `h8 mail archive`.
     pub archive_folder: String,
     /// Email signature.
     pub signature: String,
+    /// Store a copy of sent messages in the local sent folder.
+    pub save_sent: bool,
+    /// Seconds the cached server folder list stays fresh.
+    pub folder_cache_ttl_secs: u64,
+    /// Parallel message fetches during `h8 mail sync --bodies`.
+    pub sync_jobs: usize,
+    /// Days a message stays in the local trash before `h8 mail trash purge`
+    /// removes it.
+    pub trash_retention_days: u64,
+    /// Ask before `h8 mail delete --force` and `h8 mail trash purge` unless
+    /// `-y` is given.
+    pub confirm_destructive: bool,
+    /// Per-folder fetch limits for sync and the TUI.
+    #[serde(default)]
+    pub limits: MailLimits,
+    /// `h8 mail read` settings.
+    #[serde(default)]
+    pub reader: ReaderConfig,
+    /// Message storage settings for sync.
+    #[serde(default)]
+    pub sync: MailSyncConfig,
     /// Compose settings.
     #[serde(default)]
     pub compose: ComposeConfig,
@@ -303,19 +506,93 @@ impl Default for MailConfig {
         Self {
             data_dir: None,
             editor: None,
-            pager: "less -R".to_string(),
+            pager: None,
+            pagers: Vec::new(),
             sync_folders: vec![
                 "inbox".to_string(),
                 "sent".to_string(),
                 "drafts".to_string(),
             ],
+            local_folders: crate::maildir::DEFAULT_FOLDERS
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
             archive_folder: "archive".to_string(),
             signature: String::new(),
+            save_sent: true,
+            folder_cache_ttl_secs: 3600,
+            sync_jobs: 4,
+            trash_retention_days: 30,
+            confirm_destructive: false,
+            limits: MailLimits::default(),
+            reader: ReaderConfig::default(),
+            sync: MailSyncConfig::default(),
             compose: ComposeConfig::default(),
         }
     }
 }
 
+/// Number of messages fetched per folder, configured under `[mail.limits]`.
+///
+/// `default` applies to every folder without its own entry; other keys name
+/// folders (matched case-insensitively). Unconfigured folders fall back to
+/// the caller's own default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailLimits {
+    /// Limit for folders not listed explicitly.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<usize>,
+    /// Folder-specific limits.
+    #[serde(flatten)]
+    pub folders: std::collections::HashMap<String, usize>,
+}
+
+impl MailLimits {
+    /// Configured limit for `folder`, if any.
+    pub fn configured(&self, folder: &str) -> Option<usize> {
+        self.folders
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(folder))
+            .map(|(_, limit)| *limit)
+            .or(self.default)
+    }
+
+    /// Effective sync fetch limit for `folder`.
+    pub fn for_folder(&self, folder: &str) -> usize {
+        self.configured(folder)
+            .unwrap_or(crate::sync::DEFAULT_SYNC_LIMIT)
+    }
+}
+
+/// Message reader settings, configured under `[mail.reader]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReaderConfig {
+    /// Column to wrap rendered HTML at (0 = terminal width).
+    pub wrap_width: usize,
+}
+
+impl ReaderConfig {
+    /// Wrap width to use, calling `terminal_width` only when none is
+    /// configured.
+    pub fn effective_wrap_width(&self, terminal_width: impl FnOnce() -> usize) -> usize {
+        match self.wrap_width {
+            0 => terminal_width(),
+            width => width,
+        }
+    }
+}
+
+/// Message storage settings, configured under `[mail.sync]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailSyncConfig {
+    /// Largest body stored in the Maildir, in bytes (0 = unlimited). Longer
+    /// bodies are cut and marked `[truncated]`.
+    pub max_body_bytes: usize,
+}
+
 /// Compose settings for email composition.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -326,6 +603,26 @@ pub struct ComposeConfig {
     pub quote_style: String,
     /// Whether to include signature in new messages.
     pub include_signature: bool,
+    /// Line inserted above the signature (empty for none).
+    pub signature_delimiter: String,
+    /// Where the signature goes in replies and forwards.
+    pub signature_placement: SignaturePlacement,
+    /// Blank lines above the quote in replies, where new text goes.
+    pub reply_blank_lines: usize,
+    /// Line introducing the quote in replies (empty for none); `{from}` is
+    /// replaced with the original sender.
+    pub reply_attribution: String,
+    /// Subject prefixes that already mark a reply (matched case-insensitively,
+    /// colon optional), so replying does not add another `Re:`.
+    pub reply_prefixes: Vec<String>,
+    /// Subject prefixes that already mark a forward, so forwarding does not
+    /// add another `Fwd:`.
+    pub forward_prefixes: Vec<String>,
+    /// Column to wrap draft bodies at when saving, format=flowed style
+    /// (0 = no wrapping).
+    pub wrap_width: usize,
+    /// Show a summary and ask before saving an edited draft (terminal only).
+    pub confirm_save: bool,
 }
 
 impl Default for ComposeConfig {
@@ -334,10 +631,33 @@ impl Default for ComposeConfig {
             format: "text".to_string(),
             quote_style: "> ".to_string(),
             include_signature: true,
+            signature_delimiter: "--".to_string(),
+            signature_placement: SignaturePlacement::default(),
+            reply_blank_lines: 1,
+            reply_attribution: String::new(),
+            // English, German, Scandinavian, Dutch, Italian
+            reply_prefixes: ["re", "aw", "sv", "antw", "rif"].map(String::from).to_vec(),
+            // English, German, French, Scandinavian, Dutch
+            forward_prefixes: ["fwd", "fw", "wg", "tr", "vb", "doorst"]
+                .map(String::from)
+                .to_vec(),
+            wrap_width: 0,
+            confirm_save: false,
         }
     }
 }
 
+/// Signature position relative to quoted text.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignaturePlacement {
+    /// After the quoted message (bottom-posting)
+    #[default]
+    Below,
+    /// After the new text but before the quote (top-posting)
+    Above,
+}
+
 impl AppConfig {
     /// Resolve a person alias to email address.
     ///
@@ -462,13 +782,18 @@ impl AppConfig {
             .set_default("free_slots.start_hour", 9)?
             .set_default("free_slots.end_hour", 17)?
             .set_default("free_slots.exclude_weekends", true)?
-            .set_default("mail.pager", "less -R")?
             .set_default("mail.archive_folder", "archive")?
             .set_default("mail.compose.format", "text")?
             .set_default("mail.compose.quote_style", "> ")?
-            .set_default("mail.compose.include_signature", true)?;
+            .set_default("mail.compose.include_signature", true)?
+            .set_default("mail.compose.signature_delimiter", "--")?
+            .set_default("mail.compose.signature_placement", "below")?;
 
         let mut config: AppConfig = builder.build()?.try_deserialize()?;
+        config.service_url = expand_env_vars("service_url", &config.service_url, |k| {
+            std::env::var(k).ok()
+        })?;
+        config.display.validate()?;
 
         if let Some(account) = account_override {
             config.account = account.to_string();
@@ -506,6 +831,104 @@ impl AppConfig {
     }
 }
 
+/// Expand `$VAR` and `${VAR}` references in the config value `key`.
+///
+/// `env` looks up environment variables (see [`resolve_editor`]). A
+/// reference to an unset variable is an error naming both.
+pub fn expand_env_vars(
+    key: &str,
+    value: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<String> {
+    shellexpand::env_with_context(value, |name| env(name).map(Some).ok_or(()))
+        .map(|expanded| expanded.into_owned())
+        .map_err(|e| {
+            Error::Config(format!(
+                "{key}: environment variable '{}' is not set",
+                e.var_name
+            ))
+        })
+}
+
+/// Return the first candidate that is set and not blank.
+fn first_non_empty<'a>(candidates: impl IntoIterator<Item = Option<&'a str>>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|s| !s.is_empty())
+}
+
+/// Resolve the editor command.
+///
+/// Precedence: explicit flag, `mail.editor` config, `$VISUAL`, `$EDITOR`,
+/// then [`DEFAULT_EDITOR`]. `env` looks up environment variables, which
+/// keeps the function testable; pass `|k| std::env::var(k).ok()`.
+pub fn resolve_editor(
+    flag: Option<&str>,
+    config_editor: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> String {
+    let visual = env("VISUAL");
+    let editor = env("EDITOR");
+    first_non_empty([flag, config_editor, visual.as_deref(), editor.as_deref()])
+        .unwrap_or(DEFAULT_EDITOR)
+        .to_string()
+}
+
+/// Resolve the pager command, split into program and arguments.
+///
+/// Precedence: explicit flag, `mail.pager` config, `$PAGER`, then
+/// [`DEFAULT_PAGER`].
+pub fn resolve_pager(
+    flag: Option<&str>,
+    config_pager: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    explicit_pager(flag, config_pager, env).unwrap_or_else(|| split_command(DEFAULT_PAGER))
+}
+
+/// The pager [`resolve_pager`] picks when one is set explicitly, i.e.
+/// without falling back to [`DEFAULT_PAGER`].
+fn explicit_pager(
+    flag: Option<&str>,
+    config_pager: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Option<Vec<String>> {
+    let pager = env("PAGER");
+    first_non_empty([flag, config_pager, pager.as_deref()]).map(split_command)
+}
+
+/// Split a command line on whitespace into program and arguments.
+fn split_command(command: &str) -> Vec<String> {
+    command.split_whitespace().map(String::from).collect()
+}
+
+/// Pick the first installed pager, split into program and arguments.
+///
+/// The pager chosen by [`resolve_pager`] from the flag, `mail.pager` or
+/// `$PAGER` is tried first, then each entry of `mail.pagers` (or
+/// [`DEFAULT_PAGER`] when that list is empty). `exists` reports whether a
+/// program can be run, e.g. via a `$PATH` lookup. Returns `None` when no
+/// candidate is available, in which case callers print directly to stdout.
+pub fn select_pager(
+    flag: Option<&str>,
+    config_pager: Option<&str>,
+    config_pagers: &[String],
+    env: impl Fn(&str) -> Option<String>,
+    exists: impl Fn(&str) -> bool,
+) -> Option<Vec<String>> {
+    let fallbacks: Vec<&str> = if config_pagers.is_empty() {
+        vec![DEFAULT_PAGER]
+    } else {
+        config_pagers.iter().map(String::as_str).collect()
+    };
+    explicit_pager(flag, config_pager, env)
+        .into_iter()
+        .chain(fallbacks.into_iter().map(split_command))
+        .find(|argv| argv.first().is_some_and(|program| exists(program)))
+}
+
 /// Generate environment variable prefix from app name.
 fn env_prefix() -> String {
     APP_NAME
@@ -538,10 +961,67 @@ mod tests {
     #[test]
     fn test_mail_config_defaults() {
         let config = MailConfig::default();
-        assert_eq!(config.pager, "less -R");
+        assert_eq!(config.pager, None);
         assert_eq!(config.sync_folders, vec!["inbox", "sent", "drafts"]);
         assert_eq!(config.archive_folder, "archive");
         assert!(config.signature.is_empty());
+        assert_eq!(
+            config.local_folders,
+            ["inbox", "sent", "drafts", "trash", "archive"]
+        );
+    }
+
+    #[test]
+    fn test_mail_limits_for_folder() {
+        let mut limits = MailLimits::default();
+        limits.folders.insert("Archive".to_string(), 1000);
+        assert_eq!(limits.for_folder("archive"), 1000);
+        assert_eq!(limits.for_folder("inbox"), crate::sync::DEFAULT_SYNC_LIMIT);
+        assert_eq!(limits.configured("inbox"), None);
+
+        let parsed: MailConfig = Config::builder()
+            .add_source(File::from_str(
+                "[limits]\ndefault = 50\ninbox = 500\n",
+                FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(parsed.limits.default, Some(50));
+        assert_eq!(parsed.limits.for_folder("inbox"), 500);
+        assert_eq!(parsed.limits.for_folder("sent"), 50);
+    }
+
+    #[test]
+    fn test_ids_config_word_lists() {
+        let embedded = WordLists::embedded();
+        let words = IdsConfig::default().word_lists().unwrap();
+        assert_eq!(words.adjectives, embedded.adjectives);
+        assert_eq!(words.nouns, embedded.nouns);
+
+        let temp = TempDir::new().unwrap();
+        let nouns = temp.path().join("nouns.txt");
+        fs::write(&nouns, "otter\nheron\n").unwrap();
+        let config = IdsConfig {
+            adjectives: None,
+            nouns: Some(nouns.display().to_string()),
+        };
+        let words = config.word_lists().unwrap();
+        assert_eq!(words.adjectives, embedded.adjectives);
+        assert_eq!(words.nouns, vec!["otter", "heron"]);
+    }
+
+    #[test]
+    fn test_reader_wrap_width_overrides_terminal() {
+        let reader = ReaderConfig::default();
+        assert_eq!(reader.effective_wrap_width(|| 132), 132);
+
+        let reader = ReaderConfig { wrap_width: 72 };
+        assert_eq!(
+            reader.effective_wrap_width(|| panic!("terminal width queried")),
+            72
+        );
     }
 
     #[test]
@@ -550,6 +1030,8 @@ mod tests {
         assert_eq!(config.format, "text");
         assert_eq!(config.quote_style, "> ");
         assert!(config.include_signature);
+        assert_eq!(config.signature_delimiter, "--");
+        assert_eq!(config.signature_placement, SignaturePlacement::Below);
     }
 
     #[test]
@@ -567,4 +1049,179 @@ mod tests {
     fn test_env_prefix() {
         assert_eq!(env_prefix(), "H8");
     }
+
+    fn env_with(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+    }
+
+    #[test]
+    fn test_service_url_env_expansion() {
+        let env = env_with(&[("H8_HOST", "mail.internal")]);
+        assert_eq!(
+            expand_env_vars("service_url", "http://${H8_HOST}:8787", &env).unwrap(),
+            "http://mail.internal:8787"
+        );
+        assert_eq!(
+            expand_env_vars("service_url", "http://$H8_HOST/api", &env).unwrap(),
+            "http://mail.internal/api"
+        );
+        assert_eq!(
+            expand_env_vars("service_url", "http://127.0.0.1:8787", &env).unwrap(),
+            "http://127.0.0.1:8787"
+        );
+
+        let err = expand_env_vars("service_url", "http://${H8_PORTLESS}:8787", &env).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "configuration error: service_url: environment variable 'H8_PORTLESS' is not set"
+        );
+    }
+
+    #[test]
+    fn test_resolve_editor_precedence() {
+        let env = env_with(&[("VISUAL", "code --wait"), ("EDITOR", "nano")]);
+        assert_eq!(resolve_editor(Some("hx"), Some("nvim"), &env), "hx");
+        assert_eq!(resolve_editor(None, Some("nvim"), &env), "nvim");
+        assert_eq!(resolve_editor(None, None, &env), "code --wait");
+        assert_eq!(
+            resolve_editor(None, None, env_with(&[("EDITOR", "nano")])),
+            "nano"
+        );
+        assert_eq!(resolve_editor(None, None, env_with(&[])), DEFAULT_EDITOR);
+        // Blank values fall through
+        assert_eq!(
+            resolve_editor(Some(" "), Some(""), env_with(&[])),
+            DEFAULT_EDITOR
+        );
+    }
+
+    #[test]
+    fn test_resolve_pager_precedence_and_splitting() {
+        let env = env_with(&[("PAGER", "most")]);
+        assert_eq!(
+            resolve_pager(Some("bat  --plain"), Some("less"), &env),
+            ["bat", "--plain"]
+        );
+        assert_eq!(
+            resolve_pager(None, Some("less -R -F"), &env),
+            ["less", "-R", "-F"]
+        );
+        assert_eq!(resolve_pager(None, None, &env), ["most"]);
+        assert_eq!(resolve_pager(None, None, env_with(&[])), ["less", "-R"]);
+        assert_eq!(
+            resolve_pager(None, Some("  "), env_with(&[("PAGER", "")])),
+            ["less", "-R"]
+        );
+    }
+
+    #[test]
+    fn test_select_pager_falls_back_to_available_commands() {
+        let installed = |names: &'static [&'static str]| move |p: &str| names.contains(&p);
+        let chain = vec![
+            "bat --plain".to_string(),
+            "less".to_string(),
+            "more".to_string(),
+        ];
+
+        assert_eq!(
+            select_pager(
+                None,
+                None,
+                &chain,
+                env_with(&[]),
+                installed(&["bat", "less"])
+            ),
+            Some(vec!["bat".to_string(), "--plain".to_string()])
+        );
+        assert_eq!(
+            select_pager(None, None, &chain, env_with(&[]), installed(&["more"])),
+            Some(vec!["more".to_string()])
+        );
+        assert_eq!(
+            select_pager(None, None, &chain, env_with(&[]), installed(&[])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_pager_prefers_flag_config_and_env() {
+        let all = |_: &str| true;
+        let chain = vec!["more".to_string()];
+        let env = env_with(&[("PAGER", "most -s")]);
+
+        assert_eq!(
+            select_pager(Some("bat"), Some("less"), &chain, &env, all),
+            Some(vec!["bat".to_string()])
+        );
+        assert_eq!(
+            select_pager(None, Some("less"), &chain, &env, all),
+            Some(vec!["less".to_string()])
+        );
+        assert_eq!(
+            select_pager(None, None, &chain, &env, all),
+            Some(vec!["most".to_string(), "-s".to_string()])
+        );
+        // A missing $PAGER binary falls through to the configured chain.
+        assert_eq!(
+            select_pager(None, Some(" "), &chain, &env, |p: &str| p == "more"),
+            Some(vec!["more".to_string()])
+        );
+        // Without a configured chain the default pager is the last resort.
+        assert_eq!(
+            select_pager(None, None, &[], env_with(&[]), |p: &str| p == "less"),
+            Some(vec!["less".to_string(), "-R".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_display_formats_known_timestamp() {
+        let raw = "2024-03-05T14:07:00+01:00";
+
+        let default = DisplayConfig::default();
+        assert_eq!(default.format_timestamp(raw), "2024-03-05 14:07");
+
+        let us = DisplayConfig {
+            date_format: Some("%m/%d/%Y".to_string()),
+            time_format: "%-I:%M %p".to_string(),
+        };
+        assert_eq!(us.format_timestamp(raw), "03/05/2024 2:07 PM");
+        assert_eq!(
+            us.format_timestamp("2024-03-05T09:30:00"),
+            "03/05/2024 9:30 AM"
+        );
+        assert_eq!(us.format_timestamp("2024-03-05"), "03/05/2024");
+
+        let european = DisplayConfig {
+            date_format: Some("%d.%m.%Y".to_string()),
+            time_format: "%H.%M".to_string(),
+        };
+        assert_eq!(european.format_timestamp(raw), "05.03.2024 14.07");
+
+        // Unparseable values pass through untouched
+        assert_eq!(us.format_timestamp("next tuesday"), "next tuesday");
+        assert_eq!(us.format_timestamp(""), "");
+    }
+
+    #[test]
+    fn test_display_validate_rejects_bad_formats() {
+        assert!(DisplayConfig::default().validate().is_ok());
+
+        let time_in_date = DisplayConfig {
+            date_format: Some("%Y-%m-%d %H".to_string()),
+            ..Default::default()
+        };
+        let err = time_in_date.validate().unwrap_err().to_string();
+        assert!(err.contains("display.date_format"), "{err}");
+
+        let unknown = DisplayConfig {
+            time_format: "%Q".to_string(),
+            ..Default::default()
+        };
+        let err = unknown.validate().unwrap_err().to_string();
+        assert!(err.contains("display.time_format"), "{err}");
+    }
 }
@@ -1,11 +1,35 @@
 //! SQLite database for sync state and ID management.
 
 use std::path::Path;
+use std::time::Duration;
 
-use rusqlite::{OptionalExtension, params, Connection};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior, params};
 
 use crate::error::{Error, Result};
-use crate::types::{AddressEntry, CalendarEventSync, FolderSync, MessageSync};
+use crate::types::{
+    AddressEntry, AttachmentMeta, CalendarEventSync, Folder, FolderSummary, FolderSync,
+    MessageSync, PendingFlags, Progress, ProgressCallback, ThreadMessage,
+};
+
+/// How long to wait for another connection's write lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Attempts at allocating an ID before giving up on lock contention.
+const ALLOCATE_ATTEMPTS: usize = 5;
+
+/// Remote ID prefix of messages saved locally (such as sent copies) that the
+/// server has not reported yet.
+pub const LOCAL_REMOTE_PREFIX: &str = "local:";
+
+/// Bits of `messages.flags_dirty`: which local flag changes await a push.
+/// `DIRTY_LEGACY` marks rows queued before the fields were tracked apart.
+const DIRTY_LEGACY: i64 = 1;
+const DIRTY_READ: i64 = 2;
+const DIRTY_FLAGGED: i64 = 4;
+
+/// Columns read by [`message_from_row`], for a `messages` table aliased `m`.
+const MESSAGE_COLUMNS: &str = "m.local_id, m.remote_id, m.change_key, m.folder, m.subject, m.from_addr, m.received_at, m.is_read, m.is_draft, m.has_attachments, m.synced_at, m.local_hash, m.size_bytes, m.attachments";
 
 /// Database handle for h8 sync state.
 pub struct Database {
@@ -19,6 +43,8 @@ impl Database {
             std::fs::create_dir_all(parent)?;
         }
         let conn = Connection::open(path)?;
+        // Wait for other processes' write locks instead of failing at once
+        conn.busy_timeout(BUSY_TIMEOUT)?;
         let db = Self { conn };
         db.init_schema()?;
         Ok(db)
@@ -57,6 +83,14 @@ impl Database {
                 sync_token TEXT
             );
 
+            CREATE TABLE IF NOT EXISTS sync_failures (
+                folder TEXT NOT NULL,
+                remote_id TEXT NOT NULL,
+                error TEXT,
+                failed_at TEXT,
+                PRIMARY KEY (folder, remote_id)
+            );
+
             CREATE TABLE IF NOT EXISTS id_pool (
                 short_id TEXT PRIMARY KEY,
                 status TEXT DEFAULT 'free',
@@ -87,6 +121,7 @@ impl Database {
 
             CREATE INDEX IF NOT EXISTS idx_messages_remote_id ON messages(remote_id);
             CREATE INDEX IF NOT EXISTS idx_messages_folder ON messages(folder);
+            CREATE INDEX IF NOT EXISTS idx_messages_local_hash ON messages(local_hash);
             CREATE INDEX IF NOT EXISTS idx_id_pool_status ON id_pool(status);
             CREATE INDEX IF NOT EXISTS idx_calendar_remote_id ON calendar_events(remote_id);
             CREATE INDEX IF NOT EXISTS idx_calendar_start ON calendar_events(start);
@@ -101,7 +136,33 @@ impl Database {
             );
 
             CREATE INDEX IF NOT EXISTS idx_rules_remote_id ON rules(remote_id);
+
+            CREATE TABLE IF NOT EXISTS folders (
+                name TEXT PRIMARY KEY,
+                total INTEGER DEFAULT 0,
+                unread INTEGER DEFAULT 0,
+                fetched_at TEXT NOT NULL
+            );
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+                local_id UNINDEXED,
+                subject,
+                from_addr,
+                body,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+            "#,
+        )?;
+
+        // Backfill: index metadata of messages synced before the FTS table existed
+        self.conn.execute(
+            r#"
+            INSERT INTO messages_fts (local_id, subject, from_addr, body)
+            SELECT local_id, COALESCE(subject, ''), COALESCE(from_addr, ''), ''
+            FROM messages
+            WHERE local_id NOT IN (SELECT local_id FROM messages_fts)
             "#,
+            [],
         )?;
 
         // Migration: add has_attachments column if it doesn't exist
@@ -110,15 +171,58 @@ impl Database {
             [],
         );
 
+        // Migration: threading headers (kept out of upsert_message so metadata
+        // refreshes do not clear them)
+        for column in ["message_id", "in_reply_to", "references_hdr"] {
+            let _ = self.conn.execute(
+                &format!("ALTER TABLE messages ADD COLUMN {} TEXT", column),
+                [],
+            );
+        }
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_messages_message_id ON messages(message_id)",
+            [],
+        )?;
+
+        // Migration: local flag state awaiting push to the server
+        for column in ["is_flagged", "flags_dirty"] {
+            let _ = self.conn.execute(
+                &format!(
+                    "ALTER TABLE messages ADD COLUMN {} INTEGER DEFAULT 0",
+                    column
+                ),
+                [],
+            );
+        }
+
+        // Migration: size of the stored message, known once its body is fetched
+        let _ = self
+            .conn
+            .execute("ALTER TABLE messages ADD COLUMN size_bytes INTEGER", []);
+
+        // Migration: attachment names, types and sizes as a JSON array
+        let _ = self
+            .conn
+            .execute("ALTER TABLE messages ADD COLUMN attachments TEXT", []);
+
+        // Migration: stored body cut at mail.sync.max_body_bytes
+        let _ = self.conn.execute(
+            "ALTER TABLE messages ADD COLUMN body_truncated INTEGER DEFAULT 0",
+            [],
+        );
+
         Ok(())
     }
 
     /// Insert or update a message sync record.
+    ///
+    /// A `None` size or attachment list keeps the recorded one, so metadata
+    /// refreshes do not forget what was learned earlier.
     pub fn upsert_message(&self, msg: &MessageSync) -> Result<()> {
         self.conn.execute(
             r#"
-            INSERT INTO messages (local_id, remote_id, change_key, folder, subject, from_addr, received_at, is_read, is_draft, has_attachments, synced_at, local_hash)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            INSERT INTO messages (local_id, remote_id, change_key, folder, subject, from_addr, received_at, is_read, is_draft, has_attachments, synced_at, local_hash, size_bytes, attachments)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
             ON CONFLICT(local_id) DO UPDATE SET
                 remote_id = excluded.remote_id,
                 change_key = excluded.change_key,
@@ -126,11 +230,14 @@ impl Database {
                 subject = excluded.subject,
                 from_addr = excluded.from_addr,
                 received_at = excluded.received_at,
-                is_read = excluded.is_read,
+                -- keep a read state queued by DIRTY_LEGACY or DIRTY_READ
+                is_read = CASE WHEN messages.flags_dirty & 3 != 0 THEN messages.is_read ELSE excluded.is_read END,
                 is_draft = excluded.is_draft,
                 has_attachments = excluded.has_attachments,
                 synced_at = excluded.synced_at,
-                local_hash = excluded.local_hash
+                local_hash = excluded.local_hash,
+                size_bytes = COALESCE(excluded.size_bytes, messages.size_bytes),
+                attachments = COALESCE(excluded.attachments, messages.attachments)
             "#,
             params![
                 msg.local_id,
@@ -145,6 +252,8 @@ impl Database {
                 msg.has_attachments,
                 msg.synced_at,
                 msg.local_hash,
+                msg.size_bytes,
+                attachments_to_sql(msg.attachments.as_deref()),
             ],
         )?;
         Ok(())
@@ -152,25 +261,12 @@ impl Database {
 
     /// Get a message by local ID.
     pub fn get_message(&self, local_id: &str) -> Result<Option<MessageSync>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT local_id, remote_id, change_key, folder, subject, from_addr, received_at, is_read, is_draft, has_attachments, synced_at, local_hash FROM messages WHERE local_id = ?1",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM messages m WHERE local_id = ?1"
+        ))?;
         let mut rows = stmt.query(params![local_id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(MessageSync {
-                local_id: row.get(0)?,
-                remote_id: row.get(1)?,
-                change_key: row.get(2)?,
-                folder: row.get(3)?,
-                subject: row.get(4)?,
-                from_addr: row.get(5)?,
-                received_at: row.get(6)?,
-                is_read: row.get(7)?,
-                is_draft: row.get(8)?,
-                has_attachments: row.get(9)?,
-                synced_at: row.get(10)?,
-                local_hash: row.get(11)?,
-            }))
+            Ok(Some(message_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -178,25 +274,43 @@ impl Database {
 
     /// Get a message by remote ID.
     pub fn get_message_by_remote_id(&self, remote_id: &str) -> Result<Option<MessageSync>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT local_id, remote_id, change_key, folder, subject, from_addr, received_at, is_read, is_draft, has_attachments, synced_at, local_hash FROM messages WHERE remote_id = ?1",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM messages m WHERE remote_id = ?1"
+        ))?;
         let mut rows = stmt.query(params![remote_id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(MessageSync {
-                local_id: row.get(0)?,
-                remote_id: row.get(1)?,
-                change_key: row.get(2)?,
-                folder: row.get(3)?,
-                subject: row.get(4)?,
-                from_addr: row.get(5)?,
-                received_at: row.get(6)?,
-                is_read: row.get(7)?,
-                is_draft: row.get(8)?,
-                has_attachments: row.get(9)?,
-                synced_at: row.get(10)?,
-                local_hash: row.get(11)?,
-            }))
+            Ok(Some(message_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Find a message by content hash (see [`MessageSync::compute_hash`]).
+    pub fn find_by_hash(&self, hash: &str) -> Result<Option<MessageSync>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM messages m WHERE local_hash = ?1 LIMIT 1"
+        ))?;
+        let mut rows = stmt.query(params![hash])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(message_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A locally saved copy in `folder` (remote ID starting with
+    /// [`LOCAL_REMOTE_PREFIX`]) of the message with the given Message-ID.
+    pub fn find_local_copy(&self, folder: &str, message_id: &str) -> Result<Option<MessageSync>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM messages m WHERE folder = ?1 AND message_id = ?2 AND remote_id LIKE ?3 LIMIT 1"
+        ))?;
+        let mut rows = stmt.query(params![
+            folder,
+            message_id,
+            format!("{}%", LOCAL_REMOTE_PREFIX)
+        ])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(message_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -204,25 +318,45 @@ impl Database {
 
     /// List messages in a folder.
     pub fn list_messages(&self, folder: &str, limit: usize) -> Result<Vec<MessageSync>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM messages m WHERE folder = ?1 ORDER BY received_at DESC LIMIT ?2"
+        ))?;
+        let rows = stmt.query_map(params![folder, limit], message_from_row)?;
+        let mut messages = Vec::new();
+        for row in rows {
+            messages.push(row?);
+        }
+        Ok(messages)
+    }
+
+    /// Synced message count and receive date range of every folder, by
+    /// folder name.
+    pub fn folder_summaries(&self) -> Result<Vec<FolderSummary>> {
         let mut stmt = self.conn.prepare(
-            "SELECT local_id, remote_id, change_key, folder, subject, from_addr, received_at, is_read, is_draft, has_attachments, synced_at, local_hash FROM messages WHERE folder = ?1 ORDER BY received_at DESC LIMIT ?2",
+            "SELECT folder, COUNT(*), MIN(NULLIF(received_at, '')), MAX(NULLIF(received_at, '')) FROM messages GROUP BY folder ORDER BY folder",
         )?;
-        let rows = stmt.query_map(params![folder, limit], |row| {
-            Ok(MessageSync {
-                local_id: row.get(0)?,
-                remote_id: row.get(1)?,
-                change_key: row.get(2)?,
-                folder: row.get(3)?,
-                subject: row.get(4)?,
-                from_addr: row.get(5)?,
-                received_at: row.get(6)?,
-                is_read: row.get(7)?,
-                is_draft: row.get(8)?,
-                has_attachments: row.get(9)?,
-                synced_at: row.get(10)?,
-                local_hash: row.get(11)?,
+        let rows = stmt.query_map([], |row| {
+            Ok(FolderSummary {
+                folder: row.get(0)?,
+                messages: row.get::<_, i64>(1)? as usize,
+                oldest: row.get(2)?,
+                newest: row.get(3)?,
             })
         })?;
+        let mut summaries = Vec::new();
+        for row in rows {
+            summaries.push(row?);
+        }
+        Ok(summaries)
+    }
+
+    /// List messages in a folder sent from `sender` (case-insensitive),
+    /// newest first.
+    pub fn list_messages_from(&self, folder: &str, sender: &str) -> Result<Vec<MessageSync>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM messages m WHERE folder = ?1 AND from_addr = ?2 COLLATE NOCASE ORDER BY received_at DESC"
+        ))?;
+        let rows = stmt.query_map(params![folder, sender.trim()], message_from_row)?;
         let mut messages = Vec::new();
         for row in rows {
             messages.push(row?);
@@ -236,9 +370,286 @@ impl Database {
             "DELETE FROM messages WHERE local_id = ?1",
             params![local_id],
         )?;
+        self.conn.execute(
+            "DELETE FROM messages_fts WHERE local_id = ?1",
+            params![local_id],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Set the read/flagged state of a message.
+    ///
+    /// A `None` field keeps the stored value. With `dirty` the given fields
+    /// are queued for [`Database::dirty_flags`] until the server confirms
+    /// them; a queued change is never cleared here. Returns whether the
+    /// message exists.
+    pub fn update_message_flags(
+        &self,
+        local_id: &str,
+        is_read: Option<bool>,
+        is_flagged: Option<bool>,
+        dirty: bool,
+    ) -> Result<bool> {
+        let count = self.conn.execute(
+            r#"
+            UPDATE messages SET
+                is_read = COALESCE(?2, is_read),
+                is_flagged = COALESCE(?3, is_flagged),
+                flags_dirty = flags_dirty
+                    | CASE WHEN ?4 AND ?2 IS NOT NULL THEN ?5 ELSE 0 END
+                    | CASE WHEN ?4 AND ?3 IS NOT NULL THEN ?6 ELSE 0 END
+            WHERE local_id = ?1
+            "#,
+            params![
+                local_id,
+                is_read,
+                is_flagged,
+                dirty,
+                DIRTY_READ,
+                DIRTY_FLAGGED
+            ],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Record that a message now lives in `folder`.
+    pub fn update_folder(&self, local_id: &str, folder: &str) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET folder = ?2 WHERE local_id = ?1",
+            params![local_id, folder],
+        )?;
         Ok(count > 0)
     }
 
+    /// Record the size of a message's stored content.
+    pub fn set_message_size(&self, local_id: &str, size_bytes: u64) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET size_bytes = ?2 WHERE local_id = ?1",
+            params![local_id, size_bytes],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Record whether a message's stored body was truncated.
+    pub fn set_body_truncated(&self, local_id: &str, truncated: bool) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET body_truncated = ?2 WHERE local_id = ?1",
+            params![local_id, truncated],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Whether a message's stored body was truncated, so the full content
+    /// has to come from the server.
+    pub fn is_body_truncated(&self, local_id: &str) -> Result<bool> {
+        let truncated: Option<bool> = self
+            .conn
+            .query_row(
+                "SELECT body_truncated FROM messages WHERE local_id = ?1",
+                params![local_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(truncated.unwrap_or(false))
+    }
+
+    /// Record the attachment details of a message.
+    pub fn set_message_attachments(
+        &self,
+        local_id: &str,
+        attachments: &[AttachmentMeta],
+    ) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET attachments = ?2 WHERE local_id = ?1",
+            params![local_id, attachments_to_sql(Some(attachments))],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Messages whose body was stored in the local Maildir (their size is
+    /// known), as `(local_id, folder)` pairs. Metadata-only rows are left out.
+    pub fn stored_messages(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT local_id, folder FROM messages WHERE size_bytes IS NOT NULL ORDER BY local_id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut messages = Vec::new();
+        for row in rows {
+            messages.push(row?);
+        }
+        Ok(messages)
+    }
+
+    /// Remove full-text index entries whose message row no longer exists.
+    pub fn prune_search_index(&self) -> Result<usize> {
+        let count = self.conn.execute(
+            "DELETE FROM messages_fts WHERE local_id NOT IN (SELECT local_id FROM messages)",
+            [],
+        )?;
+        Ok(count)
+    }
+
+    /// Compact the database file and refresh the query planner statistics.
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM; ANALYZE;")?;
+        Ok(())
+    }
+
+    /// Messages whose local flag changes have not been pushed to the server.
+    pub fn dirty_flags(&self) -> Result<Vec<PendingFlags>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT local_id, remote_id, folder, is_read, COALESCE(is_flagged, 0), flags_dirty FROM messages WHERE flags_dirty != 0 ORDER BY local_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let dirty: i64 = row.get(5)?;
+            // Rows queued before the fields were tracked apart push both
+            let pending = |field: i64| dirty & (field | DIRTY_LEGACY) != 0;
+            Ok(PendingFlags {
+                local_id: row.get(0)?,
+                remote_id: row.get(1)?,
+                folder: row.get(2)?,
+                is_read: pending(DIRTY_READ).then(|| row.get(3)).transpose()?,
+                is_flagged: pending(DIRTY_FLAGGED).then(|| row.get(4)).transpose()?,
+            })
+        })?;
+        let mut pending = Vec::new();
+        for row in rows {
+            pending.push(row?);
+        }
+        Ok(pending)
+    }
+
+    /// Mark the flag change of a message as pushed to the server.
+    pub fn clear_flags_dirty(&self, local_id: &str) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET flags_dirty = 0 WHERE local_id = ?1",
+            params![local_id],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Mark the flagged state of a message as pushed to the server, keeping
+    /// a queued read change.
+    pub fn clear_flagged_dirty(&self, local_id: &str) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET flags_dirty = flags_dirty & ~?2 WHERE local_id = ?1",
+            params![local_id, DIRTY_FLAGGED],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Store the threading headers of a message. `references` is the raw
+    /// `References` header value.
+    pub fn set_thread_headers(
+        &self,
+        local_id: &str,
+        message_id: Option<&str>,
+        in_reply_to: Option<&str>,
+        references: Option<&str>,
+    ) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE messages SET message_id = ?2, in_reply_to = ?3, references_hdr = ?4 WHERE local_id = ?1",
+            params![local_id, message_id, in_reply_to, references],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// List messages with their threading headers, oldest first, optionally
+    /// restricted to one folder. Messages without headers are included and
+    /// simply form single-message threads.
+    pub fn list_thread_messages(&self, folder: Option<&str>) -> Result<Vec<ThreadMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT local_id, folder, subject, from_addr, received_at, message_id, in_reply_to, references_hdr FROM messages WHERE ?1 IS NULL OR folder = ?1 ORDER BY received_at",
+        )?;
+        let rows = stmt.query_map(params![folder], |row| {
+            let references: Option<String> = row.get(7)?;
+            Ok(ThreadMessage {
+                local_id: row.get(0)?,
+                folder: row.get(1)?,
+                subject: row.get(2)?,
+                from_addr: row.get(3)?,
+                received_at: row.get(4)?,
+                message_id: row.get(5)?,
+                in_reply_to: row.get(6)?,
+                references: references
+                    .map(|r| r.split_whitespace().map(String::from).collect())
+                    .unwrap_or_default(),
+            })
+        })?;
+        let mut messages = Vec::new();
+        for row in rows {
+            messages.push(row?);
+        }
+        Ok(messages)
+    }
+
+    /// Add or refresh a message in the full-text index.
+    ///
+    /// Subject and sender are taken from the `messages` row. When `body` is
+    /// `None`, a previously indexed body is kept.
+    pub fn index_message(&self, local_id: &str, body: Option<&str>) -> Result<()> {
+        let body = match body {
+            Some(body) => body.to_string(),
+            None => self
+                .conn
+                .query_row(
+                    "SELECT body FROM messages_fts WHERE local_id = ?1",
+                    params![local_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .unwrap_or_default(),
+        };
+
+        self.conn.execute(
+            "DELETE FROM messages_fts WHERE local_id = ?1",
+            params![local_id],
+        )?;
+        self.conn.execute(
+            r#"
+            INSERT INTO messages_fts (local_id, subject, from_addr, body)
+            SELECT local_id, COALESCE(subject, ''), COALESCE(from_addr, ''), ?2
+            FROM messages WHERE local_id = ?1
+            "#,
+            params![local_id, body],
+        )?;
+        Ok(())
+    }
+
+    /// Search indexed messages, newest first.
+    ///
+    /// Terms match subject, sender and body; `from:` and `subject:` prefixes
+    /// restrict a term to that field. Terms are prefix-matched.
+    pub fn search_messages(
+        &self,
+        query: &str,
+        folder: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<MessageSync>> {
+        let fts_query = fts_query(query);
+        if fts_query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT {MESSAGE_COLUMNS}
+            FROM messages_fts f
+            JOIN messages m ON m.local_id = f.local_id
+            WHERE messages_fts MATCH ?1 AND (?2 IS NULL OR m.folder = ?2)
+            ORDER BY m.received_at DESC
+            LIMIT ?3
+            "#
+        ))?;
+        let rows = stmt.query_map(params![fts_query, folder, limit], message_from_row)?;
+        let mut messages = Vec::new();
+        for row in rows {
+            messages.push(row?);
+        }
+        Ok(messages)
+    }
+
     /// Update folder sync state.
     pub fn upsert_sync_state(&self, state: &FolderSync) -> Result<()> {
         self.conn.execute(
@@ -271,11 +682,59 @@ impl Database {
         }
     }
 
+    /// Remember a message that failed to sync so the next run retries it.
+    pub fn record_sync_failure(&self, folder: &str, remote_id: &str, error: &str) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO sync_failures (folder, remote_id, error, failed_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(folder, remote_id) DO UPDATE SET
+                error = excluded.error,
+                failed_at = excluded.failed_at
+            "#,
+            params![folder, remote_id, error, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a recorded sync failure. Returns whether one existed.
+    pub fn clear_sync_failure(&self, folder: &str, remote_id: &str) -> Result<bool> {
+        let changed = self.conn.execute(
+            "DELETE FROM sync_failures WHERE folder = ?1 AND remote_id = ?2",
+            params![folder, remote_id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Remote IDs of messages in a folder whose last sync attempt failed.
+    pub fn sync_failures(&self, folder: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT remote_id FROM sync_failures WHERE folder = ?1 ORDER BY remote_id")?;
+        let ids = stmt
+            .query_map(params![folder], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(ids)
+    }
+
     /// Seed the ID pool with adjective-noun combinations.
     pub fn seed_id_pool(&self, adjectives: &[&str], nouns: &[&str]) -> Result<usize> {
+        self.seed_id_pool_with_progress(adjectives, nouns, None)
+    }
+
+    /// Seed the ID pool, reporting progress once per adjective.
+    pub fn seed_id_pool_with_progress(
+        &self,
+        adjectives: &[&str],
+        nouns: &[&str],
+        mut progress: ProgressCallback<'_>,
+    ) -> Result<usize> {
         let tx = self.conn.unchecked_transaction()?;
         let mut count = 0;
-        for adj in adjectives {
+        for (i, adj) in adjectives.iter().enumerate() {
+            if let Some(cb) = progress.as_mut() {
+                cb(Progress::new(i + 1, adjectives.len(), "seeding ID pool"));
+            }
             for noun in nouns {
                 // Skip same-word pairs
                 if adj == noun {
@@ -294,28 +753,42 @@ impl Database {
     }
 
     /// Allocate a free ID from the pool.
+    ///
+    /// The pick and the claim run in one `BEGIN IMMEDIATE` transaction, so
+    /// concurrent syncs sharing the database never hand out the same ID.
+    /// Lock contention beyond the busy timeout is retried a few times.
     pub fn allocate_id(&self, remote_id: &str) -> Result<String> {
-        let now = chrono::Utc::now().to_rfc3339();
-
-        // Try to find a free ID
-        let mut stmt = self.conn.prepare(
-            "SELECT short_id FROM id_pool WHERE status = 'free' ORDER BY RANDOM() LIMIT 1",
-        )?;
-        let mut rows = stmt.query([])?;
+        let mut attempt = 1;
+        loop {
+            match self.try_allocate_id(remote_id) {
+                Err(Error::Database(e)) if is_busy(&e) && attempt < ALLOCATE_ATTEMPTS => {
+                    attempt += 1;
+                    std::thread::sleep(Duration::from_millis(10 * attempt as u64));
+                }
+                result => return result,
+            }
+        }
+    }
 
-        if let Some(row) = rows.next()? {
-            let short_id: String = row.get(0)?;
-            drop(rows);
-            drop(stmt);
+    fn try_allocate_id(&self, remote_id: &str) -> Result<String> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        let short_id: Option<String> = tx
+            .query_row(
+                "SELECT short_id FROM id_pool WHERE status = 'free' ORDER BY RANDOM() LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(short_id) = short_id else {
+            return Err(Error::IdPoolExhausted);
+        };
 
-            self.conn.execute(
-                "UPDATE id_pool SET status = 'used', assigned_at = ?1, message_remote_id = ?2 WHERE short_id = ?3",
-                params![now, remote_id, short_id],
-            )?;
-            Ok(short_id)
-        } else {
-            Err(Error::IdPoolExhausted)
-        }
+        tx.execute(
+            "UPDATE id_pool SET status = 'used', assigned_at = ?1, message_remote_id = ?2 WHERE short_id = ?3",
+            params![chrono::Utc::now().to_rfc3339(), remote_id, short_id],
+        )?;
+        tx.commit()?;
+        Ok(short_id)
     }
 
     /// Free an ID back to the pool.
@@ -327,6 +800,15 @@ impl Database {
         Ok(count > 0)
     }
 
+    /// Point an allocated ID at a different remote message ID.
+    pub fn reassign_id(&self, short_id: &str, remote_id: &str) -> Result<bool> {
+        let count = self.conn.execute(
+            "UPDATE id_pool SET message_remote_id = ?1 WHERE short_id = ?2 AND status = 'used'",
+            params![remote_id, short_id],
+        )?;
+        Ok(count > 0)
+    }
+
     /// Get ID by remote message ID.
     pub fn get_id_by_remote(&self, remote_id: &str) -> Result<Option<String>> {
         let mut stmt = self
@@ -705,6 +1187,86 @@ impl Database {
         Ok(count)
     }
 
+    // ─── Folder Cache ────────────────────────────────────────────────────────
+
+    /// Replace the cached server folder list.
+    pub fn store_folders(&self, folders: &[Folder]) -> Result<()> {
+        self.store_folders_at(folders, Utc::now())
+    }
+
+    fn store_folders_at(&self, folders: &[Folder], fetched_at: DateTime<Utc>) -> Result<()> {
+        let fetched_at = fetched_at.to_rfc3339();
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM folders", [])?;
+        for folder in folders {
+            tx.execute(
+                "INSERT OR REPLACE INTO folders (name, total, unread, fetched_at) VALUES (?1, ?2, ?3, ?4)",
+                params![folder.name, folder.total, folder.unread, fetched_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the cached server folder list if it was fetched within `ttl`.
+    ///
+    /// Returns `None` when nothing is cached or the cache is stale.
+    pub fn cached_folders(&self, ttl: Duration) -> Result<Option<Vec<Folder>>> {
+        self.cached_folders_at(ttl, Utc::now())
+    }
+
+    fn cached_folders_at(&self, ttl: Duration, now: DateTime<Utc>) -> Result<Option<Vec<Folder>>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, total, unread, fetched_at FROM folders ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                Folder {
+                    name: row.get(0)?,
+                    total: row.get(1)?,
+                    unread: row.get(2)?,
+                },
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+
+        let mut folders = Vec::new();
+        let mut oldest: Option<DateTime<Utc>> = None;
+        for row in rows {
+            let (folder, fetched_at) = row?;
+            // An unreadable timestamp counts as stale
+            let Ok(fetched_at) = DateTime::parse_from_rfc3339(&fetched_at) else {
+                return Ok(None);
+            };
+            let fetched_at = fetched_at.with_timezone(&Utc);
+            oldest = Some(oldest.map_or(fetched_at, |o| o.min(fetched_at)));
+            folders.push(folder);
+        }
+
+        let Some(oldest) = oldest else {
+            return Ok(None);
+        };
+        // A timestamp in the future (clock skew) is treated as fresh
+        let fresh = (now - oldest).to_std().map_or(true, |age| age <= ttl);
+        Ok(fresh.then_some(folders))
+    }
+
+    /// Get the folder list from the cache, calling `fetch` and caching its
+    /// result when the cache is stale, empty, or `force` is set.
+    pub fn folders_or_fetch(
+        &self,
+        ttl: Duration,
+        force: bool,
+        fetch: impl FnOnce() -> Result<Vec<Folder>>,
+    ) -> Result<Vec<Folder>> {
+        if !force && let Some(folders) = self.cached_folders(ttl)? {
+            return Ok(folders);
+        }
+        let folders = fetch()?;
+        self.store_folders(&folders)?;
+        Ok(folders)
+    }
+
     // ─── Address Cache ───────────────────────────────────────────────────────
 
     /// Record an email address seen in sent mail.
@@ -950,10 +1512,86 @@ impl Database {
     }
 }
 
+/// Translate a user search query into an FTS5 MATCH expression.
+///
+/// Each whitespace-separated term becomes a quoted prefix phrase, so user
+/// input never reaches the FTS5 query syntax unescaped.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .filter_map(|term| {
+            let (column, value) = match term.split_once(':') {
+                Some((field, value)) if field.eq_ignore_ascii_case("from") => {
+                    (Some("from_addr"), value)
+                }
+                Some((field, value)) if field.eq_ignore_ascii_case("subject") => {
+                    (Some("subject"), value)
+                }
+                _ => (None, term),
+            };
+            if !value.chars().any(char::is_alphanumeric) {
+                return None;
+            }
+            let phrase = format!("\"{}\"*", value.replace('"', "\"\""));
+            Some(match column {
+                Some(column) => format!("{}:{}", column, phrase),
+                None => phrase,
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether a database error is lock contention worth retrying.
+fn is_busy(err: &rusqlite::Error) -> bool {
+    matches!(
+        err.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Map a row selected with [`MESSAGE_COLUMNS`].
+fn message_from_row(row: &rusqlite::Row) -> rusqlite::Result<MessageSync> {
+    Ok(MessageSync {
+        local_id: row.get(0)?,
+        remote_id: row.get(1)?,
+        change_key: row.get(2)?,
+        folder: row.get(3)?,
+        subject: row.get(4)?,
+        from_addr: row.get(5)?,
+        received_at: row.get(6)?,
+        is_read: row.get(7)?,
+        is_draft: row.get(8)?,
+        has_attachments: row.get(9)?,
+        synced_at: row.get(10)?,
+        local_hash: row.get(11)?,
+        size_bytes: row.get(12)?,
+        attachments: attachments_from_sql(row.get(13)?),
+    })
+}
+
+/// Attachment details as stored in the `attachments` column.
+fn attachments_to_sql(attachments: Option<&[AttachmentMeta]>) -> Option<String> {
+    attachments.and_then(|a| serde_json::to_string(a).ok())
+}
+
+/// Read the `attachments` column; unreadable JSON counts as unknown.
+fn attachments_from_sql(text: Option<String>) -> Option<Vec<AttachmentMeta>> {
+    serde_json::from_str(&text?).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn folder(name: &str, unread: u64) -> Folder {
+        Folder {
+            name: name.to_string(),
+            total: 10,
+            unread,
+        }
+    }
+
     #[test]
     fn test_database_creation() {
         let db = Database::open_memory().unwrap();
@@ -972,11 +1610,8 @@ mod tests {
             subject: Some("Test Subject".to_string()),
             from_addr: Some("sender@example.com".to_string()),
             received_at: Some("2024-01-01T00:00:00Z".to_string()),
-            is_read: false,
-            is_draft: false,
             has_attachments: true,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         };
 
         db.upsert_message(&msg).unwrap();
@@ -998,6 +1633,208 @@ mod tests {
         assert!(db.get_message("test-local").unwrap().is_none());
     }
 
+    #[test]
+    fn test_flag_changes_stay_dirty_until_cleared() {
+        let db = Database::open_memory().unwrap();
+        let mut msg = MessageSync {
+            local_id: "flag-me".to_string(),
+            remote_id: "remote-flag".to_string(),
+            folder: "inbox".to_string(),
+            ..Default::default()
+        };
+        db.upsert_message(&msg).unwrap();
+        assert!(db.dirty_flags().unwrap().is_empty());
+
+        assert!(
+            db.update_message_flags("flag-me", None, Some(true), true)
+                .unwrap()
+        );
+        let pending = db.dirty_flags().unwrap();
+        assert_eq!(
+            pending,
+            [PendingFlags {
+                local_id: "flag-me".to_string(),
+                remote_id: "remote-flag".to_string(),
+                folder: "inbox".to_string(),
+                is_read: None,
+                is_flagged: Some(true),
+            }]
+        );
+
+        // With only the flag queued, the server's read state still applies
+        msg.is_read = true;
+        db.upsert_message(&msg).unwrap();
+        assert!(db.get_message("flag-me").unwrap().unwrap().is_read);
+        msg.is_read = false;
+
+        assert!(
+            db.update_message_flags("flag-me", Some(true), None, true)
+                .unwrap()
+        );
+        let pending = db.dirty_flags().unwrap();
+        assert_eq!(pending[0].is_read, Some(true));
+        assert_eq!(pending[0].is_flagged, Some(true));
+
+        // A metadata refresh from the server must not undo the pending change
+        db.upsert_message(&msg).unwrap();
+        assert!(db.get_message("flag-me").unwrap().unwrap().is_read);
+
+        // A confirmed (non-dirty) update keeps the queued change
+        db.update_message_flags("flag-me", Some(true), None, false)
+            .unwrap();
+        assert_eq!(db.dirty_flags().unwrap().len(), 1);
+
+        assert!(db.clear_flags_dirty("flag-me").unwrap());
+        assert!(db.dirty_flags().unwrap().is_empty());
+        msg.is_read = false;
+        db.upsert_message(&msg).unwrap();
+        assert!(!db.get_message("flag-me").unwrap().unwrap().is_read);
+
+        assert!(
+            !db.update_message_flags("missing", Some(true), None, true)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_message_size_round_trip() {
+        let db = Database::open_memory().unwrap();
+        let mut msg = MessageSync {
+            local_id: "sized".to_string(),
+            remote_id: "remote-sized".to_string(),
+            folder: "inbox".to_string(),
+            size_bytes: Some(4096),
+            ..Default::default()
+        };
+        db.upsert_message(&msg).unwrap();
+        assert_eq!(
+            db.get_message("sized").unwrap().unwrap().size_bytes,
+            Some(4096)
+        );
+
+        // A metadata-only refresh keeps the known size
+        msg.size_bytes = None;
+        db.upsert_message(&msg).unwrap();
+        let listed = db.list_messages("inbox", 10).unwrap();
+        assert_eq!(listed[0].size_bytes, Some(4096));
+
+        assert!(db.set_message_size("sized", 123).unwrap());
+        assert_eq!(
+            db.get_message_by_remote_id("remote-sized")
+                .unwrap()
+                .unwrap()
+                .size_bytes,
+            Some(123)
+        );
+        assert!(!db.set_message_size("missing", 1).unwrap());
+    }
+
+    #[test]
+    fn test_update_folder() {
+        let db = Database::open_memory().unwrap();
+        let msg = MessageSync {
+            local_id: "moved".to_string(),
+            remote_id: "remote-moved".to_string(),
+            folder: "inbox".to_string(),
+            subject: Some("Hello".to_string()),
+            is_read: true,
+            size_bytes: Some(10),
+            ..Default::default()
+        };
+        db.upsert_message(&msg).unwrap();
+
+        assert!(db.update_folder("moved", "archive").unwrap());
+        let stored = db.get_message("moved").unwrap().unwrap();
+        assert_eq!(stored.folder, "archive");
+        assert_eq!(stored.subject.as_deref(), Some("Hello"));
+        assert_eq!(stored.size_bytes, Some(10));
+        assert!(db.list_messages("inbox", 10).unwrap().is_empty());
+        assert_eq!(db.list_messages("archive", 10).unwrap().len(), 1);
+
+        assert!(!db.update_folder("missing", "archive").unwrap());
+    }
+
+    #[test]
+    fn test_message_attachments_round_trip() {
+        let db = Database::open_memory().unwrap();
+        let attachments = vec![
+            AttachmentMeta {
+                name: "report.pdf".to_string(),
+                content_type: Some("application/pdf".to_string()),
+                size: Some(2048),
+            },
+            AttachmentMeta {
+                name: "notes.txt".to_string(),
+                content_type: None,
+                size: None,
+            },
+        ];
+        let mut msg = MessageSync {
+            local_id: "attached".to_string(),
+            remote_id: "remote-attached".to_string(),
+            folder: "inbox".to_string(),
+            has_attachments: true,
+            attachments: Some(attachments.clone()),
+            ..Default::default()
+        };
+        db.upsert_message(&msg).unwrap();
+        let stored = db.get_message("attached").unwrap().unwrap();
+        assert_eq!(stored.attachments.as_ref(), Some(&attachments));
+
+        // A refresh without details keeps the stored ones
+        msg.attachments = None;
+        db.upsert_message(&msg).unwrap();
+        let listed = db.list_messages("inbox", 10).unwrap();
+        assert_eq!(listed[0].attachments.as_ref(), Some(&attachments));
+
+        assert!(db.set_message_attachments("attached", &[]).unwrap());
+        let stored = db.get_message("attached").unwrap().unwrap();
+        assert_eq!(stored.attachments, Some(Vec::new()));
+        assert!(!db.set_message_attachments("missing", &[]).unwrap());
+
+        // Unreadable JSON is treated as unknown
+        db.conn
+            .execute(
+                "UPDATE messages SET attachments = 'not json' WHERE local_id = 'attached'",
+                [],
+            )
+            .unwrap();
+        assert_eq!(
+            db.get_message("attached").unwrap().unwrap().attachments,
+            None
+        );
+    }
+
+    #[test]
+    fn test_stored_messages_and_index_pruning() {
+        let db = Database::open_memory().unwrap();
+        for (id, size) in [("stored", Some(10)), ("metadata-only", None)] {
+            db.upsert_message(&MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: "inbox".to_string(),
+                subject: Some("Hello".to_string()),
+                size_bytes: size,
+                ..Default::default()
+            })
+            .unwrap();
+            db.index_message(id, Some("body")).unwrap();
+        }
+        assert_eq!(
+            db.stored_messages().unwrap(),
+            vec![("stored".to_string(), "inbox".to_string())]
+        );
+
+        // Leave an index entry behind without its message row
+        db.conn
+            .execute("DELETE FROM messages WHERE local_id = 'stored'", [])
+            .unwrap();
+        assert_eq!(db.prune_search_index().unwrap(), 1);
+        assert_eq!(db.prune_search_index().unwrap(), 0);
+        assert_eq!(db.search_messages("hello", None, 10).unwrap().len(), 1);
+        db.vacuum().unwrap();
+    }
+
     #[test]
     fn test_sync_state() {
         let db = Database::open_memory().unwrap();
@@ -1014,6 +1851,64 @@ mod tests {
         assert_eq!(retrieved.sync_token, Some("token-123".to_string()));
     }
 
+    #[test]
+    fn test_folder_summaries() {
+        let db = Database::open_memory().unwrap();
+        assert!(db.folder_summaries().unwrap().is_empty());
+
+        let messages = [
+            ("a", "inbox", Some("2024-03-01T00:00:00Z")),
+            ("b", "inbox", Some("2024-01-01T00:00:00Z")),
+            ("c", "inbox", None),
+            ("d", "sent", None),
+        ];
+        for (id, folder, received_at) in messages {
+            db.upsert_message(&MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("remote-{id}"),
+                folder: folder.to_string(),
+                received_at: received_at.map(String::from),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let summaries = db.folder_summaries().unwrap();
+        assert_eq!(
+            summaries,
+            vec![
+                FolderSummary {
+                    folder: "inbox".to_string(),
+                    messages: 3,
+                    oldest: Some("2024-01-01T00:00:00Z".to_string()),
+                    newest: Some("2024-03-01T00:00:00Z".to_string()),
+                },
+                FolderSummary {
+                    folder: "sent".to_string(),
+                    messages: 1,
+                    oldest: None,
+                    newest: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_failures() {
+        let db = Database::open_memory().unwrap();
+
+        db.record_sync_failure("inbox", "r2", "boom").unwrap();
+        db.record_sync_failure("inbox", "r1", "boom").unwrap();
+        db.record_sync_failure("inbox", "r1", "again").unwrap();
+        db.record_sync_failure("sent", "r3", "boom").unwrap();
+        assert_eq!(db.sync_failures("inbox").unwrap(), vec!["r1", "r2"]);
+
+        assert!(db.clear_sync_failure("inbox", "r1").unwrap());
+        assert!(!db.clear_sync_failure("inbox", "r1").unwrap());
+        assert_eq!(db.sync_failures("inbox").unwrap(), vec!["r2"]);
+        assert_eq!(db.sync_failures("sent").unwrap(), vec!["r3"]);
+    }
+
     #[test]
     fn test_id_pool() {
         let db = Database::open_memory().unwrap();
@@ -1055,4 +1950,254 @@ mod tests {
         let result = db.allocate_id("remote-2");
         assert!(matches!(result, Err(Error::IdPoolExhausted)));
     }
+
+    #[test]
+    fn test_find_by_hash_dedups_identical_content() {
+        let db = Database::open_memory().unwrap();
+        db.seed_id_pool(&["cold"], &["lamp", "frog"]).unwrap();
+
+        let content = b"Subject: Report\nFrom: a@example.com\n\nSame body";
+        let hash = MessageSync::compute_hash(content);
+        let local_id = db.allocate_id("remote-1").unwrap();
+        db.upsert_message(&MessageSync {
+            local_id: local_id.clone(),
+            remote_id: "remote-1".to_string(),
+            folder: "inbox".to_string(),
+            subject: Some("Report".to_string()),
+            local_hash: Some(hash),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Same content arriving under a new remote ID resolves to the existing entry
+        let dup_hash = MessageSync::compute_hash(content);
+        let mut existing = db.find_by_hash(&dup_hash).unwrap().unwrap();
+        assert_eq!(existing.local_id, local_id);
+
+        existing.remote_id = "remote-2".to_string();
+        db.upsert_message(&existing).unwrap();
+        assert!(db.reassign_id(&local_id, "remote-2").unwrap());
+
+        assert_eq!(db.list_messages("inbox", 10).unwrap().len(), 1);
+        assert_eq!(
+            db.get_remote_by_id(&local_id).unwrap().as_deref(),
+            Some("remote-2")
+        );
+        assert!(db.get_message_by_remote_id("remote-1").unwrap().is_none());
+        assert!(db.find_by_hash("unknown").unwrap().is_none());
+    }
+
+    fn index_test_message(db: &Database, local_id: &str, from: &str, subject: &str, body: &str) {
+        db.upsert_message(&MessageSync {
+            local_id: local_id.to_string(),
+            remote_id: format!("remote-{}", local_id),
+            folder: "inbox".to_string(),
+            subject: Some(subject.to_string()),
+            from_addr: Some(from.to_string()),
+            received_at: Some("2026-01-01T00:00:00Z".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        db.index_message(local_id, Some(body)).unwrap();
+    }
+
+    #[test]
+    fn test_list_messages_from_sender() {
+        let db = Database::open_memory().unwrap();
+        index_test_message(&db, "a", "News@Example.com", "Weekly", "");
+        index_test_message(&db, "b", "alice@example.com", "Lunch", "");
+        index_test_message(&db, "c", "news@example.com", "Daily", "");
+        let mut other_folder = db.get_message("c").unwrap().unwrap();
+        other_folder.local_id = "d".to_string();
+        other_folder.remote_id = "remote-d".to_string();
+        other_folder.folder = "archive".to_string();
+        db.upsert_message(&other_folder).unwrap();
+
+        let mut ids: Vec<String> = db
+            .list_messages_from("inbox", "news@example.com")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.local_id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["a", "c"]);
+        assert!(
+            db.list_messages_from("inbox", "bob@example.com")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_thread_headers_survive_metadata_upsert() {
+        let db = Database::open_memory().unwrap();
+        index_test_message(&db, "cold-lamp", "a@example.com", "Plan", "");
+        index_test_message(&db, "blue-frog", "b@example.com", "No headers", "");
+        assert!(
+            db.set_thread_headers(
+                "cold-lamp",
+                Some("<2@example.com>"),
+                Some("<1@example.com>"),
+                Some("<0@example.com> <1@example.com>"),
+            )
+            .unwrap()
+        );
+        assert!(!db.set_thread_headers("missing", None, None, None).unwrap());
+
+        // A later metadata refresh must not clear the headers
+        let mut msg = db.get_message("cold-lamp").unwrap().unwrap();
+        msg.is_read = true;
+        db.upsert_message(&msg).unwrap();
+
+        let messages = db.list_thread_messages(Some("inbox")).unwrap();
+        assert_eq!(messages.len(), 2);
+        let msg = messages.iter().find(|m| m.local_id == "cold-lamp").unwrap();
+        assert_eq!(msg.message_id.as_deref(), Some("<2@example.com>"));
+        assert_eq!(msg.in_reply_to.as_deref(), Some("<1@example.com>"));
+        assert_eq!(msg.references, ["<0@example.com>", "<1@example.com>"]);
+        let bare = messages.iter().find(|m| m.local_id == "blue-frog").unwrap();
+        assert!(bare.message_id.is_none() && bare.references.is_empty());
+        assert!(db.list_thread_messages(Some("sent")).unwrap().is_empty());
+    }
+
+    fn search_ids(db: &Database, query: &str) -> Vec<String> {
+        let mut ids: Vec<String> = db
+            .search_messages(query, Some("inbox"), 10)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.local_id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_search_messages_fts() {
+        let db = Database::open_memory().unwrap();
+        index_test_message(
+            &db,
+            "cold-lamp",
+            "Alice <alice@example.com>",
+            "Budget review",
+            "The quarterly budget is attached",
+        );
+        index_test_message(
+            &db,
+            "blue-frog",
+            "Bob <bob@example.com>",
+            "Lunch",
+            "Pizza on Friday?",
+        );
+        index_test_message(
+            &db,
+            "fast-lamp",
+            "Alice <alice@example.com>",
+            "Re: Lunch",
+            "Count me in",
+        );
+
+        assert_eq!(search_ids(&db, "quarterly"), ["cold-lamp"]);
+        assert_eq!(search_ids(&db, "from:alice"), ["cold-lamp", "fast-lamp"]);
+        assert_eq!(search_ids(&db, "subject:lunch"), ["blue-frog", "fast-lamp"]);
+        assert_eq!(search_ids(&db, "from:alice subject:lunch"), ["fast-lamp"]);
+        assert_eq!(search_ids(&db, "pizz"), ["blue-frog"]);
+        assert!(search_ids(&db, "nothing-matches-this").is_empty());
+        assert!(search_ids(&db, "\" ( *").is_empty());
+        assert!(
+            db.search_messages("budget", Some("sent"), 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_index_message_keeps_body_and_follows_delete() {
+        let db = Database::open_memory().unwrap();
+        index_test_message(
+            &db,
+            "cold-lamp",
+            "alice@example.com",
+            "Budget",
+            "quarterly numbers",
+        );
+
+        // Re-indexing metadata only keeps the stored body
+        db.index_message("cold-lamp", None).unwrap();
+        assert_eq!(search_ids(&db, "quarterly"), ["cold-lamp"]);
+
+        db.delete_message("cold-lamp").unwrap();
+        assert!(search_ids(&db, "quarterly").is_empty());
+    }
+
+    #[test]
+    fn test_cached_folders_fresh_hit() {
+        let db = Database::open_memory().unwrap();
+        let ttl = Duration::from_secs(3600);
+        assert!(db.cached_folders(ttl).unwrap().is_none());
+
+        db.store_folders(&[folder("inbox", 2), folder("Projects", 0)])
+            .unwrap();
+        let cached = db.cached_folders(ttl).unwrap().unwrap();
+        assert_eq!(cached, [folder("inbox", 2), folder("Projects", 0)]);
+
+        // A fresh cache is served without calling the server
+        let folders = db
+            .folders_or_fetch(ttl, false, || panic!("fresh cache must not refetch"))
+            .unwrap();
+        assert_eq!(folders.len(), 2);
+    }
+
+    #[test]
+    fn test_cached_folders_stale_miss() {
+        let db = Database::open_memory().unwrap();
+        let ttl = Duration::from_secs(60);
+        let fetched_at = Utc::now() - chrono::Duration::seconds(120);
+        db.store_folders_at(&[folder("inbox", 1)], fetched_at)
+            .unwrap();
+
+        assert!(db.cached_folders(ttl).unwrap().is_none());
+        assert!(
+            db.cached_folders_at(ttl, fetched_at + chrono::Duration::seconds(30))
+                .unwrap()
+                .is_some()
+        );
+
+        let folders = db
+            .folders_or_fetch(ttl, false, || Ok(vec![folder("inbox", 5)]))
+            .unwrap();
+        assert_eq!(folders, [folder("inbox", 5)]);
+        assert_eq!(
+            db.cached_folders(ttl).unwrap().unwrap(),
+            [folder("inbox", 5)]
+        );
+    }
+
+    #[test]
+    fn test_folders_force_refresh() {
+        let db = Database::open_memory().unwrap();
+        let ttl = Duration::from_secs(3600);
+        db.store_folders(&[folder("inbox", 1), folder("old", 0)])
+            .unwrap();
+
+        let mut fetched = false;
+        let folders = db
+            .folders_or_fetch(ttl, true, || {
+                fetched = true;
+                Ok(vec![folder("inbox", 3)])
+            })
+            .unwrap();
+        assert!(fetched);
+        assert_eq!(folders, [folder("inbox", 3)]);
+        assert_eq!(
+            db.cached_folders(ttl).unwrap().unwrap(),
+            [folder("inbox", 3)]
+        );
+
+        // A failed refresh leaves the previous cache in place
+        assert!(
+            db.folders_or_fetch(ttl, true, || Err(Error::Offline))
+                .is_err()
+        );
+        assert_eq!(db.cached_folders(ttl).unwrap().unwrap().len(), 1);
+    }
 }
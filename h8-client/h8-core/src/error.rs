@@ -24,6 +24,10 @@ pub enum Error {
     #[error("service error: {0}")]
     Service(String),
 
+    /// The service could not be connected to.
+    #[error("service unreachable: {0}")]
+    Unreachable(String),
+
     /// IO error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -36,14 +40,46 @@ pub enum Error {
     #[error("ID pool exhausted - no free IDs available")]
     IdPoolExhausted,
 
+    /// Service call attempted while offline mode is enabled.
+    #[error("offline mode: service calls are disabled (unset --offline or `offline` in config)")]
+    Offline,
+
     /// ID not found.
     #[error("ID not found: {0}")]
     IdNotFound(String),
+
+    /// A recipient is not a valid email address.
+    #[error("configuration error: invalid email address: {0}")]
+    InvalidAddress(String),
+
+    /// The sender is not a valid email address.
+    #[error("configuration error: invalid sender address: {0}")]
+    InvalidSender(String),
+
+    /// Compose frontmatter is not valid YAML.
+    #[error("configuration error: parsing frontmatter YAML: {0}")]
+    FrontmatterParse(#[source] serde_yaml::Error),
+
+    /// Compose frontmatter has no closing delimiter.
+    #[error("configuration error: unclosed frontmatter (missing closing ---)")]
+    UnclosedFrontmatter,
+
+    /// An RFC 822 message could not be parsed.
+    #[error("invalid RFC 822 message: {0}")]
+    InvalidMessage(String),
+
+    /// A message has no `to` recipients.
+    #[error("configuration error: no recipients specified")]
+    NoRecipients,
 }
 
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
-        Error::Service(err.to_string())
+        if err.is_connect() {
+            Error::Unreachable(err.to_string())
+        } else {
+            Error::Service(err.to_string())
+        }
     }
 }
 
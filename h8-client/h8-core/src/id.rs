@@ -3,8 +3,13 @@
 //! This module provides ID generation for emails using a memorable
 //! adjective-noun format like "cold-lamp" or "blue-frog".
 
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
 use crate::db::Database;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::types::ProgressCallback;
 
 /// Word lists for ID generation.
 pub struct WordLists {
@@ -17,7 +22,7 @@ impl WordLists {
     pub fn from_toml(content: &str) -> Result<Self> {
         let parsed: toml::Value = content
             .parse()
-            .map_err(|e| crate::error::Error::Config(format!("parsing word lists: {e}")))?;
+            .map_err(|e| Error::Config(format!("parsing word lists: {e}")))?;
 
         let adjectives = parsed
             .get("adjectives")
@@ -42,6 +47,16 @@ impl WordLists {
         Ok(Self { adjectives, nouns })
     }
 
+    /// Load word lists from newline-separated files.
+    ///
+    /// See [`read_word_list`] for the file format.
+    pub fn from_files(adjectives: &Path, nouns: &Path) -> Result<Self> {
+        Ok(Self {
+            adjectives: read_word_list(adjectives)?,
+            nouns: read_word_list(nouns)?,
+        })
+    }
+
     /// Get embedded word lists (compiled into binary).
     pub fn embedded() -> Self {
         Self {
@@ -51,6 +66,44 @@ impl WordLists {
     }
 }
 
+/// Read a word list with one word per line.
+///
+/// Words are trimmed and lowercased; blank lines, `#` comments and
+/// duplicates are skipped. Words containing whitespace or `-` would break
+/// the `adjective-noun` format and are rejected. Errors if no words remain.
+pub fn read_word_list(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Config(format!("reading word list {}: {e}", path.display())))?;
+    let words = parse_word_list(&content)
+        .map_err(|e| Error::Config(format!("word list {}: {e}", path.display())))?;
+    if words.is_empty() {
+        return Err(Error::Config(format!(
+            "word list {} contains no words",
+            path.display()
+        )));
+    }
+    Ok(words)
+}
+
+/// Parse newline-separated words, keeping the first occurrence of each.
+fn parse_word_list(content: &str) -> std::result::Result<Vec<String>, String> {
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let word = line.trim().to_lowercase();
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+        if word.contains(|c: char| c.is_whitespace() || c == '-') {
+            return Err(format!("line {}: invalid word '{}'", index + 1, word));
+        }
+        if seen.insert(word.clone()) {
+            words.push(word);
+        }
+    }
+    Ok(words)
+}
+
 /// ID generator that manages the pool of human-readable IDs.
 pub struct IdGenerator<'a> {
     db: &'a Database,
@@ -69,6 +122,18 @@ impl<'a> IdGenerator<'a> {
         self.db.seed_id_pool(&adj_refs, &noun_refs)
     }
 
+    /// Initialize the ID pool, reporting seeding progress.
+    pub fn init_pool_with_progress(
+        &self,
+        words: &WordLists,
+        progress: ProgressCallback<'_>,
+    ) -> Result<usize> {
+        let adj_refs: Vec<&str> = words.adjectives.iter().map(|s| s.as_str()).collect();
+        let noun_refs: Vec<&str> = words.nouns.iter().map(|s| s.as_str()).collect();
+        self.db
+            .seed_id_pool_with_progress(&adj_refs, &noun_refs, progress)
+    }
+
     /// Allocate a new ID for a message.
     pub fn allocate(&self, remote_id: &str) -> Result<String> {
         self.db.allocate_id(remote_id)
@@ -104,7 +169,8 @@ impl<'a> IdGenerator<'a> {
         remote_id: &str,
         display_name: Option<&str>,
     ) -> Result<String> {
-        self.db.get_or_create_rule_id(remote_id, display_name, &WordLists::embedded())
+        self.db
+            .get_or_create_rule_id(remote_id, display_name, &WordLists::embedded())
     }
 
     /// Resolve a rule short ID to its remote ID.
@@ -228,6 +294,7 @@ const EMBEDDED_NOUNS: &[&str] = &[
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Progress;
 
     #[test]
     fn test_word_lists_embedded() {
@@ -282,4 +349,119 @@ mod tests {
         assert_eq!(stats.free, 4);
         assert_eq!(stats.used, 0);
     }
+
+    #[test]
+    fn test_concurrent_allocation_is_unique() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("ids.db");
+        let words = WordLists {
+            adjectives: (0..12).map(|i| format!("adj{i}")).collect(),
+            nouns: (0..12).map(|i| format!("noun{i}")).collect(),
+        };
+        IdGenerator::new(&Database::open(&path).unwrap())
+            .init_pool(&words)
+            .unwrap();
+
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+        let handles: Vec<_> = (0..2)
+            .map(|worker| {
+                let path = path.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let db = Database::open(&path).unwrap();
+                    let id_gen = IdGenerator::new(&db);
+                    barrier.wait();
+                    (0..60)
+                        .map(|i| {
+                            let remote = format!("remote-{worker}-{i}");
+                            (id_gen.allocate(&remote).unwrap(), remote)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let allocated: Vec<(String, String)> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+
+        let unique: std::collections::HashSet<&String> =
+            allocated.iter().map(|(id, _)| id).collect();
+        assert_eq!(unique.len(), 120);
+
+        let db = Database::open(&path).unwrap();
+        let id_gen = IdGenerator::new(&db);
+        for (id, remote) in &allocated {
+            assert_eq!(id_gen.resolve(id).unwrap().as_ref(), Some(remote));
+        }
+        let stats = id_gen.stats().unwrap();
+        assert_eq!((stats.used, stats.free), (120, 24));
+    }
+
+    #[test]
+    fn test_init_pool_reports_progress() {
+        let db = Database::open_memory().unwrap();
+        let id_gen = IdGenerator::new(&db);
+        let words = WordLists {
+            adjectives: vec!["cold".to_string(), "blue".to_string(), "calm".to_string()],
+            nouns: vec!["lamp".to_string(), "frog".to_string()],
+        };
+
+        let mut last = 0;
+        let mut calls = 0;
+        let mut cb = |p: Progress| {
+            assert!(p.current > last);
+            assert_eq!(p.total, 3);
+            last = p.current;
+            calls += 1;
+        };
+        id_gen
+            .init_pool_with_progress(&words, Some(&mut cb))
+            .unwrap();
+
+        assert_eq!(calls, 3);
+        assert_eq!(last, 3);
+    }
+
+    #[test]
+    fn test_word_lists_from_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let adjectives = temp.path().join("adjectives.txt");
+        let nouns = temp.path().join("nouns.txt");
+        fs::write(&adjectives, "# team colours\nTeal\n\n  amber \nteal\n").unwrap();
+        fs::write(&nouns, "otter\nheron\notter\n").unwrap();
+
+        let words = WordLists::from_files(&adjectives, &nouns).unwrap();
+        assert_eq!(words.adjectives, vec!["teal", "amber"]);
+        assert_eq!(words.nouns, vec!["otter", "heron"]);
+
+        let db = Database::open_memory().unwrap();
+        let id_gen = IdGenerator::new(&db);
+        assert_eq!(id_gen.init_pool(&words).unwrap(), 4);
+        let id = id_gen.allocate("remote-1").unwrap();
+        let (adj, noun) = id.split_once('-').unwrap();
+        assert!(words.adjectives.iter().any(|w| w == adj));
+        assert!(words.nouns.iter().any(|w| w == noun));
+        assert_eq!(id_gen.resolve(&id).unwrap(), Some("remote-1".to_string()));
+    }
+
+    #[test]
+    fn test_read_word_list_errors() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let empty = temp.path().join("empty.txt");
+        fs::write(&empty, "\n# nothing here\n   \n").unwrap();
+        let err = read_word_list(&empty).unwrap_err();
+        assert!(err.to_string().ends_with("contains no words"), "{err}");
+
+        let bad = temp.path().join("bad.txt");
+        fs::write(&bad, "teal\nsea-green\n").unwrap();
+        let err = read_word_list(&bad).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("line 2: invalid word 'sea-green'"),
+            "{err}"
+        );
+
+        assert!(read_word_list(&temp.path().join("missing.txt")).is_err());
+    }
 }
@@ -7,6 +7,8 @@
 //! - Human-readable ID generation (adjective-noun format)
 //! - Local Maildir storage
 //! - Email compose format parsing
+//! - Conversation threading from stored headers
+//! - Folder sync from the service into the local database
 
 pub mod compose;
 pub mod config;
@@ -16,17 +18,23 @@ pub mod id;
 pub mod maildir;
 pub mod paths;
 pub mod service;
+pub mod sync;
+pub mod thread;
 pub mod types;
 
 pub use compose::{ComposeBuilder, ComposeDocument};
-pub use config::{AppConfig, CalendarConfig, CalendarView, Location, ResourceEntry, ResourceGroup, TripConfig, UnsubscribeConfig};
+pub use config::{
+    AppConfig, CalendarConfig, CalendarView, DisplayConfig, Location, ResourceEntry, ResourceGroup,
+    SignaturePlacement, TripConfig, TuiConfig, UnsubscribeConfig, resolve_editor, resolve_pager,
+    select_pager,
+};
 pub use db::Database;
 pub use error::{Error, Result};
 pub use id::IdGenerator;
-pub use maildir::Maildir;
+pub use maildir::{FolderStats, Maildir};
 pub use paths::AppPaths;
 pub use service::ServiceClient;
-pub use types::AddressEntry;
+pub use types::{AddressEntry, BulkResult, Folder, HealthStatus, Progress};
 
 /// Convert HTML content to readable plain text.
 ///
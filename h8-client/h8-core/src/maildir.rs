@@ -15,26 +15,48 @@
 //!   .sync.db  # SQLite sync state
 //! ```
 
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::error::Result;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use log::warn;
+
+use crate::compose::ComposeDocument;
+use crate::config::ComposeConfig;
+use crate::error::{Error, Result};
+use crate::types::{Progress, ProgressCallback};
 
 /// Standard Maildir folder names.
 pub const FOLDER_INBOX: &str = "inbox";
 pub const FOLDER_SENT: &str = "sent";
 pub const FOLDER_DRAFTS: &str = "drafts";
 pub const FOLDER_TRASH: &str = "trash";
+pub const FOLDER_ARCHIVE: &str = "archive";
+
+/// Folders created by [`Maildir::init`].
+pub const DEFAULT_FOLDERS: [&str; 5] = [
+    FOLDER_INBOX,
+    FOLDER_SENT,
+    FOLDER_DRAFTS,
+    FOLDER_TRASH,
+    FOLDER_ARCHIVE,
+];
 
 /// Maildir subdirectory names.
 const SUBDIR_NEW: &str = "new";
 const SUBDIR_CUR: &str = "cur";
 const SUBDIR_TMP: &str = "tmp";
 
+/// Attempts at finding a free unique ID before `store` gives up.
+const MAX_STORE_ATTEMPTS: usize = 16;
+
 /// Message flags for Maildir filename encoding.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MessageFlags {
     /// P: Passed (forwarded)
     pub passed: bool,
@@ -129,6 +151,65 @@ pub struct MaildirMessage {
     pub is_new: bool,
 }
 
+/// Lazy iterator over the messages of a folder, from [`Maildir::iter`].
+pub struct MaildirIter {
+    folder: String,
+    subdirs: std::array::IntoIter<(PathBuf, bool), 2>,
+    current: Option<(fs::ReadDir, bool)>,
+}
+
+impl Iterator for MaildirIter {
+    type Item = Result<MaildirMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((entries, is_new)) = self.current.as_mut() else {
+                let (dir_path, is_new) = self.subdirs.next()?;
+                if !dir_path.exists() {
+                    continue;
+                }
+                match fs::read_dir(&dir_path) {
+                    Ok(entries) => self.current = Some((entries, is_new)),
+                    Err(e) => return Some(Err(e.into())),
+                }
+                continue;
+            };
+            let is_new = *is_new;
+
+            let entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    self.current = None;
+                    continue;
+                }
+            };
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_file() => {}
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            let filename = entry.file_name();
+            let filename_str = filename.to_string_lossy();
+
+            let (base_id, flags) = parse_filename(&filename_str);
+            if base_id.is_empty() {
+                warn!("skipping maildir file {:?}: no message ID", entry.path());
+                continue;
+            }
+
+            return Some(Ok(MaildirMessage {
+                id: base_id.to_string(),
+                flags,
+                path: entry.path(),
+                folder: self.folder.clone(),
+                is_new,
+            }));
+        }
+    }
+}
+
 impl MaildirMessage {
     /// Read the message content.
     pub fn read_content(&self) -> Result<String> {
@@ -147,6 +228,17 @@ impl MaildirMessage {
     }
 }
 
+/// Message counts for a folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FolderStats {
+    /// All messages in the folder.
+    pub total: usize,
+    /// Messages without the Seen flag.
+    pub unread: usize,
+    /// Combined size of the message files in bytes.
+    pub size: u64,
+}
+
 /// Maildir storage manager for an account.
 pub struct Maildir {
     /// Base path for the account's mail storage.
@@ -155,6 +247,8 @@ pub struct Maildir {
     account: String,
     /// Hostname for generating unique IDs.
     hostname: String,
+    /// Per-instance delivery counter, guarantees in-process ID uniqueness.
+    counter: AtomicU64,
 }
 
 impl Maildir {
@@ -168,6 +262,7 @@ impl Maildir {
             base_path,
             account: account.to_string(),
             hostname,
+            counter: AtomicU64::new(0),
         })
     }
 
@@ -181,12 +276,16 @@ impl Maildir {
         &self.account
     }
 
-    /// Initialize the Maildir structure, creating all necessary directories.
+    /// Initialize the Maildir structure with the [`DEFAULT_FOLDERS`].
     pub fn init(&self) -> Result<()> {
-        self.init_folder(FOLDER_INBOX)?;
-        self.init_folder(FOLDER_SENT)?;
-        self.init_folder(FOLDER_DRAFTS)?;
-        self.init_folder(FOLDER_TRASH)?;
+        self.init_with(&DEFAULT_FOLDERS)
+    }
+
+    /// Initialize the Maildir structure with a custom folder set.
+    pub fn init_with<S: AsRef<str>>(&self, folders: &[S]) -> Result<()> {
+        for folder in folders {
+            self.init_folder(folder.as_ref())?;
+        }
         Ok(())
     }
 
@@ -211,8 +310,12 @@ impl Maildir {
             .map(|d| d.as_micros())
             .unwrap_or(0);
         let pid = std::process::id();
+        let seq = self.counter.fetch_add(1, Ordering::Relaxed);
         let random: u32 = rand::random();
-        format!("{}.{}.{:x}.{}", timestamp, pid, random, self.hostname)
+        format!(
+            "{}.{}_{}.{:x}.{}",
+            timestamp, pid, seq, random, self.hostname
+        )
     }
 
     /// Store a new message in a folder.
@@ -220,6 +323,9 @@ impl Maildir {
     /// Uses the Maildir delivery protocol:
     /// 1. Write to tmp/ with unique filename
     /// 2. Move to new/ or cur/ depending on flags
+    ///
+    /// If the generated ID already exists in tmp/ or the destination, a new
+    /// ID is generated and the delivery retried.
     pub fn store(
         &self,
         folder: &str,
@@ -229,14 +335,6 @@ impl Maildir {
         self.init_folder(folder)?;
 
         let folder_path = self.folder_path(folder);
-        let unique_id = self.generate_unique_id();
-
-        // Write to tmp first
-        let tmp_path = folder_path.join(SUBDIR_TMP).join(&unique_id);
-        let mut file = File::create(&tmp_path)?;
-        file.write_all(content)?;
-        file.sync_all()?;
-        drop(file);
 
         // Determine destination (new/ or cur/)
         let (dest_subdir, is_new) = if flags.seen {
@@ -244,25 +342,54 @@ impl Maildir {
         } else {
             (SUBDIR_NEW, true)
         };
-
-        // Build filename with flags
         let info = flags.to_info();
-        let filename = if info.is_empty() {
-            unique_id.clone()
-        } else {
-            format!("{}:{}", unique_id, info)
-        };
 
-        let dest_path = folder_path.join(dest_subdir).join(&filename);
-        fs::rename(&tmp_path, &dest_path)?;
+        for _ in 0..MAX_STORE_ATTEMPTS {
+            let unique_id = self.generate_unique_id();
+
+            // Write to tmp first, refusing to clobber an existing file
+            let tmp_path = folder_path.join(SUBDIR_TMP).join(&unique_id);
+            let mut file = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp_path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            };
+            file.write_all(content)?;
+            file.sync_all()?;
+            drop(file);
 
-        Ok(MaildirMessage {
-            id: unique_id,
-            flags: flags.clone(),
-            path: dest_path,
-            folder: folder.to_string(),
-            is_new,
-        })
+            // Build filename with flags
+            let filename = if info.is_empty() {
+                unique_id.clone()
+            } else {
+                format!("{}:{}", unique_id, info)
+            };
+
+            let dest_path = folder_path.join(dest_subdir).join(&filename);
+            if dest_path.exists() {
+                fs::remove_file(&tmp_path)?;
+                continue;
+            }
+            fs::rename(&tmp_path, &dest_path)?;
+            sync_dir(&folder_path.join(dest_subdir))?;
+
+            return Ok(MaildirMessage {
+                id: unique_id,
+                flags: flags.clone(),
+                path: dest_path,
+                folder: folder.to_string(),
+                is_new,
+            });
+        }
+
+        Err(Error::Io(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("could not allocate a unique message ID in {}", folder),
+        )))
     }
 
     /// Store a message with a specific ID (for sync operations).
@@ -284,31 +411,76 @@ impl Maildir {
         file.sync_all()?;
         drop(file);
 
-        // Determine destination (new/ or cur/)
-        let (dest_subdir, is_new) = if flags.seen {
-            (SUBDIR_CUR, false)
-        } else {
-            (SUBDIR_NEW, true)
-        };
+        let dest_path = self.message_path(folder, id, flags);
+        fs::rename(&tmp_path, &dest_path)?;
+        if let Some(dest_dir) = dest_path.parent() {
+            sync_dir(dest_dir)?;
+        }
+
+        Ok(MaildirMessage {
+            id: id.to_string(),
+            flags: flags.clone(),
+            path: dest_path,
+            folder: folder.to_string(),
+            is_new: !flags.seen,
+        })
+    }
+
+    /// Overwrite an existing message with new content, keeping its ID.
+    ///
+    /// The content is written to `tmp/` and renamed into place, so readers
+    /// see either the old or the new version. Returns `Error::IdNotFound`
+    /// if the message does not exist.
+    pub fn replace_content(
+        &self,
+        folder: &str,
+        id: &str,
+        content: &[u8],
+        flags: &MessageFlags,
+    ) -> Result<MaildirMessage> {
+        let existing = self
+            .get(folder, id)?
+            .ok_or_else(|| Error::IdNotFound(format!("{} (not in {})", id, folder)))?;
+        let message = self.store_with_id(folder, content, flags, id)?;
+        if existing.path != message.path {
+            fs::remove_file(&existing.path)?;
+        }
+        Ok(message)
+    }
 
-        // Build filename with flags
+    /// Path at which a message with the given flags is stored: `new/` while
+    /// unseen, `cur/` once seen, with the flags encoded in the filename.
+    pub fn message_path(&self, folder: &str, id: &str, flags: &MessageFlags) -> PathBuf {
+        let subdir = if flags.seen { SUBDIR_CUR } else { SUBDIR_NEW };
         let info = flags.to_info();
         let filename = if info.is_empty() {
             id.to_string()
         } else {
             format!("{}:{}", id, info)
         };
+        self.folder_path(folder).join(subdir).join(filename)
+    }
 
-        let dest_path = folder_path.join(dest_subdir).join(&filename);
-        fs::rename(&tmp_path, &dest_path)?;
+    /// Validate a compose document and store it in the drafts folder.
+    ///
+    /// Nothing is written if the document fails validation.
+    pub fn store_draft(&self, doc: &ComposeDocument) -> Result<MaildirMessage> {
+        self.store_draft_with(doc, &ComposeConfig::default())
+    }
 
-        Ok(MaildirMessage {
-            id: id.to_string(),
-            flags: flags.clone(),
-            path: dest_path,
-            folder: folder.to_string(),
-            is_new,
-        })
+    /// Like [`Maildir::store_draft`], wrapping the body per `config`.
+    pub fn store_draft_with(
+        &self,
+        doc: &ComposeDocument,
+        config: &ComposeConfig,
+    ) -> Result<MaildirMessage> {
+        doc.validate()?;
+        let content = doc.to_string_with(config)?;
+        let flags = MessageFlags {
+            draft: true,
+            ..Default::default()
+        };
+        self.store(FOLDER_DRAFTS, content.as_bytes(), &flags)
     }
 
     /// Get a message by ID from a folder.
@@ -325,11 +497,9 @@ impl Maildir {
                 let filename = entry.file_name();
                 let filename_str = filename.to_string_lossy();
 
-                // Extract base ID (before colon)
-                let base_id = filename_str.split(':').next().unwrap_or(&filename_str);
+                let (base_id, flags) = parse_filename(&filename_str);
 
                 if base_id == id {
-                    let flags = self.parse_flags_from_filename(&filename_str);
                     return Ok(Some(MaildirMessage {
                         id: id.to_string(),
                         flags,
@@ -346,38 +516,22 @@ impl Maildir {
 
     /// List all messages in a folder.
     pub fn list(&self, folder: &str) -> Result<Vec<MaildirMessage>> {
-        let mut messages = Vec::new();
-
-        for (subdir, is_new) in [(SUBDIR_NEW, true), (SUBDIR_CUR, false)] {
-            let dir_path = self.folder_path(folder).join(subdir);
-            if !dir_path.exists() {
-                continue;
-            }
-
-            for entry in fs::read_dir(&dir_path)? {
-                let entry = entry?;
-                if !entry.file_type()?.is_file() {
-                    continue;
-                }
-
-                let filename = entry.file_name();
-                let filename_str = filename.to_string_lossy();
+        self.iter(folder).collect()
+    }
 
-                // Extract base ID (before colon)
-                let base_id = filename_str.split(':').next().unwrap_or(&filename_str);
-                let flags = self.parse_flags_from_filename(&filename_str);
-
-                messages.push(MaildirMessage {
-                    id: base_id.to_string(),
-                    flags,
-                    path: entry.path(),
-                    folder: folder.to_string(),
-                    is_new,
-                });
-            }
+    /// Iterate over all messages in a folder without reading the whole
+    /// folder up front: `new/` is listed first, then `cur/`.
+    pub fn iter(&self, folder: &str) -> MaildirIter {
+        let folder_path = self.folder_path(folder);
+        MaildirIter {
+            folder: folder.to_string(),
+            subdirs: [
+                (folder_path.join(SUBDIR_NEW), true),
+                (folder_path.join(SUBDIR_CUR), false),
+            ]
+            .into_iter(),
+            current: None,
         }
-
-        Ok(messages)
     }
 
     /// Delete a message by ID from a folder.
@@ -390,6 +544,43 @@ impl Maildir {
         }
     }
 
+    /// Trashed messages received before `cutoff`. `received_at` looks up a
+    /// message's receive time by ID; messages without one fall back to the
+    /// file's modification time. Only the trash folder is ever considered.
+    pub fn expired_trash(
+        &self,
+        cutoff: SystemTime,
+        received_at: impl Fn(&str) -> Option<SystemTime>,
+    ) -> Result<Vec<MaildirMessage>> {
+        let mut expired = Vec::new();
+        for msg in self.list(FOLDER_TRASH)? {
+            let age = match received_at(&msg.id) {
+                Some(time) => time,
+                None => fs::metadata(&msg.path)?.modified()?,
+            };
+            if age < cutoff {
+                expired.push(msg);
+            }
+        }
+        expired.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(expired)
+    }
+
+    /// Permanently delete trashed messages older than `cutoff` (see
+    /// [`Maildir::expired_trash`]). Returns the IDs that were removed.
+    pub fn purge_trash(
+        &self,
+        cutoff: SystemTime,
+        received_at: impl Fn(&str) -> Option<SystemTime>,
+    ) -> Result<Vec<String>> {
+        let mut purged = Vec::new();
+        for msg in self.expired_trash(cutoff, received_at)? {
+            fs::remove_file(&msg.path)?;
+            purged.push(msg.id);
+        }
+        Ok(purged)
+    }
+
     /// Update message flags (moves between new/cur as needed).
     pub fn update_flags(
         &self,
@@ -398,24 +589,7 @@ impl Maildir {
         flags: &MessageFlags,
     ) -> Result<Option<MaildirMessage>> {
         if let Some(msg) = self.get(folder, id)? {
-            let folder_path = self.folder_path(folder);
-
-            // Determine new location
-            let (new_subdir, is_new) = if flags.seen {
-                (SUBDIR_CUR, false)
-            } else {
-                (SUBDIR_NEW, true)
-            };
-
-            // Build new filename
-            let info = flags.to_info();
-            let new_filename = if info.is_empty() {
-                id.to_string()
-            } else {
-                format!("{}:{}", id, info)
-            };
-
-            let new_path = folder_path.join(new_subdir).join(&new_filename);
+            let new_path = self.message_path(folder, id, flags);
 
             // Move if needed (even if same dir, filename might change)
             if msg.path != new_path {
@@ -427,7 +601,7 @@ impl Maildir {
                 flags: flags.clone(),
                 path: new_path,
                 folder: folder.to_string(),
-                is_new,
+                is_new: !flags.seen,
             }))
         } else {
             Ok(None)
@@ -435,35 +609,84 @@ impl Maildir {
     }
 
     /// Move a message to another folder.
+    ///
+    /// The message is renamed into place, so an interrupted move leaves it
+    /// in exactly one folder. When the folders live on different filesystems
+    /// it is copied into the destination's `tmp/`, synced, renamed into
+    /// place and only then removed from the source. If that fallback is
+    /// interrupted before the removal, the next move of the same ID finds it
+    /// already in the destination and only removes the leftover source.
+    /// A destination copy whose content differs is kept under a new ID
+    /// instead, and the message is moved as usual.
     pub fn move_to(
         &self,
         folder: &str,
         id: &str,
         dest_folder: &str,
     ) -> Result<Option<MaildirMessage>> {
-        if let Some(msg) = self.get(folder, id)? {
-            // Read content
-            let content = msg.read_bytes()?;
+        self.move_with(&StdFs, folder, id, dest_folder)
+    }
 
-            // Store in new folder
-            let new_msg = self.store_with_id(dest_folder, &content, &msg.flags, id)?;
+    fn move_with(
+        &self,
+        fs_ops: &impl FsOps,
+        folder: &str,
+        id: &str,
+        dest_folder: &str,
+    ) -> Result<Option<MaildirMessage>> {
+        let Some(msg) = self.get(folder, id)? else {
+            return Ok(None);
+        };
+        if folder == dest_folder {
+            return Ok(Some(msg));
+        }
 
-            // Delete from old folder
-            fs::remove_file(&msg.path)?;
+        if let Some(existing) = self.get(dest_folder, id)? {
+            // Finish a previously interrupted cross-filesystem move
+            if fs::read(&existing.path)? == fs::read(&msg.path)? {
+                fs_ops.remove(&msg.path)?;
+                sync_parent(&msg.path)?;
+                return Ok(Some(existing));
+            }
+            let aside_id = self.generate_unique_id();
+            let aside_path = self.message_path(dest_folder, &aside_id, &existing.flags);
+            warn!("{dest_folder} already holds a different message {id}; keeping it as {aside_id}");
+            fs_ops.rename(&existing.path, &aside_path)?;
+            sync_parent(&aside_path)?;
+        }
 
-            Ok(Some(new_msg))
-        } else {
-            Ok(None)
+        self.init_folder(dest_folder)?;
+        let dest_path = self.message_path(dest_folder, id, &msg.flags);
+        let is_new = !msg.flags.seen;
+        match fs_ops.rename(&msg.path, &dest_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+                let tmp_path = self.folder_path(dest_folder).join(SUBDIR_TMP).join(id);
+                fs_ops.copy_synced(&msg.path, &tmp_path)?;
+                fs_ops.rename(&tmp_path, &dest_path)?;
+                sync_parent(&dest_path)?;
+                fs_ops.remove(&msg.path)?;
+            }
+            Err(e) => return Err(e.into()),
         }
+        sync_parent(&dest_path)?;
+        sync_parent(&msg.path)?;
+
+        Ok(Some(MaildirMessage {
+            id: id.to_string(),
+            flags: msg.flags,
+            path: dest_path,
+            folder: dest_folder.to_string(),
+            is_new,
+        }))
     }
 
-    /// Parse flags from a Maildir filename.
-    fn parse_flags_from_filename(&self, filename: &str) -> MessageFlags {
-        if let Some(info_start) = filename.find(':') {
-            MessageFlags::from_info(&filename[info_start + 1..])
-        } else {
-            MessageFlags::default()
-        }
+    /// Restore a message from the trash folder to `dest_folder`.
+    ///
+    /// Returns `Error::IdNotFound` if the message is not in trash.
+    pub fn undelete(&self, id: &str, dest_folder: &str) -> Result<MaildirMessage> {
+        self.move_to(FOLDER_TRASH, id, dest_folder)?
+            .ok_or_else(|| Error::IdNotFound(format!("{} (not in {})", id, FOLDER_TRASH)))
     }
 
     /// Count messages in a folder.
@@ -492,6 +715,31 @@ impl Maildir {
         Ok((new_count, cur_count))
     }
 
+    /// Count total and unread messages in a folder and sum their file
+    /// sizes, from directory entries alone without reading message contents.
+    pub fn folder_stats(&self, folder: &str) -> Result<FolderStats> {
+        let mut stats = FolderStats::default();
+        for subdir in [SUBDIR_NEW, SUBDIR_CUR] {
+            let path = self.folder_path(folder).join(subdir);
+            if !path.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(&path)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_file() {
+                    continue;
+                }
+                stats.total += 1;
+                stats.size += entry.metadata()?.len();
+                let filename = entry.file_name();
+                if !parse_filename(&filename.to_string_lossy()).1.seen {
+                    stats.unread += 1;
+                }
+            }
+        }
+        Ok(stats)
+    }
+
     /// List all folders.
     pub fn list_folders(&self) -> Result<Vec<String>> {
         let mut folders = Vec::new();
@@ -519,6 +767,157 @@ impl Maildir {
         folders.sort();
         Ok(folders)
     }
+
+    /// Write every message in a folder to `writer` as an mbox stream,
+    /// gzip-compressed when `gzip` is set. Returns the number of messages.
+    pub fn export_mbox<W: Write>(&self, folder: &str, writer: W, gzip: bool) -> Result<usize> {
+        self.export_mbox_with_progress(folder, writer, gzip, None)
+    }
+
+    /// [`Self::export_mbox`], reporting progress after each message.
+    pub fn export_mbox_with_progress<W: Write>(
+        &self,
+        folder: &str,
+        writer: W,
+        gzip: bool,
+        progress: ProgressCallback<'_>,
+    ) -> Result<usize> {
+        let mut messages = self.list(folder)?;
+        messages.sort_by(|a, b| a.id.cmp(&b.id));
+
+        if gzip {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            write_mbox(&messages, &mut encoder, progress)?;
+            encoder.finish()?;
+        } else {
+            let mut writer = writer;
+            write_mbox(&messages, &mut writer, progress)?;
+            writer.flush()?;
+        }
+        Ok(messages.len())
+    }
+}
+
+/// Keys of the messages `h8 mail fetch` already wrote to the standalone
+/// Maildir at `output`, so a resumed fetch can skip them.
+///
+/// Fetched files are named `<timestamp>.<key>.h8:2,<flags>`. Only `cur/` and
+/// `new/` are scanned: files still in `tmp/` were never finished.
+pub fn fetched_keys(output: &Path) -> Result<HashSet<String>> {
+    let mut keys = HashSet::new();
+    for subdir in [SUBDIR_CUR, SUBDIR_NEW] {
+        let entries = match fs::read_dir(output.join(subdir)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            let (id, _) = parse_filename(&name);
+            if let [_, key, "h8"] = id.split('.').collect::<Vec<_>>()[..] {
+                keys.insert(key.to_string());
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Split a Maildir filename into its base ID and flags.
+///
+/// Only the last `:2,` starts the info section, so other colons stay part of
+/// the ID. A name with a colon but no `:2,` section is treated as an
+/// unflagged message under its full name, with a warning.
+fn parse_filename(filename: &str) -> (&str, MessageFlags) {
+    if let Some(pos) = filename.rfind(":2,") {
+        return (
+            &filename[..pos],
+            MessageFlags::from_info(&filename[pos + 1..]),
+        );
+    }
+    if filename.contains(':') {
+        warn!("maildir filename {filename:?} has no `:2,` info section; treating it as unflagged");
+    }
+    (filename, MessageFlags::default())
+}
+
+/// Append messages in mboxrd form: each one starts with a `From ` separator
+/// line, and body lines matching `>*From ` gain an extra `>`.
+fn write_mbox(
+    messages: &[MaildirMessage],
+    out: &mut impl Write,
+    mut progress: ProgressCallback<'_>,
+) -> Result<()> {
+    let separator = format!(
+        "From MAILER-DAEMON {}\n",
+        chrono::Utc::now().format("%a %b %e %H:%M:%S %Y")
+    );
+    for (i, message) in messages.iter().enumerate() {
+        let content = message.read_bytes()?;
+        out.write_all(separator.as_bytes())?;
+        for line in content.split_inclusive(|&b| b == b'\n') {
+            let line = line.strip_suffix(b"\r\n").unwrap_or(line);
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            let start = line.iter().position(|&b| b != b'>').unwrap_or(line.len());
+            if line[start..].starts_with(b"From ") {
+                out.write_all(b">")?;
+            }
+            out.write_all(line)?;
+            out.write_all(b"\n")?;
+        }
+        out.write_all(b"\n")?;
+        if let Some(cb) = progress.as_mut() {
+            cb(Progress::new(i + 1, messages.len(), "exporting messages"));
+        }
+    }
+    Ok(())
+}
+
+/// File operations used by [`Maildir::move_to`], injectable so tests can
+/// simulate folders on different filesystems.
+trait FsOps {
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    /// Copy `from` to `to` and flush the copy to disk.
+    fn copy_synced(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    fn remove(&self, path: &Path) -> std::io::Result<()>;
+}
+
+/// [`FsOps`] backed by `std::fs`.
+struct StdFs;
+
+impl FsOps for StdFs {
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn copy_synced(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        fs::copy(from, to)?;
+        File::open(to)?.sync_all()
+    }
+
+    fn remove(&self, path: &Path) -> std::io::Result<()> {
+        fs::remove_file(path)
+    }
+}
+
+/// Sync the directory containing `path`.
+fn sync_parent(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) => sync_dir(dir),
+        None => Ok(()),
+    }
+}
+
+/// Flush a directory entry to disk so a preceding rename survives power loss.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Directory handles cannot be synced on this platform; the rename is best effort.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
 }
 
 #[cfg(test)]
@@ -565,6 +964,24 @@ mod tests {
         assert!(maildir.folder_path(FOLDER_INBOX).join(SUBDIR_CUR).exists());
         assert!(maildir.folder_path(FOLDER_INBOX).join(SUBDIR_TMP).exists());
         assert!(maildir.folder_path(FOLDER_DRAFTS).exists());
+        assert!(maildir.folder_path(FOLDER_ARCHIVE).exists());
+    }
+
+    #[test]
+    fn test_init_with_custom_folders() {
+        let (_temp, maildir) = test_maildir();
+        maildir.init_with(&["inbox", "projects", "legal"]).unwrap();
+
+        assert_eq!(
+            maildir.list_folders().unwrap(),
+            ["inbox", "legal", "projects"]
+        );
+        for folder in ["inbox", "projects", "legal"] {
+            for subdir in [SUBDIR_NEW, SUBDIR_CUR, SUBDIR_TMP] {
+                assert!(maildir.folder_path(folder).join(subdir).is_dir());
+            }
+        }
+        assert!(!maildir.folder_path(FOLDER_SENT).exists());
     }
 
     #[test]
@@ -585,6 +1002,54 @@ mod tests {
         assert!(retrieved_content.contains("Hello, world!"));
     }
 
+    #[test]
+    fn test_iter_matches_list_and_is_lazy() {
+        let (_temp, maildir) = test_maildir();
+
+        let seen = MessageFlags {
+            seen: true,
+            ..Default::default()
+        };
+        for i in 0..6 {
+            let flags = if i % 2 == 0 {
+                seen.clone()
+            } else {
+                MessageFlags::default()
+            };
+            maildir
+                .store(FOLDER_INBOX, format!("Message {}", i).as_bytes(), &flags)
+                .unwrap();
+        }
+
+        let listed: HashSet<String> = maildir
+            .list(FOLDER_INBOX)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        let iterated: Vec<MaildirMessage> =
+            maildir.iter(FOLDER_INBOX).collect::<Result<_>>().unwrap();
+        assert_eq!(iterated.len(), 6);
+        assert_eq!(
+            iterated
+                .iter()
+                .map(|m| m.id.clone())
+                .collect::<HashSet<_>>(),
+            listed
+        );
+
+        // new/ is yielded before cur/
+        assert!(iterated[..3].iter().all(|m| m.is_new));
+        assert!(iterated[3..].iter().all(|m| !m.is_new));
+        assert!(iterated.iter().all(|m| m.folder == FOLDER_INBOX));
+
+        let first_two: Vec<_> = maildir.iter(FOLDER_INBOX).take(2).collect();
+        assert_eq!(first_two.len(), 2);
+        assert!(first_two.iter().all(|m| m.is_ok()));
+
+        assert_eq!(maildir.iter("missing-folder").count(), 0);
+    }
+
     #[test]
     fn test_store_seen_message() {
         let (_temp, maildir) = test_maildir();
@@ -598,6 +1063,21 @@ mod tests {
         assert!(msg.path.to_string_lossy().contains("/cur/"));
     }
 
+    #[test]
+    fn test_store_ids_unique_in_tight_loop() {
+        let (_temp, maildir) = test_maildir();
+
+        let flags = MessageFlags::default();
+        let mut ids = std::collections::HashSet::new();
+        for i in 0..500 {
+            let msg = maildir
+                .store(FOLDER_INBOX, format!("Message {}", i).as_bytes(), &flags)
+                .unwrap();
+            assert!(ids.insert(msg.id));
+        }
+        assert_eq!(maildir.list(FOLDER_INBOX).unwrap().len(), 500);
+    }
+
     #[test]
     fn test_list_messages() {
         let (_temp, maildir) = test_maildir();
@@ -610,6 +1090,71 @@ mod tests {
         assert_eq!(messages.len(), 2);
     }
 
+    #[test]
+    fn test_parse_filename_only_splits_on_info_separator() {
+        let (id, flags) = parse_filename("1700000000.123.host:2,FS");
+        assert_eq!(id, "1700000000.123.host");
+        assert!(flags.seen && flags.flagged);
+
+        let (id, flags) = parse_filename("host:8080:2,R");
+        assert_eq!(id, "host:8080");
+        assert!(flags.replied && !flags.seen);
+
+        // No info section: the whole name is the ID and nothing is flagged
+        let (id, flags) = parse_filename("odd:name");
+        assert_eq!(id, "odd:name");
+        assert_eq!(flags, MessageFlags::default());
+
+        let (id, flags) = parse_filename("plain-id");
+        assert_eq!(id, "plain-id");
+        assert_eq!(flags, MessageFlags::default());
+    }
+
+    #[test]
+    fn test_fetched_keys_skips_unfinished_files() {
+        let temp = TempDir::new().unwrap();
+        assert!(fetched_keys(temp.path()).unwrap().is_empty());
+
+        for subdir in [SUBDIR_CUR, SUBDIR_NEW, SUBDIR_TMP] {
+            fs::create_dir_all(temp.path().join(subdir)).unwrap();
+        }
+        let write = |subdir: &str, name: &str| {
+            fs::write(temp.path().join(subdir).join(name), "Subject: x\n\n").unwrap();
+        };
+        write(SUBDIR_CUR, "1700000000.aaaa1111.h8:2,S");
+        write(SUBDIR_NEW, "1700000100.bbbb2222.h8:2,");
+        // Interrupted write, never moved out of tmp/
+        write(SUBDIR_TMP, "1700000200.cccc3333.h8:2,");
+        // Not written by a fetch
+        write(SUBDIR_CUR, "1700000300.42.otherhost:2,S");
+
+        let keys = fetched_keys(temp.path()).unwrap();
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["aaaa1111", "bbbb2222"]);
+    }
+
+    #[test]
+    fn test_list_tolerates_malformed_filenames() {
+        let (_temp, maildir) = test_maildir();
+        maildir.init_folder(FOLDER_INBOX).unwrap();
+        let cur = maildir.folder_path(FOLDER_INBOX).join(SUBDIR_CUR);
+        fs::write(cur.join("a:b:2,S"), b"extra colon").unwrap();
+        fs::write(cur.join("stray:colon"), b"no info").unwrap();
+        fs::write(cur.join(":2,S"), b"no id").unwrap();
+
+        let mut messages = maildir.list(FOLDER_INBOX).unwrap();
+        messages.sort_by(|a, b| a.id.cmp(&b.id));
+        let ids: Vec<_> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["a:b", "stray:colon"]);
+        assert!(messages[0].flags.seen);
+        assert!(!messages[1].flags.seen);
+
+        let found = maildir.get(FOLDER_INBOX, "a:b").unwrap().unwrap();
+        assert_eq!(found.read_content().unwrap(), "extra colon");
+        assert!(maildir.get(FOLDER_INBOX, "a").unwrap().is_none());
+    }
+
     #[test]
     fn test_delete_message() {
         let (_temp, maildir) = test_maildir();
@@ -664,6 +1209,181 @@ mod tests {
         assert!(maildir.get(FOLDER_TRASH, &msg.id).unwrap().is_some());
     }
 
+    /// [`FsOps`] that records calls, treats each top-level folder as its own
+    /// filesystem when `cross_fs` is set, and can fail removals.
+    #[derive(Default)]
+    struct FakeFs {
+        cross_fs: bool,
+        fail_remove: bool,
+        calls: std::cell::RefCell<Vec<&'static str>>,
+    }
+
+    impl FakeFs {
+        fn folder_of(path: &Path) -> Option<&Path> {
+            path.parent()?.parent()
+        }
+    }
+
+    impl FsOps for FakeFs {
+        fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+            self.calls.borrow_mut().push("rename");
+            if self.cross_fs && Self::folder_of(from) != Self::folder_of(to) {
+                return Err(ErrorKind::CrossesDevices.into());
+            }
+            StdFs.rename(from, to)
+        }
+
+        fn copy_synced(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+            self.calls.borrow_mut().push("copy");
+            StdFs.copy_synced(from, to)
+        }
+
+        fn remove(&self, path: &Path) -> std::io::Result<()> {
+            self.calls.borrow_mut().push("remove");
+            if self.fail_remove {
+                return Err(std::io::Error::other("simulated crash"));
+            }
+            StdFs.remove(path)
+        }
+    }
+
+    #[test]
+    fn test_move_same_fs_is_single_rename() {
+        let (_temp, maildir) = test_maildir();
+        let msg = maildir
+            .store(FOLDER_INBOX, b"Same fs", &MessageFlags::default())
+            .unwrap();
+
+        let fs_ops = FakeFs::default();
+        let moved = maildir
+            .move_with(&fs_ops, FOLDER_INBOX, &msg.id, FOLDER_TRASH)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(*fs_ops.calls.borrow(), ["rename"]);
+        assert_eq!(moved.read_content().unwrap(), "Same fs");
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_none());
+        assert_eq!(maildir.list(FOLDER_TRASH).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_move_cross_fs_copies_then_removes() {
+        let (_temp, maildir) = test_maildir();
+        let mut flags = MessageFlags::default();
+        flags.mark_read();
+        let msg = maildir.store(FOLDER_INBOX, b"Cross fs", &flags).unwrap();
+
+        let fs_ops = FakeFs {
+            cross_fs: true,
+            ..Default::default()
+        };
+        let moved = maildir
+            .move_with(&fs_ops, FOLDER_INBOX, &msg.id, FOLDER_TRASH)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            *fs_ops.calls.borrow(),
+            ["rename", "copy", "rename", "remove"]
+        );
+        assert_eq!(
+            moved.path,
+            maildir.message_path(FOLDER_TRASH, &msg.id, &flags)
+        );
+        assert_eq!(moved.read_content().unwrap(), "Cross fs");
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_none());
+        let tmp = maildir.folder_path(FOLDER_TRASH).join(SUBDIR_TMP);
+        assert_eq!(fs::read_dir(tmp).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_interrupted_cross_fs_move_is_completed() {
+        let (_temp, maildir) = test_maildir();
+        let msg = maildir
+            .store(FOLDER_INBOX, b"Interrupted", &MessageFlags::default())
+            .unwrap();
+
+        let crashing = FakeFs {
+            cross_fs: true,
+            fail_remove: true,
+            ..Default::default()
+        };
+        assert!(
+            maildir
+                .move_with(&crashing, FOLDER_INBOX, &msg.id, FOLDER_TRASH)
+                .is_err()
+        );
+        // The message is never lost: both copies carry the same ID
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_some());
+        assert!(maildir.get(FOLDER_TRASH, &msg.id).unwrap().is_some());
+
+        let moved = maildir
+            .move_to(FOLDER_INBOX, &msg.id, FOLDER_TRASH)
+            .unwrap()
+            .unwrap();
+        assert_eq!(moved.read_content().unwrap(), "Interrupted");
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_none());
+        assert_eq!(maildir.list(FOLDER_TRASH).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_move_keeps_differing_destination_copy() {
+        let (_temp, maildir) = test_maildir();
+        let msg = maildir
+            .store(FOLDER_INBOX, b"Moved", &MessageFlags::default())
+            .unwrap();
+        maildir
+            .store_with_id(FOLDER_TRASH, b"Older", &MessageFlags::default(), &msg.id)
+            .unwrap();
+
+        let moved = maildir
+            .move_to(FOLDER_INBOX, &msg.id, FOLDER_TRASH)
+            .unwrap()
+            .unwrap();
+        assert_eq!(moved.read_content().unwrap(), "Moved");
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_none());
+
+        let mut contents: Vec<String> = maildir
+            .list(FOLDER_TRASH)
+            .unwrap()
+            .iter()
+            .map(|m| m.read_content().unwrap())
+            .collect();
+        contents.sort();
+        assert_eq!(contents, ["Moved", "Older"]);
+    }
+
+    #[test]
+    fn test_undelete_message() {
+        let (_temp, maildir) = test_maildir();
+
+        let msg = maildir
+            .store(FOLDER_INBOX, b"Oops", &MessageFlags::default())
+            .unwrap();
+        maildir
+            .move_to(FOLDER_INBOX, &msg.id, FOLDER_TRASH)
+            .unwrap();
+
+        let restored = maildir.undelete(&msg.id, FOLDER_INBOX).unwrap();
+        assert_eq!(restored.folder, FOLDER_INBOX);
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_some());
+        assert!(maildir.get(FOLDER_TRASH, &msg.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undelete_requires_trash() {
+        let (_temp, maildir) = test_maildir();
+
+        let msg = maildir
+            .store(FOLDER_INBOX, b"Not deleted", &MessageFlags::default())
+            .unwrap();
+        assert!(matches!(
+            maildir.undelete(&msg.id, FOLDER_INBOX),
+            Err(Error::IdNotFound(_))
+        ));
+        assert!(maildir.get(FOLDER_INBOX, &msg.id).unwrap().is_some());
+    }
+
     #[test]
     fn test_count_messages() {
         let (_temp, maildir) = test_maildir();
@@ -681,6 +1401,92 @@ mod tests {
         assert_eq!(cur_count, 1);
     }
 
+    #[test]
+    fn test_replace_content_keeps_id() {
+        let (_temp, maildir) = test_maildir();
+        let draft_flags = MessageFlags {
+            draft: true,
+            ..Default::default()
+        };
+        let original = maildir
+            .store(FOLDER_DRAFTS, b"Subject: v1\n\nFirst", &draft_flags)
+            .unwrap();
+
+        let replaced = maildir
+            .replace_content(
+                FOLDER_DRAFTS,
+                &original.id,
+                b"Subject: v2\n\nSecond",
+                &draft_flags,
+            )
+            .unwrap();
+        assert_eq!(replaced.id, original.id);
+        assert_eq!(replaced.read_content().unwrap(), "Subject: v2\n\nSecond");
+        assert_eq!(maildir.list(FOLDER_DRAFTS).unwrap().len(), 1);
+
+        // New flags move the file; the old one must not linger
+        let seen = MessageFlags {
+            draft: true,
+            seen: true,
+            ..Default::default()
+        };
+        let moved = maildir
+            .replace_content(FOLDER_DRAFTS, &original.id, b"v3", &seen)
+            .unwrap();
+        assert!(!original.path.exists());
+        assert_eq!(maildir.list(FOLDER_DRAFTS).unwrap().len(), 1);
+        let fetched = maildir.get(FOLDER_DRAFTS, &original.id).unwrap().unwrap();
+        assert_eq!(fetched.path, moved.path);
+        assert_eq!(fetched.read_content().unwrap(), "v3");
+
+        assert!(matches!(
+            maildir.replace_content(FOLDER_DRAFTS, "missing", b"x", &seen),
+            Err(Error::IdNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_folder_stats() {
+        let (_temp, maildir) = test_maildir();
+        assert_eq!(
+            maildir.folder_stats(FOLDER_INBOX).unwrap(),
+            FolderStats::default()
+        );
+
+        let flags = MessageFlags::default();
+        maildir.store(FOLDER_INBOX, b"New 1", &flags).unwrap();
+        maildir.store(FOLDER_INBOX, b"New 2", &flags).unwrap();
+        let seen_flags = MessageFlags {
+            seen: true,
+            ..Default::default()
+        };
+        maildir.store(FOLDER_INBOX, b"Read 1", &seen_flags).unwrap();
+        maildir.store(FOLDER_SENT, b"Sent", &seen_flags).unwrap();
+
+        let stats = maildir.folder_stats(FOLDER_INBOX).unwrap();
+        assert_eq!(
+            stats,
+            FolderStats {
+                total: 3,
+                unread: 2,
+                size: 16
+            }
+        );
+
+        // Other clients may leave unread mail in cur/; the flags decide
+        let cur = maildir.folder_path(FOLDER_INBOX).join(SUBDIR_CUR);
+        fs::write(cur.join("external:2,F"), b"Flagged").unwrap();
+        let stats = maildir.folder_stats(FOLDER_INBOX).unwrap();
+        assert_eq!(
+            stats,
+            FolderStats {
+                total: 4,
+                unread: 3,
+                size: 23
+            }
+        );
+    }
+
     #[test]
     fn test_list_folders() {
         let (_temp, maildir) = test_maildir();
@@ -712,4 +1518,166 @@ mod tests {
         let retrieved = maildir.get(FOLDER_INBOX, custom_id).unwrap().unwrap();
         assert_eq!(retrieved.id, custom_id);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_syncs_destination_dir() {
+        let (_temp, maildir) = test_maildir();
+
+        let msg = maildir
+            .store(FOLDER_INBOX, b"Durable", &MessageFlags::default())
+            .unwrap();
+        assert!(msg.path.exists());
+        sync_dir(&maildir.folder_path(FOLDER_INBOX).join(SUBDIR_NEW)).unwrap();
+
+        let tmp_entries = fs::read_dir(maildir.folder_path(FOLDER_INBOX).join(SUBDIR_TMP))
+            .unwrap()
+            .count();
+        assert_eq!(tmp_entries, 0);
+
+        let seen = MessageFlags {
+            seen: true,
+            ..MessageFlags::default()
+        };
+        let msg = maildir
+            .store_with_id(FOLDER_INBOX, b"Durable seen", &seen, "durable-id")
+            .unwrap();
+        assert!(msg.path.exists());
+        assert_eq!(msg.read_content().unwrap(), "Durable seen");
+    }
+
+    #[test]
+    fn test_store_draft_from_reader() {
+        let (_temp, maildir) = test_maildir();
+
+        let input = "---\nto: alice@example.com\ncc: bob@example.com\nsubject: Scripted\n---\n\nBody from a pipe";
+        let doc = ComposeDocument::from_reader(input.as_bytes()).unwrap();
+        let draft = maildir.store_draft(&doc).unwrap();
+        assert!(draft.flags.draft);
+        assert_eq!(draft.folder, FOLDER_DRAFTS);
+
+        let stored = ComposeDocument::parse(&draft.read_content().unwrap()).unwrap();
+        assert_eq!(stored.to, vec!["alice@example.com"]);
+        assert_eq!(stored.cc, vec!["bob@example.com"]);
+        assert_eq!(stored.subject, "Scripted");
+        assert_eq!(stored.body, "Body from a pipe");
+    }
+
+    #[test]
+    fn test_store_draft_rejects_invalid() {
+        let (_temp, maildir) = test_maildir();
+
+        let doc =
+            ComposeDocument::from_reader("---\nsubject: No recipients\n---\n".as_bytes()).unwrap();
+        assert!(maildir.store_draft(&doc).is_err());
+        assert!(maildir.list(FOLDER_DRAFTS).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_export_mbox_gzip_round_trip() {
+        use flate2::read::GzDecoder;
+
+        let (_temp, maildir) = test_maildir();
+        let first = b"From: alice@example.com\nSubject: First\n\nHello\nFrom here on\n";
+        let second = b"From: bob@example.com\nSubject: Second\n\nBye\n";
+        let flags = MessageFlags::default();
+        maildir
+            .store_with_id(FOLDER_INBOX, first, &flags, "msg-1")
+            .unwrap();
+        maildir
+            .store_with_id(FOLDER_INBOX, second, &flags, "msg-2")
+            .unwrap();
+
+        let mut compressed = Vec::new();
+        let mut updates = Vec::new();
+        let mut cb = |p: Progress| updates.push((p.current, p.total));
+        let count = maildir
+            .export_mbox_with_progress(FOLDER_INBOX, &mut compressed, true, Some(&mut cb))
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(updates, [(1, 2), (2, 2)]);
+        assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+
+        let mut mbox = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut mbox)
+            .unwrap();
+
+        let mut messages: Vec<Vec<&str>> = Vec::new();
+        for line in mbox.lines() {
+            if line.starts_with("From ") {
+                messages.push(Vec::new());
+            } else {
+                messages.last_mut().unwrap().push(line);
+            }
+        }
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains(&"Subject: First"));
+        assert!(messages[0].contains(&">From here on"));
+        assert!(messages[1].contains(&"Subject: Second"));
+    }
+
+    #[test]
+    fn test_purge_trash_only_removes_old_trash() {
+        use std::time::Duration;
+
+        let (_temp, maildir) = test_maildir();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86_400);
+        let flags = MessageFlags::default();
+
+        // Old by received_at, old by mtime only, and recent.
+        maildir
+            .store_with_id(FOLDER_TRASH, b"old", &flags, "t-old")
+            .unwrap();
+        let stale = maildir
+            .store_with_id(FOLDER_TRASH, b"stale", &flags, "t-stale")
+            .unwrap();
+        maildir
+            .store_with_id(FOLDER_TRASH, b"new", &flags, "t-new")
+            .unwrap();
+        File::options()
+            .write(true)
+            .open(&stale.path)
+            .unwrap()
+            .set_modified(now - day * 60)
+            .unwrap();
+        // Old messages outside the trash must survive.
+        let inbox = maildir
+            .store_with_id(FOLDER_INBOX, b"keep", &flags, "i-old")
+            .unwrap();
+        File::options()
+            .write(true)
+            .open(&inbox.path)
+            .unwrap()
+            .set_modified(now - day * 60)
+            .unwrap();
+
+        let received = |id: &str| match id {
+            "t-old" | "i-old" => Some(now - day * 45),
+            "t-new" => Some(now - day),
+            _ => None,
+        };
+        let cutoff = now - day * 30;
+
+        let expired: Vec<String> = maildir
+            .expired_trash(cutoff, received)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(expired, vec!["t-old", "t-stale"]);
+
+        let purged = maildir.purge_trash(cutoff, received).unwrap();
+        assert_eq!(purged, vec!["t-old", "t-stale"]);
+
+        let remaining: Vec<String> = maildir
+            .list(FOLDER_TRASH)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(remaining, vec!["t-new"]);
+        assert!(maildir.get(FOLDER_INBOX, "i-old").unwrap().is_some());
+    }
 }
@@ -1,7 +1,7 @@
 //! Path discovery and management for h8.
 
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::error::{Error, Result};
 
@@ -25,13 +25,22 @@ pub struct AppPaths {
 impl AppPaths {
     /// Discover application paths based on XDG conventions and CLI options.
     pub fn discover(cli_config: Option<PathBuf>) -> Result<Self> {
-        let global_config = default_config_dir()?.join("config.toml");
+        Self::discover_with_env(cli_config, |k| env::var(k).ok())
+    }
+
+    /// Like [`AppPaths::discover`], but looks up `XDG_*` variables through
+    /// `env`, which keeps discovery testable.
+    pub fn discover_with_env(
+        cli_config: Option<PathBuf>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let global_config = config_dir_with_env(&env)?.join("config.toml");
         let local_config = env::current_dir()
             .map_err(|e| Error::Path(format!("determining current directory: {e}")))?
             .join("config.toml");
         let cli_config = cli_config.map(expand_path).transpose()?;
-        let state_dir = default_state_dir()?;
-        let data_dir = default_data_dir()?;
+        let state_dir = state_dir_with_env(&env)?;
+        let data_dir = data_dir_with_env(&env)?;
 
         Ok(Self {
             global_config,
@@ -42,6 +51,18 @@ impl AppPaths {
         })
     }
 
+    /// The single config file that takes precedence: the CLI-specified file,
+    /// else the local `config.toml` if it exists, else the global one.
+    pub fn effective_config(&self) -> &Path {
+        if let Some(path) = &self.cli_config {
+            path
+        } else if self.local_config.exists() {
+            &self.local_config
+        } else {
+            &self.global_config
+        }
+    }
+
     /// Get the mail data directory for an account.
     pub fn mail_dir(&self, account: &str) -> PathBuf {
         self.data_dir.join("mail").join(account)
@@ -71,9 +92,28 @@ pub fn expand_str_path(text: &str) -> Result<PathBuf> {
 
 /// Get the default config directory following XDG conventions.
 pub fn default_config_dir() -> Result<PathBuf> {
-    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
-        let mut path = PathBuf::from(dir);
-        path.push(APP_NAME);
+    config_dir_with_env(|k| env::var(k).ok())
+}
+
+/// Get the default state directory following XDG conventions.
+pub fn default_state_dir() -> Result<PathBuf> {
+    state_dir_with_env(|k| env::var(k).ok())
+}
+
+/// Get the default data directory following XDG conventions.
+pub fn default_data_dir() -> Result<PathBuf> {
+    data_dir_with_env(|k| env::var(k).ok())
+}
+
+/// `$<var>/h8` when the XDG variable is set and non-empty.
+fn xdg_dir(env: impl Fn(&str) -> Option<String>, var: &str) -> Option<PathBuf> {
+    env(var)
+        .filter(|v| !v.is_empty())
+        .map(|dir| PathBuf::from(dir).join(APP_NAME))
+}
+
+fn config_dir_with_env(env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    if let Some(path) = xdg_dir(env, "XDG_CONFIG_HOME") {
         return Ok(path);
     }
     if let Some(mut dir) = dirs::config_dir() {
@@ -85,11 +125,8 @@ pub fn default_config_dir() -> Result<PathBuf> {
         .ok_or_else(|| Error::Path("unable to determine configuration directory".into()))
 }
 
-/// Get the default state directory following XDG conventions.
-pub fn default_state_dir() -> Result<PathBuf> {
-    if let Some(dir) = env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
-        let mut path = PathBuf::from(dir);
-        path.push(APP_NAME);
+fn state_dir_with_env(env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    if let Some(path) = xdg_dir(env, "XDG_STATE_HOME") {
         return Ok(path);
     }
     if let Some(mut dir) = dirs::state_dir() {
@@ -101,11 +138,8 @@ pub fn default_state_dir() -> Result<PathBuf> {
         .ok_or_else(|| Error::Path("unable to determine state directory".into()))
 }
 
-/// Get the default data directory following XDG conventions.
-pub fn default_data_dir() -> Result<PathBuf> {
-    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
-        let mut path = PathBuf::from(dir);
-        path.push(APP_NAME);
+fn data_dir_with_env(env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    if let Some(path) = xdg_dir(env, "XDG_DATA_HOME") {
         return Ok(path);
     }
     if let Some(mut dir) = dirs::data_dir() {
@@ -142,6 +176,43 @@ mod tests {
         assert!(db_path.ends_with("mail/test@example.com/.sync.db"));
     }
 
+    #[test]
+    fn test_discover_honors_xdg_env() {
+        let env = |key: &str| match key {
+            "XDG_CONFIG_HOME" => Some("/xdg/config".to_string()),
+            "XDG_STATE_HOME" => Some("/xdg/state".to_string()),
+            "XDG_DATA_HOME" => Some("/xdg/data".to_string()),
+            _ => None,
+        };
+        let paths = AppPaths::discover_with_env(Some(PathBuf::from("/tmp/h8.toml")), env).unwrap();
+        assert_eq!(
+            paths.global_config,
+            PathBuf::from("/xdg/config/h8/config.toml")
+        );
+        assert_eq!(paths.state_dir, PathBuf::from("/xdg/state/h8"));
+        assert_eq!(paths.data_dir, PathBuf::from("/xdg/data/h8"));
+        assert_eq!(paths.cli_config, Some(PathBuf::from("/tmp/h8.toml")));
+
+        // Empty variables fall back to the platform defaults
+        let empty = AppPaths::discover_with_env(None, |_| Some(String::new())).unwrap();
+        assert!(!empty.state_dir.starts_with("/xdg"));
+    }
+
+    #[test]
+    fn test_effective_config() {
+        let mut paths = AppPaths::discover_with_env(None, |_| None).unwrap();
+        paths.local_config = PathBuf::from("/nonexistent/h8/config.toml");
+        assert_eq!(paths.effective_config(), paths.global_config.as_path());
+
+        let dir = tempfile::tempdir().unwrap();
+        paths.local_config = dir.path().join("config.toml");
+        std::fs::write(&paths.local_config, "").unwrap();
+        assert_eq!(paths.effective_config(), paths.local_config.as_path());
+
+        paths.cli_config = Some(PathBuf::from("/tmp/h8.toml"));
+        assert_eq!(paths.effective_config(), Path::new("/tmp/h8.toml"));
+    }
+
     #[test]
     fn test_expand_path_tilde() {
         let expanded = expand_str_path("~/test").unwrap();
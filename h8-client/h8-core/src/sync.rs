@@ -0,0 +1,819 @@
+//! Mail synchronization between the service and local storage.
+//!
+//! [`sync_folder`] pulls message metadata for one folder, allocates
+//! human-readable IDs for new messages and records them in the sync
+//! database. Full message bodies are not fetched here; the report lists the
+//! messages whose bodies are missing from the Maildir so callers can fetch
+//! them with whatever concurrency suits them.
+//!
+//! Progress is checkpointed per message: the folder's sync cursor (the
+//! receive time and ID of the newest message handled) is saved as it
+//! advances, and messages that fail are recorded. An interrupted sync
+//! resumes after the cursor, and failed messages are retried on the next
+//! run whatever their position.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::db::Database;
+use crate::error::{Error, Result};
+use crate::id::IdGenerator;
+use crate::maildir::{FOLDER_DRAFTS, FOLDER_SENT, Maildir};
+use crate::service::ServiceClient;
+use crate::types::{AttachmentMeta, FolderSync, MessageSync, Progress, ProgressCallback};
+
+/// Number of messages requested per folder when no limit is given.
+pub const DEFAULT_SYNC_LIMIT: usize = 100;
+
+/// Line appended to a body cut by [`truncate_body`].
+pub const TRUNCATED_MARKER: &str = "[truncated]";
+
+/// Cut `body` to at most `max_bytes` bytes, on a character boundary, and
+/// append [`TRUNCATED_MARKER`] on its own line. Returns `None` when the body
+/// fits or `max_bytes` is 0 (unlimited).
+pub fn truncate_body(body: &str, max_bytes: usize) -> Option<String> {
+    if max_bytes == 0 || body.len() <= max_bytes {
+        return None;
+    }
+    let mut end = max_bytes;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    let kept = body[..end].trim_end_matches(['\r', '\n']);
+    Some(format!("{}\n\n{}\n", kept, TRUNCATED_MARKER))
+}
+
+/// Source of server-side message listings, implemented by [`ServiceClient`].
+pub trait MailSource {
+    /// List message metadata (no bodies) in a folder, newest first.
+    fn mail_list(&self, account: &str, folder: &str, limit: usize) -> Result<Value>;
+}
+
+impl MailSource for ServiceClient {
+    fn mail_list(&self, account: &str, folder: &str, limit: usize) -> Result<Value> {
+        ServiceClient::mail_list(self, account, folder, limit, false)
+    }
+}
+
+/// Options for [`sync_folder`].
+#[derive(Debug, Clone)]
+pub struct SyncOptions<'a> {
+    /// Account to sync.
+    pub account: &'a str,
+    /// Maximum number of messages to request from the server.
+    pub limit: usize,
+    /// Ignore messages received before this time.
+    pub cutoff: Option<DateTime<Utc>>,
+    /// Pass over messages behind the saved sync cursor. Off for a full sync.
+    pub resume: bool,
+}
+
+impl<'a> SyncOptions<'a> {
+    pub fn new(account: &'a str) -> Self {
+        Self {
+            account,
+            limit: DEFAULT_SYNC_LIMIT,
+            cutoff: None,
+            resume: true,
+        }
+    }
+}
+
+/// A synced message whose full content is not yet stored locally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyFetch {
+    pub local_id: String,
+    pub remote_id: String,
+    pub folder: String,
+}
+
+/// Outcome of syncing one folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Messages newly recorded in the sync database.
+    pub synced: usize,
+    /// Messages that were already known (including rebound duplicates).
+    pub skipped: usize,
+    /// Messages behind the sync cursor that an earlier run already handled.
+    pub resumed: usize,
+    /// Description of each message that could not be synced.
+    pub failed: Vec<String>,
+    /// Messages whose bodies are missing from the Maildir. Only filled when a
+    /// Maildir is passed to [`sync_folder`].
+    pub pending_bodies: Vec<BodyFetch>,
+}
+
+/// What happened to a single listed message.
+enum Outcome {
+    Synced,
+    Skipped,
+    Filtered,
+}
+
+/// Sync the metadata of one folder into `db`, oldest message first.
+///
+/// New messages get an ID from `id_gen` and are recorded with their thread
+/// headers; the sender is cached for address completion. A message already
+/// known by remote ID is skipped, and one with the same Message-ID, subject,
+/// sender, recipients and date as an entry in the same folder whose remote
+/// ID is no longer listed is rebound to its new remote ID instead of being
+/// duplicated. A failure on one message is recorded in the report and the
+/// database, and the rest of the folder is still synced; a failed listing is
+/// returned as an error. `progress` is called after each message.
+pub fn sync_folder(
+    client: &impl MailSource,
+    db: &Database,
+    maildir: Option<&Maildir>,
+    id_gen: &IdGenerator<'_>,
+    folder: &str,
+    opts: &SyncOptions<'_>,
+    mut progress: ProgressCallback<'_>,
+) -> Result<SyncReport> {
+    let listing = client.mail_list(opts.account, folder, opts.limit)?;
+    let messages = listing
+        .as_array()
+        .ok_or_else(|| Error::Service("expected array from server".to_string()))?;
+
+    let saved = db
+        .get_sync_state(folder)?
+        .and_then(|state| state.sync_token)
+        .and_then(|token| parse_cursor(&token));
+    let mut cursor = if opts.resume { saved } else { None };
+    let resume_from = cursor.clone();
+    let retry: HashSet<String> = db.sync_failures(folder)?.into_iter().collect();
+    let live: HashSet<&str> = messages
+        .iter()
+        .filter_map(|msg| str_field(msg, "item_id").or_else(|| str_field(msg, "id")))
+        .collect();
+
+    // Oldest first so the cursor only moves forward; undated messages first
+    let mut ordered: Vec<(Option<Cursor>, &Value)> = messages
+        .iter()
+        .map(|msg| (message_cursor(msg), msg))
+        .collect();
+    ordered.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut report = SyncReport::default();
+    let total = ordered.len();
+    for (done, (position, msg)) in ordered.into_iter().enumerate() {
+        let remote_id = position.as_ref().map(|(_, id)| id.as_str());
+        let behind_cursor = matches!((&position, &resume_from), (Some(p), Some(c)) if p <= c);
+        // Only pass over messages an earlier run actually recorded
+        let known = match remote_id {
+            Some(id) if behind_cursor && !retry.contains(id) => {
+                db.get_message_by_remote_id(id)?.is_some()
+            }
+            _ => false,
+        };
+        if known {
+            report.resumed += 1;
+            if let (Some(maildir), Some(id)) = (maildir, remote_id)
+                && let Some(fetch) = missing_body(db, maildir, folder, id)?
+            {
+                report.pending_bodies.push(fetch);
+            }
+        } else {
+            let mut handled = true;
+            match sync_message(db, maildir, id_gen, folder, opts, &live, msg, &mut report) {
+                Ok(outcome) => {
+                    match outcome {
+                        Outcome::Synced => report.synced += 1,
+                        Outcome::Skipped => report.skipped += 1,
+                        // Not handled: a later run with another cutoff must
+                        // still see it
+                        Outcome::Filtered => handled = false,
+                    }
+                    if let Some(id) = remote_id
+                        && retry.contains(id)
+                    {
+                        db.clear_sync_failure(folder, id)?;
+                    }
+                }
+                Err(e) => {
+                    let id = str_field(msg, "item_id")
+                        .or_else(|| str_field(msg, "id"))
+                        .filter(|id| !id.is_empty());
+                    if let Some(id) = id {
+                        db.record_sync_failure(folder, id, &e.to_string())?;
+                    }
+                    let label = id
+                        .or_else(|| str_field(msg, "subject"))
+                        .unwrap_or("(unknown)");
+                    report.failed.push(format!("{}: {}", label, e));
+                }
+            }
+
+            if handled && position.is_some() && position > cursor {
+                cursor = position;
+                if let Some((received, id)) = &cursor {
+                    db.upsert_sync_state(&FolderSync {
+                        folder: folder.to_string(),
+                        last_sync: Some(Utc::now().to_rfc3339()),
+                        sync_token: Some(format!("{} {}", received.to_rfc3339(), id)),
+                    })?;
+                }
+            }
+        }
+
+        if let Some(cb) = progress.as_mut() {
+            cb(Progress::new(
+                done + 1,
+                total,
+                format!("Syncing {}", folder),
+            ));
+        }
+    }
+    Ok(report)
+}
+
+/// Position of a message in sync order: receive time, then remote ID.
+type Cursor = (DateTime<Utc>, String);
+
+fn parse_cursor(token: &str) -> Option<Cursor> {
+    let (received, id) = token.split_once(' ')?;
+    let received = DateTime::parse_from_rfc3339(received).ok()?;
+    Some((received.with_timezone(&Utc), id.to_string()))
+}
+
+fn message_cursor(msg: &Value) -> Option<Cursor> {
+    let received = DateTime::parse_from_rfc3339(str_field(msg, "datetime_received")?).ok()?;
+    let id = str_field(msg, "item_id")
+        .or_else(|| str_field(msg, "id"))
+        .filter(|id| !id.is_empty())?;
+    Some((received.with_timezone(&Utc), id.to_string()))
+}
+
+/// The body fetch for a known message whose content is not in the Maildir.
+fn missing_body(
+    db: &Database,
+    maildir: &Maildir,
+    folder: &str,
+    remote_id: &str,
+) -> Result<Option<BodyFetch>> {
+    let Some(existing) = db.get_message_by_remote_id(remote_id)? else {
+        return Ok(None);
+    };
+    if maildir.get(folder, &existing.local_id)?.is_some() {
+        return Ok(None);
+    }
+    Ok(Some(BodyFetch {
+        local_id: existing.local_id,
+        remote_id: remote_id.to_string(),
+        folder: folder.to_string(),
+    }))
+}
+
+/// Hash of the listing fields that identify a message. Listings carry no
+/// body, so messages without a Message-ID get no hash: subject, sender and
+/// date alone do not tell two messages apart.
+fn listing_hash(msg: &Value) -> Option<String> {
+    let message_id = str_field(msg, "message_id").filter(|id| !id.trim().is_empty())?;
+    let list = |key: &str| {
+        msg.get(key)
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    };
+    let headers = format!(
+        "Message-ID: {}\nSubject: {}\nFrom: {}\nTo: {}\nCc: {}\nDate: {}\n\n",
+        message_id,
+        str_field(msg, "subject").unwrap_or(""),
+        str_field(msg, "from").unwrap_or(""),
+        list("to"),
+        list("cc"),
+        str_field(msg, "datetime_received").unwrap_or(""),
+    );
+    Some(MessageSync::compute_hash(headers.as_bytes()))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sync_message(
+    db: &Database,
+    maildir: Option<&Maildir>,
+    id_gen: &IdGenerator<'_>,
+    folder: &str,
+    opts: &SyncOptions<'_>,
+    live: &HashSet<&str>,
+    msg: &Value,
+    report: &mut SyncReport,
+) -> Result<Outcome> {
+    let date = str_field(msg, "datetime_received").unwrap_or("");
+    if let Some(cutoff) = opts.cutoff
+        && let Ok(received) = DateTime::parse_from_rfc3339(date)
+        && received.with_timezone(&Utc) < cutoff
+    {
+        return Ok(Outcome::Filtered);
+    }
+
+    let remote_id = str_field(msg, "item_id")
+        .or_else(|| str_field(msg, "id"))
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| Error::Service("message has no item id".to_string()))?;
+
+    let attachments = AttachmentMeta::from_listing(msg.get("attachments"));
+
+    // Skip if already synced, but still report a missing body
+    if let Some(existing) = db.get_message_by_remote_id(remote_id)? {
+        // Backfill attachment details for messages synced before they were kept
+        if existing.attachments.is_none()
+            && let Some(ref attachments) = attachments
+        {
+            db.set_message_attachments(&existing.local_id, attachments)?;
+        }
+        if let Some(maildir) = maildir
+            && let Some(fetch) = missing_body(db, maildir, folder, remote_id)?
+        {
+            report.pending_bodies.push(fetch);
+        }
+        return Ok(Outcome::Skipped);
+    }
+
+    let subject = str_field(msg, "subject").unwrap_or("(no subject)");
+    let from = str_field(msg, "from").unwrap_or("unknown");
+    let local_hash = listing_hash(msg);
+    let change_key = str_field(msg, "changekey").map(String::from);
+
+    // A copy saved locally when the message was sent, or the same message
+    // under a new remote ID (e.g. re-created on the server): rebind the
+    // existing entry instead of duplicating it, unless its own remote ID is
+    // still listed and so names a different message
+    let local_copy = match str_field(msg, "message_id") {
+        Some(message_id) => db.find_local_copy(folder, message_id)?,
+        None => None,
+    };
+    let rebind = match (local_copy, &local_hash) {
+        (Some(existing), _) => Some(existing),
+        (None, Some(hash)) => db.find_by_hash(hash)?.filter(|existing| {
+            existing.folder == folder && !live.contains(existing.remote_id.as_str())
+        }),
+        (None, None) => None,
+    };
+    if let Some(mut existing) = rebind {
+        db.reassign_id(&existing.local_id, remote_id)?;
+        existing.remote_id = remote_id.to_string();
+        existing.change_key = change_key;
+        existing.synced_at = Some(Utc::now().to_rfc3339());
+        existing.local_hash = local_hash;
+        existing.attachments = attachments;
+        db.upsert_message(&existing)?;
+        return Ok(Outcome::Skipped);
+    }
+
+    // Store metadata only; bodies are fetched on demand
+    let local_id = id_gen.allocate(remote_id)?;
+    let bool_field = |key: &str| msg.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+    db.upsert_message(&MessageSync {
+        local_id: local_id.clone(),
+        remote_id: remote_id.to_string(),
+        change_key,
+        folder: folder.to_string(),
+        subject: Some(subject.to_string()),
+        from_addr: Some(from.to_string()),
+        received_at: Some(date.to_string()),
+        is_read: bool_field("is_read"),
+        is_draft: folder == FOLDER_DRAFTS,
+        has_attachments: bool_field("has_attachments"),
+        synced_at: Some(Utc::now().to_rfc3339()),
+        local_hash,
+        size_bytes: None,
+        attachments,
+    })?;
+    db.index_message(&local_id, None)?;
+    db.set_thread_headers(
+        &local_id,
+        str_field(msg, "message_id"),
+        str_field(msg, "in_reply_to"),
+        str_field(msg, "references"),
+    )?;
+
+    // Cache email addresses for autocomplete
+    if let Some((email, name)) = parse_email_address(from) {
+        let _ = if folder == FOLDER_SENT {
+            db.record_sent_address(&email, name.as_deref())
+        } else {
+            db.record_received_address(&email, name.as_deref())
+        };
+    }
+
+    if maildir.is_some() {
+        report.pending_bodies.push(BodyFetch {
+            local_id,
+            remote_id: remote_id.to_string(),
+            folder: folder.to_string(),
+        });
+    }
+    Ok(Outcome::Synced)
+}
+
+/// Parse an email address string like "Name <email@x.com>" or "email@x.com".
+/// Returns (lowercased email, Option<name>).
+fn parse_email_address(s: &str) -> Option<(String, Option<String>)> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+
+    // Check for "Name <email>" format
+    if let Some(start) = s.find('<')
+        && let Some(end) = s.find('>')
+    {
+        let email = s[start + 1..end].trim().to_lowercase();
+        let name = s[..start].trim();
+        let name = if name.is_empty() {
+            None
+        } else {
+            Some(name.trim_matches('"').to_string())
+        };
+        if email.contains('@') {
+            return Some((email, name));
+        }
+    }
+
+    // Just an email address
+    if s.contains('@') {
+        return Some((s.to_lowercase(), None));
+    }
+
+    None
+}
+
+fn str_field<'v>(msg: &'v Value, key: &str) -> Option<&'v str> {
+    msg.get(key).and_then(|v| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use crate::id::WordLists;
+
+    /// Serves a fixed listing, or fails if none is set.
+    struct FakeSource(Option<Value>);
+
+    impl MailSource for FakeSource {
+        fn mail_list(&self, _account: &str, _folder: &str, _limit: usize) -> Result<Value> {
+            self.0
+                .clone()
+                .ok_or_else(|| Error::Service("connection refused".to_string()))
+        }
+    }
+
+    fn setup() -> (TempDir, Database) {
+        let temp = TempDir::new().unwrap();
+        let db = Database::open(&temp.path().join("sync.db")).unwrap();
+        let words = WordLists {
+            adjectives: vec!["cold".to_string(), "blue".to_string(), "red".to_string()],
+            nouns: vec!["lamp".to_string(), "frog".to_string()],
+        };
+        IdGenerator::new(&db).init_pool(&words).unwrap();
+        (temp, db)
+    }
+
+    fn listed(id: &str, subject: &str) -> Value {
+        json!({
+            "item_id": id,
+            "subject": subject,
+            "from": "Alice <Alice@Example.com>",
+            "datetime_received": "2026-03-01T09:00:00Z",
+            "is_read": true,
+        })
+    }
+
+    #[test]
+    fn test_sync_folder_skips_existing() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let opts = SyncOptions::new("me@example.com");
+        let source = FakeSource(Some(json!([listed("r1", "One"), listed("r2", "Two")])));
+
+        let first = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((first.synced, first.skipped), (2, 0));
+        assert!(first.failed.is_empty());
+        let stored = db.get_message_by_remote_id("r1").unwrap().unwrap();
+        assert_eq!(stored.subject.as_deref(), Some("One"));
+        assert!(stored.is_read);
+
+        let second = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((second.synced, second.resumed), (0, 2));
+
+        let mut full = opts.clone();
+        full.resume = false;
+        let third = sync_folder(&source, &db, None, &id_gen, "inbox", &full, None).unwrap();
+        assert_eq!((third.synced, third.skipped, third.resumed), (0, 2, 0));
+        let again = db.get_message_by_remote_id("r1").unwrap().unwrap();
+        assert_eq!(again.local_id, stored.local_id);
+    }
+
+    #[test]
+    fn test_sync_folder_rebinds_only_recreated_messages() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let mut opts = SyncOptions::new("me@example.com");
+        opts.resume = false;
+        let with_id = |id: &str, message_id: Option<&str>| {
+            let mut msg = listed(id, "Status");
+            if let Some(message_id) = message_id {
+                msg["message_id"] = json!(message_id);
+            }
+            msg
+        };
+
+        // Same subject, sender and date but different messages
+        let source = FakeSource(Some(json!([
+            with_id("r1", Some("<one@example.com>")),
+            with_id("r2", Some("<two@example.com>")),
+            with_id("r3", None),
+            with_id("r4", None),
+        ])));
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((report.synced, report.skipped), (4, 0));
+
+        // Re-created under a new remote ID while the old one is still
+        // listed: a second copy on the server, not a move
+        let source = FakeSource(Some(json!([
+            with_id("r1", Some("<one@example.com>")),
+            with_id("r1-copy", Some("<one@example.com>")),
+        ])));
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((report.synced, report.skipped), (1, 1));
+        assert!(db.get_message_by_remote_id("r1").unwrap().is_some());
+
+        // Once the old remote ID is gone, the entry is rebound
+        let local_id = db.get_message_by_remote_id("r2").unwrap().unwrap().local_id;
+        let source = FakeSource(Some(json!([with_id("r2-new", Some("<two@example.com>"))])));
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((report.synced, report.skipped), (0, 1));
+        let rebound = db.get_message_by_remote_id("r2-new").unwrap().unwrap();
+        assert_eq!(rebound.local_id, local_id);
+        assert!(db.get_message_by_remote_id("r2").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sync_folder_binds_local_sent_copy() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let opts = SyncOptions::new("me@example.com");
+        let local_id = id_gen.allocate("local:abc").unwrap();
+        db.upsert_message(&MessageSync {
+            local_id: local_id.clone(),
+            remote_id: "local:abc".to_string(),
+            folder: FOLDER_SENT.to_string(),
+            subject: Some("Status".to_string()),
+            from_addr: Some("me@example.com".to_string()),
+            is_read: true,
+            local_hash: Some("abc".to_string()),
+            size_bytes: Some(120),
+            ..Default::default()
+        })
+        .unwrap();
+        db.set_thread_headers(&local_id, Some("<sent@example.com>"), None, None)
+            .unwrap();
+
+        let mut sent = listed("r1", "Status");
+        sent["message_id"] = json!("<sent@example.com>");
+        let source = FakeSource(Some(json!([sent])));
+        let report = sync_folder(&source, &db, None, &id_gen, FOLDER_SENT, &opts, None).unwrap();
+        assert_eq!((report.synced, report.skipped), (0, 1));
+        let bound = db.get_message_by_remote_id("r1").unwrap().unwrap();
+        assert_eq!(bound.local_id, local_id);
+        assert_eq!(bound.size_bytes, Some(120));
+        assert_eq!(
+            db.get_remote_by_id(&local_id).unwrap().as_deref(),
+            Some("r1")
+        );
+        assert!(db.get_message_by_remote_id("local:abc").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sync_folder_stores_attachment_details() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let mut opts = SyncOptions::new("me@example.com");
+        opts.resume = false;
+
+        let source = FakeSource(Some(json!([listed("r1", "One")])));
+        sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!(
+            db.get_message_by_remote_id("r1")
+                .unwrap()
+                .unwrap()
+                .attachments,
+            None
+        );
+
+        // Details appearing in a later listing are backfilled
+        let mut msg = listed("r1", "One");
+        msg["attachments"] = json!([
+            {"name": "report.pdf", "content_type": "application/pdf", "size": 2048},
+            {"name": "notes.txt", "content_type": null, "size": null},
+        ]);
+        let source = FakeSource(Some(json!([msg])));
+        sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        let stored = db.get_message_by_remote_id("r1").unwrap().unwrap();
+        let attachments = stored.attachments.unwrap();
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].name, "report.pdf");
+        assert_eq!(
+            attachments[0].content_type.as_deref(),
+            Some("application/pdf")
+        );
+        assert_eq!(attachments[0].size, Some(2048));
+        assert_eq!(attachments[1].content_type, None);
+    }
+
+    #[test]
+    fn test_sync_folder_reports_pending_bodies() {
+        let (temp, db) = setup();
+        let maildir = Maildir::new(temp.path().join("mail"), "me@example.com").unwrap();
+        let id_gen = IdGenerator::new(&db);
+        let opts = SyncOptions::new("me@example.com");
+        let source = FakeSource(Some(json!([listed("r1", "One")])));
+
+        let report =
+            sync_folder(&source, &db, Some(&maildir), &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!(report.pending_bodies.len(), 1);
+        assert_eq!(report.pending_bodies[0].remote_id, "r1");
+
+        // Still pending on the next run while the body is missing
+        let report =
+            sync_folder(&source, &db, Some(&maildir), &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!(report.resumed, 1);
+        assert_eq!(report.pending_bodies.len(), 1);
+    }
+
+    #[test]
+    fn test_sync_folder_failures() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let opts = SyncOptions::new("me@example.com");
+
+        let down = FakeSource(None);
+        assert!(sync_folder(&down, &db, None, &id_gen, "inbox", &opts, None).is_err());
+
+        // A bad entry is reported without stopping the rest of the folder
+        let mut broken = listed("", "Broken");
+        broken.as_object_mut().unwrap().remove("item_id");
+        let source = FakeSource(Some(json!([broken, listed("r2", "Two")])));
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!(report.synced, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert!(
+            report.failed[0].starts_with("Broken:"),
+            "{:?}",
+            report.failed
+        );
+        assert!(db.get_message_by_remote_id("r2").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_truncate_body() {
+        assert_eq!(truncate_body("short", 0), None);
+        assert_eq!(truncate_body("short", 5), None);
+        assert_eq!(
+            truncate_body("line one\nline two\n", 9).as_deref(),
+            Some("line one\n\n[truncated]\n")
+        );
+        // Never splits a multi-byte character
+        assert_eq!(
+            truncate_body("aöb", 2).as_deref(),
+            Some("a\n\n[truncated]\n")
+        );
+    }
+
+    #[test]
+    fn test_sync_folder_cutoff() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let mut opts = SyncOptions::new("me@example.com");
+        opts.cutoff = Some("2026-04-01T00:00:00Z".parse().unwrap());
+        let source = FakeSource(Some(json!([listed("r1", "Old")])));
+
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!(report, SyncReport::default());
+        assert!(db.get_message_by_remote_id("r1").unwrap().is_none());
+    }
+
+    fn listed_at(id: &str, received: &str) -> Value {
+        let mut msg = listed(id, id);
+        msg["datetime_received"] = json!(received);
+        msg
+    }
+
+    #[test]
+    fn test_sync_folder_resumes_after_crash() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let opts = SyncOptions::new("me@example.com");
+        // Listed newest first, as the server does
+        let source = FakeSource(Some(json!([
+            listed_at("r4", "2026-03-04T09:00:00Z"),
+            listed_at("r3", "2026-03-03T09:00:00Z"),
+            listed_at("r2", "2026-03-02T09:00:00Z"),
+            listed_at("r1", "2026-03-01T09:00:00Z"),
+        ])));
+
+        // Simulate a crash once two messages have been handled
+        let crashed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut crash = |p: Progress| assert!(p.current < 2, "crash");
+            sync_folder(
+                &source,
+                &db,
+                None,
+                &id_gen,
+                "inbox",
+                &opts,
+                Some(&mut crash),
+            )
+        }));
+        assert!(crashed.is_err());
+        assert!(db.get_message_by_remote_id("r2").unwrap().is_some());
+        assert!(db.get_message_by_remote_id("r3").unwrap().is_none());
+
+        let mut seen = Vec::new();
+        let mut track = |p: Progress| seen.push(p.current);
+        let report = sync_folder(
+            &source,
+            &db,
+            None,
+            &id_gen,
+            "inbox",
+            &opts,
+            Some(&mut track),
+        )
+        .unwrap();
+        assert_eq!((report.synced, report.skipped, report.resumed), (2, 0, 2));
+        assert_eq!(seen, vec![1, 2, 3, 4]);
+        assert!(db.get_message_by_remote_id("r4").unwrap().is_some());
+
+        let state = db.get_sync_state("inbox").unwrap().unwrap();
+        assert_eq!(
+            state.sync_token.as_deref(),
+            Some("2026-03-04T09:00:00+00:00 r4")
+        );
+    }
+
+    #[test]
+    fn test_sync_folder_cutoff_and_unknown_messages_behind_cursor() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let mut opts = SyncOptions::new("me@example.com");
+        opts.cutoff = Some("2026-03-02T00:00:00Z".parse().unwrap());
+        let source = FakeSource(Some(json!([
+            listed_at("r1", "2026-03-01T09:00:00Z"),
+            listed_at("r2", "2026-03-02T09:00:00Z"),
+        ])));
+
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!(report.synced, 1);
+        assert!(db.get_message_by_remote_id("r1").unwrap().is_none());
+
+        // Without the cutoff, the filtered message behind the cursor syncs,
+        // as does one that appears behind it later
+        opts.cutoff = None;
+        let source = FakeSource(Some(json!([
+            listed_at("r0", "2026-02-28T09:00:00Z"),
+            listed_at("r1", "2026-03-01T09:00:00Z"),
+            listed_at("r2", "2026-03-02T09:00:00Z"),
+        ])));
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((report.synced, report.resumed), (2, 1));
+        assert!(db.get_message_by_remote_id("r0").unwrap().is_some());
+        assert!(db.get_message_by_remote_id("r1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sync_folder_retries_failed_messages() {
+        let (_temp, db) = setup();
+        let id_gen = IdGenerator::new(&db);
+        let opts = SyncOptions::new("me@example.com");
+        let first = FakeSource(Some(json!([listed_at("r2", "2026-03-02T09:00:00Z")])));
+        sync_folder(&first, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        db.record_sync_failure("inbox", "r1", "database is locked")
+            .unwrap();
+        let source = FakeSource(Some(json!([
+            listed_at("r2", "2026-03-02T09:00:00Z"),
+            listed_at("r1", "2026-03-01T09:00:00Z"),
+        ])));
+
+        // r1 is behind the cursor but failed last time, so it is retried
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &opts, None).unwrap();
+        assert_eq!((report.synced, report.resumed), (1, 1));
+        assert!(db.get_message_by_remote_id("r1").unwrap().is_some());
+        assert!(db.sync_failures("inbox").unwrap().is_empty());
+
+        // A full sync ignores the cursor
+        let mut full = opts.clone();
+        full.resume = false;
+        let report = sync_folder(&source, &db, None, &id_gen, "inbox", &full, None).unwrap();
+        assert_eq!((report.synced, report.skipped, report.resumed), (0, 2, 0));
+    }
+}
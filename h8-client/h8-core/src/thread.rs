@@ -0,0 +1,258 @@
+//! Conversation reconstruction from stored threading headers.
+
+use std::collections::HashMap;
+
+use crate::types::ThreadMessage;
+
+/// A message and the replies threaded under it.
+#[derive(Debug, Clone)]
+pub struct ThreadNode {
+    pub message: ThreadMessage,
+    pub replies: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    /// Whether this node or any reply below it is the given message.
+    pub fn contains(&self, local_id: &str) -> bool {
+        self.message.local_id == local_id || self.replies.iter().any(|r| r.contains(local_id))
+    }
+
+    /// Depth-first walk yielding each message with its nesting depth
+    /// (0 for this node).
+    pub fn flatten(&self) -> Vec<(usize, &ThreadMessage)> {
+        let mut out = Vec::new();
+        self.flatten_into(0, &mut out);
+        out
+    }
+
+    /// Number of messages in this node and all replies below it.
+    pub fn count(&self) -> usize {
+        1 + self.replies.iter().map(ThreadNode::count).sum::<usize>()
+    }
+
+    /// The most recently received message in this node or below it.
+    pub fn latest(&self) -> &ThreadMessage {
+        self.replies
+            .iter()
+            .map(ThreadNode::latest)
+            .fold(&self.message, |latest, candidate| {
+                if candidate.received_at > latest.received_at {
+                    candidate
+                } else {
+                    latest
+                }
+            })
+    }
+
+    fn flatten_into<'a>(&'a self, depth: usize, out: &mut Vec<(usize, &'a ThreadMessage)>) {
+        out.push((depth, &self.message));
+        for reply in &self.replies {
+            reply.flatten_into(depth + 1, out);
+        }
+    }
+}
+
+/// Build conversation trees from a set of messages.
+///
+/// A message is placed under the message named by its `In-Reply-To`, or
+/// failing that the closest ancestor from `References` that is present.
+/// Messages whose parent is unknown become roots. Roots and replies are
+/// ordered by date, oldest first. Duplicate Message-IDs (e.g. the same
+/// mail in two folders) are collapsed onto the first occurrence.
+pub fn build_thread_tree(messages: Vec<ThreadMessage>) -> Vec<ThreadNode> {
+    let mut by_message_id: HashMap<String, usize> = HashMap::new();
+    let mut nodes: Vec<ThreadMessage> = Vec::with_capacity(messages.len());
+    for message in messages {
+        if let Some(ref id) = message.message_id {
+            if by_message_id.contains_key(id) {
+                continue;
+            }
+            by_message_id.insert(id.clone(), nodes.len());
+        }
+        nodes.push(message);
+    }
+
+    let mut parents: Vec<Option<usize>> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            message
+                .in_reply_to
+                .iter()
+                .chain(message.references.iter().rev())
+                .filter_map(|id| by_message_id.get(id).copied())
+                .find(|&parent| parent != index)
+        })
+        .collect();
+
+    // Break reference cycles by promoting the message that closes the loop
+    for start in 0..nodes.len() {
+        let mut seen = vec![false; nodes.len()];
+        let mut current = start;
+        seen[current] = true;
+        while let Some(parent) = parents[current] {
+            if seen[parent] {
+                parents[current] = None;
+                break;
+            }
+            seen[parent] = true;
+            current = parent;
+        }
+    }
+
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut roots = Vec::new();
+    for (index, parent) in parents.iter().enumerate() {
+        match parent {
+            Some(parent) => children[*parent].push(index),
+            None => roots.push(index),
+        }
+    }
+
+    let by_date = |a: &usize, b: &usize| {
+        nodes[*a]
+            .received_at
+            .cmp(&nodes[*b].received_at)
+            .then(a.cmp(b))
+    };
+    roots.sort_by(by_date);
+    for list in &mut children {
+        list.sort_by(by_date);
+    }
+
+    fn assemble(index: usize, nodes: &[ThreadMessage], children: &[Vec<usize>]) -> ThreadNode {
+        ThreadNode {
+            message: nodes[index].clone(),
+            replies: children[index]
+                .iter()
+                .map(|&child| assemble(child, nodes, children))
+                .collect(),
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|root| assemble(root, &nodes, &children))
+        .collect()
+}
+
+/// Collapse messages to one entry per conversation: the latest message of
+/// each thread and the number of messages in it, newest thread first.
+pub fn latest_per_thread(messages: Vec<ThreadMessage>) -> Vec<(ThreadMessage, usize)> {
+    let mut rows: Vec<(ThreadMessage, usize)> = build_thread_tree(messages)
+        .iter()
+        .map(|tree| (tree.latest().clone(), tree.count()))
+        .collect();
+    rows.sort_by(|a, b| b.0.received_at.cmp(&a.0.received_at));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, date: &str, in_reply_to: Option<&str>, refs: &[&str]) -> ThreadMessage {
+        ThreadMessage {
+            local_id: id.to_string(),
+            folder: "inbox".to_string(),
+            subject: Some(format!("subject {id}")),
+            from_addr: None,
+            received_at: Some(date.to_string()),
+            message_id: Some(format!("<{id}@example.com>")),
+            in_reply_to: in_reply_to.map(|p| format!("<{p}@example.com>")),
+            references: refs.iter().map(|r| format!("<{r}@example.com>")).collect(),
+        }
+    }
+
+    fn shape(tree: &ThreadNode) -> Vec<(usize, String)> {
+        tree.flatten()
+            .into_iter()
+            .map(|(depth, m)| (depth, m.local_id.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_branching_thread() {
+        // root
+        // |- reply-b (earlier)
+        // |  `- nested
+        // `- reply-a (later)
+        let messages = vec![
+            message("reply-a", "2026-01-03T10:00:00Z", Some("root"), &["root"]),
+            message(
+                "nested",
+                "2026-01-04T10:00:00Z",
+                Some("reply-b"),
+                &["root", "reply-b"],
+            ),
+            message("root", "2026-01-01T10:00:00Z", None, &[]),
+            message("reply-b", "2026-01-02T10:00:00Z", Some("root"), &["root"]),
+        ];
+
+        let trees = build_thread_tree(messages);
+        assert_eq!(trees.len(), 1);
+        assert_eq!(
+            shape(&trees[0]),
+            [
+                (0, "root".to_string()),
+                (1, "reply-b".to_string()),
+                (2, "nested".to_string()),
+                (1, "reply-a".to_string()),
+            ]
+        );
+        assert!(trees[0].contains("nested"));
+        assert!(!trees[0].contains("other"));
+    }
+
+    #[test]
+    fn test_latest_per_thread_collapses_conversations() {
+        let messages = vec![
+            message("root", "2026-01-01T10:00:00Z", None, &[]),
+            message("reply", "2026-01-02T10:00:00Z", Some("root"), &["root"]),
+            message("single", "2026-01-04T10:00:00Z", None, &[]),
+            message("latest", "2026-01-03T10:00:00Z", Some("root"), &["root"]),
+        ];
+
+        let rows: Vec<(String, usize)> = latest_per_thread(messages)
+            .into_iter()
+            .map(|(m, count)| (m.local_id, count))
+            .collect();
+        assert_eq!(rows, [("single".to_string(), 1), ("latest".to_string(), 3)]);
+    }
+
+    #[test]
+    fn test_falls_back_to_references() {
+        // In-Reply-To names a message that was never synced; the closest
+        // known ancestor from References is used instead.
+        let messages = vec![
+            message("root", "2026-01-01T10:00:00Z", None, &[]),
+            message(
+                "late",
+                "2026-01-05T10:00:00Z",
+                Some("missing"),
+                &["root", "missing"],
+            ),
+        ];
+        let trees = build_thread_tree(messages);
+        assert_eq!(trees.len(), 1);
+        assert_eq!(
+            shape(&trees[0]),
+            [(0, "root".to_string()), (1, "late".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unrelated_messages_and_cycles_become_roots() {
+        let mut a = message("a", "2026-01-02T10:00:00Z", Some("b"), &[]);
+        let b = message("b", "2026-01-01T10:00:00Z", Some("a"), &[]);
+        let c = message("c", "2026-01-03T10:00:00Z", None, &[]);
+        let duplicate = message("a", "2026-01-09T10:00:00Z", None, &[]);
+        a.folder = "sent".to_string();
+
+        let trees = build_thread_tree(vec![a, b, c, duplicate]);
+        let total: usize = trees.iter().map(|t| t.flatten().len()).sum();
+        assert_eq!(total, 3);
+        assert_eq!(trees.len(), 2);
+        assert_eq!(trees[1].message.local_id, "c");
+    }
+}
@@ -1,23 +1,137 @@
 //! Common types used across h8.
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// Fetch format for mail export.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FetchFormat {
     Maildir,
-    Mbox,
+    /// Single mbox file, written as `mail.mbox.gz` when `gzip` is set.
+    Mbox {
+        gzip: bool,
+    },
+    /// JSON array of `{headers, body, attachments}` objects.
+    Json,
 }
 
 impl FetchFormat {
     pub fn as_str(&self) -> &'static str {
         match self {
             FetchFormat::Maildir => "maildir",
-            FetchFormat::Mbox => "mbox",
+            FetchFormat::Mbox { .. } => "mbox",
+            FetchFormat::Json => "json",
+        }
+    }
+
+    /// Whether the output should be gzip-compressed.
+    pub fn gzip(&self) -> bool {
+        matches!(self, FetchFormat::Mbox { gzip: true })
+    }
+}
+
+/// Result of probing the service for reachability.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HealthStatus {
+    /// Whether the service answered at the HTTP level at all.
+    pub reachable: bool,
+    /// Whether the service reported itself healthy.
+    pub healthy: bool,
+    /// HTTP status code of the probe response, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// Parsed body of the `/health` response, if it returned JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+    /// Connection or protocol error when the service was not reachable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A single failed item in a bulk operation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkFailure {
+    pub id: String,
+    pub error: String,
+}
+
+/// Per-ID outcome of a bulk operation (mark, move, delete, ...).
+///
+/// Operations run for every ID; failures are collected instead of aborting.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BulkResult {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<BulkFailure>,
+}
+
+impl BulkResult {
+    /// Run `op` for each ID and aggregate the outcomes.
+    pub fn run<F>(ids: &[String], mut op: F) -> Self
+    where
+        F: FnMut(&str) -> std::result::Result<(), String>,
+    {
+        let mut result = Self::default();
+        for id in ids {
+            match op(id) {
+                Ok(()) => result.succeed(id),
+                Err(error) => result.fail(id, error),
+            }
+        }
+        result
+    }
+
+    /// Record a successful ID.
+    pub fn succeed(&mut self, id: &str) {
+        self.succeeded.push(id.to_string());
+    }
+
+    /// Record a failed ID with its error.
+    pub fn fail(&mut self, id: &str, error: impl Into<String>) {
+        self.failed.push(BulkFailure {
+            id: id.to_string(),
+            error: error.into(),
+        });
+    }
+
+    /// Total number of processed IDs.
+    pub fn total(&self) -> usize {
+        self.succeeded.len() + self.failed.len()
+    }
+
+    /// Whether every ID succeeded.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Progress update emitted by long-running h8-core operations.
+///
+/// Frontends pass an `&mut dyn FnMut(Progress)` callback and render it
+/// however they like (CLI status line, TUI gauge, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of items processed so far.
+    pub current: usize,
+    /// Total number of items to process.
+    pub total: usize,
+    /// Short description of the current step.
+    pub message: String,
+}
+
+impl Progress {
+    /// Create a progress update.
+    pub fn new(current: usize, total: usize, message: impl Into<String>) -> Self {
+        Self {
+            current,
+            total,
+            message: message.into(),
         }
     }
 }
 
+/// Optional progress callback accepted by long-running operations.
+pub type ProgressCallback<'a> = Option<&'a mut dyn FnMut(Progress)>;
+
 /// Calendar event creation request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CalendarCreate {
@@ -43,6 +157,14 @@ pub struct SendEmail {
     pub html: bool,
 }
 
+/// Mail recipient with an optional display name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recipient {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub email: String,
+}
+
 /// Contact creation request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContactCreate {
@@ -72,6 +194,14 @@ pub struct FetchMail {
     pub format: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<usize>,
+    #[serde(default)]
+    pub gzip: bool,
+    /// Keep the output of an interrupted fetch instead of starting over.
+    #[serde(default)]
+    pub resume: bool,
+    /// Fetch keys of messages already written, skipped when resuming.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skip: Vec<String>,
 }
 
 /// Draft save request.
@@ -111,7 +241,7 @@ pub struct DraftUpdate {
 }
 
 /// Sync state for a message.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MessageSync {
     pub local_id: String,
     pub remote_id: String,
@@ -125,6 +255,87 @@ pub struct MessageSync {
     pub has_attachments: bool,
     pub synced_at: Option<String>,
     pub local_hash: Option<String>,
+    /// Size of the stored message in bytes, once its body is fetched.
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    /// Attachment details from the server listing; `None` when unknown.
+    #[serde(default)]
+    pub attachments: Option<Vec<AttachmentMeta>>,
+}
+
+/// Name, type and size of one attachment, cached with the message metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentMeta {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl AttachmentMeta {
+    /// Read the `attachments` array of a message listing. Returns `None`
+    /// when the listing has no (valid) attachment details.
+    pub fn from_listing(value: Option<&serde_json::Value>) -> Option<Vec<Self>> {
+        serde_json::from_value(value?.clone()).ok()
+    }
+}
+
+impl MessageSync {
+    /// Compute a stable content hash for deduplication.
+    ///
+    /// Headers are unfolded, lowercased by name, whitespace-collapsed and
+    /// sorted; body lines lose trailing whitespace and line endings are
+    /// normalized, so transport differences do not change the hash.
+    pub fn compute_hash(content: &[u8]) -> String {
+        let text = String::from_utf8_lossy(content).replace("\r\n", "\n");
+        let (head, body) = text.split_once("\n\n").unwrap_or((text.as_str(), ""));
+
+        let mut headers: Vec<String> = Vec::new();
+        for line in head.lines() {
+            if line.starts_with([' ', '\t'])
+                && let Some(last) = headers.last_mut()
+            {
+                last.push(' ');
+                last.push_str(line.trim());
+            } else if let Some((name, value)) = line.split_once(':') {
+                headers.push(format!(
+                    "{}: {}",
+                    name.trim().to_ascii_lowercase(),
+                    value.trim()
+                ));
+            }
+        }
+        let mut headers: Vec<String> = headers
+            .into_iter()
+            .map(|h| h.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        headers.sort();
+
+        let body: Vec<&str> = body.lines().map(str::trim_end).collect();
+        let body = body.join("\n");
+
+        let mut hasher = Sha256::new();
+        hasher.update(headers.join("\n").as_bytes());
+        hasher.update(b"\n\n");
+        hasher.update(body.trim_end().as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+/// Local read/flagged state of a message that still has to be pushed to the
+/// server. Only the fields changed locally are set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingFlags {
+    pub local_id: String,
+    pub remote_id: String,
+    pub folder: String,
+    pub is_read: Option<bool>,
+    pub is_flagged: Option<bool>,
 }
 
 /// Folder sync state.
@@ -135,6 +346,41 @@ pub struct FolderSync {
     pub sync_token: Option<String>,
 }
 
+/// Synced message count and receive date range of a folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderSummary {
+    pub folder: String,
+    pub messages: usize,
+    /// Earliest `received_at`, if any message has one.
+    pub oldest: Option<String>,
+    /// Latest `received_at`, if any message has one.
+    pub newest: Option<String>,
+}
+
+/// Server mail folder with item counts, as cached in the sync database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Folder {
+    pub name: String,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default)]
+    pub unread: u64,
+}
+
+/// Stored message with the headers needed to rebuild its conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMessage {
+    pub local_id: String,
+    pub folder: String,
+    pub subject: Option<String>,
+    pub from_addr: Option<String>,
+    pub received_at: Option<String>,
+    pub message_id: Option<String>,
+    pub in_reply_to: Option<String>,
+    /// Message-IDs from the `References` header, oldest first.
+    pub references: Vec<String>,
+}
+
 /// Sync state for a calendar event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CalendarEventSync {
@@ -160,14 +406,108 @@ pub struct AddressEntry {
     pub receive_count: i64,
 }
 
+/// Human-readable byte count using binary units.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_attachment_meta_serialization() {
+        let meta = AttachmentMeta {
+            name: "report.pdf".to_string(),
+            content_type: Some("application/pdf".to_string()),
+            size: Some(2048),
+        };
+        let json = serde_json::to_string(&meta).unwrap();
+        assert_eq!(serde_json::from_str::<AttachmentMeta>(&json).unwrap(), meta);
+
+        // Unknown type and size are omitted and read back as None
+        let bare = AttachmentMeta {
+            name: "notes.txt".to_string(),
+            content_type: None,
+            size: None,
+        };
+        let json = serde_json::to_string(&bare).unwrap();
+        assert_eq!(json, r#"{"name":"notes.txt"}"#);
+        assert_eq!(serde_json::from_str::<AttachmentMeta>(&json).unwrap(), bare);
+    }
+
+    #[test]
+    fn test_attachment_meta_from_listing() {
+        let listing = serde_json::json!([
+            {"name": "a.png", "content_type": "image/png", "size": 10},
+            {"name": "b.txt", "content_type": null, "size": null},
+        ]);
+        let parsed = AttachmentMeta::from_listing(Some(&listing)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].content_type.as_deref(), Some("image/png"));
+        assert_eq!(parsed[1].size, None);
+
+        assert_eq!(AttachmentMeta::from_listing(None), None);
+        let invalid = serde_json::json!([{"size": 10}]);
+        assert_eq!(AttachmentMeta::from_listing(Some(&invalid)), None);
+    }
+
     #[test]
     fn test_fetch_format_as_str() {
         assert_eq!(FetchFormat::Maildir.as_str(), "maildir");
-        assert_eq!(FetchFormat::Mbox.as_str(), "mbox");
+        assert_eq!(FetchFormat::Mbox { gzip: true }.as_str(), "mbox");
+        assert!(FetchFormat::Mbox { gzip: true }.gzip());
+        assert!(!FetchFormat::Mbox { gzip: false }.gzip());
+        assert_eq!(FetchFormat::Json.as_str(), "json");
+    }
+
+    #[test]
+    fn test_bulk_result_partial_failure() {
+        use crate::maildir::{FOLDER_INBOX, FOLDER_TRASH, Maildir, MessageFlags};
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let maildir = Maildir::new(temp.path().to_path_buf(), "test@example.com").unwrap();
+        let a = maildir
+            .store(FOLDER_INBOX, b"A", &MessageFlags::default())
+            .unwrap();
+        let b = maildir
+            .store(FOLDER_INBOX, b"B", &MessageFlags::default())
+            .unwrap();
+        let ids = vec![a.id.clone(), "missing-id".to_string(), b.id.clone()];
+
+        let result = BulkResult::run(&ids, |id| {
+            match maildir.move_to(FOLDER_INBOX, id, FOLDER_TRASH) {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err("message not found".to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        });
+
+        assert!(!result.is_success());
+        assert_eq!(result.total(), 3);
+        assert_eq!(result.succeeded, vec![a.id.clone(), b.id.clone()]);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].id, "missing-id");
+        // The failure did not stop later IDs from being processed.
+        assert!(maildir.get(FOLDER_TRASH, &b.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_bulk_result_all_succeed() {
+        let ids = vec!["a".to_string(), "b".to_string()];
+        let result = BulkResult::run(&ids, |_| Ok(()));
+        assert!(result.is_success());
+        assert_eq!(result.succeeded.len(), 2);
     }
 
     #[test]
@@ -198,4 +538,38 @@ mod tests {
         let json = serde_json::to_string(&draft).unwrap();
         assert!(json.contains("Draft"));
     }
+
+    #[test]
+    fn test_compute_hash_identical_content() {
+        let a = b"Subject: Hello\nFrom: a@example.com\n\nBody text\n";
+        let b = b"Subject: Hello\nFrom: a@example.com\n\nBody text\n";
+        let hash = MessageSync::compute_hash(a);
+        assert_eq!(hash, MessageSync::compute_hash(b));
+        assert_eq!(hash.len(), 64);
+    }
+
+    #[test]
+    fn test_compute_hash_normalizes() {
+        let lf = b"Subject: Hello\nFrom: a@example.com\n\nBody text\n";
+        let crlf = b"from:  a@example.com\r\nSUBJECT: Hello\r\n\r\nBody text  \r\n\r\n";
+        let folded = b"Subject:\n Hello\nFrom: a@example.com\n\nBody text";
+        let hash = MessageSync::compute_hash(lf);
+        assert_eq!(hash, MessageSync::compute_hash(crlf));
+        assert_eq!(hash, MessageSync::compute_hash(folded));
+    }
+
+    #[test]
+    fn test_compute_hash_differs_on_content() {
+        let a = b"Subject: Hello\n\nBody one";
+        let b = b"Subject: Hello\n\nBody two";
+        assert_ne!(MessageSync::compute_hash(a), MessageSync::compute_hash(b));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
 }
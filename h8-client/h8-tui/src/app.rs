@@ -2,7 +2,11 @@
 
 use std::collections::HashSet;
 
+use h8_core::compose::split_address_list;
 use h8_core::types::MessageSync;
+use h8_core::{ComposeBuilder, ComposeDocument};
+
+use crate::handlers::{Binding, KeyAction, Keymap};
 
 /// Application modes for the modal TUI system.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,6 +29,8 @@ pub enum AppMode {
     FolderSelect,
     /// Compose new email.
     Compose,
+    /// Attachment list of the selected email.
+    Attachments,
 }
 
 /// Search modes for filtering emails.
@@ -148,6 +154,99 @@ impl SortOption {
     }
 }
 
+/// Field of the compose form that receives input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComposeField {
+    #[default]
+    To,
+    Subject,
+    Body,
+}
+
+impl ComposeField {
+    pub const ALL: [ComposeField; 3] =
+        [ComposeField::To, ComposeField::Subject, ComposeField::Body];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ComposeField::To => "To",
+            ComposeField::Subject => "Subject",
+            ComposeField::Body => "Body",
+        }
+    }
+
+    /// The following field, wrapping from Body back to To.
+    pub fn next(self) -> Self {
+        match self {
+            ComposeField::To => ComposeField::Subject,
+            ComposeField::Subject => ComposeField::Body,
+            ComposeField::Body => ComposeField::To,
+        }
+    }
+}
+
+/// In-progress message in the compose form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComposeForm {
+    /// Comma-separated recipients.
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    /// Field receiving input.
+    pub focus: ComposeField,
+}
+
+impl ComposeForm {
+    /// Text of a field.
+    pub fn field(&self, field: ComposeField) -> &str {
+        match field {
+            ComposeField::To => &self.to,
+            ComposeField::Subject => &self.subject,
+            ComposeField::Body => &self.body,
+        }
+    }
+
+    fn focused_mut(&mut self) -> &mut String {
+        match self.focus {
+            ComposeField::To => &mut self.to,
+            ComposeField::Subject => &mut self.subject,
+            ComposeField::Body => &mut self.body,
+        }
+    }
+
+    /// Move focus to the next field.
+    pub fn next_field(&mut self) {
+        self.focus = self.focus.next();
+    }
+
+    /// Type a character into the focused field.
+    pub fn input(&mut self, c: char) {
+        self.focused_mut().push(c);
+    }
+
+    /// Delete the last character of the focused field.
+    pub fn backspace(&mut self) {
+        self.focused_mut().pop();
+    }
+
+    /// Enter starts a new line in the body and moves on from single-line fields.
+    pub fn newline(&mut self) {
+        match self.focus {
+            ComposeField::Body => self.body.push('\n'),
+            _ => self.next_field(),
+        }
+    }
+
+    /// Build a compose document from the entered fields.
+    pub fn to_document(&self) -> ComposeDocument {
+        ComposeBuilder::new()
+            .to(split_address_list(&self.to))
+            .subject(self.subject.trim())
+            .body(&self.body)
+            .build()
+    }
+}
+
 /// Which pane is currently focused.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum FocusedPane {
@@ -319,6 +418,17 @@ impl FolderInfo {
     }
 }
 
+/// An attachment of an email, as listed by the service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentInfo {
+    /// Position in the message, used to download it.
+    pub index: usize,
+    pub name: String,
+    /// Size in bytes, when the server reports it.
+    pub size: Option<u64>,
+    pub content_type: String,
+}
+
 /// Pending data operation to be executed by main loop.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -333,6 +443,14 @@ pub enum PendingAction {
     LoadFolder(String),
     /// Open email in viewer.
     ViewEmail(String),
+    /// Reload the current folder in the background.
+    Refresh,
+    /// Save the compose form as a draft.
+    SaveDraft,
+    /// Fetch the attachment list of an email.
+    LoadAttachments(String),
+    /// Download an attachment (by index) of the listed email.
+    DownloadAttachment(usize),
 }
 
 impl Default for PendingAction {
@@ -386,6 +504,17 @@ pub struct App {
 
     /// Pending action to be executed by main loop.
     pub pending_action: PendingAction,
+
+    /// Normal-mode key bindings.
+    pub keymap: Keymap,
+
+    /// Compose form state.
+    pub compose: ComposeForm,
+
+    /// Attachments of the listed email (local_id -> attachments).
+    pub attachments: Option<(String, Vec<AttachmentInfo>)>,
+    /// Attachment list selection index.
+    pub attachment_selection: usize,
 }
 
 impl Default for App {
@@ -422,6 +551,18 @@ impl App {
             status_message: None,
             g_prefix: false,
             pending_action: PendingAction::None,
+            keymap: Keymap::default(),
+            compose: ComposeForm::default(),
+            attachments: None,
+            attachment_selection: 0,
+        }
+    }
+
+    /// Normal-mode action bound to a key, if any.
+    pub fn binding_for(&self, action: &KeyAction) -> Option<Binding> {
+        match action {
+            KeyAction::Char(c) => self.keymap.lookup(*c),
+            _ => None,
         }
     }
 
@@ -547,13 +688,62 @@ impl App {
     /// Enter compose mode.
     pub fn enter_compose(&mut self) {
         self.mode = AppMode::Compose;
+        self.compose = ComposeForm::default();
         self.set_status("Composing new email...");
     }
 
+    /// Show the attachment list for an email.
+    pub fn open_attachments(&mut self, local_id: &str, attachments: Vec<AttachmentInfo>) {
+        if attachments.is_empty() {
+            self.set_status("No attachments");
+            return;
+        }
+        self.attachments = Some((local_id.to_string(), attachments));
+        self.attachment_selection = 0;
+        self.mode = AppMode::Attachments;
+    }
+
+    /// Attachment under the cursor in the attachment list.
+    pub fn selected_attachment(&self) -> Option<&AttachmentInfo> {
+        self.attachments
+            .as_ref()
+            .and_then(|(_, list)| list.get(self.attachment_selection))
+    }
+
     /// Get indices of emails to operate on (selected or current).
     pub fn get_operation_indices(&self) -> Vec<usize> {
         self.email_selection.get_operation_indices()
     }
+
+    /// Replace the email list, keeping cursor and selections on the same
+    /// messages (matched by local_id) where they still exist.
+    pub fn replace_emails(&mut self, emails: Vec<MessageSync>) {
+        let cursor_id = self.current_email().map(|e| e.local_id.clone());
+        let selected_ids: HashSet<String> = self
+            .email_selection
+            .selected_indices
+            .iter()
+            .filter_map(|&i| self.emails.get(i).map(|e| e.local_id.clone()))
+            .collect();
+
+        let old_index = self.email_selection.index;
+        let old_row = old_index.saturating_sub(self.email_selection.offset);
+        self.emails = emails;
+
+        let selection = &mut self.email_selection;
+        selection.selected_indices = self
+            .emails
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| selected_ids.contains(&e.local_id))
+            .map(|(i, _)| i)
+            .collect();
+        selection.index = cursor_id
+            .and_then(|id| self.emails.iter().position(|e| e.local_id == id))
+            .unwrap_or_else(|| old_index.min(self.emails.len().saturating_sub(1)));
+        // Keep the cursor on the same screen row where possible.
+        selection.offset = selection.index.saturating_sub(old_row);
+    }
 }
 
 #[cfg(test)]
@@ -872,16 +1062,10 @@ mod tests {
             app.emails.push(MessageSync {
                 local_id: "1".to_string(),
                 remote_id: "r1".to_string(),
-                change_key: None,
                 folder: "inbox".to_string(),
                 subject: Some("Test".to_string()),
                 from_addr: Some("test@example.com".to_string()),
-                received_at: None,
-                is_read: false,
-                is_draft: false,
-                has_attachments: false,
-                synced_at: None,
-                local_hash: None,
+                ..Default::default()
             });
 
             // No explicit selection = 1 (current)
@@ -952,16 +1136,9 @@ mod tests {
             app.emails.push(MessageSync {
                 local_id: "1".to_string(),
                 remote_id: "r1".to_string(),
-                change_key: None,
                 folder: "inbox".to_string(),
                 subject: Some("Test".to_string()),
-                from_addr: None,
-                received_at: None,
-                is_read: false,
-                is_draft: false,
-                has_attachments: false,
-                synced_at: None,
-                local_hash: None,
+                ..Default::default()
             });
 
             // Single delete
@@ -1016,16 +1193,9 @@ mod tests {
             app.emails.push(MessageSync {
                 local_id: "1".to_string(),
                 remote_id: "r1".to_string(),
-                change_key: None,
                 folder: "inbox".to_string(),
                 subject: Some("Test Email".to_string()),
-                from_addr: None,
-                received_at: None,
-                is_read: false,
-                is_draft: false,
-                has_attachments: false,
-                synced_at: None,
-                local_hash: None,
+                ..Default::default()
             });
 
             let email = app.current_email().unwrap();
@@ -1041,6 +1211,44 @@ mod tests {
             assert_eq!(app.search_mode, SearchMode::From);
             assert!(matches!(app.mode, AppMode::Search(SearchMode::From)));
         }
+
+        fn email(id: &str) -> MessageSync {
+            MessageSync {
+                local_id: id.to_string(),
+                remote_id: format!("r{id}"),
+                folder: "inbox".to_string(),
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_app_replace_emails_preserves_selection_by_id() {
+            let mut app = App::new();
+            app.emails = ["a", "b", "c", "d"].iter().map(|id| email(id)).collect();
+            app.email_selection.index = 2;
+            app.email_selection.selected_indices = [1, 3].into_iter().collect();
+
+            // New mail arrives at the top and "d" disappears.
+            app.replace_emails(["new", "a", "b", "c"].iter().map(|id| email(id)).collect());
+
+            assert_eq!(app.current_email().unwrap().local_id, "c");
+            assert_eq!(app.email_selection.get_selected_indices(), vec![2]);
+        }
+
+        #[test]
+        fn test_app_replace_emails_clamps_missing_cursor() {
+            let mut app = App::new();
+            app.emails = ["a", "b", "c"].iter().map(|id| email(id)).collect();
+            app.email_selection.index = 2;
+
+            app.replace_emails(vec![email("a")]);
+            assert_eq!(app.email_selection.index, 0);
+            assert!(!app.email_selection.has_selections());
+
+            app.replace_emails(Vec::new());
+            assert_eq!(app.email_selection.index, 0);
+            assert!(app.current_email().is_none());
+        }
     }
 
     // FolderInfo tests
@@ -1056,4 +1264,100 @@ mod tests {
             assert_eq!(folder.total_count, 0);
         }
     }
+
+    // Compose form tests
+    mod compose_form_tests {
+        use super::*;
+
+        #[test]
+        fn test_focus_cycles_through_fields() {
+            let mut form = ComposeForm::default();
+            assert_eq!(form.focus, ComposeField::To);
+            form.next_field();
+            assert_eq!(form.focus, ComposeField::Subject);
+            form.next_field();
+            assert_eq!(form.focus, ComposeField::Body);
+            form.next_field();
+            assert_eq!(form.focus, ComposeField::To);
+        }
+
+        #[test]
+        fn test_input_goes_to_focused_field() {
+            let mut form = ComposeForm::default();
+            "ab".chars().for_each(|c| form.input(c));
+            form.backspace();
+            form.newline(); // Enter leaves single-line fields
+            "Hi".chars().for_each(|c| form.input(c));
+            form.next_field();
+            form.input('x');
+            form.newline(); // but starts a new line in the body
+            form.input('y');
+
+            assert_eq!(form.to, "a");
+            assert_eq!(form.subject, "Hi");
+            assert_eq!(form.body, "x\ny");
+        }
+
+        #[test]
+        fn test_to_document() {
+            let form = ComposeForm {
+                to: "jane@example.com, Bob <bob@example.com>".to_string(),
+                subject: " Lunch? ".to_string(),
+                body: "Noon at the usual place.".to_string(),
+                focus: ComposeField::Body,
+            };
+            let doc = form.to_document();
+            assert_eq!(doc.to, ["jane@example.com", "Bob <bob@example.com>"]);
+            assert_eq!(doc.subject, "Lunch?");
+            assert_eq!(doc.body, "Noon at the usual place.");
+            assert!(doc.validate().is_ok());
+
+            assert!(ComposeForm::default().to_document().validate().is_err());
+        }
+
+        #[test]
+        fn test_enter_compose_resets_form() {
+            let mut app = App::new();
+            app.compose.subject = "old".to_string();
+            app.enter_compose();
+            assert_eq!(app.mode, AppMode::Compose);
+            assert_eq!(app.compose, ComposeForm::default());
+        }
+    }
+
+    mod attachment_tests {
+        use super::*;
+
+        fn attachment(index: usize, name: &str) -> AttachmentInfo {
+            AttachmentInfo {
+                index,
+                name: name.to_string(),
+                size: Some(1024),
+                content_type: "application/pdf".to_string(),
+            }
+        }
+
+        #[test]
+        fn test_open_attachments() {
+            let mut app = App::new();
+            app.attachment_selection = 3;
+            app.open_attachments(
+                "brave-owl",
+                vec![attachment(0, "a.pdf"), attachment(1, "b.pdf")],
+            );
+            assert_eq!(app.mode, AppMode::Attachments);
+            assert_eq!(app.attachment_selection, 0);
+            assert_eq!(app.selected_attachment().unwrap().name, "a.pdf");
+            assert_eq!(app.attachments.as_ref().unwrap().0, "brave-owl");
+        }
+
+        #[test]
+        fn test_open_empty_attachments_stays_normal() {
+            let mut app = App::new();
+            app.open_attachments("brave-owl", Vec::new());
+            assert_eq!(app.mode, AppMode::Normal);
+            assert!(app.attachments.is_none());
+            assert_eq!(app.status_message.as_deref(), Some("No attachments"));
+        }
+    }
 }
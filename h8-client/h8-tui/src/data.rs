@@ -3,12 +3,21 @@
 //! Provides email loading from Maildir and sync database,
 //! with server synchronization via h8-service.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use h8_core::sync::{SyncOptions, SyncReport};
 use h8_core::types::MessageSync;
-use h8_core::{AppConfig, AppPaths, Database, Maildir, ServiceClient};
+use h8_core::{
+    AppConfig, AppPaths, ComposeDocument, Database, Folder, IdGenerator, Maildir, ServiceClient,
+};
 
-use crate::app::FolderInfo;
+use serde_json::Value;
+
+use crate::app::{AttachmentInfo, FolderInfo};
+
+/// Emails loaded per folder when `[mail.limits]` does not configure one.
+pub const EMAIL_LIMIT: usize = 500;
 
 /// Result type for data operations.
 pub type Result<T> = std::result::Result<T, DataError>;
@@ -25,6 +34,8 @@ pub enum DataError {
     DatabaseNotFound(PathBuf),
     /// Maildir not found.
     MaildirNotFound(PathBuf),
+    /// Message not in the sync database.
+    MessageNotFound(String),
     /// Generic I/O error.
     Io(std::io::Error),
 }
@@ -36,6 +47,7 @@ impl std::fmt::Display for DataError {
             DataError::NoAccount => write!(f, "No account configured"),
             DataError::DatabaseNotFound(p) => write!(f, "Database not found: {}", p.display()),
             DataError::MaildirNotFound(p) => write!(f, "Maildir not found: {}", p.display()),
+            DataError::MessageNotFound(id) => write!(f, "Message not found: {}", id),
             DataError::Io(e) => write!(f, "I/O error: {}", e),
         }
     }
@@ -177,7 +189,7 @@ impl DataSource {
             std::fs::create_dir_all(&mail_dir)?;
             let maildir = Maildir::new(mail_dir, account)?;
             // Initialize if needed
-            maildir.init()?;
+            maildir.init_with(&self.config.mail.local_folders)?;
             self.maildir = Some(maildir);
         }
         Ok(self.maildir.as_ref().unwrap())
@@ -186,12 +198,23 @@ impl DataSource {
     /// Get or initialize the service client.
     fn get_service_client(&mut self) -> Result<&ServiceClient> {
         if self.service_client.is_none() {
-            let client = ServiceClient::new(&self.config.service_url, None)?;
+            let client = ServiceClient::new(&self.config.service_url, None)?
+                .with_offline(self.config.offline);
             self.service_client = Some(client);
         }
         Ok(self.service_client.as_ref().unwrap())
     }
 
+    /// Number of emails to load for a folder: its `[mail.limits]` entry,
+    /// or [`EMAIL_LIMIT`] when none is configured.
+    pub fn email_limit(&self, folder: &str) -> usize {
+        self.config
+            .mail
+            .limits
+            .configured(folder)
+            .unwrap_or(EMAIL_LIMIT)
+    }
+
     /// Load emails from the sync database for a folder.
     pub fn load_emails(&mut self, folder: &str, limit: usize) -> Result<Vec<MessageSync>> {
         let db = self.get_db()?;
@@ -216,6 +239,21 @@ impl DataSource {
             });
         }
 
+        // Server folders that have not been synced yet still get listed
+        for server in self.server_folders() {
+            if !folders
+                .iter()
+                .any(|f| f.name.eq_ignore_ascii_case(&server.name))
+            {
+                folders.push(FolderInfo {
+                    display_name: folder_display_name(&server.name),
+                    name: server.name,
+                    unread_count: server.unread as usize,
+                    total_count: server.total as usize,
+                });
+            }
+        }
+
         // Ensure standard folders are first in consistent order
         let order = ["inbox", "sent", "drafts", "trash", "archive"];
         folders.sort_by(|a, b| {
@@ -227,6 +265,46 @@ impl DataSource {
         Ok(folders)
     }
 
+    /// Server folder list from the sync database cache, however old.
+    /// Empty when nothing is cached; see [`Self::refresh_server_folders`].
+    fn server_folders(&mut self) -> Vec<Folder> {
+        let Ok(db) = self.get_db() else {
+            return Vec::new();
+        };
+        db.cached_folders(Duration::MAX)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Re-query the server folder list through the service when the cached
+    /// one is older than `mail.folder_cache_ttl_secs`.
+    pub fn refresh_server_folders(&mut self) -> Result<()> {
+        let account = self.account.clone().ok_or(DataError::NoAccount)?;
+        self.get_db()?;
+        self.get_service_client()?;
+        let (Some(db), Some(client)) = (&self.db, &self.service_client) else {
+            return Err(DataError::NoAccount);
+        };
+
+        let ttl = Duration::from_secs(self.config.mail.folder_cache_ttl_secs);
+        db.folders_or_fetch(ttl, false, || client.mail_folders(&account))?;
+        Ok(())
+    }
+
+    /// Validate a composed message and store it in the drafts folder,
+    /// appending the configured signature. Returns the draft ID.
+    pub fn save_draft(&mut self, doc: &ComposeDocument) -> Result<String> {
+        let mut doc = doc.clone();
+        if !self.config.mail.signature.is_empty() {
+            doc.add_signature_with(&self.config.mail.signature, &self.config.mail.compose);
+        }
+        let compose = self.config.mail.compose.clone();
+        let maildir = self.get_maildir()?;
+        let draft = maildir.store_draft_with(&doc, &compose)?;
+        Ok(draft.id)
+    }
+
     /// Get a single email by local ID.
     #[allow(dead_code)]
     pub fn get_email(&mut self, local_id: &str) -> Result<Option<MessageSync>> {
@@ -359,10 +437,7 @@ impl DataSource {
         // Update folder in database
         let db = self.get_db()?;
         for id in local_ids {
-            if let Some(mut msg) = db.get_message(id)? {
-                msg.folder = "trash".to_string();
-                db.upsert_message(&msg)?;
-            }
+            db.update_folder(id, "trash")?;
         }
 
         log::info!(
@@ -440,10 +515,7 @@ impl DataSource {
         // Update folder in database
         let db = self.get_db()?;
         for id in local_ids {
-            if let Some(mut msg) = db.get_message(id)? {
-                msg.folder = target_folder.to_string();
-                db.upsert_message(&msg)?;
-            }
+            db.update_folder(id, target_folder)?;
         }
 
         log::info!(
@@ -457,6 +529,68 @@ impl DataSource {
         Ok(moved)
     }
 
+    /// Pull new message metadata for a folder from the server into the
+    /// sync database.
+    pub fn sync_folder(&mut self, folder: &str) -> Result<SyncReport> {
+        let account = self.account.clone().ok_or(DataError::NoAccount)?;
+        self.get_db()?;
+        self.get_service_client()?;
+        let (Some(db), Some(client)) = (&self.db, &self.service_client) else {
+            return Err(DataError::NoAccount);
+        };
+
+        let id_gen = IdGenerator::new(db);
+        if id_gen.stats()?.total() == 0 {
+            id_gen.init_pool(&self.config.ids.word_lists()?)?;
+        }
+        let mut opts = SyncOptions::new(&account);
+        opts.limit = self.config.mail.limits.for_folder(folder);
+        Ok(h8_core::sync::sync_folder(
+            client, db, None, &id_gen, folder, &opts, None,
+        )?)
+    }
+
+    /// Server ID of an email, looked up in the sync database.
+    fn remote_id(&mut self, local_id: &str) -> Result<String> {
+        let db = self.get_db()?;
+        db.get_message(local_id)?
+            .map(|msg| msg.remote_id)
+            .ok_or_else(|| DataError::MessageNotFound(local_id.to_string()))
+    }
+
+    /// List the attachments of an email through the service.
+    pub fn load_attachments(
+        &mut self,
+        folder: &str,
+        local_id: &str,
+    ) -> Result<Vec<AttachmentInfo>> {
+        let account = self.account.clone().ok_or(DataError::NoAccount)?;
+        let remote_id = self.remote_id(local_id)?;
+        let client = self.get_service_client()?;
+        let response = client.mail_attachments_list(&account, folder, &remote_id)?;
+        Ok(attachments_from_response(&response))
+    }
+
+    /// Download an attachment of an email into `dir`, returning the path
+    /// the service saved it to.
+    pub fn download_attachment(
+        &mut self,
+        folder: &str,
+        local_id: &str,
+        index: usize,
+        dir: &Path,
+    ) -> Result<String> {
+        let account = self.account.clone().ok_or(DataError::NoAccount)?;
+        let remote_id = self.remote_id(local_id)?;
+        let client = self.get_service_client()?;
+        let result = client.mail_attachment_download(&account, folder, &remote_id, index, dir)?;
+        Ok(result
+            .get("path")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .unwrap_or_else(|| dir.display().to_string()))
+    }
+
     /// Mark emails as read.
     pub fn mark_read(&mut self, folder: &str, local_ids: &[&str]) -> Result<usize> {
         let maildir = self.get_maildir()?;
@@ -472,13 +606,10 @@ impl DataSource {
             }
         }
 
-        // Update in database
+        // Update in database; the next `h8 mail sync` pushes the change
         let db = self.get_db()?;
         for id in local_ids {
-            if let Some(mut msg) = db.get_message(id)? {
-                msg.is_read = true;
-                db.upsert_message(&msg)?;
-            }
+            db.update_message_flags(id, Some(true), None, true)?;
         }
 
         Ok(marked)
@@ -499,13 +630,10 @@ impl DataSource {
             }
         }
 
-        // Update in database
+        // Update in database; the next `h8 mail sync` pushes the change
         let db = self.get_db()?;
         for id in local_ids {
-            if let Some(mut msg) = db.get_message(id)? {
-                msg.is_read = false;
-                db.upsert_message(&msg)?;
-            }
+            db.update_message_flags(id, Some(false), None, true)?;
         }
 
         Ok(marked)
@@ -571,6 +699,40 @@ pub enum SearchMode {
     All,
 }
 
+/// Build the attachment list from a `/mail/{id}/attachments` response.
+///
+/// Entries without an index take their position; anything that is not a
+/// list yields no attachments.
+fn attachments_from_response(response: &Value) -> Vec<AttachmentInfo> {
+    let Some(items) = response.as_array() else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let index = item
+                .get("index")
+                .and_then(Value::as_u64)
+                .map_or(i, |n| n as usize);
+            AttachmentInfo {
+                index,
+                name: item
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .filter(|n| !n.is_empty())
+                    .map_or_else(|| format!("attachment_{}", index), String::from),
+                size: item.get("size").and_then(Value::as_u64),
+                content_type: item
+                    .get("content_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("application/octet-stream")
+                    .to_string(),
+            }
+        })
+        .collect()
+}
+
 /// Get a display name for a folder.
 fn folder_display_name(name: &str) -> String {
     match name {
@@ -616,10 +778,113 @@ mod tests {
         assert!(matches!(result, Err(DataError::NoAccount)));
     }
 
+    #[test]
+    fn test_sync_folder_no_account() {
+        let mut ds = DataSource::new().unwrap();
+        assert!(matches!(ds.sync_folder("inbox"), Err(DataError::NoAccount)));
+    }
+
     #[test]
     fn test_detect_accounts_empty() {
         let ds = DataSource::new().unwrap();
         // Just verify it doesn't crash - actual accounts depend on filesystem
         let _ = ds.detect_accounts();
     }
+
+    #[test]
+    fn test_email_limit_uses_config() {
+        let paths = AppPaths::discover(None).unwrap();
+        let mut config = AppConfig::default();
+        config.mail.limits.folders.insert("archive".to_string(), 20);
+        let ds = DataSource::with_paths_and_config(paths.clone(), config.clone());
+        assert_eq!(ds.email_limit("inbox"), EMAIL_LIMIT);
+        assert_eq!(ds.email_limit("Archive"), 20);
+
+        config.mail.limits.default = Some(200);
+        let ds = DataSource::with_paths_and_config(paths, config);
+        assert_eq!(ds.email_limit("inbox"), 200);
+        assert_eq!(ds.email_limit("Archive"), 20);
+    }
+
+    #[test]
+    fn test_attachments_from_response() {
+        let response = serde_json::json!([
+            {"index": 0, "name": "report.pdf", "size": 52341, "content_type": "application/pdf"},
+            {"index": 5, "name": "", "size": null},
+            {"name": "notes.txt", "content_type": "text/plain"},
+        ]);
+        let attachments = attachments_from_response(&response);
+        assert_eq!(
+            attachments,
+            [
+                AttachmentInfo {
+                    index: 0,
+                    name: "report.pdf".to_string(),
+                    size: Some(52341),
+                    content_type: "application/pdf".to_string(),
+                },
+                AttachmentInfo {
+                    index: 5,
+                    name: "attachment_5".to_string(),
+                    size: None,
+                    content_type: "application/octet-stream".to_string(),
+                },
+                AttachmentInfo {
+                    index: 2,
+                    name: "notes.txt".to_string(),
+                    size: None,
+                    content_type: "text/plain".to_string(),
+                },
+            ]
+        );
+
+        assert!(attachments_from_response(&serde_json::json!({"error": "nope"})).is_empty());
+    }
+
+    #[test]
+    fn test_load_attachments_no_account() {
+        let mut ds = DataSource::new().unwrap();
+        assert!(matches!(
+            ds.load_attachments("inbox", "brave-owl"),
+            Err(DataError::NoAccount)
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        ds.set_mail_data_dir(dir.path().to_path_buf());
+        ds.set_account("me@example.com").unwrap();
+        assert!(matches!(
+            ds.load_attachments("inbox", "brave-owl"),
+            Err(DataError::MessageNotFound(id)) if id == "brave-owl"
+        ));
+    }
+
+    #[test]
+    fn test_load_folders_uses_stale_server_folder_cache() {
+        let paths = AppPaths::discover(None).unwrap();
+        let mut config = AppConfig {
+            service_url: "http://127.0.0.1:9".to_string(),
+            ..Default::default()
+        };
+        config.mail.folder_cache_ttl_secs = 0;
+        let mut ds = DataSource::with_paths_and_config(paths, config);
+        let dir = tempfile::tempdir().unwrap();
+        ds.set_mail_data_dir(dir.path().to_path_buf());
+        ds.set_account("me@example.com").unwrap();
+        let projects = Folder {
+            name: "Projects".to_string(),
+            total: 7,
+            unread: 3,
+        };
+        ds.get_db().unwrap().store_folders(&[projects]).unwrap();
+
+        // Listing folders never queries the service, stale cache or not
+        let folders = ds.load_folders().unwrap();
+        let listed = folders.iter().find(|f| f.name == "Projects").unwrap();
+        assert_eq!((listed.unread_count, listed.total_count), (3, 7));
+
+        // An explicit refresh does, and a failed one keeps the cache
+        assert!(ds.refresh_server_folders().is_err());
+        let folders = ds.load_folders().unwrap();
+        assert!(folders.iter().any(|f| f.name == "Projects"));
+    }
 }
@@ -31,8 +31,10 @@ pub enum KeyAction {
     ToggleSelect,
     /// Select all (Ctrl-A).
     SelectAll,
-    /// Cycle search mode (Tab).
+    /// Cycle search mode / next form field (Tab).
     CycleSearchMode,
+    /// Submit the compose form (Ctrl-S).
+    Send,
     /// No action / Unknown key.
     Noop,
 }
@@ -57,6 +59,7 @@ impl From<KeyEvent> for KeyAction {
 
             // Selection
             (KeyCode::Char('a'), KeyModifiers::CONTROL) => KeyAction::SelectAll,
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => KeyAction::Send,
             (KeyCode::Char(' '), _) => KeyAction::ToggleSelect,
 
             // Actions
@@ -135,6 +138,12 @@ mod tests {
         assert_eq!(action, KeyAction::SelectAll);
     }
 
+    #[test]
+    fn test_send() {
+        let action: KeyAction = key_event(KeyCode::Char('s'), KeyModifiers::CONTROL).into();
+        assert_eq!(action, KeyAction::Send);
+    }
+
     #[test]
     fn test_toggle_select() {
         let action: KeyAction = key_event(KeyCode::Char(' '), KeyModifiers::NONE).into();
@@ -0,0 +1,224 @@
+//! Configurable normal-mode key bindings.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
+
+/// Normal-mode actions that can be rebound in `[tui.keys]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Quit,
+    Down,
+    Up,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    DeselectAll,
+    Delete,
+    Search,
+    Sort,
+    Help,
+    Refresh,
+    Compose,
+    FolderSelect,
+    FolderMenu,
+    ActionsMenu,
+    MarkMenu,
+    ViewMenu,
+    Attachments,
+}
+
+impl Binding {
+    /// All bindable actions.
+    pub const ALL: [Binding; 20] = [
+        Binding::Quit,
+        Binding::Down,
+        Binding::Up,
+        Binding::Left,
+        Binding::Right,
+        Binding::Top,
+        Binding::Bottom,
+        Binding::DeselectAll,
+        Binding::Delete,
+        Binding::Search,
+        Binding::Sort,
+        Binding::Help,
+        Binding::Refresh,
+        Binding::Compose,
+        Binding::FolderSelect,
+        Binding::FolderMenu,
+        Binding::ActionsMenu,
+        Binding::MarkMenu,
+        Binding::ViewMenu,
+        Binding::Attachments,
+    ];
+
+    /// Action name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Binding::Quit => "quit",
+            Binding::Down => "down",
+            Binding::Up => "up",
+            Binding::Left => "left",
+            Binding::Right => "right",
+            Binding::Top => "top",
+            Binding::Bottom => "bottom",
+            Binding::DeselectAll => "deselect_all",
+            Binding::Delete => "delete",
+            Binding::Search => "search",
+            Binding::Sort => "sort",
+            Binding::Help => "help",
+            Binding::Refresh => "refresh",
+            Binding::Compose => "compose",
+            Binding::FolderSelect => "folder_select",
+            Binding::FolderMenu => "folder_menu",
+            Binding::ActionsMenu => "actions_menu",
+            Binding::MarkMenu => "mark_menu",
+            Binding::ViewMenu => "view_menu",
+            Binding::Attachments => "attachments",
+        }
+    }
+
+    /// Keys bound to this action when it is not overridden.
+    pub fn default_keys(self) -> &'static [char] {
+        match self {
+            Binding::Quit => &['q'],
+            Binding::Down => &['j'],
+            Binding::Up => &['k'],
+            Binding::Left => &['h'],
+            Binding::Right => &['l'],
+            Binding::Top => &['g'],
+            Binding::Bottom => &['G'],
+            Binding::DeselectAll => &['V'],
+            Binding::Delete => &['d'],
+            Binding::Search => &['/', ':'],
+            Binding::Sort => &['s'],
+            Binding::Help => &['?'],
+            Binding::Refresh => &['r'],
+            Binding::Compose => &['a'],
+            Binding::FolderSelect => &['S'],
+            Binding::FolderMenu => &['f'],
+            Binding::ActionsMenu => &['t'],
+            Binding::MarkMenu => &['m'],
+            Binding::ViewMenu => &['v'],
+            Binding::Attachments => &['A'],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Binding> {
+        Binding::ALL.into_iter().find(|b| b.name() == name)
+    }
+}
+
+/// Character-to-action map consulted in normal mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    keys: HashMap<char, Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&HashMap::new()).expect("default key bindings do not conflict")
+    }
+}
+
+impl Keymap {
+    /// Build a keymap from `[tui.keys]` overrides (action name -> key).
+    ///
+    /// An override replaces all default keys of that action; other actions
+    /// keep their defaults. Unknown actions, keys that are not a single
+    /// character, and two actions sharing a key are errors.
+    pub fn from_config(overrides: &HashMap<String, String>) -> Result<Self> {
+        let mut chosen: HashMap<Binding, char> = HashMap::new();
+        for (name, key) in overrides {
+            let binding = Binding::from_name(name)
+                .ok_or_else(|| anyhow!("unknown action '{}' in [tui.keys]", name))?;
+            let mut chars = key.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                bail!(
+                    "[tui.keys] {} must be a single character, got '{}'",
+                    name,
+                    key
+                );
+            };
+            chosen.insert(binding, c);
+        }
+
+        let mut keys = HashMap::new();
+        for binding in Binding::ALL {
+            let bound: Vec<char> = match chosen.get(&binding) {
+                Some(c) => vec![*c],
+                None => binding.default_keys().to_vec(),
+            };
+            for c in bound {
+                if let Some(other) = keys.insert(c, binding) {
+                    bail!(
+                        "[tui.keys] key '{}' is bound to both {} and {}",
+                        c,
+                        other.name(),
+                        binding.name()
+                    );
+                }
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    /// Action bound to a key, if any.
+    pub fn lookup(&self, key: char) -> Option<Binding> {
+        self.keys.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.lookup('q'), Some(Binding::Quit));
+        assert_eq!(keymap.lookup('/'), Some(Binding::Search));
+        assert_eq!(keymap.lookup(':'), Some(Binding::Search));
+        assert_eq!(keymap.lookup('x'), None);
+    }
+
+    #[test]
+    fn test_override_replaces_default_keys() {
+        let keymap = Keymap::from_config(&overrides(&[("quit", "x"), ("search", "n")])).unwrap();
+        assert_eq!(keymap.lookup('x'), Some(Binding::Quit));
+        assert_eq!(keymap.lookup('q'), None);
+        assert_eq!(keymap.lookup('n'), Some(Binding::Search));
+        assert_eq!(keymap.lookup('/'), None);
+        assert_eq!(keymap.lookup('j'), Some(Binding::Down));
+    }
+
+    #[test]
+    fn test_conflicts_are_rejected() {
+        // 'd' is still bound to delete
+        let err = Keymap::from_config(&overrides(&[("quit", "d")])).unwrap_err();
+        assert!(err.to_string().contains("bound to both"));
+
+        let err = Keymap::from_config(&overrides(&[("up", "x"), ("down", "x")])).unwrap_err();
+        assert!(err.to_string().contains("'x'"));
+
+        // Swapping two keys is fine
+        let keymap = Keymap::from_config(&overrides(&[("up", "j"), ("down", "k")])).unwrap();
+        assert_eq!(keymap.lookup('j'), Some(Binding::Up));
+    }
+
+    #[test]
+    fn test_invalid_entries_are_rejected() {
+        assert!(Keymap::from_config(&overrides(&[("launch", "x")])).is_err());
+        assert!(Keymap::from_config(&overrides(&[("quit", "xx")])).is_err());
+        assert!(Keymap::from_config(&overrides(&[("quit", "")])).is_err());
+    }
+}
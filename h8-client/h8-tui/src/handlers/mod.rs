@@ -1,16 +1,20 @@
 //! Key event handlers for the TUI.
 
 mod key_action;
+mod keymap;
+mod mouse;
 
 pub use key_action::KeyAction;
+pub use keymap::{Binding, Keymap};
+pub use mouse::handle_mouse;
 
 use crate::app::{App, AppMode, FocusedPane, PendingAction, SortOption, WhichKeyContext};
 
 /// Handle a key action in the application.
 /// Returns true if the app should quit.
 pub fn handle_key(app: &mut App, action: KeyAction) -> bool {
-    // Clear g-prefix on any key that's not 'g' (in normal mode)
-    if matches!(app.mode, AppMode::Normal) && !matches!(action, KeyAction::Char('g')) {
+    // Clear g-prefix on any key that's not the go-to-top key (in normal mode)
+    if matches!(app.mode, AppMode::Normal) && app.binding_for(&action) != Some(Binding::Top) {
         let was_g_prefix = app.g_prefix;
         app.g_prefix = false;
 
@@ -38,20 +42,54 @@ pub fn handle_key(app: &mut App, action: KeyAction) -> bool {
         AppMode::WhichKey(_) => handle_which_key_mode(app, action),
         AppMode::FolderSelect => handle_folder_select_mode(app, action),
         AppMode::Compose => handle_compose_mode(app, action),
+        AppMode::Attachments => handle_attachments_mode(app, action),
     }
 }
 
 fn handle_normal_mode(app: &mut App, action: KeyAction) -> bool {
+    // Printable keys go through the configurable keymap
+    if let KeyAction::Char(_) = action {
+        return match app.binding_for(&action) {
+            Some(binding) => handle_binding(app, binding),
+            None => false,
+        };
+    }
+
     match action {
         KeyAction::Quit => return true,
-        KeyAction::Char('q') => return true,
 
         // Navigation
-        KeyAction::Down | KeyAction::Char('j') => handle_down(app),
-        KeyAction::Up | KeyAction::Char('k') => handle_up(app),
-        KeyAction::Left | KeyAction::Char('h') => handle_left(app),
-        KeyAction::Right | KeyAction::Char('l') => handle_right(app),
-        KeyAction::Char('g') => {
+        KeyAction::Down => handle_down(app),
+        KeyAction::Up => handle_up(app),
+        KeyAction::Left => handle_left(app),
+        KeyAction::Right => handle_right(app),
+        KeyAction::PageDown => handle_page_down(app),
+        KeyAction::PageUp => handle_page_up(app),
+
+        // Selection
+        KeyAction::ToggleSelect => handle_toggle_select(app),
+        KeyAction::SelectAll => handle_select_all(app),
+
+        KeyAction::Select => handle_enter(app),
+        KeyAction::Escape => app.clear_status(),
+
+        _ => {}
+    }
+    false
+}
+
+/// Run a normal-mode action bound to a key.
+/// Returns true if the app should quit.
+fn handle_binding(app: &mut App, binding: Binding) -> bool {
+    match binding {
+        Binding::Quit => return true,
+
+        // Navigation
+        Binding::Down => handle_down(app),
+        Binding::Up => handle_up(app),
+        Binding::Left => handle_left(app),
+        Binding::Right => handle_right(app),
+        Binding::Top => {
             if app.g_prefix {
                 handle_go_top(app);
                 app.g_prefix = false;
@@ -59,34 +97,27 @@ fn handle_normal_mode(app: &mut App, action: KeyAction) -> bool {
                 app.g_prefix = true;
             }
         }
-        KeyAction::Char('G') => handle_go_bottom(app),
-        KeyAction::PageDown => handle_page_down(app),
-        KeyAction::PageUp => handle_page_up(app),
+        Binding::Bottom => handle_go_bottom(app),
 
         // Selection
-        KeyAction::ToggleSelect => handle_toggle_select(app),
-        KeyAction::SelectAll => handle_select_all(app),
-        KeyAction::Char('V') => app.email_selection.deselect_all(),
+        Binding::DeselectAll => app.email_selection.deselect_all(),
 
         // Actions
-        KeyAction::Char('d') => app.enter_delete(),
-        KeyAction::Char('/') | KeyAction::Char(':') => app.enter_search(),
-        KeyAction::Char('s') => app.enter_sort(),
-        KeyAction::Char('?') => app.enter_help(),
-        KeyAction::Char('r') => app.set_status("Refreshing..."),
-        KeyAction::Char('a') => app.enter_compose(),
-        KeyAction::Char('S') => app.enter_folder_select(),
+        Binding::Delete => app.enter_delete(),
+        Binding::Search => app.enter_search(),
+        Binding::Sort => app.enter_sort(),
+        Binding::Help => app.enter_help(),
+        Binding::Refresh => app.set_status("Refreshing..."),
+        Binding::Compose => app.enter_compose(),
+        Binding::FolderSelect => app.enter_folder_select(),
 
         // Which-key menus
-        KeyAction::Char('f') => app.enter_which_key(WhichKeyContext::Folder),
-        KeyAction::Char('t') => app.enter_which_key(WhichKeyContext::Actions),
-        KeyAction::Char('m') => app.enter_which_key(WhichKeyContext::Mark),
-        KeyAction::Char('v') => app.enter_which_key(WhichKeyContext::View),
+        Binding::FolderMenu => app.enter_which_key(WhichKeyContext::Folder),
+        Binding::ActionsMenu => app.enter_which_key(WhichKeyContext::Actions),
+        Binding::MarkMenu => app.enter_which_key(WhichKeyContext::Mark),
+        Binding::ViewMenu => app.enter_which_key(WhichKeyContext::View),
 
-        KeyAction::Select => handle_enter(app),
-        KeyAction::Escape => app.clear_status(),
-
-        _ => {}
+        Binding::Attachments => handle_attachments(app),
     }
     false
 }
@@ -263,6 +294,39 @@ fn handle_compose_mode(app: &mut App, action: KeyAction) -> bool {
             app.set_status("Compose cancelled");
             app.return_to_normal();
         }
+        KeyAction::CycleSearchMode => app.compose.next_field(),
+        KeyAction::Char(c) => app.compose.input(c),
+        KeyAction::ToggleSelect => app.compose.input(' '),
+        KeyAction::Backspace => app.compose.backspace(),
+        KeyAction::Select => app.compose.newline(),
+        KeyAction::Send => match app.compose.to_document().validate() {
+            Ok(()) => app.pending_action = PendingAction::SaveDraft,
+            Err(e) => app.set_status(format!("Cannot send: {}", e)),
+        },
+        KeyAction::Quit => return true,
+        _ => {}
+    }
+    false
+}
+
+fn handle_attachments_mode(app: &mut App, action: KeyAction) -> bool {
+    let count = app.attachments.as_ref().map_or(0, |(_, list)| list.len());
+    match action {
+        KeyAction::Escape | KeyAction::Char('q') => app.return_to_normal(),
+        KeyAction::Down | KeyAction::Char('j') => {
+            app.attachment_selection = (app.attachment_selection + 1).min(count.saturating_sub(1));
+        }
+        KeyAction::Up | KeyAction::Char('k') => {
+            app.attachment_selection = app.attachment_selection.saturating_sub(1);
+        }
+        KeyAction::Select | KeyAction::Char('d') => {
+            if let Some(attachment) = app.selected_attachment() {
+                let index = attachment.index;
+                app.set_status(format!("Downloading {}...", attachment.name));
+                app.pending_action = PendingAction::DownloadAttachment(index);
+            }
+        }
+        KeyAction::Quit => return true,
         _ => {}
     }
     false
@@ -358,6 +422,19 @@ fn handle_select_all(app: &mut App) {
     }
 }
 
+fn handle_attachments(app: &mut App) {
+    let Some(email) = app.current_email() else {
+        return;
+    };
+    if !email.has_attachments {
+        app.set_status("No attachments");
+        return;
+    }
+    let local_id = email.local_id.clone();
+    app.set_status("Loading attachments...");
+    app.pending_action = PendingAction::LoadAttachments(local_id);
+}
+
 fn handle_enter(app: &mut App) {
     match app.focused_pane {
         FocusedPane::Left => {
@@ -389,16 +466,11 @@ mod tests {
         MessageSync {
             local_id: id.to_string(),
             remote_id: format!("r{}", id),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some(format!("Test {}", id)),
             from_addr: Some("test@example.com".to_string()),
-            received_at: None,
             is_read: true,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         }
     }
 
@@ -412,6 +484,57 @@ mod tests {
             assert!(handle_key(&mut app, KeyAction::Char('q')));
         }
 
+        #[test]
+        fn test_remapped_quit() {
+            let overrides = [("quit".to_string(), "x".to_string())].into();
+            let mut app = App::new();
+            app.keymap = Keymap::from_config(&overrides).unwrap();
+
+            assert!(!handle_key(&mut app, KeyAction::Char('q')));
+            assert!(handle_key(&mut app, KeyAction::Char('x')));
+            // Ctrl-C still quits
+            assert!(handle_key(&mut app, KeyAction::Quit));
+        }
+
+        #[test]
+        fn test_compose_typing_and_send() {
+            let mut app = App::new();
+            handle_key(&mut app, KeyAction::Char('a'));
+            assert_eq!(app.mode, AppMode::Compose);
+
+            // Normal-mode bindings are plain text while composing
+            for c in "q@example.com".chars() {
+                handle_key(&mut app, KeyAction::Char(c));
+            }
+            handle_key(&mut app, KeyAction::CycleSearchMode);
+            handle_key(&mut app, KeyAction::Char('H'));
+            handle_key(&mut app, KeyAction::ToggleSelect);
+            handle_key(&mut app, KeyAction::Char('j'));
+            assert_eq!(app.compose.to, "q@example.com");
+            assert_eq!(app.compose.subject, "H j");
+
+            handle_key(&mut app, KeyAction::Send);
+            assert_eq!(app.pending_action, PendingAction::SaveDraft);
+            assert_eq!(app.mode, AppMode::Compose);
+        }
+
+        #[test]
+        fn test_compose_send_rejects_invalid_recipients() {
+            let mut app = App::new();
+            app.enter_compose();
+            for c in "not an address".chars() {
+                handle_key(&mut app, KeyAction::Char(c));
+            }
+            handle_key(&mut app, KeyAction::Send);
+            assert_eq!(app.pending_action, PendingAction::None);
+            assert!(
+                app.status_message
+                    .as_deref()
+                    .unwrap()
+                    .starts_with("Cannot send")
+            );
+        }
+
         #[test]
         fn test_quit_on_ctrl_c() {
             let mut app = App::new();
@@ -848,4 +971,52 @@ mod tests {
             );
         }
     }
+
+    // Attachment list tests
+    mod attachments_mode {
+        use super::*;
+        use crate::app::AttachmentInfo;
+
+        fn attachment(index: usize, name: &str) -> AttachmentInfo {
+            AttachmentInfo {
+                index,
+                name: name.to_string(),
+                size: None,
+                content_type: "text/plain".to_string(),
+            }
+        }
+
+        #[test]
+        fn test_attachments_key_requests_list() {
+            let mut app = App::new();
+            app.emails = vec![create_test_email("1")];
+            handle_key(&mut app, KeyAction::Char('A'));
+            assert_eq!(app.pending_action, PendingAction::None);
+            assert_eq!(app.status_message.as_deref(), Some("No attachments"));
+
+            app.emails[0].has_attachments = true;
+            handle_key(&mut app, KeyAction::Char('A'));
+            assert_eq!(
+                app.pending_action,
+                PendingAction::LoadAttachments("1".to_string())
+            );
+        }
+
+        #[test]
+        fn test_navigate_and_download() {
+            let mut app = App::new();
+            app.open_attachments("1", vec![attachment(0, "a.txt"), attachment(1, "b.txt")]);
+
+            handle_key(&mut app, KeyAction::Char('j'));
+            handle_key(&mut app, KeyAction::Char('j'));
+            assert_eq!(app.attachment_selection, 1);
+
+            handle_key(&mut app, KeyAction::Select);
+            assert_eq!(app.pending_action, PendingAction::DownloadAttachment(1));
+            assert_eq!(app.mode, AppMode::Attachments);
+
+            handle_key(&mut app, KeyAction::Escape);
+            assert_eq!(app.mode, AppMode::Normal);
+        }
+    }
 }
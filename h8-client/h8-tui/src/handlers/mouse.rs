@@ -0,0 +1,173 @@
+//! Mouse event handling for the TUI.
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+
+use super::{KeyAction, handle_key};
+use crate::app::{App, AppMode, FocusedPane};
+use crate::ui::{list_row_at, pane_areas};
+
+/// Handle a mouse event on a screen of `area`.
+///
+/// A left click focuses the pane under the cursor and moves the cursor to
+/// the clicked folder or email; the wheel moves the cursor in the focused
+/// list. Mouse input is ignored while an overlay is open.
+/// Returns true if the app should quit.
+pub fn handle_mouse(app: &mut App, event: MouseEvent, area: Rect) -> bool {
+    if !matches!(app.mode, AppMode::Normal) {
+        return false;
+    }
+    match event.kind {
+        MouseEventKind::ScrollDown => handle_key(app, KeyAction::Down),
+        MouseEventKind::ScrollUp => handle_key(app, KeyAction::Up),
+        MouseEventKind::Down(MouseButton::Left) => {
+            handle_click(app, event.column, event.row, area);
+            false
+        }
+        _ => false,
+    }
+}
+
+fn handle_click(app: &mut App, column: u16, row: u16, area: Rect) {
+    let [left, middle, right] = pane_areas(area);
+    let position = Position::new(column, row);
+
+    if left.contains(position) {
+        let offset = folder_offset(app, left);
+        app.focus_left();
+        if let Some(index) = list_row_at(left, row, offset).filter(|&i| i < app.folders.len()) {
+            app.folder_selection.index = index;
+        }
+    } else if middle.contains(position) {
+        app.focus_middle();
+        if let Some(index) =
+            list_row_at(middle, row, app.email_selection.offset).filter(|&i| i < app.emails.len())
+        {
+            app.email_selection.index = index;
+        }
+    } else if right.contains(position) {
+        app.focus_right();
+    }
+}
+
+/// Scroll offset of the folder list as drawn: it only follows the cursor
+/// while the pane is focused.
+fn folder_offset(app: &App, pane: Rect) -> usize {
+    if app.focused_pane != FocusedPane::Left {
+        return 0;
+    }
+    let height = pane.height.saturating_sub(2) as usize;
+    (app.folder_selection.index + 1).saturating_sub(height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use h8_core::types::MessageSync;
+
+    use crate::app::FolderInfo;
+
+    /// 100x31 screen: panes are 30 rows high, at x 0, 20 and 60.
+    const SCREEN: Rect = Rect::new(0, 0, 100, 31);
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    fn click(column: u16, row: u16) -> MouseEvent {
+        mouse(MouseEventKind::Down(MouseButton::Left), column, row)
+    }
+
+    fn email(id: &str) -> MessageSync {
+        MessageSync {
+            local_id: id.to_string(),
+            remote_id: format!("r{}", id),
+            folder: "inbox".to_string(),
+            subject: Some(format!("Test {}", id)),
+            is_read: true,
+            ..Default::default()
+        }
+    }
+
+    fn app_with_emails(count: usize) -> App {
+        let mut app = App::new();
+        app.emails = (0..count).map(|i| email(&i.to_string())).collect();
+        app
+    }
+
+    #[test]
+    fn test_click_selects_email_row() {
+        let mut app = app_with_emails(5);
+        app.focus_left();
+
+        handle_mouse(&mut app, click(30, 3), SCREEN);
+        assert_eq!(app.focused_pane, FocusedPane::Middle);
+        assert_eq!(app.email_selection.index, 2);
+
+        // Rows are relative to the scroll offset
+        let mut app = app_with_emails(50);
+        app.email_selection.offset = 20;
+        handle_mouse(&mut app, click(30, 1), SCREEN);
+        assert_eq!(app.email_selection.index, 20);
+    }
+
+    #[test]
+    fn test_click_outside_rows_only_focuses() {
+        let mut app = app_with_emails(3);
+        app.email_selection.index = 1;
+        app.focus_right();
+
+        // Top border
+        handle_mouse(&mut app, click(30, 0), SCREEN);
+        assert_eq!(app.focused_pane, FocusedPane::Middle);
+        assert_eq!(app.email_selection.index, 1);
+
+        // Below the last email
+        handle_mouse(&mut app, click(30, 10), SCREEN);
+        assert_eq!(app.email_selection.index, 1);
+
+        handle_mouse(&mut app, click(80, 5), SCREEN);
+        assert_eq!(app.focused_pane, FocusedPane::Right);
+        assert_eq!(app.email_selection.index, 1);
+    }
+
+    #[test]
+    fn test_click_selects_folder() {
+        let mut app = App::new();
+        app.folders = ["inbox", "sent", "drafts"]
+            .iter()
+            .map(|&name| FolderInfo::new(name, name))
+            .collect();
+
+        handle_mouse(&mut app, click(5, 2), SCREEN);
+        assert_eq!(app.focused_pane, FocusedPane::Left);
+        assert_eq!(app.folder_selection.index, 1);
+    }
+
+    #[test]
+    fn test_scroll_moves_focused_selection() {
+        let mut app = app_with_emails(5);
+        handle_mouse(&mut app, mouse(MouseEventKind::ScrollDown, 80, 5), SCREEN);
+        handle_mouse(&mut app, mouse(MouseEventKind::ScrollDown, 80, 5), SCREEN);
+        assert_eq!(app.email_selection.index, 2);
+        assert_eq!(app.focused_pane, FocusedPane::Middle);
+
+        handle_mouse(&mut app, mouse(MouseEventKind::ScrollUp, 80, 5), SCREEN);
+        assert_eq!(app.email_selection.index, 1);
+    }
+
+    #[test]
+    fn test_mouse_ignored_in_overlays() {
+        let mut app = app_with_emails(5);
+        app.enter_help();
+        handle_mouse(&mut app, click(30, 3), SCREEN);
+        handle_mouse(&mut app, mouse(MouseEventKind::ScrollDown, 30, 3), SCREEN);
+        assert_eq!(app.email_selection.index, 0);
+    }
+}
@@ -9,7 +9,7 @@ mod handlers;
 mod ui;
 
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use crossterm::{
@@ -19,19 +19,23 @@ use crossterm::{
 };
 use h8_core::paths::expand_str_path;
 use h8_core::{AppConfig, AppPaths};
-use ratatui::{Terminal, backend::CrosstermBackend};
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Rect};
 
 use app::App;
 use data::DataSource;
-use handlers::{KeyAction, handle_key};
+use handlers::{Binding, KeyAction, Keymap, handle_key, handle_mouse};
 
 /// Event polling timeout in milliseconds.
 const POLL_TIMEOUT_MS: u64 = 100;
 
-/// Maximum number of emails to load per folder.
-const EMAIL_LIMIT: usize = 500;
-
 fn main() -> Result<()> {
+    // Load configuration shared with the CLI before taking over the terminal,
+    // so config errors are printed normally
+    let paths = AppPaths::discover(None)?;
+    AppConfig::ensure_default(&paths.global_config)?;
+    let config = AppConfig::load(&paths, None)?;
+    let keymap = Keymap::from_config(&config.tui.keys)?;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -39,13 +43,9 @@ fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Load configuration shared with the CLI
-    let paths = AppPaths::discover(None)?;
-    AppConfig::ensure_default(&paths.global_config)?;
-    let config = AppConfig::load(&paths, None)?;
-
     // Create app state and data source
     let mut app = App::new();
+    app.keymap = keymap;
     let mut data_source = DataSource::with_paths(paths);
 
     // Respect custom mail data directory overrides
@@ -67,7 +67,9 @@ fn main() -> Result<()> {
     }
 
     // Run the main loop
-    let result = run_app(&mut terminal, &mut app, &mut data_source);
+    let refresh_interval = (config.tui.refresh_interval_secs > 0)
+        .then(|| Duration::from_secs(config.tui.refresh_interval_secs));
+    let result = run_app(&mut terminal, &mut app, &mut data_source, refresh_interval);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -91,8 +93,23 @@ fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
     data_source: &mut DataSource,
+    refresh_interval: Option<Duration>,
 ) -> Result<()> {
+    let mut last_refresh = Instant::now();
     loop {
+        // Schedule a background refresh once the interval has elapsed
+        if let Some(interval) = refresh_interval
+            && last_refresh.elapsed() >= interval
+        {
+            if matches!(app.mode, app::AppMode::Normal)
+                && app.search_query.is_empty()
+                && app.pending_action == app::PendingAction::None
+            {
+                app.pending_action = app::PendingAction::Refresh;
+            }
+            last_refresh = Instant::now();
+        }
+
         // Draw the UI
         terminal.draw(|frame| ui::draw(frame, app))?;
 
@@ -101,11 +118,21 @@ fn run_app<B: ratatui::backend::Backend>(
 
         // Poll for events
         if event::poll(Duration::from_millis(POLL_TIMEOUT_MS))? {
-            if let Event::Key(key_event) = event::read()? {
-                let action = KeyAction::from(key_event);
-                if handle_key_with_data(app, data_source, action) {
-                    break;
+            match event::read()? {
+                Event::Key(key_event) => {
+                    let action = KeyAction::from(key_event);
+                    if handle_key_with_data(app, data_source, action) {
+                        break;
+                    }
+                }
+                Event::Mouse(mouse_event) => {
+                    let size = terminal.size()?;
+                    let area = Rect::new(0, 0, size.width, size.height);
+                    if handle_mouse(app, mouse_event, area) {
+                        break;
+                    }
                 }
+                _ => {}
             }
         }
 
@@ -121,7 +148,9 @@ fn run_app<B: ratatui::backend::Backend>(
 /// Handle a key action with data source integration.
 fn handle_key_with_data(app: &mut App, data_source: &mut DataSource, action: KeyAction) -> bool {
     // Check for refresh action
-    if matches!(app.mode, app::AppMode::Normal) && matches!(action, KeyAction::Char('r')) {
+    if matches!(app.mode, app::AppMode::Normal)
+        && app.binding_for(&action) == Some(Binding::Refresh)
+    {
         refresh_data(app, data_source);
         return false;
     }
@@ -168,6 +197,63 @@ fn process_pending_action(app: &mut App, data_source: &mut DataSource) {
         PendingAction::ViewEmail(id) => {
             view_email(app, data_source, &id);
         }
+        PendingAction::Refresh => {
+            reload_emails(app, data_source);
+        }
+        PendingAction::SaveDraft => {
+            save_draft(app, data_source);
+        }
+        PendingAction::LoadAttachments(id) => {
+            load_attachments(app, data_source, &id);
+        }
+        PendingAction::DownloadAttachment(index) => {
+            download_attachment(app, data_source, index);
+        }
+    }
+}
+
+/// Fetch the attachment list of an email and show it.
+fn load_attachments(app: &mut App, data_source: &mut DataSource, local_id: &str) {
+    match data_source.load_attachments(&app.current_folder, local_id) {
+        Ok(attachments) => {
+            let count = attachments.len();
+            app.open_attachments(local_id, attachments);
+            if count > 0 {
+                app.set_status(format!("{} attachment(s)", count));
+            }
+        }
+        Err(e) => app.set_status(format!("Failed to load attachments: {}", e)),
+    }
+}
+
+/// Download an attachment of the listed email into the working directory.
+fn download_attachment(app: &mut App, data_source: &mut DataSource, index: usize) {
+    let Some(local_id) = app.attachments.as_ref().map(|(id, _)| id.clone()) else {
+        return;
+    };
+    match data_source.download_attachment(
+        &app.current_folder,
+        &local_id,
+        index,
+        std::path::Path::new("."),
+    ) {
+        Ok(path) => app.set_status(format!("Downloaded: {}", path)),
+        Err(e) => app.set_status(format!("Download failed: {}", e)),
+    }
+}
+
+/// Store the compose form as a draft and leave compose mode.
+fn save_draft(app: &mut App, data_source: &mut DataSource) {
+    let doc = app.compose.to_document();
+    match data_source.save_draft(&doc) {
+        Ok(id) => {
+            app.set_status(format!(
+                "Draft saved: {} (send with `h8 mail send {}`)",
+                id, id
+            ));
+            app.return_to_normal();
+        }
+        Err(e) => app.set_status(format!("Failed to save draft: {}", e)),
     }
 }
 
@@ -237,7 +323,7 @@ fn execute_mark_unread(app: &mut App, data_source: &mut DataSource) {
 fn load_folder(app: &mut App, data_source: &mut DataSource, folder: &str) {
     app.current_folder = folder.to_string();
     app.current_email_body = None; // Clear cached body when changing folders
-    match data_source.load_emails(folder, EMAIL_LIMIT) {
+    match data_source.load_emails(folder, data_source.email_limit(folder)) {
         Ok(emails) => {
             app.emails = emails;
             app.email_selection.reset();
@@ -260,13 +346,14 @@ fn view_email(app: &mut App, data_source: &mut DataSource, local_id: &str) {
         Ok(Some(content)) => {
             // Extract body from the raw email content
             // The format is: From: ...\r\nSubject: ...\r\nDate: ...\r\n\r\n<body>
-            let body = if let Some(pos) = content.find("\r\n\r\n") {
-                content[pos + 4..].to_string()
+            let (headers, body) = if let Some(pos) = content.find("\r\n\r\n") {
+                (&content[..pos], &content[pos + 4..])
             } else if let Some(pos) = content.find("\n\n") {
-                content[pos + 2..].to_string()
+                (&content[..pos], &content[pos + 2..])
             } else {
-                content.clone()
+                ("", content.as_str())
             };
+            let body = h8_core::compose::decode_transfer_encoding(headers, body).into_owned();
 
             app.current_email_body = Some((local_id.to_string(), body));
             app.focus_right();
@@ -323,7 +410,10 @@ fn load_account_data(
         app.folders = folders;
     }
 
-    let emails = data_source.load_emails(&app.current_folder, EMAIL_LIMIT)?;
+    let emails = data_source.load_emails(
+        &app.current_folder,
+        data_source.email_limit(&app.current_folder),
+    )?;
     app.emails = emails;
     app.email_selection.reset();
 
@@ -334,6 +424,12 @@ fn load_account_data(
 fn refresh_data(app: &mut App, data_source: &mut DataSource) {
     app.set_status("Refreshing...");
 
+    // Pull new messages from the server; local data is shown either way
+    let synced = data_source.sync_folder(&app.current_folder);
+    if let Err(e) = data_source.refresh_server_folders() {
+        log::warn!("Folder list refresh failed: {}", e);
+    }
+
     // Reload folders
     if let Ok(folders) = data_source.load_folders() {
         if !folders.is_empty() {
@@ -342,11 +438,21 @@ fn refresh_data(app: &mut App, data_source: &mut DataSource) {
     }
 
     // Reload emails
-    match data_source.load_emails(&app.current_folder, EMAIL_LIMIT) {
+    app.search_query.clear();
+    match data_source.load_emails(
+        &app.current_folder,
+        data_source.email_limit(&app.current_folder),
+    ) {
         Ok(emails) => {
-            app.emails = emails;
-            app.email_selection.reset();
-            app.set_status(format!("Loaded {} emails", app.emails.len()));
+            app.replace_emails(emails);
+            let loaded = format!("Loaded {} emails", app.emails.len());
+            match synced {
+                Ok(report) if report.synced > 0 => {
+                    app.set_status(format!("{} ({} new)", loaded, report.synced))
+                }
+                Ok(_) => app.set_status(loaded),
+                Err(e) => app.set_status(format!("{} (sync failed: {})", loaded, e)),
+            }
         }
         Err(e) => {
             app.set_status(format!("Refresh failed: {}", e));
@@ -354,6 +460,22 @@ fn refresh_data(app: &mut App, data_source: &mut DataSource) {
     }
 }
 
+/// Quietly reload the current folder, keeping cursor and selections.
+fn reload_emails(app: &mut App, data_source: &mut DataSource) {
+    if let Ok(folders) = data_source.load_folders()
+        && !folders.is_empty()
+    {
+        app.folders = folders;
+    }
+    match data_source.load_emails(
+        &app.current_folder,
+        data_source.email_limit(&app.current_folder),
+    ) {
+        Ok(emails) => app.replace_emails(emails),
+        Err(e) => log::warn!("Auto-refresh failed: {}", e),
+    }
+}
+
 /// Execute search with current query.
 fn execute_search(app: &mut App, data_source: &mut DataSource) {
     let search_mode = match &app.search_mode {
@@ -366,7 +488,7 @@ fn execute_search(app: &mut App, data_source: &mut DataSource) {
         &app.current_folder,
         &app.search_query,
         search_mode,
-        EMAIL_LIMIT,
+        data_source.email_limit(&app.current_folder),
     ) {
         Ok(emails) => {
             let count = emails.len();
@@ -440,142 +562,104 @@ fn load_demo_data(app: &mut App) {
         MessageSync {
             local_id: "h8-1".to_string(),
             remote_id: "AAMkAGQ...1".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Weekly Team Update - Q4 Planning".to_string()),
             from_addr: Some("manager@company.com".to_string()),
             received_at: Some("2024-12-09 09:30:00".to_string()),
-            is_read: false,
-            is_draft: false,
             has_attachments: true,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-2".to_string(),
             remote_id: "AAMkAGQ...2".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Re: Code Review Request".to_string()),
             from_addr: Some("colleague@company.com".to_string()),
             received_at: Some("2024-12-09 08:45:00".to_string()),
-            is_read: false,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-3".to_string(),
             remote_id: "AAMkAGQ...3".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Build Failed: main branch".to_string()),
             from_addr: Some("ci@github.com".to_string()),
             received_at: Some("2024-12-09 07:15:00".to_string()),
-            is_read: false,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-4".to_string(),
             remote_id: "AAMkAGQ...4".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Meeting Invite: Architecture Review".to_string()),
             from_addr: Some("calendar@company.com".to_string()),
             received_at: Some("2024-12-08 16:00:00".to_string()),
             is_read: true,
-            is_draft: false,
             has_attachments: true,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-5".to_string(),
             remote_id: "AAMkAGQ...5".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Your weekly digest".to_string()),
             from_addr: Some("newsletter@techweekly.com".to_string()),
             received_at: Some("2024-12-08 10:00:00".to_string()),
             is_read: true,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-6".to_string(),
             remote_id: "AAMkAGQ...6".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Re: Project Deadline Extension".to_string()),
             from_addr: Some("pm@company.com".to_string()),
             received_at: Some("2024-12-07 14:30:00".to_string()),
             is_read: true,
-            is_draft: false,
             has_attachments: true,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-7".to_string(),
             remote_id: "AAMkAGQ...7".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Holiday Schedule 2024".to_string()),
             from_addr: Some("hr@company.com".to_string()),
             received_at: Some("2024-12-06 09:00:00".to_string()),
             is_read: true,
-            is_draft: false,
             has_attachments: true,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-8".to_string(),
             remote_id: "AAMkAGQ...8".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Security Alert: New login detected".to_string()),
             from_addr: Some("security@company.com".to_string()),
             received_at: Some("2024-12-05 22:15:00".to_string()),
             is_read: true,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-9".to_string(),
             remote_id: "AAMkAGQ...9".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Expense Report Approved".to_string()),
             from_addr: Some("finance@company.com".to_string()),
             received_at: Some("2024-12-05 11:00:00".to_string()),
             is_read: true,
-            is_draft: false,
             has_attachments: true,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
         MessageSync {
             local_id: "h8-10".to_string(),
             remote_id: "AAMkAGQ...10".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Welcome to the team!".to_string()),
             from_addr: Some("onboarding@company.com".to_string()),
             received_at: Some("2024-12-01 09:00:00".to_string()),
             is_read: true,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         },
     ];
 
@@ -164,16 +164,11 @@ mod tests {
         MessageSync {
             local_id: id.to_string(),
             remote_id: format!("r{}", id),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some(subject.to_string()),
             from_addr: Some("test@example.com".to_string()),
-            received_at: None,
             is_read,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         }
     }
 
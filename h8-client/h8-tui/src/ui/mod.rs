@@ -24,25 +24,7 @@ use crate::app::App;
 /// Draw the main application UI.
 pub fn draw(frame: &mut Frame, app: &App) {
     let area = frame.area();
-
-    // Main layout: content area + status bar
-    let main_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(1)])
-        .split(area);
-
-    let content_area = main_layout[0];
-    let status_area = main_layout[1];
-
-    // Three-pane layout: left (20%) | middle (40%) | right (40%)
-    let panes = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(20),
-            Constraint::Percentage(40),
-            Constraint::Percentage(40),
-        ])
-        .split(content_area);
+    let (panes, status_area) = split_layout(area);
 
     // Draw the three panes
     draw_left_pane(frame, app, panes[0]);
@@ -60,10 +42,47 @@ pub fn draw(frame: &mut Frame, app: &App) {
         AppMode::Delete | AppMode::DeleteMultiple => draw_delete_confirm(frame, app, area),
         AppMode::WhichKey(ctx) => draw_which_key(frame, ctx, area),
         AppMode::Search(_) => overlays::draw_search(frame, app, area),
+        AppMode::Compose => overlays::draw_compose(frame, app, area),
+        AppMode::Attachments => overlays::draw_attachments(frame, app, area),
         _ => {}
     }
 }
 
+/// Split the screen into the three panes and the status bar below them.
+fn split_layout(area: Rect) -> ([Rect; 3], Rect) {
+    // Main layout: content area + status bar
+    let main_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(area);
+
+    // Three-pane layout: left (20%) | middle (40%) | right (40%)
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(40),
+            Constraint::Percentage(40),
+        ])
+        .split(main_layout[0]);
+
+    ([panes[0], panes[1], panes[2]], main_layout[1])
+}
+
+/// Areas of the left, middle and right panes on a screen of `area`.
+pub fn pane_areas(area: Rect) -> [Rect; 3] {
+    split_layout(area).0
+}
+
+/// Index of the list item drawn at screen `row` inside a bordered pane
+/// scrolled to `offset`; `None` on the borders or outside the pane.
+pub fn list_row_at(pane: Rect, row: u16, offset: usize) -> Option<usize> {
+    if row <= pane.y || row >= pane.bottom().saturating_sub(1) {
+        return None;
+    }
+    Some(offset + (row - pane.y - 1) as usize)
+}
+
 /// Create a centered rectangle for popups.
 pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -101,6 +120,32 @@ mod tests {
         assert!(centered.y + centered.height <= area.height);
     }
 
+    #[test]
+    fn test_pane_areas() {
+        let [left, middle, right] = pane_areas(Rect::new(0, 0, 100, 31));
+        assert_eq!(left, Rect::new(0, 0, 20, 30));
+        assert_eq!(middle, Rect::new(20, 0, 40, 30));
+        assert_eq!(right, Rect::new(60, 0, 40, 30));
+    }
+
+    #[test]
+    fn test_list_row_at() {
+        let pane = Rect::new(20, 0, 40, 30);
+        // Borders are not rows
+        assert_eq!(list_row_at(pane, 0, 0), None);
+        assert_eq!(list_row_at(pane, 29, 0), None);
+        assert_eq!(list_row_at(pane, 30, 0), None);
+
+        assert_eq!(list_row_at(pane, 1, 0), Some(0));
+        assert_eq!(list_row_at(pane, 28, 0), Some(27));
+        assert_eq!(list_row_at(pane, 3, 10), Some(12));
+
+        let offset_pane = Rect::new(0, 5, 20, 10);
+        assert_eq!(list_row_at(offset_pane, 5, 0), None);
+        assert_eq!(list_row_at(offset_pane, 6, 0), Some(0));
+        assert_eq!(list_row_at(offset_pane, 2, 0), None);
+    }
+
     #[test]
     fn test_centered_rect_small_area() {
         let area = Rect::new(0, 0, 10, 10);
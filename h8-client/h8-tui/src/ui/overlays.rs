@@ -1,15 +1,16 @@
 //! Modal overlay widgets (help, sort menu, delete confirm, etc).
 
+use h8_core::types::format_size;
 use ratatui::{
     Frame,
-    layout::Rect,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
 
 use super::centered_rect;
-use crate::app::{App, AppMode, SortOption, WhichKeyContext};
+use crate::app::{App, AppMode, ComposeField, SortOption, WhichKeyContext};
 
 /// Draw the help overlay.
 pub fn draw_help(frame: &mut Frame, app: &App, area: Rect) {
@@ -212,6 +213,102 @@ pub fn draw_search(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(para, bar_area);
 }
 
+/// Draw the compose form overlay.
+pub fn draw_compose(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = centered_rect(80, 80, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Green))
+        .title(" Compose ");
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let fields = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(3),
+        ])
+        .split(inner);
+
+    for (field, field_area) in ComposeField::ALL.iter().zip(fields.iter()) {
+        let focused = *field == app.compose.focus;
+        let border_style = if focused {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let field_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .title(format!(" {} ", field.label()));
+
+        let mut lines: Vec<Line> = app
+            .compose
+            .field(*field)
+            .split('\n')
+            .map(|line| Line::from(line.to_string()))
+            .collect();
+        if focused && let Some(last) = lines.last_mut() {
+            last.push_span(Span::styled(
+                "_",
+                Style::default().add_modifier(Modifier::SLOW_BLINK),
+            ));
+        }
+
+        let para = Paragraph::new(lines)
+            .block(field_block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(para, *field_area);
+    }
+}
+
+/// Draw the attachment list overlay.
+pub fn draw_attachments(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = centered_rect(60, 40, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title(" Attachments (Enter: download) ");
+
+    let attachments = app.attachments.as_ref().map_or(&[][..], |(_, list)| list);
+    let items: Vec<ListItem> = attachments
+        .iter()
+        .enumerate()
+        .map(|(i, att)| {
+            let style = if i == app.attachment_selection {
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let size = att.size.map(format_size).unwrap_or_else(|| "?".to_string());
+            ListItem::new(Line::from(vec![
+                Span::styled(att.name.clone(), style),
+                Span::styled(
+                    format!("  {}, {}", size, att.content_type),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items).block(block);
+
+    let mut state = ListState::default();
+    state.select(Some(app.attachment_selection));
+
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
 fn get_help_text() -> &'static str {
     r#"# Navigation
 
@@ -234,7 +331,8 @@ V:            Clear all selections
 
 d:            Delete (respects multi-select)
 e:            Edit / open external editor
-a:            Compose new email
+a:            Compose new email (Tab: next field, Ctrl-s: save draft)
+A:            List attachments (Enter: download to current directory)
 r:            Refresh
 Enter:        Open selected email
 
@@ -373,6 +471,24 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_draw_compose() {
+        let backend = TestBackend::new(80, 30);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        let mut app = App::new();
+        app.enter_compose();
+        app.compose.to = "alice@example.com".to_string();
+        app.compose.body = "line one\nline two".to_string();
+
+        terminal
+            .draw(|frame| {
+                let area = frame.area();
+                draw_compose(frame, &app, area);
+            })
+            .unwrap();
+    }
+
     #[test]
     fn test_get_help_text_not_empty() {
         let text = get_help_text();
@@ -380,4 +496,36 @@ mod tests {
         assert!(text.contains("Navigation"));
         assert!(text.contains("Selection"));
     }
+
+    #[test]
+    fn test_draw_attachments() {
+        let backend = TestBackend::new(80, 30);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        let mut app = App::new();
+        app.open_attachments(
+            "1",
+            vec![crate::app::AttachmentInfo {
+                index: 0,
+                name: "report.pdf".to_string(),
+                size: Some(52341),
+                content_type: "application/pdf".to_string(),
+            }],
+        );
+
+        terminal
+            .draw(|frame| {
+                let area = frame.area();
+                draw_attachments(frame, &app, area);
+            })
+            .unwrap();
+        let rendered: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(rendered.contains("report.pdf  51.1 KiB, application/pdf"));
+    }
 }
@@ -70,7 +70,7 @@ pub fn draw_right_pane(frame: &mut Frame, app: &App, area: Rect) {
     }
     if email.has_attachments {
         status_parts.push(Span::styled(
-            "ATTACHMENTS",
+            "ATTACHMENTS (A to list)",
             Style::default().fg(Color::Cyan),
         ));
     }
@@ -134,16 +134,11 @@ mod tests {
         MessageSync {
             local_id: "1".to_string(),
             remote_id: "r1".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Test Subject".to_string()),
             from_addr: Some("sender@example.com".to_string()),
             received_at: Some("2024-01-15 10:30:00".to_string()),
-            is_read: false,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         }
     }
 
@@ -100,6 +100,7 @@ fn get_mode_info(mode: &AppMode) -> (&'static str, Color) {
         AppMode::WhichKey(_) => ("WHICH", Color::Cyan),
         AppMode::FolderSelect => ("FOLDER", Color::Yellow),
         AppMode::Compose => ("COMPOSE", Color::Green),
+        AppMode::Attachments => ("FILES", Color::Cyan),
     }
 }
 
@@ -112,7 +113,8 @@ fn get_help_hint(mode: &AppMode) -> &'static str {
         AppMode::Sort => "j/k: move | Enter: apply | Esc: cancel",
         AppMode::WhichKey(_) => "key: select | Esc: cancel",
         AppMode::FolderSelect => "Enter: select | Esc: cancel",
-        AppMode::Compose => "Ctrl-s: send | Esc: cancel",
+        AppMode::Compose => "Tab: next field | Ctrl-s: save draft | Esc: cancel",
+        AppMode::Attachments => "j/k: move | Enter/d: download | Esc: close",
     }
 }
 
@@ -163,16 +165,10 @@ mod tests {
         app.emails.push(MessageSync {
             local_id: "1".to_string(),
             remote_id: "r1".to_string(),
-            change_key: None,
             folder: "inbox".to_string(),
             subject: Some("Test".to_string()),
-            from_addr: None,
-            received_at: None,
             is_read: true,
-            is_draft: false,
-            has_attachments: false,
-            synced_at: None,
-            local_hash: None,
+            ..Default::default()
         });
         app.email_selection.toggle_selection();
 
@@ -28,7 +28,13 @@ def get_config_dir() -> Path:
 
 
 def get_config_path() -> Path:
-    """Get the path to config.toml."""
+    """Get the path to config.toml.
+
+    ``H8_CONFIG`` (set by ``h8 service start``) overrides the XDG location.
+    """
+    override = os.environ.get("H8_CONFIG")
+    if override:
+        return Path(override).expanduser()
     return get_config_dir() / "config.toml"
 
 
@@ -61,17 +67,21 @@ exclude_weekends = true
 
 
 def load_config() -> dict[str, Any]:
-    """Load configuration from config.toml."""
+    """Load configuration from config.toml.
+
+    ``H8_ACCOUNT`` (set by ``h8 service start``) overrides the configured
+    default account.
+    """
     config_path = get_config_path()
 
     if not config_path.exists():
         create_default_config()
 
+    config = DEFAULT_CONFIG.copy()
     if config_path.exists():
         with open(config_path, "rb") as f:
             user_config = tomllib.load(f)
         # Merge with defaults
-        config = DEFAULT_CONFIG.copy()
         config.update(user_config)
         if "free_slots" in user_config:
             config["free_slots"] = {
@@ -80,9 +90,11 @@ def load_config() -> dict[str, Any]:
             }
         if "people" in user_config:
             config["people"] = {**DEFAULT_CONFIG["people"], **user_config["people"]}
-        return config
 
-    return DEFAULT_CONFIG.copy()
+    account = os.environ.get("H8_ACCOUNT")
+    if account:
+        config["account"] = account
+    return config
 
 
 def resolve_person_alias(alias: str) -> str:
@@ -139,6 +139,18 @@ def delete_contact(account: Account, item_id: str) -> dict:
     return {"success": False, "error": "Contact not found"}
 
 
+# Update keys accepted by update_contact and the EWS fields they change
+_UPDATE_FIELDS = {
+    "display_name": "display_name",
+    "given_name": "given_name",
+    "surname": "surname",
+    "company": "company_name",
+    "job_title": "job_title",
+    "email": "email_addresses",
+    "phone": "phone_numbers",
+}
+
+
 def update_contact(account: Account, item_id: str, updates: dict) -> dict:
     """Update an existing contact.
 
@@ -184,7 +196,9 @@ def update_contact(account: Account, item_id: str, updates: dict) -> dict:
                 PhoneNumber(phone_number=updates["phone"], label="BusinessPhone")
             ]
 
-        contact.save(update_fields=list(updates.keys()))
+        contact.save(
+            update_fields=[_UPDATE_FIELDS[k] for k in updates if k in _UPDATE_FIELDS]
+        )
 
         return _contact_to_dict(contact)
     except Exception as e:
@@ -2,6 +2,10 @@
 
 import os
 import email
+import hashlib
+import threading
+from collections import OrderedDict
+from email.utils import parseaddr
 from email.mime.text import MIMEText
 from email.mime.multipart import MIMEMultipart
 from datetime import datetime, timedelta
@@ -22,8 +26,20 @@ class DeferredSendTime(ExtendedProperty):
     property_type = "SystemTime"
 
 
-# Register the extended property on Message class
+# Extended property for follow-up flags
+# PR_FLAG_STATUS (0x1090): 0 = not flagged, 1 = complete, 2 = flagged
+class FlagStatus(ExtendedProperty):
+    """MAPI property holding the follow-up flag state."""
+
+    property_tag = 0x1090
+    property_type = "Integer"
+
+
+FLAG_STATUS_FLAGGED = 2
+
+# Register the extended properties on Message class
 Message.register("deferred_send_time", DeferredSendTime)
+Message.register("flag_status", FlagStatus)
 
 
 FOLDER_MAP = {
@@ -56,6 +72,41 @@ def get_folder(account: Account, folder_name: str):
         raise ValueError(f"Folder '{folder_name}' not found")
 
 
+def list_folders(account: Account) -> list[dict]:
+    """List mail folders with total and unread item counts.
+
+    Covers every folder ``get_folder`` can resolve: the well-known folders
+    under their short names (inbox, sent, ...), then all other folders under
+    ``account.root`` by name. Well-known folders the mailbox lacks are left
+    out.
+    """
+    folders = []
+    well_known_ids = set()
+    for name in FOLDER_MAP:
+        try:
+            folder = get_folder(account, name)
+        except Exception:
+            continue
+        if folder is None:
+            continue
+        well_known_ids.add(folder.id)
+        folders.append(_folder_to_dict(name, folder))
+
+    for folder in account.root.walk():
+        if folder.id in well_known_ids:
+            continue
+        folders.append(_folder_to_dict(folder.name, folder))
+    return folders
+
+
+def _folder_to_dict(name: str, folder) -> dict:
+    return {
+        "name": name,
+        "total": folder.total_count or 0,
+        "unread": folder.unread_count or 0,
+    }
+
+
 def list_messages(
     account: Account,
     folder: str = "inbox",
@@ -80,13 +131,18 @@ def list_messages(
         "datetime_received",
         "is_read",
         "has_attachments",
+        "message_id",
+        "in_reply_to",
+        "references",
     )[:limit]
 
-    messages = []
-    for item in query:
-        if not hasattr(item, "subject"):
-            continue
+    items = [item for item in query if hasattr(item, "subject")]
+    attachments = _attachment_details(
+        account, [item for item in items if item.has_attachments]
+    )
 
+    messages = []
+    for item in items:
         messages.append(
             {
                 "id": item.id,
@@ -100,12 +156,48 @@ def list_messages(
                 else None,
                 "is_read": item.is_read,
                 "has_attachments": item.has_attachments,
+                # null when the details could not be fetched
+                "attachments": attachments.get(item.id)
+                if item.has_attachments
+                else [],
+                "message_id": item.message_id,
+                "in_reply_to": item.in_reply_to,
+                "references": item.references,
             }
         )
 
     return messages
 
 
+def _attachment_details(account: Account, items: list) -> dict:
+    """Name, type and size of the attachments of each item, keyed by item ID.
+
+    FindItem does not return attachments, so they are fetched in one batch.
+    Failures leave the details out rather than failing the listing, so the
+    listing reports them as unknown instead of as no attachments.
+    """
+    if not items:
+        return {}
+
+    details = {}
+    try:
+        fetched = account.fetch(ids=items, only_fields=["attachments"])
+        for item in fetched:
+            if item is None or isinstance(item, Exception):
+                continue
+            details[item.id] = [
+                {
+                    "name": att.name or f"attachment_{i}",
+                    "content_type": getattr(att, "content_type", None),
+                    "size": getattr(att, "size", None),
+                }
+                for i, att in enumerate(item.attachments or [])
+            ]
+    except Exception:
+        pass
+    return details
+
+
 def get_message(account: Account, item_id: str, folder: str = "inbox") -> dict:
     """Get a full message by ID including body."""
     from exchangelib import ItemId
@@ -130,6 +222,9 @@ def get_message(account: Account, item_id: str, folder: str = "inbox") -> dict:
             else None,
             "is_read": item.is_read,
             "has_attachments": item.has_attachments,
+            "message_id": item.message_id,
+            "in_reply_to": item.in_reply_to,
+            "references": item.references,
             "body": item.body,
             "body_type": "html" if isinstance(item.body, HTMLBody) else "text",
         }
@@ -143,20 +238,44 @@ def fetch_messages(
     output_dir: str,
     format: str = "maildir",
     limit: Optional[int] = None,
+    gzip: bool = False,
+    resume: bool = False,
+    skip: Optional[list] = None,
 ) -> dict:
-    """Fetch messages and save to maildir or mbox format."""
+    """Fetch messages and save to maildir, mbox, or JSON format.
+
+    With ``gzip``, mbox output is written as ``mail.mbox.gz``. With
+    ``resume``, output from an interrupted fetch is kept and messages whose
+    fetch key (see ``_fetch_key``) is in ``skip`` are not written again.
+    """
     mail_folder = get_folder(account, folder)
+    skip = set(skip or [])
 
     if format == "maildir":
-        return _fetch_to_maildir(mail_folder, output_dir, limit)
+        return _fetch_to_maildir(mail_folder, output_dir, limit, skip)
     elif format == "mbox":
-        return _fetch_to_mbox(mail_folder, output_dir, limit)
+        return _fetch_to_mbox(mail_folder, output_dir, limit, gzip, resume, skip)
+    elif format == "json":
+        if resume:
+            return {"error": "resume is not supported for JSON output"}
+        return _fetch_to_json(mail_folder, output_dir, limit)
     else:
         return {"error": f"Unknown format: {format}"}
 
 
-def _fetch_to_maildir(mail_folder, output_dir: str, limit: Optional[int]) -> dict:
-    """Save messages to Maildir format."""
+def _fetch_key(item) -> str:
+    """Stable short key for an item, used in fetched file names and progress."""
+    return hashlib.sha1(item.id.encode()).hexdigest()[:16]
+
+
+def _fetch_to_maildir(
+    mail_folder, output_dir: str, limit: Optional[int], skip: Optional[set] = None
+) -> dict:
+    """Save messages to Maildir format.
+
+    Each message is written to ``tmp/`` and then moved into place, so an
+    interrupted fetch never leaves a partial message in ``cur/`` or ``new/``.
+    """
     # Create Maildir structure
     cur_dir = os.path.join(output_dir, "cur")
     new_dir = os.path.join(output_dir, "new")
@@ -165,15 +284,21 @@ def _fetch_to_maildir(mail_folder, output_dir: str, limit: Optional[int]) -> dic
     os.makedirs(cur_dir, exist_ok=True)
     os.makedirs(new_dir, exist_ok=True)
     os.makedirs(tmp_dir, exist_ok=True)
+    skip = skip or set()
 
     query = mail_folder.all().order_by("-datetime_received")
     if limit:
         query = query[:limit]
 
     count = 0
+    skipped = 0
     for item in query:
         if not hasattr(item, "subject"):
             continue
+        key = _fetch_key(item)
+        if key in skip:
+            skipped += 1
+            continue
 
         # Build email message
         msg = _item_to_email(item)
@@ -185,26 +310,47 @@ def _fetch_to_maildir(mail_folder, output_dir: str, limit: Optional[int]) -> dic
             else datetime.now().timestamp()
         )
         flags = "S" if item.is_read else ""
-        filename = f"{int(timestamp)}.{item.id[:20]}.h8:2,{flags}"
+        filename = f"{int(timestamp)}.{key}.h8:2,{flags}"
 
         # Save to cur or new based on read status
         target_dir = cur_dir if item.is_read else new_dir
-        filepath = os.path.join(target_dir, filename)
+        tmp_path = os.path.join(tmp_dir, filename)
 
-        with open(filepath, "w") as f:
+        with open(tmp_path, "w") as f:
             f.write(msg.as_string())
+        os.replace(tmp_path, os.path.join(target_dir, filename))
 
         count += 1
 
-    return {"success": True, "count": count, "output": output_dir}
+    return {"success": True, "count": count, "skipped": skipped, "output": output_dir}
+
 
+def _fetch_to_mbox(
+    mail_folder,
+    output_dir: str,
+    limit: Optional[int],
+    gzip: bool = False,
+    resume: bool = False,
+    skip: Optional[set] = None,
+) -> dict:
+    """Save messages to mbox format, gzip-compressed if requested.
 
-def _fetch_to_mbox(mail_folder, output_dir: str, limit: Optional[int]) -> dict:
-    """Save messages to mbox format."""
+    The key of each appended message is recorded in ``mail.mbox.part`` until
+    the fetch completes, so an interrupted fetch can be resumed. Without that
+    file there is nothing to resume, and ``resume`` starts over.
+    """
     import mailbox
 
     os.makedirs(output_dir, exist_ok=True)
     mbox_path = os.path.join(output_dir, "mail.mbox")
+    part_path = mbox_path + ".part"
+    skip = skip or set()
+    if not resume or not os.path.exists(part_path):
+        # Start over instead of appending to an earlier (complete) export
+        skip = set()
+        for path in (mbox_path, part_path):
+            if os.path.exists(path):
+                os.remove(path)
 
     mbox = mailbox.mbox(mbox_path)
     mbox.lock()
@@ -214,19 +360,96 @@ def _fetch_to_mbox(mail_folder, output_dir: str, limit: Optional[int]) -> dict:
         query = query[:limit]
 
     count = 0
+    skipped = 0
     try:
-        for item in query:
-            if not hasattr(item, "subject"):
-                continue
-
-            msg = _item_to_email(item)
-            mbox.add(msg)
-            count += 1
+        with open(part_path, "a") as progress:
+            for item in query:
+                if not hasattr(item, "subject"):
+                    continue
+                key = _fetch_key(item)
+                if key in skip:
+                    skipped += 1
+                    continue
+
+                msg = _item_to_email(item)
+                mbox.add(msg)
+                mbox.flush()
+                progress.write(key + "\n")
+                progress.flush()
+                count += 1
     finally:
         mbox.unlock()
         mbox.close()
+    os.remove(part_path)
 
-    return {"success": True, "count": count, "output": mbox_path}
+    if gzip:
+        import gzip as gzip_module
+        import shutil
+
+        gz_path = mbox_path + ".gz"
+        with open(mbox_path, "rb") as src, gzip_module.open(gz_path, "wb") as dst:
+            shutil.copyfileobj(src, dst)
+        os.remove(mbox_path)
+        mbox_path = gz_path
+
+    return {"success": True, "count": count, "skipped": skipped, "output": mbox_path}
+
+
+def _fetch_to_json(mail_folder, output_dir: str, limit: Optional[int]) -> dict:
+    """Save messages as a JSON array of {headers, body, attachments} objects."""
+    import json
+
+    os.makedirs(output_dir, exist_ok=True)
+    json_path = os.path.join(output_dir, "mail.json")
+
+    query = mail_folder.all().order_by("-datetime_received")
+    if limit:
+        query = query[:limit]
+
+    messages = []
+    for item in query:
+        if not hasattr(item, "subject"):
+            continue
+        messages.append(_item_to_json(item))
+
+    with open(json_path, "w") as f:
+        json.dump(messages, f, indent=2, ensure_ascii=False)
+
+    return {"success": True, "count": len(messages), "output": json_path}
+
+
+def _item_to_json(item) -> dict:
+    """Convert an EWS item to a portable {headers, body, attachments} dict."""
+    import base64
+
+    headers = {
+        "Subject": item.subject or "",
+        "From": item.sender.email_address if item.sender else "",
+        "To": ", ".join(r.email_address for r in (item.to_recipients or [])),
+        "Message-ID": f"<{item.id}@ews>",
+    }
+    if item.cc_recipients:
+        headers["Cc"] = ", ".join(r.email_address for r in item.cc_recipients)
+    if item.datetime_received:
+        headers["Date"] = item.datetime_received.strftime("%a, %d %b %Y %H:%M:%S %z")
+
+    attachments = []
+    for att in item.attachments or []:
+        entry = {
+            "name": getattr(att, "name", None),
+            "content_type": getattr(att, "content_type", None),
+            "size": getattr(att, "size", None),
+        }
+        content = getattr(att, "content", None)
+        if isinstance(content, bytes):
+            entry["content"] = base64.b64encode(content).decode("ascii")
+        attachments.append(entry)
+
+    return {
+        "headers": headers,
+        "body": str(item.body) if item.body else "",
+        "attachments": attachments,
+    }
 
 
 def _item_to_email(item) -> email.message.EmailMessage:
@@ -287,25 +510,92 @@ def build_file_attachments(specs):
     return attachments
 
 
+def _mailbox(recipient) -> Mailbox:
+    """Build a Mailbox from a ``{"name", "email"}`` dict or an address string.
+
+    Strings may carry a display name (``"Doe, Jane" <jane@example.com>``).
+    """
+    if isinstance(recipient, dict):
+        return Mailbox(name=recipient.get("name"), email_address=recipient["email"])
+    name, address = parseaddr(recipient)
+    return Mailbox(name=name or None, email_address=address or recipient)
+
+
+# Results of recent sends keyed by the client's send token, so a retried
+# send returns the first result instead of delivering the message twice.
+# Tokens whose send is still running map to an Event in _sending_tokens.
+_SENT_TOKENS_MAX = 1000
+_sent_tokens: "OrderedDict[str, dict]" = OrderedDict()
+_sending_tokens: "dict[str, threading.Event]" = {}
+_sent_tokens_lock = threading.Lock()
+
+
 def send_message(account: Account, message_data: dict) -> dict:
+    """Send an email message, dropping retries that reuse a send token.
+
+    A request whose ``send_token`` matches a recent successful send is not
+    delivered again; the earlier result is returned with ``duplicate: True``.
+    A request arriving while the first send with its token is still running
+    waits for that send to finish instead of delivering in parallel.
+    See ``_deliver_message`` for the accepted keys.
+    """
+    token = message_data.get("send_token")
+    if not token:
+        return _deliver_message(account, message_data)
+
+    while True:
+        with _sent_tokens_lock:
+            previous = _sent_tokens.get(token)
+            if previous is not None:
+                return {**previous, "duplicate": True}
+            in_flight = _sending_tokens.get(token)
+            if in_flight is None:
+                done = threading.Event()
+                _sending_tokens[token] = done
+                break
+        # Another request is sending this token; if it fails, try again
+        in_flight.wait()
+
+    try:
+        result = _deliver_message(account, message_data)
+        with _sent_tokens_lock:
+            _sent_tokens[token] = result
+            while len(_sent_tokens) > _SENT_TOKENS_MAX:
+                _sent_tokens.popitem(last=False)
+        return result
+    finally:
+        with _sent_tokens_lock:
+            _sending_tokens.pop(token, None)
+        done.set()
+
+
+def _deliver_message(account: Account, message_data: dict) -> dict:
     """Send an email message, optionally scheduled for later delivery.
 
     Args:
         account: EWS account
         message_data: Dict with keys:
-            - to: list of recipient emails (required)
+            - to: list of recipient emails
             - subject: email subject (required)
             - body: email body text
             - cc: list of CC recipients
+            - bcc: list of BCC recipients (at least one of to/cc/bcc is required)
             - html: if True, body is HTML
             - schedule_at: ISO datetime string for delayed delivery (optional)
             - attachments: list of attachment specs (see build_file_attachments)
+            - message_id: client-generated Message-ID header (optional)
+            - send_token: client-generated token for deduplicating retries
+            - in_reply_to: In-Reply-To header for threading (optional)
+            - references: References header for threading (optional)
 
     Returns:
         Dict with success status and message info
     """
-    to_recipients = [Mailbox(email_address=addr) for addr in message_data["to"]]
-    cc_recipients = [Mailbox(email_address=addr) for addr in message_data.get("cc", [])]
+    to_recipients = [_mailbox(addr) for addr in message_data.get("to", [])]
+    cc_recipients = [_mailbox(addr) for addr in message_data.get("cc", [])]
+    bcc_recipients = [_mailbox(addr) for addr in message_data.get("bcc", [])]
+    if not (to_recipients or cc_recipients or bcc_recipients):
+        raise ValueError("at least one of to, cc or bcc is required")
 
     body = message_data.get("body", "")
     if message_data.get("html", False):
@@ -317,10 +607,17 @@ def send_message(account: Account, message_data: dict) -> dict:
         account=account,
         subject=message_data["subject"],
         body=body,
-        to_recipients=to_recipients,
+        to_recipients=to_recipients if to_recipients else None,
         cc_recipients=cc_recipients if cc_recipients else None,
+        bcc_recipients=bcc_recipients if bcc_recipients else None,
         attachments=attachments if attachments else None,
     )
+    if message_data.get("message_id"):
+        msg.message_id = message_data["message_id"]
+    if message_data.get("in_reply_to"):
+        msg.in_reply_to = message_data["in_reply_to"]
+    if message_data.get("references"):
+        msg.references = message_data["references"]
 
     # Handle scheduled/deferred sending
     schedule_at = message_data.get("schedule_at")
@@ -361,7 +658,7 @@ def send_message(account: Account, message_data: dict) -> dict:
             "scheduled": True,
             "schedule_at": send_time.isoformat(),
             "subject": message_data["subject"],
-            "to": message_data["to"],
+            "to": message_data.get("to", []),
             "attachments": [a.name for a in attachments],
         }
 
@@ -371,7 +668,7 @@ def send_message(account: Account, message_data: dict) -> dict:
     return {
         "success": True,
         "subject": message_data["subject"],
-        "to": message_data["to"],
+        "to": message_data.get("to", []),
         "attachments": [a.name for a in attachments],
     }
 
@@ -386,9 +683,9 @@ def save_draft(account: Account, draft_data: dict) -> dict:
     Returns:
         Dict with id, changekey, and success status
     """
-    to_recipients = [Mailbox(email_address=addr) for addr in draft_data.get("to", [])]
-    cc_recipients = [Mailbox(email_address=addr) for addr in draft_data.get("cc", [])]
-    bcc_recipients = [Mailbox(email_address=addr) for addr in draft_data.get("bcc", [])]
+    to_recipients = [_mailbox(addr) for addr in draft_data.get("to", [])]
+    cc_recipients = [_mailbox(addr) for addr in draft_data.get("cc", [])]
+    bcc_recipients = [_mailbox(addr) for addr in draft_data.get("bcc", [])]
 
     body = draft_data.get("body", "")
     if draft_data.get("html", False):
@@ -447,15 +744,15 @@ def update_draft(account: Account, item_id: str, update_data: dict) -> dict:
     # Update fields
     if "to" in update_data:
         draft.to_recipients = [
-            Mailbox(email_address=addr) for addr in update_data["to"]
+            _mailbox(addr) for addr in update_data["to"]
         ]
     if "cc" in update_data:
         draft.cc_recipients = [
-            Mailbox(email_address=addr) for addr in update_data["cc"]
+            _mailbox(addr) for addr in update_data["cc"]
         ]
     if "bcc" in update_data:
         draft.bcc_recipients = [
-            Mailbox(email_address=addr) for addr in update_data["bcc"]
+            _mailbox(addr) for addr in update_data["bcc"]
         ]
     if "subject" in update_data:
         draft.subject = update_data["subject"]
@@ -1197,6 +1494,39 @@ def batch_mark_messages(
     }
 
 
+def set_flags(
+    account: Account,
+    item_id: str,
+    read: bool | None = None,
+    flagged: bool | None = None,
+) -> dict:
+    """Set the read and/or flagged state of a single message.
+
+    Fields left as None are not touched on the server.
+    """
+    from exchangelib import ItemId
+
+    try:
+        items = list(account.fetch(ids=[ItemId(id=item_id)]))
+        if not items or items[0] is None:
+            return {"success": False, "error": f"Message not found: {item_id}"}
+
+        item = items[0]
+        update_fields = []
+        if read is not None:
+            item.is_read = read
+            update_fields.append("is_read")
+        if flagged is not None:
+            item.flag_status = FLAG_STATUS_FLAGGED if flagged else None
+            update_fields.append("flag_status")
+        if update_fields:
+            item.save(update_fields=update_fields)
+
+        return {"success": True, "id": item_id, "read": read, "flagged": flagged}
+    except Exception as e:
+        return {"success": False, "error": f"Failed to set flags: {e}"}
+
+
 def mark_as_spam(
     account: Account,
     item_id: str,
@@ -22,7 +22,7 @@ import uvicorn
 from fastapi import FastAPI
 from fastapi import File, Form, HTTPException, UploadFile
 from fastapi.concurrency import run_in_threadpool
-from pydantic import BaseModel, Field, field_validator
+from pydantic import BaseModel, Field, field_validator, model_validator
 
 from h8 import auth, calendar, contacts, free, mail, people, resolve, resources, rules_oof, unsubscribe
 from h8.config import get_config, resolve_person_alias
@@ -54,6 +54,10 @@ class CalendarCreate(BaseModel):
     end: str
     location: Optional[str] = None
     body: Optional[str] = None
+    is_all_day: bool = False
+    attendees: List[str] = Field(default_factory=list)
+    required_attendees: List[str] = Field(default_factory=list)
+    optional_attendees: List[str] = Field(default_factory=list)
 
 
 class CalendarInvite(BaseModel):
@@ -89,24 +93,42 @@ class CalendarRsvp(BaseModel):
     message: Optional[str] = None
 
 
+class Recipient(BaseModel):
+    """Recipient with an explicit display name and address."""
+
+    name: Optional[str] = None
+    email: str
+
+
 class SendEmail(BaseModel):
-    to: list[str]
-    cc: list[str] = Field(default_factory=list)
+    to: list[str | Recipient] = Field(default_factory=list)
+    cc: list[str | Recipient] = Field(default_factory=list)
+    bcc: list[str | Recipient] = Field(default_factory=list)
     subject: str
     body: str = ""
     html: bool = False
     schedule_at: Optional[str] = None  # ISO datetime for delayed delivery
+    message_id: Optional[str] = None  # client-generated Message-ID header
+    send_token: Optional[str] = None  # client-generated token to drop duplicate sends
+    in_reply_to: Optional[str] = None  # Message-ID being replied to
+    references: Optional[str] = None  # thread Message-IDs, space-separated
 
-    @field_validator("to", "cc", mode="before")
+    @field_validator("to", "cc", "bcc", mode="before")
     @classmethod
     def _coerce_list(cls, v):
         if v is None:
             return []
-        if isinstance(v, str):
+        if isinstance(v, (str, dict)):
             return [v]
         if isinstance(v, (list, tuple)):
             return list(v)
-        raise ValueError("must be a string or list of strings")
+        raise ValueError("must be a recipient or list of recipients")
+
+    @model_validator(mode="after")
+    def _require_recipient(self):
+        if not (self.to or self.cc or self.bcc):
+            raise ValueError("at least one of to, cc or bcc is required")
+        return self
 
 
 class ContactCreate(BaseModel):
@@ -137,6 +159,9 @@ class FetchMail(BaseModel):
     output: str
     format: str = "maildir"
     limit: Optional[int] = None
+    gzip: bool = False
+    resume: bool = False  # keep output from an interrupted fetch
+    skip: list[str] = Field(default_factory=list)  # fetch keys already written
 
 
 class DraftSave(BaseModel):
@@ -719,6 +744,14 @@ async def mail_list(
     )
 
 
+@app.get("/mail/folders")
+async def mail_folders(account: Optional[str] = None):
+    """List mail folders with total and unread counts."""
+    email = current_account_email(account)
+    acct = auth.get_account(email)
+    return await safe_call_with_retry(mail.list_folders, email, acct)
+
+
 @app.get("/mail/search")
 async def mail_search(
     q: str,
@@ -784,18 +817,20 @@ async def mail_send(payload: SendEmail, account: Optional[str] = None):
 async def mail_send_files(
     to: List[str] = Form(default_factory=list),
     cc: List[str] = Form(default_factory=list),
+    bcc: List[str] = Form(default_factory=list),
     subject: str = Form(""),
     body: str = Form(""),
     html: bool = Form(False),
     schedule_at: Optional[str] = Form(None),
+    message_id: Optional[str] = Form(None),
     attachments: List[UploadFile] = File(default_factory=list),
     account: Optional[str] = None,
 ):
     """Send an email with file attachments using multipart/form-data.
 
     This complements ``/mail/send`` (JSON, supports base64 attachments) with an
-    efficient streaming upload path for binary files. ``to`` and ``cc`` may each
-    be repeated to supply multiple recipients.
+    efficient streaming upload path for binary files. ``to``, ``cc`` and
+    ``bcc`` may each be repeated to supply multiple recipients.
     """
     email = current_account_email(account)
     acct = auth.get_account(email)
@@ -810,10 +845,12 @@ async def mail_send_files(
     message_data = {
         "to": to,
         "cc": cc,
+        "bcc": bcc,
         "subject": subject,
         "body": body,
         "html": html,
         "schedule_at": schedule_at,
+        "message_id": message_id,
         "attachments": att_specs,
     }
     return await safe_call_with_retry(
@@ -833,6 +870,9 @@ async def mail_fetch(payload: FetchMail, account: Optional[str] = None):
         payload.output,
         payload.format,
         payload.limit,
+        payload.gzip,
+        payload.resume,
+        payload.skip,
     )
 
 
@@ -1040,6 +1080,32 @@ async def mail_mark_batch(payload: MailBatchMark, account: Optional[str] = None)
     )
 
 
+class MailFlags(BaseModel):
+    """Request model for setting message flags."""
+
+    read: Optional[bool] = None
+    flagged: Optional[bool] = None
+
+
+@app.post("/mail/{item_id}/flags")
+async def mail_set_flags(
+    item_id: str,
+    payload: MailFlags,
+    account: Optional[str] = None,
+):
+    """Set the read/flagged state of a single message."""
+    email = current_account_email(account)
+    acct = auth.get_account(email)
+    return await safe_call_with_retry(
+        mail.set_flags,
+        email,
+        acct,
+        item_id,
+        payload.read,
+        payload.flagged,
+    )
+
+
 class MailSpam(BaseModel):
     """Request model for marking a message as spam."""
 
@@ -1107,11 +1173,11 @@ async def contacts_delete(item_id: str, account: Optional[str] = None):
     return await safe_call_with_retry(contacts.delete_contact, email, acct, item_id)
 
 
-@app.put("/contacts/{item_id}")
+@app.api_route("/contacts/{item_id}", methods=["PATCH", "PUT"])
 async def contacts_update(
     item_id: str, payload: ContactUpdate, account: Optional[str] = None
 ):
-    """Update an existing contact."""
+    """Update an existing contact; only the supplied fields change."""
     email = current_account_email(account)
     acct = auth.get_account(email)
     update_data = {k: v for k, v in payload.model_dump().items() if v is not None}
@@ -0,0 +1,39 @@
+"""Tests for the config module."""
+
+import os
+import tempfile
+from pathlib import Path
+from unittest.mock import patch
+
+from h8.config import get_config_path, load_config
+
+
+class TestServiceEnvironment:
+    """Tests for the H8_CONFIG / H8_ACCOUNT overrides set by `h8 service start`."""
+
+    def test_config_path_from_env(self):
+        """H8_CONFIG points the service at the CLI's config file."""
+        with patch.dict(os.environ, {"H8_CONFIG": "/tmp/h8/custom.toml"}):
+            assert get_config_path() == Path("/tmp/h8/custom.toml")
+
+    def test_config_path_defaults_to_xdg(self):
+        """Without H8_CONFIG the XDG location is used."""
+        env = {"XDG_CONFIG_HOME": "/xdg"}
+        with patch.dict(os.environ, env, clear=True):
+            assert get_config_path() == Path("/xdg/h8/config.toml")
+
+    def test_account_from_env_overrides_file(self):
+        """H8_ACCOUNT wins over the account in the config file."""
+        with tempfile.TemporaryDirectory() as tmp:
+            path = Path(tmp) / "config.toml"
+            path.write_text('account = "file@example.com"\ntimezone = "UTC"\n')
+
+            with patch.dict(os.environ, {"H8_CONFIG": str(path)}):
+                os.environ.pop("H8_ACCOUNT", None)
+                config = load_config()
+                assert config["account"] == "file@example.com"
+                assert config["timezone"] == "UTC"
+
+            env = {"H8_CONFIG": str(path), "H8_ACCOUNT": "env@example.com"}
+            with patch.dict(os.environ, env):
+                assert load_config()["account"] == "env@example.com"
@@ -0,0 +1,33 @@
+"""Tests for the contacts module."""
+
+from unittest.mock import MagicMock
+
+# Mock exchangelib before importing contacts module
+import sys
+
+sys.modules.setdefault("exchangelib", MagicMock())
+sys.modules.setdefault("exchangelib.account", MagicMock())
+sys.modules.setdefault("exchangelib.indexed_properties", MagicMock())
+
+from h8 import contacts
+
+
+class TestUpdateContact:
+    """Tests for update_contact function."""
+
+    def test_saves_only_supplied_fields(self):
+        """update_contact should save the EWS fields behind the given keys."""
+        mock_account = MagicMock()
+        contact = MagicMock()
+        contact.email_addresses = []
+        contact.phone_numbers = []
+        mock_account.fetch.return_value = [contact]
+
+        contacts.update_contact(
+            mock_account, "id-1", {"company": "Acme", "email": "a@example.com"}
+        )
+
+        assert contact.company_name == "Acme"
+        contact.save.assert_called_once_with(
+            update_fields=["company_name", "email_addresses"]
+        )
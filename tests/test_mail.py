@@ -66,6 +66,62 @@ class TestGetFolder:
             mail.get_folder(mock_account, "unknown")
 
 
+class TestListFolders:
+    """Tests for list_folders function."""
+
+    def _folder(self, folder_id, name, total, unread, folder_class="IPF.Note"):
+        folder = MagicMock()
+        folder.id = folder_id
+        folder.name = name
+        folder.total_count = total
+        folder.unread_count = unread
+        folder.folder_class = folder_class
+        return folder
+
+    def test_well_known_and_custom_folders(self):
+        """list_folders should use short names and include every root folder."""
+        mock_account = MagicMock()
+        inbox = self._folder("in", "Posteingang", 10, 2)
+        mock_account.inbox = inbox
+        mock_account.sent = self._folder("se", "Gesendet", 5, 0)
+        mock_account.drafts = self._folder("dr", "Entwuerfe", 1, 0)
+        mock_account.trash = self._folder("tr", "Papierkorb", 0, 0)
+        mock_account.junk = self._folder("ju", "Junk", None, None)
+        projects = self._folder("pr", "Projects", 7, 3)
+        calendar = self._folder("ca", "Calendar", 50, 0, "IPF.Appointment")
+        mock_account.root.walk.return_value = [inbox, projects, calendar]
+
+        result = mail.list_folders(mock_account)
+
+        names = [f["name"] for f in result]
+        assert names == [
+            "inbox",
+            "sent",
+            "drafts",
+            "trash",
+            "junk",
+            "Projects",
+            "Calendar",
+        ]
+        assert result[0] == {"name": "inbox", "total": 10, "unread": 2}
+        assert result[4] == {"name": "junk", "total": 0, "unread": 0}
+        assert result[5] == {"name": "Projects", "total": 7, "unread": 3}
+
+    def test_missing_well_known_folder_is_skipped(self):
+        """list_folders should tolerate a mailbox without e.g. a junk folder."""
+        mock_account = MagicMock()
+        mock_account.inbox = self._folder("in", "Inbox", 1, 0)
+        mock_account.sent = self._folder("se", "Sent", 0, 0)
+        mock_account.drafts = self._folder("dr", "Drafts", 0, 0)
+        mock_account.trash = self._folder("tr", "Trash", 0, 0)
+        type(mock_account).junk = PropertyMock(side_effect=Exception("not found"))
+        mock_account.root.walk.return_value = []
+
+        result = mail.list_folders(mock_account)
+
+        assert [f["name"] for f in result] == ["inbox", "sent", "drafts", "trash"]
+
+
 class TestListMessages:
     """Tests for list_messages function."""
 
@@ -85,6 +141,9 @@ class TestListMessages:
         mock_item.datetime_received = MagicMock(isoformat=lambda: "2024-01-01T12:00:00")
         mock_item.is_read = True
         mock_item.has_attachments = False
+        mock_item.message_id = "<2@example.com>"
+        mock_item.in_reply_to = "<1@example.com>"
+        mock_item.references = "<0@example.com> <1@example.com>"
 
         mock_query = MagicMock()
         mock_query.filter.return_value = mock_query
@@ -99,6 +158,36 @@ class TestListMessages:
         assert result[0]["id"] == "msg-123"
         assert result[0]["subject"] == "Test Subject"
         assert result[0]["from"] == "sender@example.com"
+        assert result[0]["message_id"] == "<2@example.com>"
+        assert result[0]["in_reply_to"] == "<1@example.com>"
+        assert result[0]["references"] == "<0@example.com> <1@example.com>"
+        assert "references" in mock_query.only.call_args.args
+
+    def test_list_messages_unknown_attachments_are_null(self):
+        """Attachment details that could not be fetched are reported as null."""
+        mock_account = MagicMock()
+        mock_folder = MagicMock()
+        mock_account.inbox = mock_folder
+        mock_account.fetch.side_effect = Exception("fetch failed")
+
+        with_files = MagicMock(id="msg-1", has_attachments=True, sender=None)
+        without = MagicMock(id="msg-2", has_attachments=False, sender=None)
+        for item in (with_files, without):
+            item.to_recipients = []
+            item.cc_recipients = []
+            item.datetime_received = None
+
+        mock_query = MagicMock()
+        mock_query.filter.return_value = mock_query
+        mock_query.order_by.return_value = mock_query
+        mock_query.only.return_value = mock_query
+        mock_query.__getitem__ = lambda self, key: [with_files, without]
+        mock_folder.all.return_value = mock_query
+
+        result = mail.list_messages(mock_account, "inbox", 20, False)
+
+        assert result[0]["attachments"] is None
+        assert result[1]["attachments"] == []
 
     def test_list_messages_unread_filter(self):
         """list_messages should filter by unread when specified."""
@@ -137,6 +226,126 @@ class TestFetchMessages:
             assert os.path.isdir(os.path.join(tmpdir, "tmp"))
             assert result["success"] is True
 
+    def test_fetch_to_json_structure(self):
+        """_fetch_to_json should write an array of {headers, body, attachments}."""
+        import json
+        from datetime import datetime, timezone
+
+        attachment = MagicMock()
+        attachment.name = "report.pdf"
+        attachment.content_type = "application/pdf"
+        attachment.size = 3
+        attachment.content = b"PDF"
+
+        item = MagicMock()
+        item.id = "AAMk123"
+        item.subject = "Quarterly report"
+        item.sender.email_address = "alice@example.com"
+        item.to_recipients = [MagicMock(email_address="bob@example.com")]
+        item.cc_recipients = None
+        item.datetime_received = datetime(2026, 1, 15, 9, 30, tzinfo=timezone.utc)
+        item.body = "See attached."
+        item.is_read = True
+        item.attachments = [attachment]
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            mock_folder = MagicMock()
+            mock_query = MagicMock()
+            mock_query.order_by.return_value = mock_query
+            mock_query.__iter__ = lambda self: iter([item])
+            mock_folder.all.return_value = mock_query
+
+            result = mail._fetch_to_json(mock_folder, tmpdir, None)
+
+            assert result["success"] is True
+            assert result["count"] == 1
+            with open(result["output"]) as f:
+                data = json.load(f)
+
+        assert isinstance(data, list)
+        msg = data[0]
+        assert set(msg) >= {"headers", "body", "attachments"}
+        assert msg["headers"]["Subject"] == "Quarterly report"
+        assert msg["headers"]["From"] == "alice@example.com"
+        assert msg["headers"]["To"] == "bob@example.com"
+        assert "Cc" not in msg["headers"]
+        assert msg["body"] == "See attached."
+        assert msg["attachments"][0]["name"] == "report.pdf"
+        assert msg["attachments"][0]["content"] == "UERG"
+
+    def test_fetch_to_mbox_gzip(self):
+        """_fetch_to_mbox with gzip should leave only a readable mail.mbox.gz."""
+        import gzip
+        import mailbox
+        from datetime import datetime, timezone
+
+        items = []
+        for subject in ("First", "Second"):
+            item = MagicMock()
+            item.id = f"AAMk-{subject}"
+            item.subject = subject
+            item.sender.email_address = "alice@example.com"
+            item.to_recipients = [MagicMock(email_address="bob@example.com")]
+            item.cc_recipients = None
+            item.datetime_received = datetime(2026, 1, 15, 9, 30, tzinfo=timezone.utc)
+            item.body = f"{subject} body"
+            items.append(item)
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            mock_folder = MagicMock()
+            mock_query = MagicMock()
+            mock_query.order_by.return_value = mock_query
+            mock_query.__iter__ = lambda self: iter(items)
+            mock_folder.all.return_value = mock_query
+
+            html_body = type("HTMLBody", (str,), {})
+            with patch.object(mail, "HTMLBody", html_body):
+                result = mail._fetch_to_mbox(mock_folder, tmpdir, None, gzip=True)
+
+            assert result["count"] == 2
+            assert result["output"] == os.path.join(tmpdir, "mail.mbox.gz")
+            assert not os.path.exists(os.path.join(tmpdir, "mail.mbox"))
+
+            plain = os.path.join(tmpdir, "plain.mbox")
+            with gzip.open(result["output"], "rb") as src, open(plain, "wb") as dst:
+                dst.write(src.read())
+            subjects = [m["Subject"] for m in mailbox.mbox(plain)]
+
+        assert subjects == ["First", "Second"]
+
+    def test_fetch_to_mbox_resume_after_complete_fetch_starts_over(self):
+        """Resuming a finished mbox fetch should rewrite, not append."""
+        import mailbox
+
+        item = MagicMock()
+        item.id = "AAMk-Only"
+        item.subject = "Only"
+        item.sender.email_address = "alice@example.com"
+        item.to_recipients = []
+        item.cc_recipients = None
+        item.datetime_received = None
+        item.body = "Only body"
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            mock_folder = MagicMock()
+            mock_query = MagicMock()
+            mock_query.order_by.return_value = mock_query
+            mock_query.__iter__ = lambda self: iter([item])
+            mock_folder.all.return_value = mock_query
+
+            html_body = type("HTMLBody", (str,), {})
+            with patch.object(mail, "HTMLBody", html_body):
+                mail._fetch_to_mbox(mock_folder, tmpdir, None)
+                result = mail._fetch_to_mbox(
+                    mock_folder, tmpdir, None, resume=True, skip={"stale"}
+                )
+
+            assert result["count"] == 1
+            assert result["skipped"] == 0
+            subjects = [m["Subject"] for m in mailbox.mbox(result["output"])]
+
+        assert subjects == ["Only"]
+
     def test_fetch_unknown_format(self):
         """fetch_messages should return error for unknown format."""
         mock_account = MagicMock()
@@ -173,6 +382,82 @@ class TestSendMessage:
         assert result["success"] is True
         assert result["subject"] == "Test Subject"
 
+    def test_send_message_splits_display_names(self):
+        """Recipients keep display name and address separate."""
+        mock_mailbox = MagicMock()
+
+        with patch.object(mail, "Message", MagicMock()), patch.object(
+            mail, "Mailbox", mock_mailbox
+        ):
+            mail.send_message(
+                MagicMock(),
+                {
+                    "to": [{"name": "Doe, Jane", "email": "jane@example.com"}],
+                    "cc": ['"Bob B" <bob@example.com>', "carol@example.com"],
+                    "subject": "Hi",
+                },
+            )
+
+        calls = [c.kwargs for c in mock_mailbox.call_args_list]
+        assert calls == [
+            {"name": "Doe, Jane", "email_address": "jane@example.com"},
+            {"name": "Bob B", "email_address": "bob@example.com"},
+            {"name": None, "email_address": "carol@example.com"},
+        ]
+
+
+    def test_send_message_uses_client_message_id(self):
+        """A client-generated Message-ID is set on the outgoing message."""
+        mock_msg = MagicMock()
+
+        with patch.object(mail, "Message", return_value=mock_msg), patch.object(
+            mail, "Mailbox", MagicMock()
+        ):
+            mail.send_message(
+                MagicMock(),
+                {
+                    "to": ["jane@example.com"],
+                    "subject": "Hi",
+                    "message_id": "<abc@example.com>",
+                },
+            )
+
+        assert mock_msg.message_id == "<abc@example.com>"
+        mock_msg.send.assert_called_once()
+
+    def test_send_message_concurrent_retry_waits_for_first_send(self):
+        """A retry arriving mid-send is not delivered a second time."""
+        import threading
+
+        started = threading.Event()
+        release = threading.Event()
+        delivered = []
+
+        def deliver(account, message_data):
+            delivered.append(message_data["send_token"])
+            started.set()
+            release.wait(5)
+            return {"success": True, "subject": "Hi"}
+
+        data = {"to": ["jane@example.com"], "subject": "Hi", "send_token": "tok-race"}
+        results = []
+        with patch.object(mail, "_deliver_message", side_effect=deliver):
+            first = threading.Thread(
+                target=lambda: results.append(mail.send_message(MagicMock(), data))
+            )
+            first.start()
+            assert started.wait(5)
+            retry = threading.Thread(
+                target=lambda: results.append(mail.send_message(MagicMock(), data))
+            )
+            retry.start()
+            release.set()
+            first.join(5)
+            retry.join(5)
+
+        assert delivered == ["tok-race"]
+        assert sorted(r.get("duplicate", False) for r in results) == [False, True]
+
 
 class TestDraftOperations:
     """Tests for draft management functions."""
@@ -402,6 +687,43 @@ class TestEmptyFolder:
         assert result["folder"] == "trash"
 
 
+class TestSetFlags:
+    """Tests for set_flags function."""
+
+    def test_set_flags_not_found(self):
+        """set_flags should return error when message not found."""
+        mock_account = MagicMock()
+        mock_account.fetch.return_value = [None]
+
+        result = mail.set_flags(mock_account, "nonexistent-id", read=True)
+
+        assert result["success"] is False
+        assert "not found" in result["error"]
+
+    def test_set_flags_read_and_flagged(self):
+        """set_flags should save only the fields that were given."""
+        mock_account = MagicMock()
+        mock_item = MagicMock()
+        mock_account.fetch.return_value = [mock_item]
+
+        result = mail.set_flags(mock_account, "msg-123", read=True, flagged=True)
+
+        assert mock_item.is_read is True
+        assert mock_item.flag_status == mail.FLAG_STATUS_FLAGGED
+        mock_item.save.assert_called_once_with(update_fields=["is_read", "flag_status"])
+        assert result["success"] is True
+
+    def test_set_flags_read_only(self):
+        """set_flags should leave the flag untouched when flagged is None."""
+        mock_account = MagicMock()
+        mock_item = MagicMock()
+        mock_account.fetch.return_value = [mock_item]
+
+        mail.set_flags(mock_account, "msg-123", read=False)
+
+        mock_item.save.assert_called_once_with(update_fields=["is_read"])
+
+
 class TestMarkAsSpam:
     """Tests for mark_as_spam function."""
 